@@ -1,31 +1,1553 @@
 /* auto-generated by NAPI-RS */
 /* eslint-disable */
-export declare function buildIndex(path: string): void
+/**
+ * Handle returned by `createIngestStream`. Batches pushed here are queued
+ * and inserted by a background thread, so `push` never waits on the graph.
+ */
+export declare class IngestStream {
+  /**
+   * Queue a batch. `vectors` holds `ids.length` vectors back to back.
+   * Returns "busy" without queueing anything when the queue is full.
+   * Otherwise `vectors` is read only when the batch is inserted, so leave
+   * the buffer unchanged until `finish` returns.
+   */
+  push(ids: Array<string>, vectors: Float32Array): PushStatus
+  /**
+   * Wait for every queued batch to be inserted and close the stream.
+   * Returns how many points were inserted and how long that took;
+   * nothing is saved, so call `buildIndex` to persist.
+   */
+  finish(): WriteSummary
+}
+
+/**
+ * Open a collection read-only by memory-mapping the segments its writer
+ * dumps (see `sharedSegments`). Several processes attached to the same
+ * collection share one copy of the vectors in the OS page cache.
+ */
+/**
+ * Iterator returned by `searchStream`. Each step yields the next chunk of
+ * results, best first, so a large k never crosses into JS as one array.
+ */
+export declare class SearchStream {
+  [Symbol.iterator](): Iterator<Array<SearchResult>, void, void>
+}
+
+/**
+ * `a + b`, element by element, for building queries in JS without a
+ * linear algebra library.
+ */
+export declare function add(a: Float32Array, b: Float32Array): Float32Array
+
+/**
+ * Run `text` through the analyzer configured for `field` (or the default
+ * one: split on word boundaries and lowercase), to see the terms a lexical
+ * index would store for it.
+ */
+export declare function analyze(path: string, field: string, text: string): Array<TextToken>
+
+export interface AnalyzerConfig {
+  /** Lowercase terms, default true */
+  lowercase?: boolean
+  /** Snowball stemmer language, e.g. "english" or "german" */
+  stemmer?: string
+  /** Words to drop; matched after lowercasing */
+  stopwords?: Array<string>
+  /** Index character n-grams of each term instead of the term itself */
+  ngram?: NgramRange
+}
+
+export declare function attachReadonly(path: string): void
+
+export interface ApiToken {
+  token: string
+  /** URL names of the collections the token may use; "*" for all */
+  collections: Array<string>
+  /**
+   * "read" (stats, health, search) or "write" (also inserts, deletes
+   * and builds)
+   */
+  scope: string
+}
+
+export interface AuditEntry {
+  /** When the operation ran (ms since epoch) */
+  at: number
+  /**
+   * "insert", "delete", "restore", "deleteByFilter", "updatePayloads",
+   * "build", "compact", "reindex", "rollback", "stage", "promote" or
+   * "setTokenVectors"
+   */
+  op: string
+  /**
+   * Point ids the operation changed. For builds, compactions and
+   * reindexes, the tombstones they purged
+   */
+  ids: Array<string>
+  /** Set with `setAuditActor` in the process that ran the operation */
+  actor?: string
+}
+
+export interface AuditLogOptions {
+  /** Only entries at or after this time (ms since epoch) */
+  since?: number
+}
+
+export interface BinarySearchOptions {
+  /** Return each result's payload */
+  includePayload?: boolean
+  /** Only consider points whose payload matches, written as for `boosts` */
+  filter?: Record<string, any>
+}
+
+export interface AutoCompactionConfig {
+  /** Fraction of graph nodes that are dead before compacting, default 0.2 */
+  tombstoneRatio?: number
+  /** Minimum number of dead graph nodes before compacting, default 1000 */
+  minDeleted?: number
+}
+
+/**
+ * Save the collection, purging expired tombstones first. IVF collections
+ * also retrain their centroids once more points have been inserted since
+ * the last training than it used. Returns what was saved and how long it
+ * took, with the sequence number now committed.
+ */
+export declare function buildIndex(path: string): WriteSummary
+
+/**
+ * Check that the graph and the stored vectors agree, as a quick smoke
+ * test after upgrades or restores: every point has a graph node holding
+ * its vector, and searching for a random sample of points by their own
+ * vectors finds each of them at similarity ~1.
+ */
+export declare function checkIndex(path: string, options?: IndexCheckOptions | undefined | null): IndexCheckReport
+
+/**
+ * Ids changed after sequence number `seq`, in the order of their latest
+ * change, for syncing a downstream copy incrementally. Each id appears
+ * once, with its current state. A deleted id is reported until its
+ * tombstone is purged; asking for changes from before a purged deletion
+ * throws, and the downstream copy has to be rebuilt from a full read.
+ */
+export declare function changesSince(path: string, seq: number): ChangeSet
+
+export interface ChangeSet {
+  /** Ids inserted, updated or restored since then and live now */
+  upserted: Array<string>
+  /** Ids deleted since then and still deleted */
+  deleted: Array<string>
+  /** The collection's current sequence number, to pass next time */
+  seq: number
+}
+
+export interface CalibrationRange {
+  min: number
+  max: number
+}
+
+export interface CapacityAlert {
+  /**
+   * "elements" for graph nodes against `maxElements`, "memory" for the
+   * estimated memory against `memoryLimitBytes`
+   */
+  kind: string
+  /** The level reached, as a fraction of the limit */
+  level: number
+  /** Current usage as a fraction of the limit */
+  usage: number
+  limit: number
+}
+
+export interface CapacityAlertConfig {
+  /**
+   * Fractions of `maxElements` (and of `memoryLimitBytes`), default
+   * [0.8, 0.95]
+   */
+  levels?: Array<number>
+  /**
+   * Also raise alerts as the estimated memory of the vectors and graph
+   * reaches the levels of this many bytes
+   */
+  memoryLimitBytes?: number
+}
+
+/**
+ * Drop every staged vector, abandoning a migration. Returns false if none
+ * were staged.
+ */
+export declare function clearStaging(path: string): boolean
+
+/**
+ * Drop a collection from the in-process cache and release its writer
+ * lock. Unsaved changes are discarded; call `buildIndex` first to keep them.
+ */
+export declare function closeCollection(path: string): boolean
+
+/** Search results as parallel arrays, best first, from `columnar: true` */
+export interface ColumnarSearchResults {
+  /** Result ids; absent with `handles` */
+  ids?: Array<string>
+  /** Result handles, with `handles` */
+  handles?: BigUint64Array
+  scores: Float64Array
+  insertedAt: Float64Array
+  /** Each result's payload (null when it has none), when requested */
+  payloads?: Array<Record<string, any> | undefined | null>
+  /** The budget ran out before k results were found; only with `timeoutMs` */
+  partial?: boolean
+}
 
 export interface CollectionConfig {
   path: string
   dimensions: number
   indexType: string
   metric: string
+  /** HNSW M parameter (max connections per node), default 16 */
+  m?: number
+  /** HNSW ef_construction, default 200 */
+  efConstruction?: number
+  /** Expected number of elements, used to size the graph, default 100000 */
+  maxElements?: number
+  /**
+   * Lists of `indexType: "ivf"` collections, default √n of the points
+   * each time the centroids are trained. Saved with the collection
+   */
+  ivfLists?: number
+  /**
+   * Compress the points of an `indexType: "ivf"` collection to product
+   * quantizer codes of this many bytes, keeping the full vectors on
+   * disk for rescoring (IVF-PQ). Must divide `dimensions`
+   */
+  pqSubvectors?: number
+  /**
+   * Hash tables of `indexType: "lsh"` collections, default 8; more find
+   * more of the true neighbours
+   */
+  lshTables?: number
+  /**
+   * Hyperplanes per LSH table, 1 to 64, default 12; more make buckets
+   * smaller and searches faster but miss more neighbours
+   */
+  lshBits?: number
+  /**
+   * Open without taking the writer lock; mutations are rejected and
+   * `reloadCollection` picks up the writer's latest save
+   */
+  readOnly?: boolean
+  /**
+   * Also dump the graph and vectors as mmap-able segments on every save,
+   * so other processes can `attachReadonly` without copying the vectors
+   */
+  sharedSegments?: boolean
+  /**
+   * Keep deleted points restorable for this many days; `buildIndex`
+   * only purges older tombstones. Default: purge on every build
+   */
+  tombstoneRetentionDays?: number
+  /**
+   * Make `insertVector` throw when the id already exists instead of
+   * replacing the point. Saved with the collection
+   */
+  strictInsert?: boolean
+  /**
+   * Record every mutating operation in an append-only audit log, read
+   * back with `getAuditLog`. Saved with the collection
+   */
+  auditLog?: boolean
+  /**
+   * How far each save is flushed to disk: "none" (default) leaves it to
+   * the OS, "flush" syncs the metadata file before it replaces the old
+   * one, "fsync" also syncs the segments and directory. Saved with the
+   * collection
+   */
+  durability?: string
+  /**
+   * Check a bloom filter before the id map, so lookups of ids that were
+   * never inserted mostly skip it. Saved with the collection
+   */
+  idFilter?: boolean
+  /**
+   * Open without rebuilding the graph: searches scan every vector until
+   * the graph, built in the background, is swapped in
+   */
+  lazyIndex?: boolean
+  /**
+   * Compact in the background when dead graph nodes cross these
+   * thresholds. Saved with the collection
+   */
+  autoCompaction?: AutoCompactionConfig
+  /**
+   * Usage levels that raise capacity alerts, reported by `health` and
+   * passed to the `onCapacityAlert` callback. Saved with the collection
+   */
+  capacityAlerts?: CapacityAlertConfig
+  /**
+   * Limits on the collection's size; inserts past them fail with a
+   * `QUOTA_EXCEEDED` error. Saved with the collection
+   */
+  quota?: QuotaConfig
+  /**
+   * Limits on each point's id and payload; inserts and payload updates
+   * past them fail with an `ID_TOO_LONG` or `PAYLOAD_TOO_LARGE` error.
+   * Saved with the collection
+   */
+  entryLimits?: EntryLimitsConfig
+  /**
+   * Embedding model the vectors come from, e.g. "text-embedding-3-small".
+   * Inserts tagged with a different model are rejected, so outputs of
+   * incompatible models aren't mixed. Can be added to an untagged
+   * collection but not changed. Saved with the collection
+   */
+  modelTag?: string
+  /**
+   * Payload field types, e.g. `{ title: "string", price: "float",
+   * tags: "string[]" }`. Inserts whose payload doesn't conform are
+   * rejected. Saved with the collection
+   */
+  schema?: Record<string, string>
+  /**
+   * How the text of payload fields is split into terms for lexical
+   * matching, keyed by field. Saved with the collection
+   */
+  analyzers?: Record<string, AnalyzerConfig>
+  /**
+   * Build the graph deterministically, so the same inserts in the same
+   * order give an identical index. Saved with the collection
+   */
+  seed?: number
+  /**
+   * Re-insert points in shuffled order (by `seed` if set) when the graph
+   * is rebuilt, for better recall on sorted or clustered data. Saved
+   * with the collection
+   */
+  shuffleOnRebuild?: boolean
+  /**
+   * Scale inserted vectors and queries to unit length, so exact scoring
+   * is a dot product instead of a full cosine. Only set on creation;
+   * saved with the collection
+   */
+  normalize?: boolean
+}
+
+export interface CollectionDiff {
+  /** Live ids of A that B doesn't have live; every list is sorted */
+  onlyInA: Array<string>
+  onlyInB: Array<string>
+  /** Ids in both whose vectors differ beyond the tolerance, or in length */
+  vectorChanged: Array<string>
+  payloadChanged: Array<string>
+}
+
+export interface CollectionMetadata {
+  /** Live points at the last save */
+  count: number
+  /** Deleted points not yet purged at the last save */
+  tombstones: number
+  /** When the collection was last saved (ms since epoch) */
+  lastSavedAt?: number
+  fileSizeBytes: number
+  /**
+   * Settings the collection was saved with; `readOnly` and `lazyIndex`
+   * are unset
+   */
+  config: CollectionConfig
 }
 
 export interface CollectionStats {
   count: number
   dimensions: number
   fileSizeBytes: number
+  /** Inserts that replaced an existing point with the same id */
+  upserts: number
+  /** Inserts that reused an internal id freed by a rebuild */
+  reusedIds: number
+  /**
+   * Graph nodes no id maps to any more, left by upserts and purges
+   * until the next rebuild
+   */
+  orphanedNodes: number
+  /** Deleted points whose ids, vectors and graph nodes are still held */
+  tombstones: number
+  /** Internal ids freed by rebuilds and not yet reused */
+  freeIds: number
+  /** Id lookups by inserts, deletes and existence checks that found the id */
+  idHits: number
+  /** Id lookups that didn't find the id */
+  idMisses: number
+  /** Misses answered by the id filter without reading the id map */
+  idFilterRejections: number
+  /**
+   * Sequence number of the latest change. Every insert, delete, payload
+   * update or other change that would be saved bumps it.
+   */
+  seq: number
+  /**
+   * Sequence number of the latest saved change. Changes after it are
+   * lost on a crash, and their numbers reused.
+   */
+  committedSeq: number
+  /** Usage against the quota, when one is set */
+  quota?: QuotaUsage
+}
+
+/**
+ * Purge expired tombstones and rebuild the graph without the nodes they
+ * and upserts left behind. Unlike `buildIndex` nothing is saved. Returns
+ * how many nodes were dropped.
+ */
+export declare function compact(path: string): number
+
+export interface ContextPair {
+  /** An id of a live point or a vector the results should be near */
+  positive: string | Float32Array
+  /** An id of a live point or a vector the results should be away from */
+  negative: string | Float32Array
 }
 
-export declare function createCollection(config: CollectionConfig): void
+/**
+ * Cosine similarity of two vectors, scored as a cosine collection's
+ * search would: a zero vector is similar to everything.
+ */
+export declare function cosineSimilarity(a: Float32Array, b: Float32Array): number
+
+/**
+ * Open or create a collection. A config naming a template registered
+ * with `registerTemplate` takes every setting it leaves unset from it.
+ */
+export declare function createCollection(config: CollectionConfig | TemplatedCollectionConfig): void
+
+/**
+ * Resolve `ids` to a set of points once, for searches to pass as
+ * `allowSet` or `denySet` instead of sending the ids every time. Unknown
+ * ids are ignored. The set follows its points through upserts, but is
+ * dropped by the next compaction or rebuild, after which searches naming
+ * it fail; create it again then.
+ */
+export declare function createIdSet(path: string, ids: Array<string>): number
+
+export interface DecayOptions {
+  /**
+   * Payload field holding a timestamp in ms since epoch. Defaults to the
+   * point's insertion time. Points without a numeric value keep their score
+   */
+  field?: string
+  halfLifeDays: number
+}
+
+/** Delete every point matching the filter. Returns how many were deleted. */
+/**
+ * Start a streaming bulk insert into a loaded collection. Batches are
+ * inserted by a background thread; `push` returns "busy" instead of
+ * blocking when `queueSize` batches are already waiting.
+ */
+export declare function createIngestStream(path: string, options?: IngestOptions | undefined | null): IngestStream
+
+export declare function deleteByFilter(path: string, filter: DeleteFilter): number
+
+export interface DefaultSearchOptions {
+  /** HNSW ef at query time */
+  efSearch?: number
+  /** Drop results scoring below this */
+  scoreThreshold?: number
+  /** Return each result's payload */
+  includePayload?: boolean
+}
+
+export interface DeleteFilter {
+  /** Points inserted at or after this time (ms since epoch) */
+  insertedAfter?: number
+  /** Points inserted before this time (ms since epoch) */
+  insertedBefore?: number
+}
+
+export interface DeletedPoint {
+  id: string
+  /** Deletion time (ms since epoch); 0 for tombstones saved before it was recorded */
+  deletedAt: number
+}
 
 export declare function deleteVector(path: string, id: string): boolean
 
-export declare function insertVector(path: string, id: string, vector: Float32Array): void
+/**
+ * Like `deleteVector`, but queued like `insertVectorAsync`. Resolves to
+ * whether the point was deleted.
+ */
+export declare function deleteVectorAsync(path: string, id: string): Promise<boolean>
+
+/**
+ * Compare the live points of two loaded collections, e.g. to validate a
+ * migration or a blue/green reindex before switching over to the copy.
+ * Both collections are read under one lock, so neither changes midway.
+ */
+export declare function diffCollections(pathA: string, pathB: string, options?: DiffOptions | undefined | null): CollectionDiff
 
-export declare function search(path: string, query: Float32Array, k: number, efSearch?: number | undefined | null): Array<SearchResult>
+export interface DiffOptions {
+  /**
+   * Largest difference in any component for vectors to count as equal,
+   * default 1e-6
+   */
+  tolerance?: number
+}
+
+/**
+ * Discovery search: the `k` points that best fit the context pairs, for
+ * browsing a region of the space rather than the neighbourhood of one
+ * vector. A point fits a pair when it is more similar to the positive
+ * example than to the negative one. With a `target`, points fitting more
+ * pairs rank first and ties go to the points nearest the target; without
+ * one, points are ranked by how far they fall on the wrong side of the
+ * pairs, so those fitting every pair score 0. Examples given by id are
+ * left out of the results. Scans every point in parallel on the worker
+ * pool; counts as a heavy operation.
+ */
+export declare function discover(path: string, context: Array<ContextPair>, k: number, options?: DiscoverOptions | undefined | null): Array<SearchResult>
+
+export interface DiscoverOptions {
+  /** An id or vector to rank the points that fit the context by */
+  target?: string | Float32Array
+  /** Return each result's payload */
+  includePayload?: boolean
+  /** Only consider points whose payload matches, written as for `boosts` */
+  filter?: Record<string, any>
+}
+
+/** Free a set made by `createIdSet`. Returns false if it was already gone. */
+export declare function dropIdSet(path: string, handle: number): boolean
+
+export interface EntryLimitsConfig {
+  /** Id length in UTF-8 bytes */
+  maxIdLength?: number
+  /** Payload size as the JSON it is saved as */
+  maxPayloadBytes?: number
+}
+
+/**
+ * Export the graph and vectors as an hnswlib index file, plus
+ * `<outFile>.ids.json` mapping hnswlib labels to ids. Returns the number
+ * of exported points.
+ */
+export declare function exportHnswlib(path: string, outFile: string): number
+
+/** Entries in the collection's audit log, oldest first. */
+export declare function getAuditLog(path: string, options?: AuditLogOptions | undefined | null): Array<AuditEntry>
+
+/**
+ * The configuration a collection was created with, as persisted on disk,
+ * plus how it is opened in this process.
+ */
+export declare function getConfig(path: string): CollectionConfig
+
+export declare function getDefaultSearchOptions(path: string): DefaultSearchOptions
+
+export declare function getOperationLimits(): OperationLimits
+
+export declare function getThreadPool(): ThreadPoolConfig
+
+/**
+ * Look up many points under one read lock. The result has one entry per
+ * id, in order, with `null` for ids that are missing or deleted.
+ */
+export declare function getVectors(path: string, ids: Array<string>): Array<StoredVector | undefined | null>
+
+/**
+ * Structured status of a collection, for orchestration deciding whether
+ * this process is fit to serve it. Never throws for an unloaded path.
+ */
+export declare function health(path: string): HealthReport
+
+export interface HealthReport {
+  path: string
+  /** Whether the collection is open in this process */
+  loaded: boolean
+  /** Loaded, has capacity left, and no error since the last save */
+  healthy: boolean
+  readOnly?: boolean
+  count?: number
+  /** Last successful save (ms since epoch), from this process or on disk */
+  lastSavedAt?: number
+  /** Changes made since the last save */
+  unsavedChanges?: boolean
+  /** Deleted points not yet purged */
+  pendingTombstones?: number
+  /** Graph nodes a `compact` would drop */
+  deadNodes?: number
+  /** Graph nodes left before `maxElements` */
+  capacityRemaining?: number
+  /** Capacity alert levels usage is at, lowest first */
+  alerts: Array<CapacityAlert>
+  /**
+   * False while the graph of a collection opened with `lazyIndex` is
+   * still being built; searches are exact scans until then
+   */
+  indexReady?: boolean
+  lastError?: string
+  /** When `lastError` happened (ms since epoch) */
+  lastErrorAt?: number
+  /** Sizes at the most recent saves in this process, oldest first */
+  history: Array<SaveSnapshot>
+}
+
+export interface HighlightOptions {
+  /** Payload field holding the text, analyzed with its field analyzer */
+  field: string
+  /** Text whose terms are looked for */
+  query: string
+}
+
+export interface IndexCheckOptions {
+  /** Live points searched for by their own vector, default 100 */
+  sampleSize?: number
+  /** HNSW ef for the sample searches, default 200 */
+  efSearch?: number
+}
+
+export interface IndexCheckReport {
+  /** No id is missing from the graph and every sampled point was found */
+  ok: boolean
+  nodes: number
+  /** Ids, live or deleted, with no graph node holding their vector */
+  missingFromGraph: Array<string>
+  /**
+   * Graph nodes matching no stored point: leftovers of upserts and
+   * purges, which `compact` drops. Not an error
+   */
+  deadNodes: number
+  sampled: number
+  /**
+   * Sampled live ids a search for their own vector didn't return as an
+   * exact match
+   */
+  sampleMisses: Array<string>
+}
+
+export interface IngestOptions {
+  /** Batches that may wait for the worker before `push` returns "busy", default 16 */
+  queueSize?: number
+  /**
+   * Embedding model of every vector pushed, checked against the
+   * collection's `modelTag` when the stream is created
+   */
+  modelTag?: string
+}
+
+/**
+ * Insert a packed bit vector into a collection created with
+ * `metric: "hamming"`: `dimensions / 8` bytes, bit i in bit `i % 8` of
+ * byte `i / 8`. Returns the insert's sequence number.
+ */
+export declare function insertBinary(path: string, id: string, vector: Uint8Array, payload?: Record<string, any> | undefined | null): number
+
+/**
+ * Insert a set of tokens, e.g. hashed shingles, into a collection created
+ * with `metric: "jaccard"`. Order and duplicates don't matter. Returns
+ * the insert's sequence number.
+ */
+export declare function insertSet(path: string, id: string, tokens: Uint32Array, payload?: Record<string, any> | undefined | null): number
+
+/**
+ * `payload` is an optional JSON object stored with the point and returned
+ * by `getVectors`. It is checked against the collection's schema, if any.
+ * Returns the insert's sequence number; it is durable once `committedSeq`
+ * in `stats` reaches it. `modelTag` names the embedding model the vector
+ * came from; it must match the collection's `modelTag`, if both are set.
+ */
+export declare function insertVector(path: string, id: string, vector: Float32Array | Float64Array | Array<number>, payload?: Record<string, any> | undefined | null, modelTag?: string | undefined | null): number
+
+/**
+ * Like `insertVector`, but queued behind the collection's other async
+ * writes and applied in a batch with them off the JS thread. Resolves once
+ * the point is inserted, to true.
+ */
+export declare function insertVectorAsync(path: string, id: string, vector: Float32Array | Float64Array | Array<number>, payload?: Record<string, any> | undefined | null, modelTag?: string | undefined | null): Promise<boolean>
+
+/** Deleted points that can still be restored, oldest deletion first. */
+/**
+ * Compute the k nearest neighbours of every live point and stream them to
+ * `outFile` and/or `onBatch`, batch by batch. Returns the number of points.
+ */
+export declare function knnGraph(path: string, k: number, options: KnnGraphOptions): number
+
+export interface KnnEntry {
+  id: string
+  /** Nearest live neighbours, best first, excluding the point itself */
+  neighbors: Array<SearchResult>
+}
+
+export interface KnnGraphOptions {
+  /** Write one JSON line `{ id, neighbors: [{ id, score }] }` per point */
+  outFile?: string
+  /** Called with each batch of entries as it is computed */
+  onBatch?: (arg: Array<KnnEntry>) => unknown
+  /** Points per batch, default 1000 */
+  batchSize?: number
+  /** HNSW ef at query time, default max(k * 10, 200) */
+  efSearch?: number
+}
+
+export declare function listDeleted(path: string): Array<DeletedPoint>
+
+/**
+ * Background operations running or recently finished, oldest first:
+ * graph builds of `lazyIndex` opens, automatic compactions and ingest
+ * streams. The last 100 finished ones are kept.
+ */
+export declare function listOperations(): Array<Operation>
+
+/** The centroid of the vectors. */
+export declare function mean(vectors: Array<Float32Array>): Float32Array
+
+export interface NgramRange {
+  min: number
+  max: number
+}
+
+/**
+ * The vector scaled to unit length. A zero vector is returned as is, as
+ * collections created with `normalize` store it.
+ */
+export declare function normalize(vector: Float32Array): Float32Array
+
+/**
+ * Read a saved collection's settings and counts from its metadata without
+ * opening it: no vectors are decoded, no graph is built and no lock is
+ * taken. Cheap enough for admin tooling to inspect many collections, and
+ * safe to call on collections another process is writing.
+ */
+export declare function openMetadata(path: string): CollectionMetadata
+
+/**
+ * Register `callback` to be called with each capacity alert the collection
+ * at `path` raises from now on, replacing any earlier one; pass nothing to
+ * unregister. An alert is raised when an insert takes usage to a level it
+ * wasn't at, and again only after usage drops back below it. Callbacks run
+ * on the JS thread after the write that raised them returns. Closing the
+ * collection unregisters its callback.
+ */
+export declare function onCapacityAlert(path: string, callback?: ((alert: CapacityAlert) => void) | undefined | null): void
+
+export interface Operation {
+  id: number
+  /**
+   * "build" for the graph build of a `lazyIndex` open, "compaction" for
+   * an automatic compaction, "ingest" for an ingest stream
+   */
+  kind: string
+  path: string
+  phase: OperationPhase
+  /** Points handled so far */
+  processed: number
+  /** Points to handle, if known up front */
+  total?: number
+  /** Set when `phase` is "failed" */
+  error?: string
+  /** ms since epoch */
+  startedAt: number
+  finishedAt?: number
+}
+
+export interface OperationLimits {
+  /** Heavy operations allowed at once across all collections */
+  maxConcurrent?: number
+  /** Heavy operations allowed at once on one collection */
+  maxPerCollection?: number
+  /** Heavy operations running now (ignored by `setOperationLimits`) */
+  running?: number
+}
+
+export type OperationPhase = 'queued' | 'running' | 'done' | 'failed'
+
+/** One operation from `listOperations`, for polling its progress. */
+export declare function operationStatus(id: number): Operation
+
+export interface OrderBy {
+  field: string
+  /** Default "asc" */
+  direction?: SortDirection
+}
+
+export interface QuotaConfig {
+  /** Live points; inserts of new ids past it fail */
+  maxPoints?: number
+  /**
+   * Estimated memory of the vectors and index, as in
+   * `capacityAlerts.memoryLimitBytes`; inserts fail once it is reached
+   */
+  maxBytes?: number
+}
+
+/** A collection's usage against its quota */
+export interface QuotaUsage {
+  points: number
+  maxPoints?: number
+  /** Estimated memory of the vectors and index */
+  bytes: number
+  maxBytes?: number
+}
+
+/**
+ * Register `config` as a template that `createCollection({ template,
+ * path })` fills in unset settings from, so collections created alike
+ * don't drift apart. Replaces any template of the same name. Templates
+ * live until the process exits; collections keep their settings either
+ * way.
+ */
+export declare function registerTemplate(name: string, config: TemplateConfig): void
+
+export declare function reindex(path: string, config: ReindexConfig): void
+
+/**
+ * Similarity of every pair of the points, scored as `search` would, for
+ * clustering diagnostics and deduplicating candidate sets. Returns an
+ * `ids.length` square matrix row by row: entry `i * ids.length + j` is
+ * the similarity of `ids[i]` and `ids[j]`. Computed in parallel on the
+ * worker pool; counts as a heavy operation. Throws on an id that isn't
+ * live.
+ */
+export declare function pairwiseSimilarities(path: string, ids: Array<string>): Float32Array
+
+export interface PayloadUpdate {
+  id: string
+  /** Fields to set; fields not listed keep their values */
+  payload: Record<string, any>
+}
+
+/**
+ * Distinct values of payload field `field` across the live points, with
+ * how many points hold each, most common first, e.g. to fill a filter
+ * dropdown. Each element of an array field counts as a value, as in
+ * filters. Ties go by the value's JSON text.
+ */
+export declare function payloadValues(path: string, field: string, options?: PayloadValuesOptions | undefined | null): Array<PayloadValueCount>
+
+export interface PayloadValueCount {
+  value: any
+  /** Live points holding the value */
+  count: number
+}
+
+export interface PayloadValuesOptions {
+  /** Distinct values returned, most common first; default 100 */
+  limit?: number
+}
+
+export interface PointValidation {
+  valid: boolean
+  /**
+   * Every check that failed, each worded like the error `insertVector`
+   * would throw
+   */
+  errors: Array<string>
+}
+
+/**
+ * Open many collections as `createCollection` would, several at a time
+ * on worker threads, for service startup. Returns once all have been
+ * tried, with one result per config in order. A collection that fails to
+ * open doesn't stop the others.
+ */
+export declare function preloadCollections(configs: Array<CollectionConfig>, options?: PreloadOptions | undefined | null): Array<PreloadResult>
+
+export interface PreloadOptions {
+  /** Collections opened at once, default the worker pool's thread count */
+  concurrency?: number
+  /** Called on the JS thread as each collection finishes opening */
+  onProgress?: (arg: PreloadProgress) => unknown
+}
+
+export interface PreloadProgress {
+  path: string
+  /** Collections finished so far, including this one */
+  done: number
+  total: number
+  /** Why the collection failed to open */
+  error?: string
+}
+
+export interface PreloadResult {
+  path: string
+  /** Why the collection failed to open; unset if it is open */
+  error?: string
+  elapsedMs: number
+}
+
+/**
+ * Swap the staged vectors in for the live ones and rebuild the index on
+ * them, under one write lock: searches see either the old model's vectors
+ * or the new one's, never a mix. Every live point needs a staged vector.
+ * Tombstones are purged, since they hold old-model vectors, and savepoints
+ * are dropped. Nothing is saved. Returns how many points were promoted.
+ */
+export declare function promoteStaging(path: string): number
+
+export interface ProjectionConfig {
+  /**
+   * One row per collection dimension, each as long as the vectors that
+   * will be passed in
+   */
+  matrix: Array<Float32Array>
+  /** Subtracted from each vector before projecting, e.g. the PCA mean */
+  mean?: Float32Array
+}
+
+export type PushStatus = 'ok' | 'busy'
+
+export interface ReindexConfig {
+  indexType?: string
+  metric?: string
+  m?: number
+  efConstruction?: number
+  maxElements?: number
+  seed?: number
+  shuffleOnRebuild?: boolean
+  ivfLists?: number
+  /** 0 turns product quantization off */
+  pqSubvectors?: number
+  lshTables?: number
+  lshBits?: number
+}
+
+/**
+ * Drop savepoint `name` and the ones taken after it. The savepoint before
+ * them, if any, still undoes their changes. Returns false if there is no
+ * such savepoint.
+ */
+export declare function releaseSavepoint(path: string, name: string): boolean
+
+/**
+ * Re-read a read-only collection from disk to pick up the writer's latest
+ * save. Returns false if nothing has been saved yet.
+ */
+export declare function reloadCollection(path: string): boolean
+
+/**
+ * Move a collection's directory to `newPath`, which must not exist yet. A
+ * loaded collection stays loaded, unsaved changes and all, under the new
+ * path; one that isn't loaded is just moved, unless another process holds
+ * its writer lock. Read-only collections can't be renamed, and readers
+ * attached to the old path must attach to the new one to see later saves.
+ * On Windows, where open files pin their directory, close the collection
+ * first.
+ */
+export declare function renameCollection(oldPath: string, newPath: string): void
+
+/**
+ * Reconcile a collection whose id maps, vectors and tombstones disagree,
+ * e.g. after a crash, and save the consistent state. Reports what was
+ * found; a consistent collection is left untouched.
+ */
+export declare function repairCollection(path: string, options?: RepairOptions | undefined | null): RepairReport
+
+export interface RepairOptions {
+  /** Also delete vectors no id maps to; otherwise they are only reported */
+  dropOrphans?: boolean
+}
+
+export interface RepairReport {
+  /** Ids whose vector was missing; dropped, since they can't be served */
+  missingVectors: Array<string>
+  /** Ids that shared an internal id with another id; dropped */
+  duplicateIds: Array<string>
+  /** Vectors no id maps to */
+  orphanedVectors: number
+  /** Tombstones of ids that don't exist; dropped */
+  staleTombstones: Array<string>
+  /** Reverse map entries that disagreed with the id map; rebuilt */
+  reverseMapFixes: number
+  /** Timestamps, payloads and deletion times left without a point; dropped */
+  strayEntries: number
+  /** Whether anything was changed and saved */
+  repaired: boolean
+}
+
+/**
+ * Map handles from a `handles: true` search back to ids, in order, with
+ * `null` for handles whose point has since been deleted or replaced.
+ * Handles carry the epoch they were returned in, so one whose point's
+ * internal id has since gone to another point resolves to `null` too, as
+ * does one from before the collection was opened.
+ */
+export declare function resolveIds(path: string, handles: BigUint64Array): Array<string | undefined | null>
+
+/** Undo a delete that has not been purged by `buildIndex` yet. */
+export declare function restoreVector(path: string, id: string): boolean
+
+/**
+ * Live points whose payload matches `filter`, ordered by id or by
+ * `orderBy`, without a vector query. Points whose `orderBy` field isn't a
+ * number come last in either direction; ties go by id. Pages taken with
+ * `offset` line up as long as the matching points don't change in between.
+ */
+export declare function retrieve(path: string, options?: RetrieveOptions | undefined | null): Array<RetrievedPoint>
+
+export interface RetrievedPoint {
+  id: string
+  /** Insertion time (ms since epoch); 0 for points saved before it was recorded */
+  insertedAt: number
+  payload?: Record<string, any>
+}
+
+export interface RetrieveOptions {
+  /** Only return points whose payload matches, written as for `boosts` */
+  filter?: Record<string, any>
+  /** Points returned, default 100 */
+  limit?: number
+  /** Matching points skipped first, for paging */
+  offset?: number
+  /** Order by a numeric payload field instead of by id */
+  orderBy?: OrderBy
+}
+
+/**
+ * Return the collection's points and staged vectors to savepoint `name`
+ * and rebuild the graph. Nothing on disk changes until the next
+ * `buildIndex`. Savepoints taken after `name` are dropped.
+ */
+export declare function rollbackTo(path: string, name: string): void
+
+/**
+ * Remember the collection's current points under `name`, so a failed job
+ * can undo its own inserts and deletes with `rollbackTo`. Savepoints live
+ * in memory only and keep what each point held before its first change
+ * after them, so they cost only what the job changes.
+ */
+export declare function savepoint(path: string, name: string): void
+
+export interface SaveSnapshot {
+  /** Save time (ms since epoch) */
+  savedAt: number
+  count: number
+  /** Tombstones at the time of the save */
+  deleted: number
+}
+
+/**
+ * `options` is either `efSearch` as a number or a `SearchOptions` object.
+ * Returns `{ results, partial }` instead of an array when `timeoutMs` is set,
+ * and parallel arrays when `columnar` is set.
+ */
+export declare function search(path: string, query: Float32Array | Float64Array | Array<number>, k: number, options?: number | SearchOptions | undefined | null): Array<SearchResult> | TimedSearchResults | ColumnarSearchResults
+
+/**
+ * The `k` points of a hamming collection nearest `query`, a packed bit
+ * vector laid out as for `insertBinary`, found by scanning every point.
+ * Scores are `1 - distance / dimensions`, so identical vectors score 1.
+ */
+export declare function searchBinary(path: string, query: Uint8Array, k: number, options?: BinarySearchOptions | undefined | null): Array<SearchResult>
+
+export interface SearchBoost {
+  /**
+   * Payload conditions that must all hold, e.g.
+   * `{ lang: "en", year: { gte: 2020 } }`. Operators: eq, ne, in, gt,
+   * gte, lt, lte, exists
+   */
+  filter: Record<string, any>
+  weight: number
+  /** "multiply" (default) or "add" */
+  mode?: string
+}
+
+export interface SearchOptions {
+  /** HNSW ef at query time, default max(k * 10, 200) */
+  efSearch?: number
+  /**
+   * IVF lists scanned at query time, default 8; more is slower but
+   * finds more of the true neighbours
+   */
+  nprobe?: number
+  /**
+   * IVF-PQ candidates per result rescored against the full vectors,
+   * default 4; more is slower but corrects more quantization error
+   */
+  pqRescore?: number
+  /**
+   * Called once with `k * rerankFactor` candidates (best first); must
+   * return one score per candidate. Results are re-ordered by these
+   * scores (higher is better) before slicing to k.
+   */
+  rerank?: (arg: Array<SearchResult>) => Array<number>
+  /** Over-fetch multiplier for `rerank` and `boosts`, default 4 */
+  rerankFactor?: number
+  /**
+   * Adjust the scores of results whose payload matches a filter, then
+   * re-rank. Candidates are over-fetched by `rerankFactor` so boosted
+   * points from just outside the top k can move up
+   */
+  boosts?: Array<SearchBoost>
+  /**
+   * Favour recent points: scores are halved for every `halfLifeDays` of
+   * age. Applied before `boosts`, with the same over-fetch
+   */
+  decay?: DecayOptions
+  /**
+   * Replace each score with an expression over it and the payload, e.g.
+   * `0.8 * $similarity + 0.2 * log(1 + payload.popularity)`. Applied
+   * after `decay` and `boosts`, with the same over-fetch. Nesting is
+   * capped at 256 levels
+   */
+  scoreExpr?: string
+  /**
+   * Token vectors of the query for ColBERT-style late interaction: each
+   * candidate is rescored by summing, over the query tokens, the best dot
+   * product with its own token vectors from `setTokenVectors`. Applied
+   * before `decay`, with the same over-fetch; candidates without token
+   * vectors are dropped
+   */
+  lateInteraction?: Array<Float32Array>
+  /** Only points inserted at or after this time (ms since epoch) */
+  insertedAfter?: number
+  /** Only points inserted before this time (ms since epoch) */
+  insertedBefore?: number
+  /**
+   * Only these points, e.g. the ones an access-control system lets the
+   * caller see. Unknown ids are ignored. A list no longer than
+   * `efSearch` is scored exactly instead of searching the index
+   */
+  allowIds?: Array<string>
+  /** Like `allowIds`, from a set made once by `createIdSet` */
+  allowSet?: number
+  /** Leave out the points in a set made by `createIdSet` */
+  denySet?: number
+  /**
+   * How many times to widen the search when deleted points leave fewer
+   * than k results, default 8
+   */
+  maxRetries?: number
+  /** Candidate-count multiplier per retry, default 2 */
+  retryGrowth?: number
+  /** Report where the query's terms occur in a text payload field */
+  highlight?: HighlightOptions
+  /** Return each result's payload */
+  includePayload?: boolean
+  /** Return only these payload keys; implies `includePayload` */
+  payloadFields?: Array<string>
+  /** Drop results scoring below this */
+  scoreThreshold?: number
+  /**
+   * Latency budget. When it runs out, search stops widening and returns
+   * `{ results, partial }` instead of an array
+   */
+  timeoutMs?: number
+  /**
+   * Return `{ ids, scores, insertedAt }` parallel arrays instead of one
+   * object per result. Can't be combined with `highlight`
+   */
+  columnar?: boolean
+  /**
+   * With `columnar`, return numeric `handles` in place of `ids`, skipping
+   * a string per result. Map them back with `resolveIds`. Can't be
+   * combined with `rerank`
+   */
+  handles?: boolean
+  /**
+   * Map the final scores onto [0, 1] so results of different collections
+   * can be merged: "unit" rescales cosine similarity's [-1, 1], "probit"
+   * ranks each score against the others returned, and `{ min, max }`
+   * rescales that range. `scoreThreshold` still applies to raw scores
+   */
+  calibration?: string | CalibrationRange
+}
 
 export interface SearchResult {
   id: string
   score: number
+  /** Insertion time (ms since epoch); 0 for points saved before it was recorded */
+  insertedAt: number
+  /** Words of the `highlight` field matching the query, when requested */
+  highlights?: Array<TextToken>
+  /** The point's payload, or the requested keys of it, when requested */
+  payload?: Record<string, any>
+}
+
+/**
+ * The `k` points of a jaccard collection most similar to the token set
+ * `query`, scored by exact Jaccard similarity. Candidates come from
+ * minhash LSH buckets, so sets less than about 0.4 similar are mostly
+ * missed unless `exhaustive` is set.
+ */
+export declare function searchSet(path: string, query: Uint32Array, k: number, options?: SetSearchOptions | undefined | null): Array<SearchResult>
+
+/**
+ * Like `search`, but returns an iterator over chunks of at most
+ * `chunkSize` results, for exporting neighbour lists with a very large k.
+ */
+export declare function searchStream(path: string, query: Float32Array | Float64Array | Array<number>, k: number, options?: SearchStreamOptions | undefined | null): SearchStream
+
+export interface SearchStreamOptions {
+  /** Results per chunk, default 1000 */
+  chunkSize?: number
+  /** HNSW ef at query time, default max(k * 10, 200) */
+  efSearch?: number
+  /** Only points inserted at or after this time (ms since epoch) */
+  insertedAfter?: number
+  /** Only points inserted before this time (ms since epoch) */
+  insertedBefore?: number
+  /** Return each result's payload */
+  includePayload?: boolean
+  /** Return only these payload keys; implies `includePayload` */
+  payloadFields?: Array<string>
+  /** Drop results scoring below this */
+  scoreThreshold?: number
+}
+
+export interface ServerConfig {
+  /** Collections to expose, keyed by the name used in URLs */
+  collections: Record<string, string>
+  /** Interface to bind, default "127.0.0.1" */
+  host?: string
+  /**
+   * When set, every request must send one of these tokens as
+   * `Authorization: Bearer <token>` and may only do what it grants
+   */
+  tokens?: Array<ApiToken>
+  /** Serve HTTPS with this certificate instead of plain HTTP */
+  tls?: TlsConfig
+}
+
+export interface SetSearchOptions {
+  /** Return each result's payload */
+  includePayload?: boolean
+  /** Only consider points whose payload matches, written as for `boosts` */
+  filter?: Record<string, any>
+  /**
+   * Compare the query with every set rather than only the LSH
+   * candidates: exact, but slower
+   */
+  exhaustive?: boolean
+}
+
+/**
+ * Serve the configured collections over HTTP/JSON from a background thread.
+ * Pass port 0 to pick a free port; the bound port is returned.
+ */
+/**
+ * Attribute this process's later audit entries for the collection to
+ * `actor`, e.g. a user or job name. Pass null to clear it.
+ */
+export declare function setAuditActor(path: string, actor?: string | undefined | null): void
+
+/**
+ * Make inserts to a path no collection is loaded from open or create one
+ * there instead of failing, for collections made on the fly (e.g. one per
+ * user). Takes the name of a template, looked up now, or the settings
+ * themselves; `indexType` defaults to "hnsw" and `metric` to "cosine".
+ * Pass null to go back to failing, the default.
+ */
+export declare function setAutoCreate(config?: string | TemplateConfig | undefined | null): void
+
+/**
+ * Replace the options every search on this collection uses when the query
+ * doesn't set them. Saved with the collection on the next `buildIndex`.
+ */
+export declare function setDefaultSearchOptions(path: string, options: DefaultSearchOptions): void
+
+/**
+ * Cap how many heavy operations (buildIndex, reindex, compact, knnGraph,
+ * pairwiseSimilarities, discover, ingest batches, background compaction)
+ * run at once. Replaces the previous limits; omitted caps are unlimited.
+ * Calls from JS over a cap throw right away instead of queueing;
+ * background work waits for a free slot.
+ */
+export declare function setOperationLimits(limits: OperationLimits): void
+
+/**
+ * Reduce the vectors passed to inserts and searches to the collection's
+ * dimensions by multiplying them with `projection.matrix`, so large
+ * embeddings can be stored as smaller projections. Vectors already stored
+ * are left as they are. Pass null to remove the projection. Saved with
+ * the collection on the next `buildIndex`.
+ */
+export declare function setProjection(path: string, projection?: ProjectionConfig | undefined | null): void
+
+/**
+ * Size the worker pool used for parallel graph work (ingest batches,
+ * knnGraph). It is separate from libuv's pool, so the two can be sized
+ * together to fit the host. Work already running finishes on the old pool.
+ */
+export declare function setThreadPool(config: ThreadPoolConfig): void
+
+/**
+ * Store a matrix of token vectors for live point `id`, e.g. a ColBERT
+ * model's per-token output, for `lateInteraction` rescoring. Every row of
+ * every point has the same length, set by the first matrix stored. Scores
+ * are dot products, so pass unit-length rows for cosine MaxSim. Replacing
+ * the point with an upsert drops its matrix. Returns false if `id` is not
+ * live.
+ */
+export declare function setTokenVectors(path: string, id: string, tokens: Array<Float32Array>): boolean
+
+export type SortDirection = 'asc' | 'desc'
+
+/**
+ * Stage `vector` as live point `id`'s embedding from the model the
+ * collection is moving to, next to the vector searches use. The first
+ * staged vector sets the staging dimensions; `modelTag` becomes the
+ * collection's tag on promotion. Upserting or deleting the point drops its
+ * staged vector, so it has to be staged again. Returns false if `id` is
+ * not live.
+ */
+export declare function stageVector(path: string, id: string, vector: Float32Array | Float64Array | Array<number>, modelTag?: string | undefined | null): boolean
+
+/** Progress of a migration, or null if nothing is staged. */
+export declare function stagingStatus(path: string): StagingStatus | null
+
+export interface StagingStatus {
+  dimensions: number
+  modelTag?: string
+  /** Live points with a staged vector */
+  staged: number
+  /** Live points still to stage before `promoteStaging` can run */
+  missing: number
 }
 
+export declare function startServer(port: number, config: ServerConfig): number
+
 export declare function stats(path: string): CollectionStats
+
+export declare function stopServer(port: number): boolean
+
+/** `a - b`, element by element, e.g. for analogy queries. */
+export declare function subtract(a: Float32Array, b: Float32Array): Float32Array
+
+export interface StoredVector {
+  id: string
+  vector: Float32Array
+  /** Insertion time (ms since epoch); 0 for points saved before it was recorded */
+  insertedAt: number
+  payload?: Record<string, any>
+}
+
+/**
+ * The settings of a `CollectionConfig` without its path, all optional, for
+ * `registerTemplate` and `setAutoCreate`
+ */
+export interface TemplateConfig {
+  dimensions?: number
+  indexType?: string
+  metric?: string
+  /** HNSW M parameter (max connections per node), default 16 */
+  m?: number
+  /** HNSW ef_construction, default 200 */
+  efConstruction?: number
+  /** Expected number of elements, used to size the graph, default 100000 */
+  maxElements?: number
+  /**
+   * Lists of `indexType: "ivf"` collections, default √n of the points
+   * each time the centroids are trained. Saved with the collection
+   */
+  ivfLists?: number
+  /**
+   * Compress the points of an `indexType: "ivf"` collection to product
+   * quantizer codes of this many bytes, keeping the full vectors on
+   * disk for rescoring (IVF-PQ). Must divide `dimensions`
+   */
+  pqSubvectors?: number
+  /**
+   * Hash tables of `indexType: "lsh"` collections, default 8; more find
+   * more of the true neighbours
+   */
+  lshTables?: number
+  /**
+   * Hyperplanes per LSH table, 1 to 64, default 12; more make buckets
+   * smaller and searches faster but miss more neighbours
+   */
+  lshBits?: number
+  /**
+   * Open without taking the writer lock; mutations are rejected and
+   * `reloadCollection` picks up the writer's latest save
+   */
+  readOnly?: boolean
+  /**
+   * Also dump the graph and vectors as mmap-able segments on every save,
+   * so other processes can `attachReadonly` without copying the vectors
+   */
+  sharedSegments?: boolean
+  /**
+   * Keep deleted points restorable for this many days; `buildIndex`
+   * only purges older tombstones. Default: purge on every build
+   */
+  tombstoneRetentionDays?: number
+  /**
+   * Make `insertVector` throw when the id already exists instead of
+   * replacing the point. Saved with the collection
+   */
+  strictInsert?: boolean
+  /**
+   * Record every mutating operation in an append-only audit log, read
+   * back with `getAuditLog`. Saved with the collection
+   */
+  auditLog?: boolean
+  /**
+   * How far each save is flushed to disk: "none" (default) leaves it to
+   * the OS, "flush" syncs the metadata file before it replaces the old
+   * one, "fsync" also syncs the segments and directory. Saved with the
+   * collection
+   */
+  durability?: string
+  /**
+   * Check a bloom filter before the id map, so lookups of ids that were
+   * never inserted mostly skip it. Saved with the collection
+   */
+  idFilter?: boolean
+  /**
+   * Open without rebuilding the graph: searches scan every vector until
+   * the graph, built in the background, is swapped in
+   */
+  lazyIndex?: boolean
+  /**
+   * Compact in the background when dead graph nodes cross these
+   * thresholds. Saved with the collection
+   */
+  autoCompaction?: AutoCompactionConfig
+  /**
+   * Usage levels that raise capacity alerts, reported by `health` and
+   * passed to the `onCapacityAlert` callback. Saved with the collection
+   */
+  capacityAlerts?: CapacityAlertConfig
+  /**
+   * Limits on the collection's size; inserts past them fail with a
+   * `QUOTA_EXCEEDED` error. Saved with the collection
+   */
+  quota?: QuotaConfig
+  /**
+   * Limits on each point's id and payload; inserts and payload updates
+   * past them fail with an `ID_TOO_LONG` or `PAYLOAD_TOO_LARGE` error.
+   * Saved with the collection
+   */
+  entryLimits?: EntryLimitsConfig
+  /**
+   * Embedding model the vectors come from, e.g. "text-embedding-3-small".
+   * Inserts tagged with a different model are rejected, so outputs of
+   * incompatible models aren't mixed. Can be added to an untagged
+   * collection but not changed. Saved with the collection
+   */
+  modelTag?: string
+  /**
+   * Payload field types, e.g. `{ title: "string", price: "float",
+   * tags: "string[]" }`. Inserts whose payload doesn't conform are
+   * rejected. Saved with the collection
+   */
+  schema?: Record<string, string>
+  /**
+   * How the text of payload fields is split into terms for lexical
+   * matching, keyed by field. Saved with the collection
+   */
+  analyzers?: Record<string, AnalyzerConfig>
+  /**
+   * Build the graph deterministically, so the same inserts in the same
+   * order give an identical index. Saved with the collection
+   */
+  seed?: number
+  /**
+   * Re-insert points in shuffled order (by `seed` if set) when the graph
+   * is rebuilt, for better recall on sorted or clustered data. Saved
+   * with the collection
+   */
+  shuffleOnRebuild?: boolean
+  /**
+   * Scale inserted vectors and queries to unit length, so exact scoring
+   * is a dot product instead of a full cosine. Only set on creation;
+   * saved with the collection
+   */
+  normalize?: boolean
+}
+
+/**
+ * A `createCollection` config naming a template, which it takes every
+ * setting it leaves unset from
+ */
+export interface TemplatedCollectionConfig extends TemplateConfig {
+  template: string
+  path: string
+}
+
+export interface TextToken {
+  term: string
+  /** Start of the source word in the text (JS string index) */
+  start: number
+  /** End of the source word in the text (exclusive) */
+  end: number
+}
+
+/** Run the checks `insertVector` would for this point without inserting it. */
+export interface ThreadPoolConfig {
+  /** Worker threads; defaults to one per CPU */
+  threads?: number
+}
+
+export interface TimedSearchResults {
+  results: Array<SearchResult>
+  /** The budget ran out before k results were found */
+  partial: boolean
+}
+
+export interface TlsConfig {
+  /** PEM file with the certificate chain, leaf first */
+  certPath: string
+  /** PEM file with the private key (PKCS#8, PKCS#1 or SEC1) */
+  keyPath: string
+}
+
+/**
+ * How far the collection in memory has drifted from what's on disk, for
+ * health checks to alert on. Each changed id counts once, by its state
+ * now; changes undone by a rollback still count until the next save.
+ */
+export declare function unsavedChanges(path: string): UnsavedChanges
+
+export interface UnsavedChanges {
+  /** Ids inserted, updated or restored since the last save and live now */
+  inserts: number
+  /** Ids deleted since the last save and still deleted */
+  deletes: number
+  /** Last successful save (ms since epoch), from this process or on disk */
+  lastSavedAt?: number
+}
+
+/**
+ * Merge new fields into the payloads of many points without re-inserting
+ * their vectors, under one lock, then save once. Nothing is changed if any
+ * merged payload fails the schema. Missing and deleted ids are skipped.
+ * Returns the number of points updated.
+ */
+export declare function updatePayloads(path: string, entries: Array<PayloadUpdate>): number
+
+export declare function validatePoint(path: string, id: string, vector: Float32Array | Float64Array | Array<number>, payload?: Record<string, any> | undefined | null, modelTag?: string | undefined | null): PointValidation
+
+/**
+ * Read every vector once and run the sample queries, so the first real
+ * searches after opening don't pay for cold caches. Matters most for
+ * collections opened with `attachReadonly`, whose vectors are paged in
+ * from the segment files on first use.
+ */
+export declare function warmup(path: string, options?: WarmupOptions | undefined | null): WarmupReport
+
+export interface WarmupOptions {
+  /**
+   * Queries like real traffic, each run once to warm the part of the
+   * graph it visits
+   */
+  sampleQueries?: Array<Float32Array>
+  /** Neighbours fetched per sample query, default 10 */
+  k?: number
+}
+
+export interface WarmupReport {
+  /** Graph nodes whose vectors were read */
+  points: number
+  /** Sample queries run */
+  queries: number
+  elapsedMs: number
+}
+
+export interface WriteSummary {
+  /** Points inserted, or saved by `buildIndex` */
+  points: number
+  /** Time spent inserting into the index, or rebuilding it (ms) */
+  indexMs: number
+  /** Time spent saving (ms) */
+  persistMs: number
+  bytesWritten: number
+  /** Sequence number now committed, if anything was saved */
+  committedSeq?: number
+}
@@ -576,9 +576,80 @@ if (!nativeBinding) {
 }
 
 module.exports = nativeBinding
+module.exports.IngestStream = nativeBinding.IngestStream
+module.exports.SearchStream = nativeBinding.SearchStream
+module.exports.add = nativeBinding.add
+module.exports.analyze = nativeBinding.analyze
+module.exports.attachReadonly = nativeBinding.attachReadonly
 module.exports.buildIndex = nativeBinding.buildIndex
+module.exports.changesSince = nativeBinding.changesSince
+module.exports.checkIndex = nativeBinding.checkIndex
+module.exports.clearStaging = nativeBinding.clearStaging
+module.exports.closeCollection = nativeBinding.closeCollection
+module.exports.compact = nativeBinding.compact
+module.exports.cosineSimilarity = nativeBinding.cosineSimilarity
 module.exports.createCollection = nativeBinding.createCollection
+module.exports.createIdSet = nativeBinding.createIdSet
+module.exports.createIngestStream = nativeBinding.createIngestStream
+module.exports.deleteByFilter = nativeBinding.deleteByFilter
 module.exports.deleteVector = nativeBinding.deleteVector
+module.exports.deleteVectorAsync = nativeBinding.deleteVectorAsync
+module.exports.diffCollections = nativeBinding.diffCollections
+module.exports.discover = nativeBinding.discover
+module.exports.dropIdSet = nativeBinding.dropIdSet
+module.exports.exportHnswlib = nativeBinding.exportHnswlib
+module.exports.getAuditLog = nativeBinding.getAuditLog
+module.exports.getConfig = nativeBinding.getConfig
+module.exports.getDefaultSearchOptions = nativeBinding.getDefaultSearchOptions
+module.exports.getOperationLimits = nativeBinding.getOperationLimits
+module.exports.getThreadPool = nativeBinding.getThreadPool
+module.exports.getVectors = nativeBinding.getVectors
+module.exports.health = nativeBinding.health
+module.exports.insertBinary = nativeBinding.insertBinary
+module.exports.insertSet = nativeBinding.insertSet
 module.exports.insertVector = nativeBinding.insertVector
+module.exports.insertVectorAsync = nativeBinding.insertVectorAsync
+module.exports.knnGraph = nativeBinding.knnGraph
+module.exports.listDeleted = nativeBinding.listDeleted
+module.exports.listOperations = nativeBinding.listOperations
+module.exports.mean = nativeBinding.mean
+module.exports.normalize = nativeBinding.normalize
+module.exports.onCapacityAlert = nativeBinding.onCapacityAlert
+module.exports.openMetadata = nativeBinding.openMetadata
+module.exports.operationStatus = nativeBinding.operationStatus
+module.exports.pairwiseSimilarities = nativeBinding.pairwiseSimilarities
+module.exports.payloadValues = nativeBinding.payloadValues
+module.exports.preloadCollections = nativeBinding.preloadCollections
+module.exports.promoteStaging = nativeBinding.promoteStaging
+module.exports.registerTemplate = nativeBinding.registerTemplate
+module.exports.reindex = nativeBinding.reindex
+module.exports.releaseSavepoint = nativeBinding.releaseSavepoint
+module.exports.reloadCollection = nativeBinding.reloadCollection
+module.exports.renameCollection = nativeBinding.renameCollection
+module.exports.repairCollection = nativeBinding.repairCollection
+module.exports.resolveIds = nativeBinding.resolveIds
+module.exports.restoreVector = nativeBinding.restoreVector
+module.exports.retrieve = nativeBinding.retrieve
+module.exports.rollbackTo = nativeBinding.rollbackTo
+module.exports.savepoint = nativeBinding.savepoint
 module.exports.search = nativeBinding.search
+module.exports.searchBinary = nativeBinding.searchBinary
+module.exports.searchSet = nativeBinding.searchSet
+module.exports.searchStream = nativeBinding.searchStream
+module.exports.setAuditActor = nativeBinding.setAuditActor
+module.exports.setAutoCreate = nativeBinding.setAutoCreate
+module.exports.setDefaultSearchOptions = nativeBinding.setDefaultSearchOptions
+module.exports.setOperationLimits = nativeBinding.setOperationLimits
+module.exports.setProjection = nativeBinding.setProjection
+module.exports.setThreadPool = nativeBinding.setThreadPool
+module.exports.setTokenVectors = nativeBinding.setTokenVectors
+module.exports.stageVector = nativeBinding.stageVector
+module.exports.stagingStatus = nativeBinding.stagingStatus
+module.exports.startServer = nativeBinding.startServer
 module.exports.stats = nativeBinding.stats
+module.exports.stopServer = nativeBinding.stopServer
+module.exports.subtract = nativeBinding.subtract
+module.exports.unsavedChanges = nativeBinding.unsavedChanges
+module.exports.updatePayloads = nativeBinding.updatePayloads
+module.exports.validatePoint = nativeBinding.validatePoint
+module.exports.warmup = nativeBinding.warmup
@@ -0,0 +1,101 @@
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi_derive::napi;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Usage levels at which a collection raises capacity alerts
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CapacityThresholds {
+    /// Fractions of the limit, ascending
+    pub levels: Vec<f64>,
+    /// Estimated memory (bytes) the levels also apply to, if any
+    pub memory_limit: Option<u64>,
+}
+
+impl Default for CapacityThresholds {
+    fn default() -> Self {
+        CapacityThresholds {
+            levels: vec![0.8, 0.95],
+            memory_limit: None,
+        }
+    }
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct CapacityAlert {
+    /// "elements" for graph nodes against `maxElements`, "memory" for the
+    /// estimated memory against `memoryLimitBytes`
+    pub kind: String,
+    /// The level reached, as a fraction of the limit
+    pub level: f64,
+    /// Current usage as a fraction of the limit
+    pub usage: f64,
+    pub limit: f64,
+}
+
+impl CapacityThresholds {
+    /// An alert for every level that `nodes` of `max_elements`, or `memory`
+    /// bytes, have reached.
+    pub fn check(&self, nodes: usize, max_elements: usize, memory: u64) -> Vec<CapacityAlert> {
+        let mut alerts = self.reached("elements", nodes as f64, max_elements as f64);
+        if let Some(limit) = self.memory_limit {
+            alerts.extend(self.reached("memory", memory as f64, limit as f64));
+        }
+        alerts
+    }
+
+    fn reached(&self, kind: &str, used: f64, limit: f64) -> Vec<CapacityAlert> {
+        let usage = if limit > 0.0 { used / limit } else { 1.0 };
+        self.levels
+            .iter()
+            .filter(|&&level| usage >= level)
+            .map(|&level| CapacityAlert {
+                kind: kind.to_string(),
+                level,
+                usage,
+                limit,
+            })
+            .collect()
+    }
+}
+
+/// Called on the JS thread without keeping the process alive
+pub type AlertCallback =
+    ThreadsafeFunction<CapacityAlert, Unknown<'static>, CapacityAlert, Status, false, true>;
+
+/// Callbacks registered with `onCapacityAlert`, by collection key
+static CALLBACKS: Lazy<Mutex<HashMap<String, AlertCallback>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn set_callback(key: String, callback: Option<AlertCallback>) {
+    let mut callbacks = CALLBACKS.lock().unwrap_or_else(|e| e.into_inner());
+    match callback {
+        Some(callback) => callbacks.insert(key, callback),
+        None => callbacks.remove(&key),
+    };
+}
+
+/// Move the collection's callback, if any, to its key after a rename.
+pub fn rename_callback(from: &str, to: String) {
+    let mut callbacks = CALLBACKS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(callback) = callbacks.remove(from) {
+        callbacks.insert(to, callback);
+    }
+}
+
+/// Queue a call of the collection's callback, if any, for each alert.
+pub fn notify(key: &str, alerts: Vec<CapacityAlert>) {
+    if alerts.is_empty() {
+        return;
+    }
+    let callbacks = CALLBACKS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(callback) = callbacks.get(key) {
+        for alert in alerts {
+            callback.call(alert, ThreadsafeFunctionCallMode::NonBlocking);
+        }
+    }
+}
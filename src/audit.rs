@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+
+const AUDIT_FILE: &str = "audit.jsonl";
+
+/// One mutating operation, stored as a line of JSON in the audit log
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    /// When the operation ran (ms since epoch)
+    pub at: i64,
+    pub op: String,
+    /// Point ids the operation changed; empty for whole-collection operations
+    pub ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+}
+
+/// Append `entry` to the audit log in collection directory `dir`. The file
+/// is only ever appended to, so earlier entries are never rewritten.
+pub fn append(dir: &Path, entry: &Entry) -> Result<(), String> {
+    let mut line =
+        serde_json::to_string(entry).map_err(|e| format!("Failed to serialize audit entry: {}", e))?;
+    line.push('\n');
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(AUDIT_FILE))
+        .map_err(|e| format!("Failed to open audit log: {}", e))?;
+    file.write_all(line.as_bytes())
+        .map_err(|e| format!("Failed to write audit log: {}", e))
+}
+
+/// Entries recorded at or after `since` (ms since epoch), oldest first.
+pub fn read(dir: &Path, since: Option<i64>) -> Result<Vec<Entry>, String> {
+    let text = match fs::read_to_string(dir.join(AUDIT_FILE)) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read audit log: {}", e)),
+    };
+
+    let lines: Vec<&str> = text.lines().filter(|line| !line.trim().is_empty()).collect();
+    let mut entries = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let entry: Entry = match serde_json::from_str(line) {
+            Ok(entry) => entry,
+            // An append cut short by a crash
+            Err(_) if i == lines.len() - 1 && !text.ends_with('\n') => break,
+            Err(e) => return Err(format!("Failed to parse audit log: {}", e)),
+        };
+        if since.is_none_or(|since| entry.at >= since) {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
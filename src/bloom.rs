@@ -0,0 +1,64 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// Bits per id at capacity; with `HASHES` probes this gives about 1% false
+/// positives
+const BITS_PER_ID: usize = 10;
+const HASHES: u64 = 7;
+const MIN_CAPACITY: usize = 1024;
+
+/// Bloom filter over point ids, checked before the id map so that lookups
+/// of ids that were never inserted mostly skip it. Ids can't be removed, so
+/// deleted ids keep matching until the filter is rebuilt.
+pub struct IdFilter {
+    bits: Vec<u64>,
+    len: usize,
+    capacity: usize,
+}
+
+impl IdFilter {
+    /// An empty filter sized for `capacity` ids.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let capacity = capacity.max(MIN_CAPACITY);
+        IdFilter {
+            bits: vec![0; (capacity * BITS_PER_ID).div_ceil(64)],
+            len: 0,
+            capacity,
+        }
+    }
+
+    /// Filter holding `ids`, with room for as many again.
+    pub fn from_ids<'a>(ids: impl ExactSizeIterator<Item = &'a str>) -> Self {
+        let mut filter = IdFilter::with_capacity(ids.len() * 2);
+        for id in ids {
+            filter.insert(id);
+        }
+        filter
+    }
+
+    pub fn insert(&mut self, id: &str) {
+        for bit in self.probes(id) {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+        self.len += 1;
+    }
+
+    /// False only if `id` was never inserted.
+    pub fn may_contain(&self, id: &str) -> bool {
+        self.probes(id).all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Whether more inserts would push the false positive rate past target.
+    pub fn is_full(&self) -> bool {
+        self.len >= self.capacity
+    }
+
+    /// Bit positions for `id`, by double hashing one 64-bit hash
+    fn probes(&self, id: &str) -> impl Iterator<Item = usize> {
+        let mut hasher = DefaultHasher::new();
+        id.hash(&mut hasher);
+        let hash = hasher.finish();
+        let (first, step) = (hash & 0xffff_ffff, (hash >> 32) | 1);
+        let bits = (self.bits.len() * 64) as u64;
+        (0..HASHES).map(move |i| (first.wrapping_add(i.wrapping_mul(step)) % bits) as usize)
+    }
+}
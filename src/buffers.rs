@@ -0,0 +1,46 @@
+use std::cell::Cell;
+use std::thread::LocalKey;
+
+/// Buffers larger than this many entries are freed instead of kept, so one
+/// search with a huge k doesn't pin its memory on the thread
+const MAX_KEPT: usize = 1 << 16;
+
+thread_local! {
+    /// (internal id, similarity) candidates of the graph and exact searches
+    static CANDIDATES: Cell<Vec<(usize, f32)>> = const { Cell::new(Vec::new()) };
+    /// (internal id, score) hits that `search` rescores and filters
+    static SCORED: Cell<Vec<(usize, f64)>> = const { Cell::new(Vec::new()) };
+}
+
+/// An empty candidate buffer, reusing the one the last search on this
+/// thread gave back, so high query rates don't allocate one per call
+pub fn candidates() -> Vec<(usize, f32)> {
+    take(&CANDIDATES)
+}
+
+/// Keep `buffer` for the next search on this thread
+pub fn recycle_candidates(buffer: Vec<(usize, f32)>) {
+    give_back(&CANDIDATES, buffer);
+}
+
+/// Like `candidates`, for scored hits
+pub fn scored() -> Vec<(usize, f64)> {
+    take(&SCORED)
+}
+
+/// Like `recycle_candidates`, for scored hits
+pub fn recycle_scored(buffer: Vec<(usize, f64)>) {
+    give_back(&SCORED, buffer);
+}
+
+fn take<T>(key: &'static LocalKey<Cell<Vec<T>>>) -> Vec<T> {
+    let mut buffer = key.take();
+    buffer.clear();
+    buffer
+}
+
+fn give_back<T>(key: &'static LocalKey<Cell<Vec<T>>>, buffer: Vec<T>) {
+    if buffer.capacity() <= MAX_KEPT {
+        key.set(buffer);
+    }
+}
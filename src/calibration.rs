@@ -0,0 +1,79 @@
+/// Maps raw similarity scores onto [0, 1] so scores from different
+/// collections can be merged and compared.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Calibration {
+    /// Cosine similarity's [-1, 1] range mapped linearly
+    Unit,
+    /// Each score's z-score among the query's results, through the normal CDF
+    Probit,
+    /// `[min, max]` mapped linearly, clamped outside it
+    Range { min: f64, max: f64 },
+}
+
+impl Calibration {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "unit" => Ok(Calibration::Unit),
+            "probit" => Ok(Calibration::Probit),
+            other => Err(format!(
+                "Invalid calibration '{}'; expected 'unit', 'probit' or {{ min, max }}",
+                other
+            )),
+        }
+    }
+
+    pub fn range(min: f64, max: f64) -> Result<Self, String> {
+        if !(min.is_finite() && max.is_finite() && min < max) {
+            return Err(format!(
+                "Calibration range needs finite min < max, got [{}, {}]",
+                min, max
+            ));
+        }
+        Ok(Calibration::Range { min, max })
+    }
+
+    /// Calibrate the scores of one query's results in place.
+    pub fn apply(&self, scores: &mut [f64]) {
+        match *self {
+            Calibration::Unit => {
+                for score in scores {
+                    *score = ((*score + 1.0) / 2.0).clamp(0.0, 1.0);
+                }
+            }
+            Calibration::Range { min, max } => {
+                for score in scores {
+                    *score = ((*score - min) / (max - min)).clamp(0.0, 1.0);
+                }
+            }
+            Calibration::Probit => {
+                let n = scores.len() as f64;
+                let mean = scores.iter().sum::<f64>() / n;
+                let variance = scores.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n;
+                let deviation = variance.sqrt();
+                for score in scores {
+                    *score = if deviation > 0.0 {
+                        normal_cdf((*score - mean) / deviation)
+                    } else {
+                        0.5
+                    };
+                }
+            }
+        }
+    }
+}
+
+/// Standard normal CDF via the Abramowitz and Stegun 7.1.26 approximation of
+/// erf, accurate to about 1e-7.
+fn normal_cdf(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.327_591_1 * x);
+    let poly = t
+        * (0.254_829_592
+            + t * (-0.284_496_736 + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let erf = 1.0 - poly * (-x * x).exp();
+    if z >= 0.0 {
+        0.5 * (1.0 + erf)
+    } else {
+        0.5 * (1.0 - erf)
+    }
+}
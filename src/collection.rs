@@ -1,125 +1,2425 @@
 use hnsw_rs::prelude::*;
-use std::collections::{HashMap, HashSet};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::File;
+use std::hash::Hash;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::alerts::{CapacityAlert, CapacityThresholds};
+use crate::bloom::IdFilter;
+use crate::hyperplane::HyperplaneIndex;
+use crate::id_set::IdSet;
+use crate::ivf::{IvfIndex, Probe};
+use crate::mapped_graph::MappedGraph;
+use crate::minhash::LshIndex;
+use crate::persistence::Durability;
+use crate::projection::InputProjection;
+use crate::schema::{payload_errors, Payload, Schema};
+use crate::text::Analyzers;
+use crate::vector_file::VectorFile;
 
 /// HNSW parameters
-const MAX_NB_CONNECTION: usize = 16; // M parameter
-const MAX_ELEMENTS: usize = 100_000;
+pub const MAX_NB_CONNECTION: usize = 16; // M parameter
+pub const MAX_ELEMENTS: usize = 100_000;
 const MAX_LAYER: usize = 16;
-const EF_CONSTRUCTION: usize = 200;
+pub const EF_CONSTRUCTION: usize = 200;
+
+/// External point id. `Collection::intern` makes the id map and tombstones
+/// share one allocation per id rather than each holding a copy.
+pub type Id = Arc<str>;
+
+pub const DEFAULT_METRIC: &str = "cosine";
+pub const DEFAULT_INDEX_TYPE: &str = "hnsw";
+/// Cosine collections indexed by k-means lists instead of a graph
+pub const IVF_INDEX_TYPE: &str = "ivf";
+/// Cosine collections bucketed by random-hyperplane LSH instead of a graph
+pub const LSH_INDEX_TYPE: &str = "lsh";
+/// Collections of packed bit vectors, compared by Hamming distance
+pub const HAMMING_METRIC: &str = "hamming";
+/// Index type of hamming collections: no graph, every search scans
+pub const FLAT_INDEX_TYPE: &str = "flat";
+/// Collections of token sets, compared by Jaccard similarity
+pub const JACCARD_METRIC: &str = "jaccard";
+/// Index type of jaccard collections: minhash signatures banded into LSH
+/// buckets pick the candidates, whose similarity is then computed exactly
+pub const MINHASH_INDEX_TYPE: &str = "minhash";
+
+/// What a collection's points are, by its metric
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PointKind {
+    /// Float vectors in the HNSW graph
+    Dense,
+    /// Packed bit vectors of hamming collections
+    Binary,
+    /// Token sets of jaccard collections
+    Set,
+}
+
+/// Per-collection index construction parameters
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HnswParams {
+    pub max_nb_connection: usize,
+    pub max_elements: usize,
+    pub ef_construction: usize,
+    /// Set for collections whose graph must be reproducible. Batches are
+    /// then inserted one point at a time, in order, instead of in parallel.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Rebuild the graph in shuffled order (by `seed` if set) instead of
+    /// insertion order, which builds poor graphs for sorted or clustered data
+    #[serde(default)]
+    pub shuffle_on_rebuild: bool,
+    /// Lists of IVF collections; √n of the points at training when unset
+    #[serde(default)]
+    pub ivf_lists: Option<usize>,
+    /// Subvectors per product-quantizer code of IVF-PQ collections, which
+    /// keep full vectors on disk; unset for plain IVF
+    #[serde(default)]
+    pub pq_subvectors: Option<usize>,
+    /// Hash tables and hyperplanes per table of LSH collections
+    #[serde(default)]
+    pub lsh_tables: Option<usize>,
+    #[serde(default)]
+    pub lsh_bits: Option<usize>,
+}
+
+impl Default for HnswParams {
+    fn default() -> Self {
+        HnswParams {
+            max_nb_connection: MAX_NB_CONNECTION,
+            max_elements: MAX_ELEMENTS,
+            ef_construction: EF_CONSTRUCTION,
+            seed: None,
+            shuffle_on_rebuild: false,
+            ivf_lists: None,
+            pq_subvectors: None,
+            lsh_tables: None,
+            lsh_bits: None,
+        }
+    }
+}
+
+impl HnswParams {
+    fn new_hnsw(&self) -> Hnsw<'static, f32, DistCosine> {
+        Hnsw::<f32, DistCosine>::new(
+            self.max_nb_connection,
+            self.max_elements,
+            MAX_LAYER,
+            self.ef_construction,
+            DistCosine,
+        )
+    }
+}
+
+/// Restricts which points a search or bulk delete considers
+#[derive(Clone, Debug, Default)]
+pub struct PointFilter {
+    /// Inclusive lower bound on insertion time (ms since epoch)
+    pub inserted_after: Option<i64>,
+    /// Exclusive upper bound on insertion time (ms since epoch)
+    pub inserted_before: Option<i64>,
+    /// Only these internal ids
+    pub allowed: Option<Arc<IdSet>>,
+    /// None of these internal ids
+    pub denied: Option<Arc<IdSet>>,
+}
+
+impl PointFilter {
+    pub fn is_empty(&self) -> bool {
+        self.inserted_after.is_none()
+            && self.inserted_before.is_none()
+            && self.allowed.is_none()
+            && self.denied.is_none()
+    }
+
+    pub fn matches(&self, internal_id: usize, inserted_at: i64) -> bool {
+        self.inserted_after.is_none_or(|after| inserted_at >= after)
+            && self.inserted_before.is_none_or(|before| inserted_at < before)
+            && self.allowed.as_ref().is_none_or(|allowed| allowed.contains(internal_id))
+            && self.denied.as_ref().is_none_or(|denied| !denied.contains(internal_id))
+    }
+}
+
+/// Thresholds at which the maintenance thread compacts a collection
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CompactionPolicy {
+    /// Fraction of graph nodes that are dead (purgeable tombstones or
+    /// replaced by upserts)
+    pub tombstone_ratio: f64,
+    /// Minimum number of dead graph nodes
+    pub min_deleted: usize,
+}
+
+impl Default for CompactionPolicy {
+    fn default() -> Self {
+        CompactionPolicy {
+            tombstone_ratio: 0.2,
+            min_deleted: 1000,
+        }
+    }
+}
+
+/// Limits on a collection's size, checked before every insert
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Quota {
+    /// Live points
+    pub max_points: Option<usize>,
+    /// Estimated memory, as in `estimated_memory`
+    pub max_bytes: Option<u64>,
+}
+
+/// Limits on each point's id and payload, checked on insert so one producer
+/// can't bloat the saved metadata
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EntryLimits {
+    /// Id length in UTF-8 bytes
+    pub max_id_length: Option<usize>,
+    /// Payload size as the JSON it is saved as
+    pub max_payload_bytes: Option<usize>,
+}
+
+/// Number of saves remembered in `Collection::save_history`
+pub const SAVE_HISTORY_LEN: usize = 100;
+
+/// Collection size at one save
+#[derive(Clone, Copy, Debug)]
+pub struct SaveRecord {
+    pub saved_at: i64,
+    pub count: usize,
+    pub deleted: usize,
+}
+
+/// Counts of id lookups made through `Collection::internal_id`
+#[derive(Default)]
+pub struct IdLookups {
+    pub hits: AtomicU64,
+    /// Includes the ones answered by the id filter
+    pub misses: AtomicU64,
+    /// Misses the id filter answered without touching the id map
+    pub filtered: AtomicU64,
+}
+
+/// Points to build a graph from off the collection lock, after a lazy open
+pub struct PendingBuild {
+    ticket: u64,
+    params: HnswParams,
+    /// (internal id, vector) in insertion order
+    points: Vec<(usize, Vec<f32>)>,
+}
+
+impl PendingBuild {
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// The slow part of the build, run without holding the collection lock.
+    /// `progress` is told every so often how many points are in.
+    pub fn build(&self, progress: impl Fn(usize)) -> Hnsw<'static, f32, DistCosine> {
+        let hnsw = self.params.new_hnsw();
+        for (done, (internal_id, vector)) in self.points.iter().enumerate() {
+            hnsw.insert((vector.as_slice(), *internal_id));
+            if (done + 1) % 1024 == 0 {
+                progress(done + 1);
+            }
+        }
+        progress(self.points.len());
+        hnsw
+    }
+}
+
+static BUILD_TICKETS: AtomicU64 = AtomicU64::new(0);
+
+/// Source of `Collection::handle_epoch`, shared so a collection opened
+/// again never repeats the epochs of its earlier instance
+static HANDLE_EPOCHS: AtomicU32 = AtomicU32::new(0);
+
+/// Vectors from the embedding model a collection is moving to, written
+/// next to the live ones while it is re-embedded. Searches don't see them
+/// until `Collection::promote_staging` swaps them in.
+#[derive(Clone, Debug, Default)]
+pub struct Staging {
+    /// Set by the first staged vector
+    pub dimensions: usize,
+    /// Becomes the collection's `model_tag` on promotion
+    pub model_tag: Option<String>,
+    /// Keyed by id, since a rebuild can move a point to another internal id
+    pub vectors: HashMap<Id, Vec<f32>>,
+}
+
+/// Undo log of `Collection::savepoint`: the state of each internal id and
+/// id as it was before the first change to it after the savepoint, up to
+/// the next savepoint, which logs the changes after it. Nothing is copied
+/// up front, so a savepoint costs only what is changed while it is the
+/// latest. The graph isn't logged; a rollback rebuilds it.
+#[derive(Default)]
+pub struct Savepoint {
+    slots: HashMap<usize, SlotState>,
+    ids: HashMap<Id, IdState>,
+    /// Dimensions and model tag of the staging area, if there was one
+    staging: Option<(usize, Option<String>)>,
+    token_dimensions: Option<usize>,
+    upserts: u64,
+    reused_ids: u64,
+    seq: u64,
+}
+
+impl Savepoint {
+    /// Take over the entries of a later savepoint's log for what this one
+    /// hasn't logged, when that savepoint goes
+    fn absorb(&mut self, later: Savepoint) {
+        for (internal_id, state) in later.slots {
+            self.slots.entry(internal_id).or_insert(state);
+        }
+        for (id, state) in later.ids {
+            self.ids.entry(id).or_insert(state);
+        }
+    }
+}
+
+/// What an internal id held when a savepoint logged it
+struct SlotState {
+    id: Option<Id>,
+    /// Read back if it was spilled, so the vector file can be replaced
+    vector: Option<Vec<f32>>,
+    norm: Option<f32>,
+    code: Option<Vec<u8>>,
+    set: Option<Vec<u32>>,
+    tokens: Option<Vec<f32>>,
+    inserted_at: Option<i64>,
+    payload: Option<Payload>,
+}
+
+/// What an id was when a savepoint logged it
+struct IdState {
+    internal_id: Option<usize>,
+    deleted: bool,
+    deleted_at: Option<i64>,
+    staged: Option<Vec<f32>>,
+}
+
+/// Inconsistencies `Collection::repair` found, by kind
+#[derive(Debug, Default)]
+pub struct RepairReport {
+    /// Ids whose internal id has no vector; dropped, since they can't be served
+    pub missing_vectors: Vec<Id>,
+    /// Ids mapped to an internal id another id also maps to; dropped
+    pub duplicate_ids: Vec<Id>,
+    /// Internal ids with a vector but no id mapped to them
+    pub orphaned_vectors: Vec<usize>,
+    /// Tombstones of ids that aren't mapped; dropped
+    pub stale_tombstones: Vec<Id>,
+    /// Reverse map entries that disagreed with the id map; rebuilt
+    pub reverse_map_fixes: usize,
+    /// Timestamps, payloads and deletion times left without a point; dropped
+    pub stray_entries: usize,
+    /// Whether anything was modified; kept orphans don't count
+    pub changed: bool,
+}
+
+/// How `search_vectors` widens the graph search when tombstones crowd live
+/// points out of the first candidate list
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// Extra searches allowed after the first one
+    pub max_retries: usize,
+    /// Multiplier applied to the candidate count on each retry
+    pub growth: usize,
+    /// No retry starts after this time
+    pub deadline: Option<Instant>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 8,
+            growth: 2,
+            deadline: None,
+        }
+    }
+}
+
+/// Result of `search_vectors`
+#[derive(Debug, Default)]
+pub struct Neighbours {
+    /// (internal id, similarity) pairs, best first. Taken from
+    /// `buffers::candidates`; give it back with `recycle_candidates`
+    pub hits: Vec<(usize, f32)>,
+    /// The deadline passed before the search found k live points or ran
+    /// out of candidates
+    pub partial: bool,
+}
+
+/// Result of `Collection::check_index`
+#[derive(Debug, Default)]
+pub struct IndexCheck {
+    pub nodes: usize,
+    /// Ids, live or deleted, with no graph node holding their vector
+    pub missing_from_graph: Vec<String>,
+    /// Graph nodes matching no stored point: leftovers of upserts and purges,
+    /// which `compact` drops
+    pub dead_nodes: usize,
+    pub sampled: usize,
+    /// Sampled live ids a search for their own vector didn't return as an
+    /// exact match
+    pub sample_misses: Vec<String>,
+}
+
+/// Search options applied when a query doesn't set them
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchDefaults {
+    pub ef_search: Option<usize>,
+    pub score_threshold: Option<f64>,
+    pub include_payload: Option<bool>,
+}
+
+pub fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Euclidean length of a vector
+pub fn norm(vector: &[f32]) -> f32 {
+    vector.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+/// `vector` as stored, with its norm. A borrowed vector is copied and
+/// measured in the same pass.
+fn stored_vector(vector: Cow<'_, [f32]>) -> (Vec<f32>, f32) {
+    match vector {
+        Cow::Owned(vector) => {
+            let norm = norm(&vector);
+            (vector, norm)
+        }
+        Cow::Borrowed(vector) => {
+            let mut stored = Vec::with_capacity(vector.len());
+            let mut squares = 0.0;
+            for &x in vector {
+                squares += x * x;
+                stored.push(x);
+            }
+            (stored, f32::sqrt(squares))
+        }
+    }
+}
+
+/// Set `map[key]` to `value`, or remove it for None
+fn set_entry<K: Hash + Eq, V>(map: &mut HashMap<K, V>, key: K, value: Option<V>) {
+    match value {
+        Some(value) => map.insert(key, value),
+        None => map.remove(&key),
+    };
+}
+
+/// Id of each internal id, in a table indexed by internal id. Saved with
+/// the collection, so loads read it instead of deriving it from the id map.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ReverseMap(Vec<Option<Id>>);
+
+impl ReverseMap {
+    /// The reverse of `id_map`
+    pub fn derive(id_map: &HashMap<Id, usize>) -> Self {
+        let mut map = ReverseMap::default();
+        for (id, &internal_id) in id_map {
+            map.insert(internal_id, id.clone());
+        }
+        map
+    }
+
+    pub fn get(&self, internal_id: &usize) -> Option<&Id> {
+        self.0.get(*internal_id)?.as_ref()
+    }
+
+    pub fn contains_key(&self, internal_id: &usize) -> bool {
+        self.get(internal_id).is_some()
+    }
+
+    pub fn insert(&mut self, internal_id: usize, id: Id) -> Option<Id> {
+        if internal_id >= self.0.len() {
+            self.0.resize(internal_id + 1, None);
+        }
+        self.0[internal_id].replace(id)
+    }
+
+    pub fn remove(&mut self, internal_id: &usize) -> Option<Id> {
+        let id = self.0.get_mut(*internal_id)?.take();
+        while self.0.last().is_some_and(Option::is_none) {
+            self.0.pop();
+        }
+        id
+    }
+
+    /// Set `internal_id`'s id, or remove it for None
+    pub fn set(&mut self, internal_id: usize, id: Option<Id>) {
+        match id {
+            Some(id) => self.insert(internal_id, id),
+            None => self.remove(&internal_id),
+        };
+    }
+
+    /// Each internal id with its id, in internal id order
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Id)> {
+        self.0.iter().enumerate().filter_map(|(internal_id, id)| Some((internal_id, id.as_ref()?)))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = usize> + '_ {
+        self.iter().map(|(internal_id, _)| internal_id)
+    }
+}
+
+impl std::ops::Index<&usize> for ReverseMap {
+    type Output = Id;
+
+    fn index(&self, internal_id: &usize) -> &Id {
+        self.get(internal_id).expect("internal id has no id")
+    }
+}
 
 pub struct Collection {
     pub hnsw: Hnsw<'static, f32, DistCosine>,
-    pub id_map: HashMap<String, usize>,
-    pub reverse_map: HashMap<usize, String>,
-    pub deleted_ids: HashSet<String>,
+    pub id_map: HashMap<Id, usize>,
+    pub reverse_map: ReverseMap,
+    pub deleted_ids: HashSet<Id>,
+    /// Deletion time (ms since epoch) of each tombstone
+    pub deleted_at: HashMap<Id, i64>,
+    /// Tombstones younger than this survive builds and can be restored
+    pub tombstone_retention_ms: Option<i64>,
     pub next_id: usize,
+    /// Internal ids below `next_id` that no graph node uses, lowest last.
+    /// Inserts take these before growing `next_id`.
+    pub free_ids: Vec<usize>,
     pub dimensions: usize,
+    pub metric: String,
+    pub index_type: String,
     pub path: PathBuf,
     pub dirty: bool,
+    /// Vectors keyed by internal ID. IVF-PQ collections move them to
+    /// `spilled` on every save.
     pub vectors: HashMap<usize, Vec<f32>>,
+    /// On-disk vectors of IVF-PQ collections
+    pub spilled: Option<VectorFile>,
+    /// Euclidean norm of every stored float vector, in memory or spilled,
+    /// so exact scoring only computes the query's. Saved in a sidecar.
+    pub norms: HashMap<usize, f32>,
+    /// Packed bit vectors of hamming collections, keyed by internal ID
+    pub codes: HashMap<usize, Vec<u8>>,
+    /// Sorted token sets of jaccard collections, keyed by internal ID
+    pub sets: HashMap<usize, Vec<u32>>,
+    /// Per-token embeddings for late-interaction rescoring, rows of
+    /// `token_dimensions` values laid end to end, keyed by internal ID
+    pub token_vectors: HashMap<usize, Vec<f32>>,
+    /// Length of every token vector; set by the first matrix stored
+    pub token_dimensions: Option<usize>,
+    /// LSH buckets over `sets`, rebuilt on load
+    pub lsh: LshIndex,
+    /// Lists of IVF collections, which leave `hnsw` empty. Trained
+    /// wherever other collections rebuild their graph.
+    pub ivf: Option<IvfIndex>,
+    /// Buckets of LSH collections, which also leave `hnsw` empty
+    pub hyperplanes: Option<HyperplaneIndex>,
+    /// Insertion time (ms since epoch) keyed by internal ID
+    pub inserted_at: HashMap<usize, i64>,
+    /// Payloads keyed by internal ID; points inserted without one have none
+    pub payloads: HashMap<usize, Payload>,
+    /// Declared payload field types checked on insert
+    pub schema: Option<Schema>,
+    /// Text analysis settings per payload field
+    pub analyzers: Analyzers,
+    pub search_defaults: SearchDefaults,
+    pub params: HnswParams,
+    /// Opened as a reader of a directory owned by another writer
+    pub read_only: bool,
+    /// Held open to keep the directory's single-writer lock
+    pub lock: Option<File>,
+    /// Dump the graph as mmap-able segments on every save
+    pub share_segments: bool,
+    /// Reject inserts of ids that are already live instead of upserting
+    pub strict_insert: bool,
+    /// Record mutating operations in the collection's audit log
+    pub audit_log: bool,
+    /// Who audit entries written by this process are attributed to
+    pub audit_actor: Option<String>,
+    /// How far saves are flushed towards disk
+    pub durability: Durability,
+    /// Applied to inserted vectors and queries before anything else
+    pub projection: Option<InputProjection>,
+    /// Scale inserted vectors and queries to unit length after projecting
+    /// them, so exact scoring is a plain dot product
+    pub normalize: bool,
+    /// Bloom filter over the id map's keys, when enabled
+    pub id_filter: Option<IdFilter>,
+    pub id_lookups: IdLookups,
+    /// Sets from `createIdSet`, by handle. Dropped by every rebuild, which
+    /// can give their internal ids to other points.
+    pub id_sets: HashMap<u32, Arc<IdSet>>,
+    /// Handle of the next id set; never reused, so a dropped set's handle
+    /// can't name a new one
+    pub next_id_set: u32,
+    /// Compact automatically when these thresholds are crossed
+    pub auto_compaction: Option<CompactionPolicy>,
+    pub capacity_thresholds: CapacityThresholds,
+    pub quota: Option<Quota>,
+    pub entry_limits: EntryLimits,
+    /// Embedding model the vectors come from; inserts tagged with another
+    /// model are rejected
+    pub model_tag: Option<String>,
+    /// Vectors staged for a model migration, if any
+    pub staging: Option<Staging>,
+    /// Kind and level of each capacity alert raised and not yet cleared
+    pub raised_alerts: Vec<(String, f64)>,
+    /// Time of the last successful save (ms since epoch)
+    pub last_saved_at: Option<i64>,
+    /// Most recent failure and when it happened (ms since epoch)
+    pub last_error: Option<(i64, String)>,
+    /// Sizes at the most recent saves in this process, oldest first
+    pub save_history: VecDeque<SaveRecord>,
+    /// Inserts that replaced a live point with the same id
+    pub upserts: u64,
+    /// Inserts that took an internal id from `free_ids`
+    pub reused_ids: u64,
+    /// Sequence number of the latest change; every change that would be
+    /// saved bumps it, and it never goes back, not even on rollback
+    pub seq: u64,
+    /// `seq` as of the last save. Changes after it are lost on a crash and
+    /// their numbers handed out again.
+    pub committed_seq: u64,
+    /// Sequence number of each id's latest insert, delete or payload update
+    pub change_seqs: HashMap<Id, u64>,
+    /// Highest sequence number whose record left `change_seqs` when its
+    /// tombstone was purged; `changes_since` can't look back past it
+    pub purged_seq: u64,
+    /// Basename of the current segment dump, if any
+    pub segments: Option<String>,
+    /// Named savepoints of this process, oldest first
+    pub savepoints: Vec<(String, Savepoint)>,
+    /// Ticket of the background build a lazy open started. Until it lands,
+    /// `hnsw` is empty and searches scan the vectors instead.
+    pub pending_build: Option<u64>,
+    /// Stamped on the handles searches return. Moves on with every rebuild,
+    /// before which no internal id can change hands.
+    pub handle_epoch: u32,
+    /// Epoch this instance of the collection started at; handles from
+    /// before it are stale
+    pub first_handle_epoch: u32,
+    /// Epoch at which each internal id last changed hands. Handles stamped
+    /// earlier name the point it held before.
+    pub reassigned_at: HashMap<usize, u32>,
+    /// The graph of a collection attached read-only, over the mmapped
+    /// segments. Searches use it instead of `hnsw`, and vectors are read
+    /// from it, since attaching leaves `vectors` empty.
+    pub mapped_graph: Option<MappedGraph>,
 }
 
 impl Collection {
-    pub fn new(path: PathBuf, dimensions: usize) -> Self {
-        let hnsw = Hnsw::<f32, DistCosine>::new(
-            MAX_NB_CONNECTION,
-            MAX_ELEMENTS,
-            MAX_LAYER,
-            EF_CONSTRUCTION,
-            DistCosine,
-        );
-
+    pub fn new(path: PathBuf, dimensions: usize, params: HnswParams) -> Self {
+        let handle_epoch = HANDLE_EPOCHS.fetch_add(1, Ordering::Relaxed);
         Collection {
-            hnsw,
+            hnsw: params.new_hnsw(),
             id_map: HashMap::new(),
-            reverse_map: HashMap::new(),
+            reverse_map: ReverseMap::default(),
             deleted_ids: HashSet::new(),
+            deleted_at: HashMap::new(),
+            tombstone_retention_ms: None,
             next_id: 0,
+            free_ids: Vec::new(),
             dimensions,
+            metric: DEFAULT_METRIC.to_string(),
+            index_type: DEFAULT_INDEX_TYPE.to_string(),
             path,
             dirty: false,
             vectors: HashMap::new(),
+            spilled: None,
+            norms: HashMap::new(),
+            codes: HashMap::new(),
+            sets: HashMap::new(),
+            token_vectors: HashMap::new(),
+            token_dimensions: None,
+            lsh: LshIndex::default(),
+            ivf: None,
+            hyperplanes: None,
+            inserted_at: HashMap::new(),
+            payloads: HashMap::new(),
+            schema: None,
+            analyzers: Analyzers::new(),
+            search_defaults: SearchDefaults::default(),
+            params,
+            read_only: false,
+            lock: None,
+            share_segments: false,
+            strict_insert: false,
+            audit_log: false,
+            audit_actor: None,
+            durability: Durability::default(),
+            projection: None,
+            normalize: false,
+            id_filter: None,
+            id_lookups: IdLookups::default(),
+            id_sets: HashMap::new(),
+            next_id_set: 0,
+            auto_compaction: None,
+            quota: None,
+            entry_limits: EntryLimits::default(),
+            model_tag: None,
+            staging: None,
+            capacity_thresholds: CapacityThresholds::default(),
+            raised_alerts: Vec::new(),
+            last_saved_at: None,
+            last_error: None,
+            save_history: VecDeque::new(),
+            upserts: 0,
+            reused_ids: 0,
+            seq: 0,
+            committed_seq: 0,
+            change_seqs: HashMap::new(),
+            purged_seq: 0,
+            segments: None,
+            savepoints: Vec::new(),
+            pending_build: None,
+            handle_epoch,
+            first_handle_epoch: handle_epoch,
+            reassigned_at: HashMap::new(),
+            mapped_graph: None,
         }
     }
 
-    /// Rebuild HNSW index from stored vectors (excluding deleted).
-    /// Used after loading from persistence or after deletions.
+    /// Rebuild HNSW index from stored vectors.
+    /// Used after loading from persistence or after deletions. Tombstones
+    /// still within retention stay in the graph so they can be restored;
+    /// searches skip them.
     pub fn rebuild_from_vectors(&mut self) {
-        self.hnsw = Hnsw::<f32, DistCosine>::new(
-            MAX_NB_CONNECTION,
-            MAX_ELEMENTS,
-            MAX_LAYER,
-            EF_CONSTRUCTION,
-            DistCosine,
+        if !self.quantized() {
+            self.unspill();
+        }
+        if self.index_type == IVF_INDEX_TYPE {
+            self.train_ivf();
+            return;
+        }
+        if self.index_type == LSH_INDEX_TYPE {
+            self.hash_hyperplanes();
+            return;
+        }
+        let hnsw = self.params.new_hnsw();
+        for internal_id in self.rebuild_order() {
+            hnsw.insert((self.vectors[&internal_id].as_slice(), internal_id));
+        }
+
+        // Swap in only once the new graph is complete
+        self.hnsw = hnsw;
+        self.ivf = None;
+        self.hyperplanes = None;
+        self.pending_build = None;
+        self.reclaim_ids();
+    }
+
+    /// Retrain the IVF centroids on the live points and refile them, in
+    /// place of a graph rebuild.
+    fn train_ivf(&mut self) {
+        let ivf = IvfIndex::train(
+            &self.rebuild_order(),
+            |internal_id| self.vector(internal_id),
+            self.params.ivf_lists,
+            self.params.pq_subvectors,
+            self.params.seed.unwrap_or(0),
         );
+        self.ivf = Some(ivf);
+        self.hyperplanes = None;
+        self.hnsw = self.params.new_hnsw();
+        self.pending_build = None;
+        self.reclaim_ids();
+    }
+
+    /// Refile the live points in fresh LSH buckets, in place of a graph
+    /// rebuild.
+    fn hash_hyperplanes(&mut self) {
+        let mut index = HyperplaneIndex::new(
+            self.dimensions,
+            self.params.lsh_tables.unwrap_or(crate::hyperplane::DEFAULT_TABLES),
+            self.params.lsh_bits.unwrap_or(crate::hyperplane::DEFAULT_BITS),
+            self.params.seed.unwrap_or(0),
+        );
+        for internal_id in self.rebuild_order() {
+            index.insert(internal_id, &self.vectors[&internal_id]);
+        }
+        self.hyperplanes = Some(index);
+        self.ivf = None;
+        self.hnsw = self.params.new_hnsw();
+        self.pending_build = None;
+        self.reclaim_ids();
+    }
+
+    /// Whether this is an IVF-PQ collection, whose full vectors live on disk
+    pub fn quantized(&self) -> bool {
+        self.index_type == IVF_INDEX_TYPE && self.params.pq_subvectors.is_some()
+    }
+
+    /// The stored vector of `internal_id`, read from disk if it was spilled
+    pub fn vector(&self, internal_id: usize) -> Option<Cow<'_, [f32]>> {
+        match self.vectors.get(&internal_id) {
+            Some(vector) => Some(Cow::Borrowed(vector)),
+            None => match &self.mapped_graph {
+                Some(graph) => graph.vector(internal_id).map(Cow::Owned),
+                None => self.spilled.as_ref()?.read(internal_id).map(Cow::Owned),
+            },
+        }
+    }
+
+    /// Run `f` on the graph searches use: the mapped one when attached
+    pub fn with_graph<R>(&self, f: impl for<'b> FnOnce(&Hnsw<'b, f32, DistCosine>) -> R) -> R {
+        match &self.mapped_graph {
+            Some(graph) => graph.with_graph(f),
+            None => f(&self.hnsw),
+        }
+    }
+
+    /// Read spilled vectors back into memory, for a collection that is no
+    /// longer quantized. The next save drops the vector file.
+    pub fn unspill(&mut self) {
+        let Some(spilled) = self.spilled.take() else {
+            return;
+        };
+        for internal_id in spilled.ids() {
+            if let Some(vector) = spilled.read(internal_id) {
+                self.vectors.entry(internal_id).or_insert(vector);
+            }
+        }
+    }
+
+    /// Compute the norms missing from the sidecar, and rescale any vector
+    /// of a normalizing collection that isn't unit length. Rescaled spilled
+    /// vectors are kept in memory until the next save appends them.
+    pub fn check_norms(&mut self) {
+        let mut ids: Vec<usize> = self.vectors.keys().copied().collect();
+        ids.extend(self.spilled.iter().flat_map(VectorFile::ids));
+        for internal_id in ids {
+            let norm = match self.norms.get(&internal_id) {
+                Some(&norm) => norm,
+                None => match self.vector(internal_id) {
+                    Some(vector) => norm(&vector),
+                    None => continue,
+                },
+            };
+            if self.normalize && norm > 0.0 && (norm - 1.0).abs() > 1e-4 {
+                if let Some(vector) = self.vector(internal_id) {
+                    let unit = vector.iter().map(|x| x / norm).collect();
+                    self.vectors.insert(internal_id, unit);
+                    self.norms.insert(internal_id, 1.0);
+                    continue;
+                }
+            }
+            self.norms.insert(internal_id, norm);
+        }
+    }
+
+    /// Graph nodes, or IVF list entries or LSH points, including dead ones
+    pub fn index_len(&self) -> usize {
+        match (&self.ivf, &self.hyperplanes) {
+            (Some(ivf), _) => ivf.len(),
+            (None, Some(hyperplanes)) => hyperplanes.len(),
+            (None, None) => self.with_graph(|graph| graph.get_nb_point()),
+        }
+    }
+
+    /// Live internal ids in the order a rebuild inserts them: internal id
+    /// order, so rebuilding the same points gives the same graph, unless
+    /// shuffling is on.
+    fn rebuild_order(&self) -> Vec<usize> {
+        let spilled = |internal_id: &usize| {
+            self.spilled.as_ref().is_some_and(|file| file.contains(*internal_id))
+        };
+        let mut internal_ids: Vec<usize> = self
+            .id_map
+            .values()
+            .copied()
+            .filter(|internal_id| self.vectors.contains_key(internal_id) || spilled(internal_id))
+            .collect();
+        internal_ids.sort_unstable();
+        internal_ids.dedup();
+        if self.params.shuffle_on_rebuild {
+            let mut rng = match self.params.seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => rand::make_rng(),
+            };
+            internal_ids.shuffle(&mut rng);
+        }
+        internal_ids
+    }
+
+    /// Serve searches by scanning the vectors until `finish_build` swaps in
+    /// the graph built from the returned points.
+    pub fn defer_build(&mut self) -> PendingBuild {
+        let ticket = BUILD_TICKETS.fetch_add(1, Ordering::Relaxed);
+        self.pending_build = Some(ticket);
+        let points = self
+            .rebuild_order()
+            .into_iter()
+            .map(|internal_id| (internal_id, self.vectors[&internal_id].clone()))
+            .collect();
+        PendingBuild {
+            ticket,
+            params: self.params,
+            points,
+        }
+    }
+
+    /// Swap in the graph built for `build`, first adding the points inserted
+    /// while it was built. Returns false, dropping the graph, if the
+    /// collection has been rebuilt since.
+    pub fn finish_build(
+        &mut self,
+        build: &PendingBuild,
+        hnsw: Hnsw<'static, f32, DistCosine>,
+    ) -> bool {
+        if self.pending_build != Some(build.ticket) {
+            return false;
+        }
+        let built: HashSet<usize> = build.points.iter().map(|(id, _)| *id).collect();
+        let mut missing: Vec<usize> = self
+            .id_map
+            .values()
+            .copied()
+            .filter(|internal_id| !built.contains(internal_id))
+            .collect();
+        missing.sort_unstable();
+        missing.dedup();
+        for internal_id in missing {
+            hnsw.insert((self.vectors[&internal_id].as_slice(), internal_id));
+        }
+        self.hnsw = hnsw;
+        self.pending_build = None;
+        true
+    }
+
+    /// Free the internal ids of points dropped since the last rebuild. Only
+    /// valid right after one: until then their nodes are still in the graph.
+    fn reclaim_ids(&mut self) {
+        self.id_sets.clear();
+        self.handle_epoch = HANDLE_EPOCHS.fetch_add(1, Ordering::Relaxed);
+        let mut used: Vec<usize> = self.id_map.values().copied().collect();
+        used.sort_unstable();
+        used.dedup();
+        self.next_id = used.last().map_or(0, |&max| max + 1);
+        self.free_ids = (0..self.next_id)
+            .rev()
+            .filter(|internal_id| used.binary_search(internal_id).is_err())
+            .collect();
+    }
+
+    /// Tombstones past the retention window, which the next purge drops.
+    fn expired_tombstones(&self) -> Vec<Id> {
+        let cutoff = match self.tombstone_retention_ms {
+            Some(retention) => now_millis() - retention,
+            None => i64::MAX,
+        };
+        self.deleted_ids
+            .iter()
+            .filter(|uuid| self.deleted_at.get(*uuid).copied().unwrap_or(0) <= cutoff)
+            .cloned()
+            .collect()
+    }
+
+    /// Drop tombstones past the retention window, with their vectors and id
+    /// mappings, for good. Returns the purged ids.
+    pub fn purge_deleted(&mut self) -> Vec<Id> {
+        let expired = self.expired_tombstones();
+        self.purge(&expired);
+        expired
+    }
+
+    /// Drop the tombstones `ids`, with their vectors and id mappings
+    fn purge(&mut self, ids: &[Id]) {
+        for uuid in ids {
+            self.log_id(uuid);
+            if let Some(&internal_id) = self.id_map.get(uuid) {
+                self.log_slot(internal_id);
+            }
+            self.deleted_ids.remove(uuid);
+            self.deleted_at.remove(uuid);
+            if let Some(seq) = self.change_seqs.remove(uuid) {
+                self.purged_seq = self.purged_seq.max(seq);
+            }
+            if let Some(internal_id) = self.id_map.remove(uuid) {
+                self.remove_stored(internal_id);
+                self.reverse_map.remove(&internal_id);
+                self.inserted_at.remove(&internal_id);
+                self.payloads.remove(&internal_id);
+            }
+        }
+        if !ids.is_empty() {
+            self.refresh_id_filter();
+        }
+    }
+
+    /// Graph nodes a compaction would drop: expired tombstones plus nodes
+    /// left behind by upserts.
+    pub fn dead_nodes(&self) -> usize {
+        self.orphaned_nodes() + self.expired_tombstones().len()
+    }
+
+    /// Graph nodes no id maps to any more: the old nodes of upserted points
+    /// and of tombstones purged since the last rebuild.
+    pub fn orphaned_nodes(&self) -> usize {
+        self.index_len().saturating_sub(self.id_map.len())
+    }
+
+    /// Whether the dead nodes have crossed the auto-compaction thresholds.
+    pub fn compaction_due(&self) -> bool {
+        let Some(policy) = self.auto_compaction else {
+            return false;
+        };
+        let total = self.index_len();
+        let dead = self.dead_nodes();
+        total > 0
+            && dead > 0
+            && dead >= policy.min_deleted
+            && dead as f64 / total as f64 >= policy.tombstone_ratio
+    }
+
+    /// Purge expired tombstones and rebuild the graph without dead nodes.
+    /// Returns how many nodes were dropped from the graph.
+    pub fn compact(&mut self) -> usize {
+        let before = self.index_len();
+        let purged = self.purge_deleted();
+        self.rebuild_from_vectors();
+        self.mark_dirty();
+        self.audit("compact", purged);
+        before.saturating_sub(self.index_len())
+    }
+
+    /// Rebuild the graph with new construction parameters, keeping all ids.
+    pub fn reindex(&mut self, params: HnswParams) {
+        let purged = self.purge_deleted();
+        self.audit("reindex", purged);
+        self.params = params;
+        self.rebuild_from_vectors();
+    }
+
+    /// Reconcile the id maps, vectors and tombstones after they have come to
+    /// disagree, e.g. through a crash, and rebuild the graph if anything
+    /// changed. Vectors no id maps to are only dropped with `drop_orphans`.
+    pub fn repair(&mut self, drop_orphans: bool) -> RepairReport {
+        let mut report = RepairReport::default();
+        self.log_everything();
+
+        // Keep one id per internal id, preferring the one the reverse map names
+        let mut by_internal: HashMap<usize, Vec<Id>> = HashMap::new();
+        for (uuid, &internal_id) in &self.id_map {
+            by_internal.entry(internal_id).or_default().push(uuid.clone());
+        }
+        for (internal_id, mut uuids) in by_internal {
+            if uuids.len() < 2 {
+                continue;
+            }
+            uuids.sort_unstable();
+            let keep = self
+                .reverse_map
+                .get(&internal_id)
+                .filter(|uuid| uuids.contains(uuid))
+                .cloned()
+                .unwrap_or_else(|| uuids[0].clone());
+            report.duplicate_ids.extend(uuids.into_iter().filter(|uuid| *uuid != keep));
+        }
+        report.missing_vectors = self
+            .id_map
+            .iter()
+            .filter(|(_, &internal_id)| !self.is_stored(internal_id))
+            .map(|(uuid, _)| uuid.clone())
+            .filter(|uuid| !report.duplicate_ids.contains(uuid))
+            .collect();
+        for uuid in report.duplicate_ids.iter().chain(&report.missing_vectors) {
+            self.id_map.remove(uuid);
+            self.deleted_ids.remove(uuid);
+        }
+
+        report.stale_tombstones = self
+            .deleted_ids
+            .iter()
+            .filter(|uuid| !self.id_map.contains_key(*uuid))
+            .cloned()
+            .collect();
+        for uuid in &report.stale_tombstones {
+            self.deleted_ids.remove(uuid);
+        }
+
+        let reverse_map = ReverseMap::derive(&self.id_map);
+        report.reverse_map_fixes = reverse_map
+            .iter()
+            .filter(|(internal_id, uuid)| self.reverse_map.get(internal_id) != Some(uuid))
+            .count()
+            + self
+                .reverse_map
+                .keys()
+                .filter(|internal_id| !reverse_map.contains_key(internal_id))
+                .count();
+        if report.reverse_map_fixes > 0 {
+            self.handle_epoch = HANDLE_EPOCHS.fetch_add(1, Ordering::Relaxed);
+            for (internal_id, uuid) in reverse_map.iter() {
+                if self.reverse_map.get(&internal_id) != Some(uuid) {
+                    self.reassigned_at.insert(internal_id, self.handle_epoch);
+                }
+            }
+        }
+        self.reverse_map = reverse_map;
+
+        report.orphaned_vectors = self
+            .vectors
+            .keys()
+            .chain(self.codes.keys())
+            .chain(self.sets.keys())
+            .copied()
+            .chain(self.spilled.iter().flat_map(VectorFile::ids))
+            .filter(|internal_id| !self.reverse_map.contains_key(internal_id))
+            .collect();
+        report.orphaned_vectors.sort_unstable();
+        report.orphaned_vectors.dedup();
+        if drop_orphans {
+            for &internal_id in &report.orphaned_vectors {
+                self.remove_stored(internal_id);
+            }
+        }
+
+        let before = self.inserted_at.len() + self.payloads.len() + self.deleted_at.len();
+        let (vectors, codes, sets) = (&self.vectors, &self.codes, &self.sets);
+        let spilled = &self.spilled;
+        let stored = |internal_id: &usize| {
+            vectors.contains_key(internal_id)
+                || codes.contains_key(internal_id)
+                || sets.contains_key(internal_id)
+                || spilled.as_ref().is_some_and(|file| file.contains(*internal_id))
+        };
+        self.inserted_at.retain(|internal_id, _| stored(internal_id));
+        self.norms.retain(|internal_id, _| stored(internal_id));
+        self.payloads.retain(|internal_id, _| stored(internal_id));
+        let deleted_ids = &self.deleted_ids;
+        self.deleted_at.retain(|uuid, _| deleted_ids.contains(uuid));
+        report.stray_entries =
+            before - (self.inserted_at.len() + self.payloads.len() + self.deleted_at.len());
+
+        report.changed = !report.missing_vectors.is_empty()
+            || !report.duplicate_ids.is_empty()
+            || !report.stale_tombstones.is_empty()
+            || report.reverse_map_fixes > 0
+            || report.stray_entries > 0
+            || (drop_orphans && !report.orphaned_vectors.is_empty());
+        if report.changed {
+            self.refresh_id_filter();
+            self.rebuild_from_vectors();
+            self.mark_dirty();
+        }
+        report
+    }
+
+    /// Undo a delete that has not been purged yet.
+    pub fn restore_vector(&mut self, id: &str) -> bool {
+        if self.deleted_ids.contains(id) {
+            self.log_id(id);
+            self.deleted_ids.remove(id);
+            self.deleted_at.remove(id);
+            let id = self.intern(id);
+            self.record_change(&id);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// `id` as an `Id` sharing the id map's allocation when it is known.
+    pub fn intern(&self, id: &str) -> Id {
+        self.id_map.get_key_value(id).map_or_else(|| Id::from(id), |(id, _)| id.clone())
+    }
+
+    /// Install loaded id maps, interning the tombstones. The reverse map is
+    /// derived from the id map only for collections saved without one.
+    pub fn set_ids(
+        &mut self,
+        id_map: HashMap<Id, usize>,
+        reverse_map: Option<ReverseMap>,
+        deleted_ids: HashSet<Id>,
+        deleted_at: HashMap<Id, i64>,
+    ) {
+        self.reverse_map = reverse_map.unwrap_or_else(|| ReverseMap::derive(&id_map));
+        self.id_map = id_map;
+        self.deleted_ids = deleted_ids.iter().map(|id| self.intern(id)).collect();
+        self.deleted_at = deleted_at.into_iter().map(|(id, at)| (self.intern(&id), at)).collect();
+        self.refresh_id_filter();
+    }
+
+    /// Turn the bloom filter in front of the id map on or off.
+    pub fn set_id_filter(&mut self, enabled: bool) {
+        self.id_filter = enabled.then(|| IdFilter::from_ids(self.id_map.keys().map(|id| &**id)));
+    }
+
+    /// Rebuild the bloom filter, if enabled, from the current id map. Needed
+    /// after ids are removed, since the filter can't forget them.
+    fn refresh_id_filter(&mut self) {
+        if self.id_filter.is_some() {
+            self.set_id_filter(true);
+        }
+    }
+
+    /// Internal id of `id`, tombstoned or not. With the bloom filter enabled,
+    /// most ids that were never inserted are turned away before the id map.
+    pub fn internal_id(&self, id: &str) -> Option<usize> {
+        if self.id_filter.as_ref().is_some_and(|filter| !filter.may_contain(id)) {
+            self.id_lookups.filtered.fetch_add(1, Ordering::Relaxed);
+            self.id_lookups.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+        let found = self.id_map.get(id).copied();
+        let counter = if found.is_some() {
+            &self.id_lookups.hits
+        } else {
+            &self.id_lookups.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        found
+    }
+
+    /// Handle of the point at `internal_id`, for `resolve_handle`: the
+    /// internal id in the low 32 bits, the current epoch in the high ones
+    pub fn handle(&self, internal_id: usize) -> u64 {
+        (self.handle_epoch as u64) << 32 | internal_id as u64
+    }
+
+    /// Id of the live point `handle` was returned for, or None if that
+    /// point has since been deleted or replaced, or its internal id has gone
+    /// to another point
+    pub fn resolve_handle(&self, handle: u64) -> Option<&Id> {
+        let epoch = (handle >> 32) as u32;
+        let internal_id = (handle & u64::from(u32::MAX)) as usize;
+        if epoch < self.first_handle_epoch || epoch > self.handle_epoch {
+            return None;
+        }
+        if self.reassigned_at.get(&internal_id).is_some_and(|&at| at > epoch) {
+            return None;
+        }
+        let id = self.reverse_map.get(&internal_id)?;
+        (!self.deleted_ids.contains(id)).then_some(id)
+    }
+
+    /// Whether `id` names a point that has not been deleted.
+    pub fn contains(&self, id: &str) -> bool {
+        self.internal_id(id).is_some() && !self.deleted_ids.contains(id)
+    }
+
+    /// Insert a dense vector. A borrowed one, e.g. straight from the
+    /// caller's buffer, is copied into the collection once.
+    pub fn insert_vector(&mut self, id: &str, vector: Cow<'_, [f32]>, payload: Option<Payload>) {
+        let internal_id = self.record_point(id, payload);
+        let (vector, norm) = stored_vector(vector);
+        self.norms.insert(internal_id, norm);
+        self.vectors.insert(internal_id, vector);
+        if let Some(ivf) = &mut self.ivf {
+            ivf.insert(internal_id, &self.vectors[&internal_id]);
+        } else if let Some(hyperplanes) = &mut self.hyperplanes {
+            hyperplanes.insert(internal_id, &self.vectors[&internal_id]);
+        } else if self.pending_build.is_none() {
+            // A pending build adds it when it lands
+            self.hnsw.insert((self.vectors[&internal_id].as_slice(), internal_id));
+        }
+    }
 
-        // Re-insert all non-deleted vectors
-        for (&internal_id, vec) in &self.vectors {
-            if let Some(uuid) = self.reverse_map.get(&internal_id) {
-                if !self.deleted_ids.contains(uuid) {
-                    self.hnsw.insert((vec.as_slice(), internal_id));
+    /// Insert many points, adding them to the graph in parallel unless the
+    /// collection is seeded.
+    pub fn insert_batch(&mut self, points: Vec<(String, Cow<'_, [f32]>)>) {
+        let internal_ids: Vec<usize> = points
+            .into_iter()
+            .map(|(id, vector)| {
+                let internal_id = self.record_point(&id, None);
+                let (vector, norm) = stored_vector(vector);
+                self.norms.insert(internal_id, norm);
+                self.vectors.insert(internal_id, vector);
+                internal_id
+            })
+            .collect();
+        if let Some(ivf) = &mut self.ivf {
+            for internal_id in internal_ids {
+                if let Some(vector) = self.vectors.get(&internal_id) {
+                    ivf.insert(internal_id, vector);
                 }
             }
+            return;
         }
+        if let Some(hyperplanes) = &mut self.hyperplanes {
+            for internal_id in internal_ids {
+                if let Some(vector) = self.vectors.get(&internal_id) {
+                    hyperplanes.insert(internal_id, vector);
+                }
+            }
+            return;
+        }
+        if self.pending_build.is_some() {
+            return;
+        }
+
+        // A later duplicate in the batch replaces an earlier one
+        let data: Vec<(&Vec<f32>, usize)> = internal_ids
+            .iter()
+            .filter_map(|internal_id| self.vectors.get(internal_id).map(|v| (v, *internal_id)))
+            .collect();
+        if self.params.seed.is_some() {
+            // Parallel inserts race for graph levels and neighbours
+            for (vector, internal_id) in data {
+                self.hnsw.insert((vector.as_slice(), internal_id));
+            }
+        } else {
+            crate::pool::install(|| self.hnsw.parallel_insert(&data));
+        }
+    }
+
+    /// Insert a packed bit vector into a hamming collection. There is no
+    /// graph; searches scan every code.
+    pub fn insert_code(&mut self, id: &str, code: Vec<u8>, payload: Option<Payload>) {
+        let internal_id = self.record_point(id, payload);
+        self.codes.insert(internal_id, code);
+    }
+
+    /// The `k` live points of a hamming collection nearest `query`, with
+    /// their distances in bits. Only internal ids `keep` accepts count.
+    pub fn search_codes(
+        &self,
+        query: &[u8],
+        k: usize,
+        keep: impl Fn(usize) -> bool,
+    ) -> Vec<(usize, u32)> {
+        let codes = self.codes.iter().map(|(&internal_id, code)| (internal_id, code.as_slice()));
+        crate::hamming::scan(codes, query, k, |internal_id| {
+            self.reverse_map
+                .get(&internal_id)
+                .is_some_and(|id| !self.deleted_ids.contains(id))
+                && keep(internal_id)
+        })
+    }
+
+    /// Insert a normalized token set into a jaccard collection and its LSH
+    /// buckets.
+    pub fn insert_set(&mut self, id: &str, tokens: Vec<u32>, payload: Option<Payload>) {
+        let internal_id = self.record_point(id, payload);
+        self.lsh.insert(internal_id, &tokens);
+        self.sets.insert(internal_id, tokens);
     }
 
-    pub fn insert_vector(&mut self, id: &str, vector: Vec<f32>) {
+    /// The `k` live points of a jaccard collection most similar to the
+    /// normalized set `query`, with their exact similarities. Only the LSH
+    /// candidates are compared unless `exhaustive`. Only internal ids
+    /// `keep` accepts count.
+    pub fn search_sets(
+        &self,
+        query: &[u32],
+        k: usize,
+        exhaustive: bool,
+        keep: impl Fn(usize) -> bool,
+    ) -> Vec<(usize, f64)> {
+        let candidates: Vec<usize> = if exhaustive {
+            self.sets.keys().copied().collect()
+        } else {
+            self.lsh.candidates(query).into_iter().collect()
+        };
+        let mut hits: Vec<(usize, f64)> = candidates
+            .into_iter()
+            .filter(|internal_id| {
+                self.reverse_map
+                    .get(internal_id)
+                    .is_some_and(|id| !self.deleted_ids.contains(id))
+                    && keep(*internal_id)
+            })
+            .filter_map(|internal_id| {
+                let tokens = self.sets.get(&internal_id)?;
+                Some((internal_id, crate::minhash::jaccard(tokens, query)))
+            })
+            .collect();
+        hits.sort_unstable_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        hits.truncate(k);
+        hits
+    }
+
+    /// What this collection's points are
+    pub fn kind(&self) -> PointKind {
+        match self.metric.as_str() {
+            HAMMING_METRIC => PointKind::Binary,
+            JACCARD_METRIC => PointKind::Set,
+            _ => PointKind::Dense,
+        }
+    }
+
+    /// Whether `internal_id` has a vector, code or set stored
+    fn is_stored(&self, internal_id: usize) -> bool {
+        self.vectors.contains_key(&internal_id)
+            || self.codes.contains_key(&internal_id)
+            || self.sets.contains_key(&internal_id)
+            || self.spilled.as_ref().is_some_and(|file| file.contains(internal_id))
+    }
+
+    /// Drop whatever is stored for `internal_id`, and its LSH buckets
+    fn remove_stored(&mut self, internal_id: usize) {
+        self.vectors.remove(&internal_id);
+        self.token_vectors.remove(&internal_id);
+        self.norms.remove(&internal_id);
+        if let Some(spilled) = &mut self.spilled {
+            spilled.remove(internal_id);
+        }
+        self.codes.remove(&internal_id);
+        if let Some(tokens) = self.sets.remove(&internal_id) {
+            self.lsh.remove(internal_id, &tokens);
+        }
+    }
+
+    /// Id bookkeeping for an insert, everything except storing the vector
+    /// and the graph insert. Returns the new internal id.
+    fn record_point(&mut self, id: &str, payload: Option<Payload>) -> usize {
+        if self.contains(id) {
+            self.upserts += 1;
+        }
+
+        let id = self.intern(id);
+        self.log_id(&id);
+
         // Handle upsert: if ID already exists, mark old one as deleted
-        if let Some(&old_internal) = self.id_map.get(id) {
-            self.deleted_ids.insert(id.to_string());
-            self.vectors.remove(&old_internal);
+        let replaced = self.id_map.get(&id).copied();
+        // The staged vector was made from what the point held before
+        if let Some(staging) = &mut self.staging {
+            staging.vectors.remove(&id);
+        }
+
+        if let Some(old_internal) = replaced {
+            self.log_slot(old_internal);
+            self.deleted_ids.insert(id.clone());
+            self.remove_stored(old_internal);
             self.reverse_map.remove(&old_internal);
+            self.inserted_at.remove(&old_internal);
+            self.payloads.remove(&old_internal);
         }
 
-        let internal_id = self.next_id;
-        self.next_id += 1;
+        let internal_id = match self.free_ids.pop() {
+            Some(internal_id) => {
+                self.reused_ids += 1;
+                self.reassigned_at.insert(internal_id, self.handle_epoch);
+                internal_id
+            }
+            None => {
+                self.next_id += 1;
+                self.next_id - 1
+            }
+        };
+        self.log_slot(internal_id);
 
-        self.id_map.insert(id.to_string(), internal_id);
-        self.reverse_map.insert(internal_id, id.to_string());
-        self.vectors.insert(internal_id, vector.clone());
+        // The point keeps its place in the id sets under its new internal id
+        if let Some(old_internal) = replaced {
+            for set in self.id_sets.values_mut().filter(|set| set.contains(old_internal)) {
+                Arc::make_mut(set).insert(internal_id);
+            }
+        }
+
+        let known = self.id_map.insert(id.clone(), internal_id).is_some();
+        self.reverse_map.insert(internal_id, id.clone());
+        match &mut self.id_filter {
+            Some(_) if known => {}
+            Some(filter) if filter.is_full() => self.refresh_id_filter(),
+            Some(filter) => filter.insert(&id),
+            None => {}
+        }
+        self.inserted_at.insert(internal_id, now_millis());
+        if let Some(payload) = payload {
+            self.payloads.insert(internal_id, payload);
+        }
 
         // Remove from deleted if it was previously deleted
-        self.deleted_ids.remove(id);
+        self.deleted_ids.remove(&id);
+        self.deleted_at.remove(&id);
 
-        self.hnsw.insert((vector.as_slice(), internal_id));
-        self.dirty = true;
+        self.record_change(&id);
+        internal_id
+    }
+
+    /// Vectors of the live points among `ids`, keyed by internal id
+    pub fn get_vectors(&self, ids: &[String]) -> HashMap<usize, Vec<f32>> {
+        ids.iter()
+            .filter(|id| self.contains(id))
+            .filter_map(|id| self.id_map.get(id.as_str()).copied())
+            .filter_map(|internal_id| {
+                self.vector(internal_id).map(|vector| (internal_id, vector.into_owned()))
+            })
+            .collect()
     }
 
-    pub fn search_vectors(&self, query: &[f32], k: usize, ef_search: usize) -> Vec<(String, f32)> {
-        let ef = std::cmp::max(ef_search, k);
-        let results = self.hnsw.search(query, k + self.deleted_ids.len(), ef);
+    /// Read the vector of every graph node once, so searches don't wait for
+    /// mmapped segment pages to be faulted in. Returns the nodes read.
+    pub fn touch_vectors(&self) -> usize {
+        self.with_graph(|graph| {
+            let mut count = 0;
+            for point in graph.get_point_indexation() {
+                std::hint::black_box(point.get_v().iter().sum::<f32>());
+                count += 1;
+            }
+            count
+        })
+    }
 
-        let mut output: Vec<(String, f32)> = Vec::new();
+    /// Verify that the graph and the stored vectors agree: every mapped id
+    /// has a node with its vector, and searching for `sample` random live
+    /// points finds each of them at similarity ~1.
+    pub fn check_index(&self, sample: usize, ef_search: usize) -> IndexCheck {
+        let mut check = IndexCheck::default();
+        let mut in_graph = HashSet::new();
+        self.with_graph(|graph| {
+            for point in graph.get_point_indexation() {
+                check.nodes += 1;
+                let internal_id = point.get_origin_id();
+                // Attached collections keep their vectors only in the graph
+                let matches = self.reverse_map.contains_key(&internal_id)
+                    && (self.mapped_graph.is_some()
+                        || self.vectors.get(&internal_id).map(Vec::as_slice)
+                            == Some(point.get_v()));
+                if matches && in_graph.insert(internal_id) {
+                    continue;
+                }
+                check.dead_nodes += 1;
+            }
+        });
+        check.missing_from_graph = self
+            .reverse_map
+            .iter()
+            .filter(|(internal_id, _)| !in_graph.contains(internal_id))
+            .map(|(_, uuid)| uuid.to_string())
+            .collect();
+        check.missing_from_graph.sort_unstable();
 
-        for neighbour in results {
-            if output.len() >= k {
-                break;
+        let mut live: Vec<&Id> = self
+            .id_map
+            .keys()
+            .filter(|uuid| !self.deleted_ids.contains(*uuid))
+            .collect();
+        live.sort_unstable();
+        live.shuffle(&mut rand::make_rng::<StdRng>());
+        live.truncate(sample);
+        check.sampled = live.len();
+
+        let ids: Vec<String> = live.iter().map(|uuid| uuid.to_string()).collect();
+        let vectors = self.get_vectors(&ids);
+        let policy = RetryPolicy::default();
+        for uuid in live {
+            let internal_id = self.id_map[uuid];
+            let found = vectors.get(&internal_id).is_some_and(|vector| {
+                let filter = PointFilter::default();
+                self.search_vectors(vector, 10, ef_search, Probe::default(), &filter, &policy)
+                    .hits
+                    .iter()
+                    .any(|&(hit, score)| hit == internal_id && score > 0.999)
+            });
+            if !found {
+                check.sample_misses.push(uuid.to_string());
             }
-            let internal_id = neighbour.d_id;
-            if let Some(uuid) = self.reverse_map.get(&internal_id) {
-                if !self.deleted_ids.contains(uuid) {
-                    // Convert distance to similarity: score = 1.0 - distance
-                    let score = 1.0 - neighbour.distance;
-                    output.push((uuid.clone(), score));
+        }
+        check
+    }
+
+    /// The `k` nearest live neighbours of each of `ids`, excluding the point
+    /// itself, computed in parallel. Ids that aren't live are skipped.
+    pub fn knn_of(
+        &self,
+        ids: &[String],
+        k: usize,
+        ef_search: usize,
+    ) -> Vec<(String, Vec<(usize, f32)>)> {
+        let vectors = self.get_vectors(ids);
+        let policy = RetryPolicy::default();
+
+        crate::pool::install(|| {
+            ids.par_iter()
+                .filter_map(|id| {
+                    let internal_id = *self.id_map.get(id.as_str())?;
+                    let vector = vectors.get(&internal_id)?;
+                    let filter = PointFilter::default();
+                    let probe = Probe::default();
+                    let mut neighbours = self
+                        .search_vectors(vector, k + 1, ef_search, probe, &filter, &policy)
+                        .hits;
+                    neighbours.retain(|&(n, _)| n != internal_id);
+                    neighbours.truncate(k);
+                    Some((id.clone(), neighbours))
+                })
+                .collect()
+        })
+    }
+
+    /// Similarity of every pair of `ids` as a search would score it, as an
+    /// `ids.len()` square matrix laid out row by row. Rows are computed in
+    /// parallel. Fails on an id that isn't live.
+    pub fn pairwise_similarities(&self, ids: &[String]) -> Result<Vec<f32>, String> {
+        let vectors = self.get_vectors(ids);
+        let points = ids
+            .iter()
+            .map(|id| {
+                let (internal_id, vector) = self
+                    .id_map
+                    .get(id.as_str())
+                    .and_then(|internal_id| vectors.get_key_value(internal_id))
+                    .ok_or_else(|| format!("Point '{}' not found", id))?;
+                Ok((*internal_id, vector.as_slice(), norm(vector)))
+            })
+            .collect::<Result<Vec<(usize, &[f32], f32)>, String>>()?;
+
+        let n = points.len();
+        let mut matrix = vec![0.0; n * n];
+        if n == 0 {
+            return Ok(matrix);
+        }
+        crate::pool::install(|| {
+            matrix.par_chunks_mut(n).zip(&points).for_each(|(row, &(_, query, query_norm))| {
+                for (cell, &(internal_id, vector, _)) in row.iter_mut().zip(&points) {
+                    *cell = self.similarity(query, query_norm, internal_id, vector);
                 }
+            })
+        });
+        Ok(matrix)
+    }
+
+    /// The `k` live points `accept` lets through that best fit the context
+    /// pairs, scored exactly in parallel. With a target, a point scores +1
+    /// for each pair whose positive it is more similar to than the negative
+    /// and -1 otherwise, plus the sigmoid of its similarity to the target to
+    /// order points of the same rank. Without one, it scores the sum over
+    /// pairs of `min(sim(positive) - sim(negative), 0)`, so every point on
+    /// the positive side of all pairs scores 0. Ties go by internal id.
+    pub fn discover(
+        &self,
+        target: Option<&[f32]>,
+        pairs: &[(Vec<f32>, Vec<f32>)],
+        k: usize,
+        accept: impl Fn(usize) -> bool + Sync,
+    ) -> Vec<(usize, f32)> {
+        let target = target.map(|target| (target.to_vec(), norm(target)));
+        let pairs: Vec<_> = pairs
+            .iter()
+            .map(|(positive, negative)| ((positive, norm(positive)), (negative, norm(negative))))
+            .collect();
+        let candidates: Vec<usize> = self
+            .reverse_map
+            .iter()
+            .filter(|(internal_id, id)| !self.deleted_ids.contains(*id) && accept(*internal_id))
+            .map(|(internal_id, _)| internal_id)
+            .collect();
+
+        let mut scored: Vec<(usize, f32)> = crate::pool::install(|| {
+            candidates
+                .par_iter()
+                .filter_map(|&internal_id| {
+                    let vector = self.vector(internal_id)?;
+                    let sim = |(query, query_norm): (&Vec<f32>, f32)| {
+                        self.similarity(query, query_norm, internal_id, &vector)
+                    };
+                    let score = match target {
+                        Some((ref target, target_norm)) => {
+                            let rank: f32 = pairs
+                                .iter()
+                                .map(|&(p, n)| if sim(p) > sim(n) { 1.0 } else { -1.0 })
+                                .sum();
+                            let closeness = sim((target, target_norm));
+                            rank + 1.0 / (1.0 + (-closeness).exp())
+                        }
+                        None => pairs.iter().map(|&(p, n)| (sim(p) - sim(n)).min(0.0)).sum(),
+                    };
+                    Some((internal_id, score))
+                })
+                .collect()
+        });
+        scored.sort_unstable_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        scored.truncate(k);
+        scored
+    }
+
+    /// Insertion time of a point; 0 for points saved before it was recorded.
+    pub fn inserted_at_of(&self, internal_id: usize) -> i64 {
+        self.inserted_at.get(&internal_id).copied().unwrap_or(0)
+    }
+
+    /// Returns (internal id, similarity) pairs, best first. IVF collections
+    /// score the points in the `probe.lists` nearest lists exactly, or for
+    /// IVF-PQ the `k * probe.rescore` of them whose codes score best. LSH
+    /// collections score the points sharing a bucket with the query. The
+    /// rest of this is about graph searches.
+    ///
+    /// Tombstoned and replaced points stay in the graph, so the first search
+    /// asks for `k` candidates and, while fewer than `k` of them are live,
+    /// retries with `policy.growth` times as many until the graph runs out
+    /// of points, the retries are used up or `policy.deadline` passes. The
+    /// deadline is checked between graph searches, not during one.
+    pub fn search_vectors(
+        &self,
+        query: &[f32],
+        k: usize,
+        ef_search: usize,
+        probe: Probe,
+        filter: &PointFilter,
+        policy: &RetryPolicy,
+    ) -> Neighbours {
+        // An allow-list no longer than the graph search's candidate list is
+        // cheaper to score exactly than to walk the graph around
+        if let Some(allowed) = filter.allowed.as_ref().filter(|a| a.len() <= ef_search) {
+            return self.rank(allowed.iter().collect(), query, k, filter);
+        }
+        if let Some(ivf) = &self.ivf {
+            let mut candidates = ivf.candidates(query, probe.lists);
+            if self.quantized() {
+                candidates.retain(|&internal_id| self.is_match(internal_id, filter));
+                candidates =
+                    ivf.shortlist(query, candidates, k.saturating_mul(probe.rescore));
             }
+            return self.rank(candidates, query, k, filter);
+        }
+        if let Some(hyperplanes) = &self.hyperplanes {
+            return self.rank(hyperplanes.candidates(query), query, k, filter);
+        }
+        if self.pending_build.is_some() {
+            return self.scan_vectors(query, k, filter);
         }
 
-        output
+        let total = self.with_graph(|graph| graph.get_nb_point());
+        let mut fetch = k.min(total).max(1);
+        let mut retries = 0;
+        // Reused across retries, and across searches on this thread
+        let mut output = crate::buffers::candidates();
+        output.reserve(k.min(total));
+
+        loop {
+            // hnsw_rs allocates ef slots up front, so never ask for more
+            // than the graph holds
+            let ef = ef_search.max(fetch).min(total.max(1));
+            let results = self.with_graph(|graph| {
+                if filter.is_empty() {
+                    graph.search(query, fetch, ef)
+                } else {
+                    // Apply the filter inside the graph search so non-matching
+                    // points don't crowd matching ones out of the candidate list
+                    let accept = |internal_id: &usize| {
+                        filter.matches(*internal_id, self.inserted_at_of(*internal_id))
+                    };
+                    graph.search_filter(query, fetch, ef, Some(&accept))
+                }
+            });
+            let exhausted = results.len() < fetch || fetch >= total;
+
+            output.clear();
+            for neighbour in results {
+                if output.len() >= k {
+                    break;
+                }
+                let internal_id = neighbour.d_id;
+                if let Some(uuid) = self.reverse_map.get(&internal_id) {
+                    if !self.deleted_ids.contains(uuid) {
+                        // Convert distance to similarity: score = 1.0 - distance
+                        let score = 1.0 - neighbour.distance;
+                        output.push((internal_id, score));
+                    }
+                }
+            }
+
+            if output.len() >= k || exhausted || retries >= policy.max_retries {
+                return Neighbours {
+                    hits: output,
+                    partial: false,
+                };
+            }
+            if policy.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Neighbours {
+                    hits: output,
+                    partial: true,
+                };
+            }
+            retries += 1;
+            fetch = fetch.saturating_mul(policy.growth).min(total);
+        }
+    }
+
+    /// Exact search over every live vector, used while the graph is built.
+    fn scan_vectors(&self, query: &[f32], k: usize, filter: &PointFilter) -> Neighbours {
+        let candidates = self.reverse_map.keys().collect();
+        self.rank(candidates, query, k, filter)
+    }
+
+    /// The `k` live points among `candidates` most similar to `query`,
+    /// scored exactly.
+    fn rank(
+        &self,
+        candidates: Vec<usize>,
+        query: &[f32],
+        k: usize,
+        filter: &PointFilter,
+    ) -> Neighbours {
+        let query_norm = norm(query);
+        let mut hits = crate::buffers::candidates();
+        crate::pool::install(|| {
+            hits.par_extend(
+                candidates
+                    .into_par_iter()
+                    .filter(|&internal_id| self.is_match(internal_id, filter))
+                    .filter_map(|internal_id| {
+                        let vector = self.vector(internal_id)?;
+                        let score = self.similarity(query, query_norm, internal_id, &vector);
+                        Some((internal_id, score))
+                    }),
+            )
+        });
+        hits.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        hits.truncate(k);
+        Neighbours {
+            hits,
+            partial: false,
+        }
+    }
+
+    /// Cosine similarity of a query and a stored vector, from the stored
+    /// norm; just the dot product when both were normalized on the way in.
+    /// Like `DistCosine`, a zero vector is similar to everything.
+    fn similarity(
+        &self,
+        query: &[f32],
+        query_norm: f32,
+        internal_id: usize,
+        vector: &[f32],
+    ) -> f32 {
+        let vector_norm = self.norms.get(&internal_id).copied().unwrap_or_else(|| norm(vector));
+        if query_norm == 0.0 || vector_norm == 0.0 {
+            return 1.0;
+        }
+        let dot: f32 = query.iter().zip(vector).map(|(q, v)| q * v).sum();
+        let cosine = if self.normalize { dot } else { dot / (query_norm * vector_norm) };
+        cosine.min(1.0)
+    }
+
+    /// Whether `internal_id` is a live point `filter` accepts
+    fn is_match(&self, internal_id: usize, filter: &PointFilter) -> bool {
+        self.reverse_map
+            .get(&internal_id)
+            .is_some_and(|uuid| !self.deleted_ids.contains(uuid))
+            && filter.matches(internal_id, self.inserted_at_of(internal_id))
     }
 
     pub fn delete_vector(&mut self, id: &str) -> bool {
-        if self.id_map.contains_key(id) && !self.deleted_ids.contains(id) {
-            self.deleted_ids.insert(id.to_string());
-            self.dirty = true;
+        if self.contains(id) {
+            let id = self.intern(id);
+            self.log_id(&id);
+            if let Some(staging) = &mut self.staging {
+                staging.vectors.remove(&id);
+            }
+            self.deleted_ids.insert(id.clone());
+            self.deleted_at.insert(id.clone(), now_millis());
+            self.record_change(&id);
             true
         } else {
             false
         }
     }
 
+    /// Tombstone every live point matching `filter`. Returns the deleted ids.
+    pub fn delete_matching(&mut self, filter: &PointFilter) -> Vec<Id> {
+        let matching: Vec<Id> = self
+            .id_map
+            .iter()
+            .filter(|(uuid, &internal_id)| {
+                !self.deleted_ids.contains(*uuid)
+                    && filter.matches(internal_id, self.inserted_at_of(internal_id))
+            })
+            .map(|(uuid, _)| uuid.clone())
+            .collect();
+
+        for uuid in &matching {
+            self.delete_vector(uuid);
+        }
+
+        matching
+    }
+
+    /// Note an unsaved change and give it the next sequence number.
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+        self.seq += 1;
+    }
+
+    /// Rough bytes held by the stored vectors and the graph.
+    pub fn estimated_memory(&self) -> u64 {
+        let vector_bytes = self.dimensions * std::mem::size_of::<f32>();
+        // Each graph node holds a copy of its vector and up to 2M neighbours
+        // on its base layer
+        let node_bytes = vector_bytes + 2 * self.params.max_nb_connection * 16;
+        let code_bytes = self.codes.len() * self.dimensions.div_ceil(8);
+        let token_bytes: usize = self.token_vectors.values().map(|t| t.len() * 4).sum();
+        // Each set is also listed in one bucket per band
+        let set_bytes: usize = self
+            .sets
+            .values()
+            .map(|tokens| tokens.len() * 4 + crate::minhash::BANDS * 8)
+            .sum();
+        // IVF and LSH collections have no graph, just list or bucket entries
+        let index_bytes = self.ivf.as_ref().map_or(0, |ivf| ivf.memory(self.dimensions))
+            + self.hyperplanes.as_ref().map_or(0, HyperplaneIndex::memory);
+        (self.vectors.len() * vector_bytes
+            + self.with_graph(|graph| graph.get_nb_point()) * node_bytes
+            + code_bytes
+            + token_bytes
+            + set_bytes) as u64
+            + index_bytes
+    }
+
+    /// Why inserting `new_points` more live points would break the quota,
+    /// if it would. Once memory has reached `max_bytes`, upserts fail too,
+    /// since the points they replace hold on to their graph nodes.
+    pub fn quota_problem(&self, new_points: usize) -> Option<String> {
+        let quota = self.quota?;
+        let points = self.active_count();
+        if let Some(max) = quota.max_points.filter(|&max| points + new_points > max) {
+            return Some(format!(
+                "QUOTA_EXCEEDED: collection at '{}' holds {} points; maxPoints is {}",
+                self.path.display(),
+                points,
+                max
+            ));
+        }
+        let bytes = self.estimated_memory();
+        if let Some(max) = quota.max_bytes.filter(|&max| bytes >= max) {
+            return Some(format!(
+                "QUOTA_EXCEEDED: collection at '{}' uses an estimated {} bytes; maxBytes is {}",
+                self.path.display(),
+                bytes,
+                max
+            ));
+        }
+        None
+    }
+
+    /// Why `id` is too long for the entry limits, if it is.
+    pub fn id_length_problem(&self, id: &str) -> Option<String> {
+        let max = self.entry_limits.max_id_length.filter(|&max| id.len() > max)?;
+        Some(format!(
+            "ID_TOO_LONG: id starting '{}' is {} bytes; maxIdLength is {}",
+            id.chars().take(32).collect::<String>(),
+            id.len(),
+            max
+        ))
+    }
+
+    /// Why `payload` is too large for the entry limits, if it is.
+    pub fn payload_size_problem(&self, id: &str, payload: &Payload) -> Option<String> {
+        let max = self.entry_limits.max_payload_bytes?;
+        let bytes = serde_json::to_vec(payload).map_or(0, |json| json.len());
+        (bytes > max).then(|| {
+            format!(
+                "PAYLOAD_TOO_LARGE: payload of '{}' is {} bytes; maxPayloadBytes is {}",
+                id, bytes, max
+            )
+        })
+    }
+
+    /// Capacity alerts for every level usage is at now.
+    pub fn capacity_alerts(&self) -> Vec<CapacityAlert> {
+        let nodes = match self.kind() {
+            PointKind::Dense => self.index_len(),
+            PointKind::Binary => self.codes.len(),
+            PointKind::Set => self.sets.len(),
+        };
+        self.capacity_thresholds.check(
+            nodes,
+            self.params.max_elements,
+            self.estimated_memory(),
+        )
+    }
+
+    /// Capacity alerts for levels reached since the last call. A level
+    /// usage has dropped below since can be raised again.
+    pub fn new_capacity_alerts(&mut self) -> Vec<CapacityAlert> {
+        let alerts = self.capacity_alerts();
+        let raised = alerts.iter().map(|a| (a.kind.clone(), a.level)).collect();
+        let previous = std::mem::replace(&mut self.raised_alerts, raised);
+        alerts
+            .into_iter()
+            .filter(|a| !previous.contains(&(a.kind.clone(), a.level)))
+            .collect()
+    }
+
+    /// Note a change to `id` under the next sequence number.
+    fn record_change(&mut self, id: &Id) {
+        self.mark_dirty();
+        self.change_seqs.insert(id.clone(), self.seq);
+    }
+
+    /// Ids changed after sequence number `since`, in the order of their
+    /// latest change: those live now, then those deleted now. Fails if
+    /// records after `since` were dropped by a purge.
+    pub fn changes_since(&self, since: u64) -> Result<(Vec<Id>, Vec<Id>), String> {
+        if since < self.purged_seq {
+            return Err(format!(
+                "Changes up to seq {} were purged with their tombstones; \
+                 resync from a full read",
+                self.purged_seq
+            ));
+        }
+        let mut changed: Vec<(&Id, u64)> = self
+            .change_seqs
+            .iter()
+            .filter(|(_, &seq)| seq > since)
+            .map(|(id, &seq)| (id, seq))
+            .collect();
+        changed.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(b.0)));
+        let (upserted, deleted) = changed
+            .into_iter()
+            .map(|(id, _)| id.clone())
+            .partition(|id| self.contains(id));
+        Ok((upserted, deleted))
+    }
+
+    /// Ids changed since the last save, as (live now, deleted now)
+    pub fn unsaved_changes(&self) -> (usize, usize) {
+        self.change_seqs
+            .iter()
+            .filter(|(_, &seq)| seq > self.committed_seq)
+            .fold((0, 0), |(live, deleted), (id, _)| {
+                if self.contains(id) {
+                    (live + 1, deleted)
+                } else {
+                    (live, deleted + 1)
+                }
+            })
+    }
+
+    pub fn record_save(&mut self) {
+        self.committed_seq = self.seq;
+        let record = SaveRecord {
+            saved_at: now_millis(),
+            count: self.active_count(),
+            deleted: self.deleted_ids.len(),
+        };
+        self.last_saved_at = Some(record.saved_at);
+        if self.save_history.len() == SAVE_HISTORY_LEN {
+            self.save_history.pop_front();
+        }
+        self.save_history.push_back(record);
+    }
+
+    pub fn record_error(&mut self, error: &str) {
+        self.last_error = Some((now_millis(), error.to_string()));
+    }
+
+    /// Merge each update's fields into the payload of its point, replacing
+    /// fields already set. Every merged payload is checked against the
+    /// schema before any is stored. Ids that aren't live are skipped.
+    /// Returns the ids updated.
+    pub fn merge_payloads(
+        &mut self,
+        updates: Vec<(String, Payload)>,
+    ) -> Result<Vec<String>, String> {
+        let mut merged = Vec::with_capacity(updates.len());
+        for (id, fields) in updates {
+            if !self.contains(&id) {
+                continue;
+            }
+            let internal_id = self.id_map[id.as_str()];
+            let mut payload = self.payloads.get(&internal_id).cloned().unwrap_or_default();
+            payload.extend(fields);
+            if let Some(schema) = &self.schema {
+                if let Some(error) = payload_errors(schema, &payload).into_iter().next() {
+                    return Err(format!("Point '{}': {}", id, error));
+                }
+            }
+            if let Some(problem) = self.payload_size_problem(&id, &payload) {
+                return Err(problem);
+            }
+            merged.push((id, internal_id, payload));
+        }
+
+        let mut ids = Vec::with_capacity(merged.len());
+        for (id, internal_id, payload) in merged {
+            self.log_slot(internal_id);
+            self.payloads.insert(internal_id, payload);
+            ids.push(id);
+        }
+        if !ids.is_empty() {
+            self.mark_dirty();
+            let seq = self.seq;
+            for id in &ids {
+                let id = self.intern(id);
+                self.change_seqs.insert(id, seq);
+            }
+        }
+        Ok(ids)
+    }
+
+    /// Start savepoint `name`, replacing an earlier savepoint of the same
+    /// name. Changes from here on are logged in it.
+    pub fn savepoint(&mut self, name: &str) {
+        if let Some(position) = self.savepoints.iter().position(|(existing, _)| existing == name) {
+            self.drop_savepoint(position);
+        }
+        let savepoint = Savepoint {
+            staging: self.staging.as_ref().map(|s| (s.dimensions, s.model_tag.clone())),
+            token_dimensions: self.token_dimensions,
+            upserts: self.upserts,
+            reused_ids: self.reused_ids,
+            seq: self.seq,
+            ..Savepoint::default()
+        };
+        self.savepoints.push((name.to_string(), savepoint));
+    }
+
+    /// Remove the savepoint at `position`, handing its log to the one
+    /// before, which then covers the changes it logged
+    fn drop_savepoint(&mut self, position: usize) {
+        let (_, savepoint) = self.savepoints.remove(position);
+        if let Some((_, earlier)) = position.checked_sub(1).map(|p| &mut self.savepoints[p]) {
+            earlier.absorb(savepoint);
+        }
+    }
+
+    /// Log what `internal_id` holds in the latest savepoint, unless it has
+    /// been changed since that savepoint already
+    fn log_slot(&mut self, internal_id: usize) {
+        match self.savepoints.last() {
+            Some((_, savepoint)) if !savepoint.slots.contains_key(&internal_id) => {}
+            _ => return,
+        }
+        let state = SlotState {
+            id: self.reverse_map.get(&internal_id).cloned(),
+            vector: self.vector(internal_id).map(Cow::into_owned),
+            norm: self.norms.get(&internal_id).copied(),
+            code: self.codes.get(&internal_id).cloned(),
+            set: self.sets.get(&internal_id).cloned(),
+            tokens: self.token_vectors.get(&internal_id).cloned(),
+            inserted_at: self.inserted_at.get(&internal_id).copied(),
+            payload: self.payloads.get(&internal_id).cloned(),
+        };
+        if let Some((_, savepoint)) = self.savepoints.last_mut() {
+            savepoint.slots.insert(internal_id, state);
+        }
+    }
+
+    /// Log what `id` is in the latest savepoint, unless it has been changed
+    /// since that savepoint already
+    fn log_id(&mut self, id: &str) {
+        match self.savepoints.last() {
+            Some((_, savepoint)) if !savepoint.ids.contains_key(id) => {}
+            _ => return,
+        }
+        let state = IdState {
+            internal_id: self.id_map.get(id).copied(),
+            deleted: self.deleted_ids.contains(id),
+            deleted_at: self.deleted_at.get(id).copied(),
+            staged: self.staging.as_ref().and_then(|s| s.vectors.get(id)).cloned(),
+        };
+        let id = self.intern(id);
+        if let Some((_, savepoint)) = self.savepoints.last_mut() {
+            savepoint.ids.insert(id, state);
+        }
+    }
+
+    /// Log every internal id and id, before a change too sweeping to track
+    fn log_everything(&mut self) {
+        if self.savepoints.is_empty() {
+            return;
+        }
+        let mut internal_ids: HashSet<usize> = self
+            .vectors
+            .keys()
+            .chain(self.codes.keys())
+            .chain(self.sets.keys())
+            .chain(self.inserted_at.keys())
+            .chain(self.payloads.keys())
+            .copied()
+            .chain(self.reverse_map.keys())
+            .collect();
+        internal_ids.extend(self.spilled.iter().flat_map(VectorFile::ids));
+        for internal_id in internal_ids {
+            self.log_slot(internal_id);
+        }
+        let ids: HashSet<Id> = self.id_map.keys().chain(&self.deleted_ids).cloned().collect();
+        for id in ids {
+            self.log_id(&id);
+        }
+    }
+
+    /// Put back what a savepoint logged
+    fn undo(&mut self, savepoint: Savepoint) {
+        for (internal_id, state) in savepoint.slots {
+            if self.reverse_map.get(&internal_id) != state.id.as_ref() {
+                self.reassigned_at.insert(internal_id, self.handle_epoch);
+            }
+            self.reverse_map.set(internal_id, state.id);
+            if let Some(spilled) = &mut self.spilled {
+                spilled.remove(internal_id);
+            }
+            set_entry(&mut self.vectors, internal_id, state.vector);
+            set_entry(&mut self.norms, internal_id, state.norm);
+            set_entry(&mut self.codes, internal_id, state.code);
+            set_entry(&mut self.sets, internal_id, state.set);
+            set_entry(&mut self.token_vectors, internal_id, state.tokens);
+            set_entry(&mut self.inserted_at, internal_id, state.inserted_at);
+            set_entry(&mut self.payloads, internal_id, state.payload);
+        }
+        for (id, state) in savepoint.ids {
+            set_entry(&mut self.id_map, id.clone(), state.internal_id);
+            set_entry(&mut self.deleted_at, id.clone(), state.deleted_at);
+            if state.deleted {
+                self.deleted_ids.insert(id.clone());
+            } else {
+                self.deleted_ids.remove(&id);
+            }
+            match state.staged {
+                Some(vector) => {
+                    self.staging.get_or_insert_with(Staging::default).vectors.insert(id, vector);
+                }
+                None => {
+                    if let Some(staging) = &mut self.staging {
+                        staging.vectors.remove(&id);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Return the points to savepoint `name` and rebuild the graph. The
+    /// savepoint is kept; later ones are dropped. Returns false if there is
+    /// no savepoint of that name.
+    pub fn rollback_to(&mut self, name: &str) -> bool {
+        let Some(position) = self.savepoints.iter().position(|(existing, _)| existing == name)
+        else {
+            return false;
+        };
+        // Internal ids that change hands now invalidate the current handles
+        self.handle_epoch = HANDLE_EPOCHS.fetch_add(1, Ordering::Relaxed);
+        // Latest first, so what each id was at the savepoint wins
+        while self.savepoints.len() > position + 1 {
+            if let Some((_, later)) = self.savepoints.pop() {
+                self.undo(later);
+            }
+        }
+        let savepoint = &mut self.savepoints[position].1;
+        let log = Savepoint {
+            slots: std::mem::take(&mut savepoint.slots),
+            ids: std::mem::take(&mut savepoint.ids),
+            ..Savepoint::default()
+        };
+        let staging = savepoint.staging.clone();
+        self.token_dimensions = savepoint.token_dimensions;
+        self.upserts = savepoint.upserts;
+        self.reused_ids = savepoint.reused_ids;
+        let since = savepoint.seq;
+        self.undo(log);
+
+        self.staging = staging.map(|(dimensions, model_tag)| Staging {
+            dimensions,
+            model_tag,
+            ..self.staging.take().unwrap_or_default()
+        });
+        self.lsh = LshIndex::build(&self.sets);
+        self.refresh_id_filter();
+        self.rebuild_from_vectors();
+        self.mark_dirty();
+
+        // Undoing the changes made after the savepoint is a change too
+        let seq = self.seq;
+        for (_, change_seq) in self.change_seqs.iter_mut().filter(|(_, s)| **s > since) {
+            *change_seq = seq;
+        }
+        true
+    }
+
+    /// Forget savepoint `name` and the ones after it. Returns false if
+    /// there is no savepoint of that name.
+    pub fn release_savepoint(&mut self, name: &str) -> bool {
+        match self.savepoints.iter().position(|(existing, _)| existing == name) {
+            Some(position) => {
+                while self.savepoints.len() > position {
+                    self.drop_savepoint(position);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Append `op` on `ids` to the audit log if it is enabled. The operation
+    /// has already happened, so a failed write is recorded as the last error
+    /// rather than returned.
+    pub fn audit<S: ToString>(&mut self, op: &str, ids: Vec<S>) {
+        if !self.audit_log {
+            return;
+        }
+        let entry = crate::audit::Entry {
+            at: now_millis(),
+            op: op.to_string(),
+            ids: ids.iter().map(S::to_string).collect(),
+            actor: self.audit_actor.clone(),
+        };
+        if let Err(e) = crate::audit::append(&self.path, &entry) {
+            self.record_error(&e);
+        }
+    }
+
+    /// Length of the vectors inserts and queries pass in: the projection's
+    /// input length if one is set, else the stored dimensions.
+    pub fn input_dimensions(&self) -> usize {
+        self.projection
+            .as_ref()
+            .map_or(self.dimensions, InputProjection::input_dimensions)
+    }
+
+    /// `vector` mapped to the stored dimensions by the projection, if any,
+    /// and scaled to unit length if the collection normalizes. A zero
+    /// vector stays zero.
+    pub fn project<'a>(&self, vector: &'a [f32]) -> Cow<'a, [f32]> {
+        let projected = match &self.projection {
+            Some(projection) => Cow::Owned(projection.apply(vector)),
+            None => Cow::Borrowed(vector),
+        };
+        if !self.normalize {
+            return projected;
+        }
+        let norm = norm(&projected);
+        if norm == 0.0 || norm == 1.0 {
+            return projected;
+        }
+        Cow::Owned(projected.iter().map(|x| x / norm).collect())
+    }
+
     pub fn active_count(&self) -> usize {
         self.id_map.len() - self.deleted_ids.len()
     }
+
+    /// Ids of the live points, sorted
+    pub fn live_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self
+            .id_map
+            .keys()
+            .filter(|id| !self.deleted_ids.contains(*id))
+            .map(|id| id.to_string())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Store the token matrix of `internal_id`, rows laid end to end,
+    /// replacing any earlier one.
+    pub fn set_token_vectors(&mut self, internal_id: usize, dimensions: usize, rows: Vec<f32>) {
+        self.log_slot(internal_id);
+        self.token_dimensions = Some(dimensions);
+        self.token_vectors.insert(internal_id, rows);
+        if let Some(id) = self.reverse_map.get(&internal_id).cloned() {
+            self.record_change(&id);
+        }
+    }
+
+    /// ColBERT-style MaxSim of `query`'s token rows against the point's:
+    /// the dot product of each query token with its best-matching point
+    /// token, summed. None if the point has no token vectors.
+    pub fn late_interaction_score(&self, internal_id: usize, query: &[Vec<f32>]) -> Option<f32> {
+        let rows = self.token_vectors.get(&internal_id)?;
+        let dimensions = self.token_dimensions?;
+        Some(
+            query
+                .iter()
+                .map(|q| {
+                    rows.chunks_exact(dimensions)
+                        .map(|row| row.iter().zip(q).map(|(a, b)| a * b).sum::<f32>())
+                        .fold(f32::NEG_INFINITY, f32::max)
+                })
+                .sum(),
+        )
+    }
+
+    /// Stage `vector` for live point `id`, starting a staging area of its
+    /// length if there is none. Searches keep using the live vectors.
+    pub fn stage_vector(&mut self, id: &str, vector: Vec<f32>, model_tag: Option<String>) {
+        let id = self.intern(id);
+        self.log_id(&id);
+        let staging = self.staging.get_or_insert_with(|| Staging {
+            dimensions: vector.len(),
+            ..Staging::default()
+        });
+        if staging.model_tag.is_none() {
+            staging.model_tag = model_tag;
+        }
+        staging.vectors.insert(id, vector);
+        self.mark_dirty();
+    }
+
+    /// Drop every staged vector, abandoning a migration. Returns false if
+    /// none were staged.
+    pub fn clear_staging(&mut self) -> bool {
+        let staged: Vec<Id> = match &self.staging {
+            Some(staging) => staging.vectors.keys().cloned().collect(),
+            None => return false,
+        };
+        for id in &staged {
+            self.log_id(id);
+        }
+        self.staging = None;
+        self.mark_dirty();
+        true
+    }
+
+    /// Live points with no staged vector yet
+    pub fn unstaged(&self) -> Vec<Id> {
+        let staged = self.staging.as_ref().map(|staging| &staging.vectors);
+        self.id_map
+            .keys()
+            .filter(|id| !self.deleted_ids.contains(*id))
+            .filter(|id| staged.is_none_or(|vectors| !vectors.contains_key(*id)))
+            .cloned()
+            .collect()
+    }
+
+    /// Make the staged vectors the live ones and rebuild the index on them,
+    /// in one step under the caller's lock. Every live point needs one.
+    /// Tombstones hold vectors from the old model and can't be restored
+    /// afterwards, so they are purged; savepoints are dropped for the same
+    /// reason. Returns how many points were promoted.
+    pub fn promote_staging(&mut self) -> Result<usize, String> {
+        if self.staging.is_none() {
+            return Err("No vectors are staged".to_string());
+        }
+        let mut unstaged = self.unstaged();
+        if !unstaged.is_empty() {
+            unstaged.sort();
+            return Err(format!(
+                "{} live points have no staged vector, e.g. '{}'",
+                unstaged.len(),
+                unstaged[0]
+            ));
+        }
+        let mut staging = self.staging.take().unwrap_or_default();
+
+        let tombstones: Vec<Id> = self.deleted_ids.iter().cloned().collect();
+        self.purge(&tombstones);
+        self.savepoints.clear();
+
+        self.vectors = self
+            .id_map
+            .iter()
+            .filter_map(|(id, &internal_id)| {
+                staging.vectors.remove(id).map(|vector| (internal_id, vector))
+            })
+            .collect();
+        self.norms = self
+            .vectors
+            .iter()
+            .map(|(&internal_id, vector)| (internal_id, norm(vector)))
+            .collect();
+        self.dimensions = staging.dimensions;
+        self.model_tag = staging.model_tag;
+        self.rebuild_from_vectors();
+
+        // Every point's vector changed
+        let ids: Vec<Id> = self.id_map.keys().cloned().collect();
+        for id in &ids {
+            self.record_change(id);
+        }
+        Ok(ids.len())
+    }
 }
@@ -0,0 +1,370 @@
+use serde_json::Value;
+
+use crate::schema::Payload;
+
+/// Scoring expression compiled once per query and evaluated per candidate,
+/// e.g. `0.8 * $similarity + 0.2 * log(1 + payload.popularity)`.
+///
+/// Numbers, `+ - * / %`, `^` (power), unary minus, parentheses and the
+/// functions abs, ceil, exp, floor, ln, log (natural), log10, max, min,
+/// pow, round and sqrt are supported. Variables are `$similarity` (the
+/// raw similarity), `$score` (after `decay` and `boosts`), `$insertedAt`
+/// (ms since epoch) and `payload.field`, with dots for nested objects.
+/// Payload fields that are missing or not numbers read as 0; booleans read
+/// as 1 or 0.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScoreExpr {
+    root: Node,
+}
+
+/// Values the variables of an expression read
+pub struct Inputs<'a> {
+    pub similarity: f64,
+    pub score: f64,
+    pub inserted_at: i64,
+    pub payload: Option<&'a Payload>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Number(f64),
+    Similarity,
+    Score,
+    InsertedAt,
+    Field(Vec<String>),
+    Negate(Box<Node>),
+    Binary(char, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Function {
+    Abs,
+    Ceil,
+    Exp,
+    Floor,
+    Ln,
+    Log10,
+    Max,
+    Min,
+    Pow,
+    Round,
+    Sqrt,
+}
+
+impl Function {
+    fn parse(name: &str) -> Option<(Function, usize)> {
+        Some(match name {
+            "abs" => (Function::Abs, 1),
+            "ceil" => (Function::Ceil, 1),
+            "exp" => (Function::Exp, 1),
+            "floor" => (Function::Floor, 1),
+            "ln" | "log" => (Function::Ln, 1),
+            "log10" => (Function::Log10, 1),
+            "max" => (Function::Max, 2),
+            "min" => (Function::Min, 2),
+            "pow" => (Function::Pow, 2),
+            "round" => (Function::Round, 1),
+            "sqrt" => (Function::Sqrt, 1),
+            _ => return None,
+        })
+    }
+
+    fn call(self, args: &[f64]) -> f64 {
+        match self {
+            Function::Abs => args[0].abs(),
+            Function::Ceil => args[0].ceil(),
+            Function::Exp => args[0].exp(),
+            Function::Floor => args[0].floor(),
+            Function::Ln => args[0].ln(),
+            Function::Log10 => args[0].log10(),
+            Function::Max => args[0].max(args[1]),
+            Function::Min => args[0].min(args[1]),
+            Function::Pow => args[0].powf(args[1]),
+            Function::Round => args[0].round(),
+            Function::Sqrt => args[0].sqrt(),
+        }
+    }
+}
+
+impl ScoreExpr {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            source,
+            chars: source.char_indices().collect(),
+            pos: 0,
+            depth: 0,
+        };
+        let root = parser.expr()?;
+        parser.skip_space();
+        if parser.pos < parser.chars.len() {
+            return Err(parser.error("unexpected input"));
+        }
+        Ok(ScoreExpr { root })
+    }
+
+    pub fn eval(&self, inputs: &Inputs) -> f64 {
+        self.root.eval(inputs)
+    }
+}
+
+impl Node {
+    fn eval(&self, inputs: &Inputs) -> f64 {
+        match self {
+            Node::Number(n) => *n,
+            Node::Similarity => inputs.similarity,
+            Node::Score => inputs.score,
+            Node::InsertedAt => inputs.inserted_at as f64,
+            Node::Field(path) => field_value(inputs.payload, path),
+            Node::Negate(node) => -node.eval(inputs),
+            Node::Binary(op, left, right) => {
+                let (left, right) = (left.eval(inputs), right.eval(inputs));
+                match op {
+                    '+' => left + right,
+                    '-' => left - right,
+                    '*' => left * right,
+                    '/' => left / right,
+                    '%' => left % right,
+                    _ => left.powf(right),
+                }
+            }
+            Node::Call(function, args) => {
+                let args: Vec<f64> = args.iter().map(|arg| arg.eval(inputs)).collect();
+                function.call(&args)
+            }
+        }
+    }
+}
+
+fn field_value(payload: Option<&Payload>, path: &[String]) -> f64 {
+    let Some((first, rest)) = path.split_first() else {
+        return 0.0;
+    };
+    let mut value = payload.and_then(|payload| payload.get(first));
+    for key in rest {
+        value = value.and_then(|v| v.get(key));
+    }
+    match value {
+        Some(Value::Number(n)) => n.as_f64().unwrap_or(0.0),
+        Some(Value::Bool(b)) => f64::from(u8::from(*b)),
+        _ => 0.0,
+    }
+}
+
+/// How deeply parentheses, calls and operators may nest. Parsing and
+/// evaluating recurse once per level, so without a cap a long enough
+/// expression overflows the stack, which takes the process down.
+const MAX_DEPTH: usize = 256;
+
+/// Recursive descent over the expression's characters
+struct Parser<'a> {
+    source: &'a str,
+    chars: Vec<(usize, char)>,
+    pos: usize,
+    /// Nesting of the node being parsed
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> String {
+        let offset = self.chars.get(self.pos).map_or(self.source.len(), |&(i, _)| i);
+        format!("Invalid scoreExpr at position {}: {}", offset, message)
+    }
+
+    /// Enter one more level of nesting, failing past `MAX_DEPTH`
+    fn descend(&mut self) -> Result<(), String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+        Ok(())
+    }
+
+    fn skip_space(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).map(|&(_, c)| c)
+    }
+
+    /// Consume `c` if it is the next non-space character
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_space();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expr(&mut self) -> Result<Node, String> {
+        let mut node = self.term()?;
+        let depth = self.depth;
+        loop {
+            let op = if self.eat('+') {
+                '+'
+            } else if self.eat('-') {
+                '-'
+            } else {
+                self.depth = depth;
+                return Ok(node);
+            };
+            // Each operator wraps the chain so far one level deeper
+            self.descend()?;
+            node = Node::Binary(op, Box::new(node), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Node, String> {
+        let mut node = self.unary()?;
+        let depth = self.depth;
+        loop {
+            let op = if self.eat('*') {
+                '*'
+            } else if self.eat('/') {
+                '/'
+            } else if self.eat('%') {
+                '%'
+            } else {
+                self.depth = depth;
+                return Ok(node);
+            };
+            self.descend()?;
+            node = Node::Binary(op, Box::new(node), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        if self.eat('-') {
+            self.descend()?;
+            let node = Node::Negate(Box::new(self.unary()?));
+            self.depth -= 1;
+            return Ok(node);
+        }
+        let base = self.atom()?;
+        if self.eat('^') {
+            // Right-associative, and binds tighter than a unary minus before it
+            self.descend()?;
+            let node = Node::Binary('^', Box::new(base), Box::new(self.unary()?));
+            self.depth -= 1;
+            return Ok(node);
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        self.skip_space();
+        match self.peek() {
+            Some('(') => {
+                self.descend()?;
+                self.pos += 1;
+                let node = self.expr()?;
+                if !self.eat(')') {
+                    return Err(self.error("expected ')'"));
+                }
+                self.depth -= 1;
+                Ok(node)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => self.number(),
+            Some('$') => {
+                self.pos += 1;
+                let start = self.pos;
+                match self.ident().as_str() {
+                    "similarity" => Ok(Node::Similarity),
+                    "score" => Ok(Node::Score),
+                    "insertedAt" => Ok(Node::InsertedAt),
+                    _ => {
+                        self.pos = start;
+                        let expected = "$similarity, $score or $insertedAt";
+                        Err(self.error(&format!("unknown variable; expected {}", expected)))
+                    }
+                }
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let start = self.pos;
+                let name = self.ident();
+                if name == "payload" {
+                    return self.field();
+                }
+                let Some((function, arity)) = Function::parse(&name) else {
+                    self.pos = start;
+                    return Err(self.error(&format!("unknown function '{}'", name)));
+                };
+                if !self.eat('(') {
+                    return Err(self.error("expected '('"));
+                }
+                self.descend()?;
+                let mut args = vec![self.expr()?];
+                while self.eat(',') {
+                    args.push(self.expr()?);
+                }
+                if !self.eat(')') {
+                    return Err(self.error("expected ')'"));
+                }
+                self.depth -= 1;
+                if args.len() != arity {
+                    self.pos = start;
+                    return Err(self.error(&format!(
+                        "{} takes {} argument(s), got {}",
+                        name,
+                        arity,
+                        args.len()
+                    )));
+                }
+                Ok(Node::Call(function, args))
+            }
+            Some(_) => Err(self.error("expected a number, variable or function")),
+            None => Err(self.error("unexpected end of expression")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Node, String> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+            self.pos += 1;
+        }
+        if self.peek().is_some_and(|c| c == 'e' || c == 'E') {
+            self.pos += 1;
+            if self.peek().is_some_and(|c| c == '+' || c == '-') {
+                self.pos += 1;
+            }
+            while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                self.pos += 1;
+            }
+        }
+        let text: String = self.chars[start..self.pos].iter().map(|&(_, c)| c).collect();
+        text.parse().map(Node::Number).map_err(|_| {
+            self.pos = start;
+            self.error(&format!("invalid number '{}'", text))
+        })
+    }
+
+    fn ident(&mut self) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().map(|&(_, c)| c).collect()
+    }
+
+    /// `.a.b` after `payload`
+    fn field(&mut self) -> Result<Node, String> {
+        let mut path = Vec::new();
+        while self.peek() == Some('.') {
+            self.pos += 1;
+            let key = self.ident();
+            if key.is_empty() {
+                return Err(self.error("expected a field name"));
+            }
+            path.push(key);
+        }
+        if path.is_empty() {
+            return Err(self.error("expected '.' and a field name after payload"));
+        }
+        Ok(Node::Field(path))
+    }
+}
@@ -0,0 +1,166 @@
+use serde_json::Value;
+
+use crate::schema::Payload;
+
+/// Predicate over a point's payload, written as an object of field
+/// conditions that must all hold, e.g.
+/// `{ lang: "en", tags: "news", year: { gte: 2020 } }`. A plain value
+/// means equality, or membership when the payload field is an array.
+/// An object value holds operators: eq, ne, in, gt, gte, lt, lte, exists.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PayloadFilter {
+    conditions: Vec<(String, Condition)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Condition {
+    Eq(Value),
+    Ne(Value),
+    In(Vec<Value>),
+    Gt(f64),
+    Gte(f64),
+    Lt(f64),
+    Lte(f64),
+    Exists(bool),
+}
+
+impl PayloadFilter {
+    pub fn parse(filter: &Payload) -> Result<Self, String> {
+        let mut conditions = Vec::new();
+        for (field, value) in filter {
+            match value {
+                Value::Object(operators) => {
+                    for (operator, operand) in operators {
+                        let condition = Condition::parse(field, operator, operand)?;
+                        conditions.push((field.clone(), condition));
+                    }
+                }
+                value => conditions.push((field.clone(), Condition::Eq(value.clone()))),
+            }
+        }
+        Ok(PayloadFilter { conditions })
+    }
+
+    pub fn matches(&self, payload: Option<&Payload>) -> bool {
+        self.conditions.iter().all(|(field, condition)| {
+            condition.holds(payload.and_then(|payload| payload.get(field)))
+        })
+    }
+}
+
+impl Condition {
+    fn parse(field: &str, operator: &str, operand: &Value) -> Result<Self, String> {
+        let number = || {
+            operand.as_f64().ok_or_else(|| {
+                format!("Filter operator '{}' on field '{}' needs a number", operator, field)
+            })
+        };
+        Ok(match operator {
+            "eq" => Condition::Eq(operand.clone()),
+            "ne" => Condition::Ne(operand.clone()),
+            "in" => match operand {
+                Value::Array(values) => Condition::In(values.clone()),
+                _ => {
+                    return Err(format!("Filter operator 'in' on field '{}' needs an array", field))
+                }
+            },
+            "gt" => Condition::Gt(number()?),
+            "gte" => Condition::Gte(number()?),
+            "lt" => Condition::Lt(number()?),
+            "lte" => Condition::Lte(number()?),
+            "exists" => match operand {
+                Value::Bool(exists) => Condition::Exists(*exists),
+                _ => {
+                    return Err(format!(
+                        "Filter operator 'exists' on field '{}' needs a boolean",
+                        field
+                    ))
+                }
+            },
+            other => {
+                return Err(format!("Unknown filter operator '{}' on field '{}'", other, field))
+            }
+        })
+    }
+
+    fn holds(&self, value: Option<&Value>) -> bool {
+        let value = value.filter(|v| !v.is_null());
+        match self {
+            Condition::Exists(exists) => value.is_some() == *exists,
+            Condition::Ne(operand) => !value.is_some_and(|v| matches_value(v, operand)),
+            Condition::Eq(operand) => value.is_some_and(|v| matches_value(v, operand)),
+            Condition::In(operands) => {
+                value.is_some_and(|v| operands.iter().any(|operand| matches_value(v, operand)))
+            }
+            Condition::Gt(bound) => value.and_then(Value::as_f64).is_some_and(|v| v > *bound),
+            Condition::Gte(bound) => value.and_then(Value::as_f64).is_some_and(|v| v >= *bound),
+            Condition::Lt(bound) => value.and_then(Value::as_f64).is_some_and(|v| v < *bound),
+            Condition::Lte(bound) => value.and_then(Value::as_f64).is_some_and(|v| v <= *bound),
+        }
+    }
+}
+
+/// Equality, with numbers compared by value and array fields matching any
+/// of their elements
+fn matches_value(value: &Value, operand: &Value) -> bool {
+    match (value, operand) {
+        (Value::Array(items), operand) if !operand.is_array() => {
+            items.iter().any(|item| equal(item, operand))
+        }
+        _ => equal(value, operand),
+    }
+}
+
+fn equal(a: &Value, b: &Value) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Score adjustment for results whose payload matches `filter`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Boost {
+    pub filter: PayloadFilter,
+    pub weight: f64,
+    /// Add `weight` instead of multiplying by it
+    pub additive: bool,
+}
+
+impl Boost {
+    pub fn apply(&self, score: f64, payload: Option<&Payload>) -> f64 {
+        if !self.filter.matches(payload) {
+            score
+        } else if self.additive {
+            score + self.weight
+        } else {
+            score * self.weight
+        }
+    }
+}
+
+/// Exponential recency decay: a score is halved for every `half_life_ms`
+/// between the point's timestamp and `now`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Decay {
+    /// Payload field holding the timestamp (ms since epoch); the insertion
+    /// time when unset
+    pub field: Option<String>,
+    pub half_life_ms: f64,
+    pub now: i64,
+}
+
+impl Decay {
+    /// Points whose payload lacks a numeric `field` keep their score.
+    pub fn apply(&self, score: f64, payload: Option<&Payload>, inserted_at: i64) -> f64 {
+        let timestamp = match &self.field {
+            Some(field) => match payload.and_then(|p| p.get(field)).and_then(Value::as_f64) {
+                Some(timestamp) => timestamp,
+                None => return score,
+            },
+            None => inserted_at as f64,
+        };
+        let age = (self.now as f64 - timestamp).max(0.0);
+        score * 0.5f64.powf(age / self.half_life_ms)
+    }
+}
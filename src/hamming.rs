@@ -0,0 +1,37 @@
+/// Number of differing bits between two packed bit vectors of equal length.
+/// Works on 8-byte words so the compiler can use popcount instructions and
+/// vectorize the loop.
+pub fn distance(a: &[u8], b: &[u8]) -> u32 {
+    let mut a_words = a.chunks_exact(8);
+    let mut b_words = b.chunks_exact(8);
+    let mut bits: u32 = a_words
+        .by_ref()
+        .zip(b_words.by_ref())
+        .map(|(x, y)| (word(x) ^ word(y)).count_ones())
+        .sum();
+    for (x, y) in a_words.remainder().iter().zip(b_words.remainder()) {
+        bits += (x ^ y).count_ones();
+    }
+    bits
+}
+
+fn word(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes.try_into().expect("chunks_exact yields 8 bytes"))
+}
+
+/// The `k` codes nearest `query` among `codes`, nearest first, with ties
+/// broken by internal id. Only ids `keep` accepts are considered.
+pub fn scan<'a>(
+    codes: impl Iterator<Item = (usize, &'a [u8])>,
+    query: &[u8],
+    k: usize,
+    keep: impl Fn(usize) -> bool,
+) -> Vec<(usize, u32)> {
+    let mut hits: Vec<(usize, u32)> = codes
+        .filter(|&(internal_id, _)| keep(internal_id))
+        .map(|(internal_id, code)| (internal_id, distance(code, query)))
+        .collect();
+    hits.sort_unstable_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+    hits.truncate(k);
+    hits
+}
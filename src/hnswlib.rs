@@ -0,0 +1,156 @@
+use hnsw_rs::prelude::{DistCosine, Hnsw, Neighbour};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::collection::{Collection, Id};
+
+/// Writes the HNSW graph in the binary layout of hnswlib's `saveIndex`
+/// (v0.7+), so it can be opened with `hnswlib.Index(space="cosine", dim)`
+/// followed by `load_index(file)`.
+///
+/// hnswlib labels are integers: label `i` is the i-th entry of the JSON
+/// array written to `<out_file>.ids.json`.
+///
+/// Returns the number of exported points.
+pub fn export_collection(collection: &Collection, out_file: &Path) -> Result<usize, String> {
+    if collection.pending_build.is_some() {
+        return Err(
+            "The index is still being built in the background; try again once it is ready"
+                .to_string(),
+        );
+    }
+    collection.with_graph(|graph| export_graph(collection, graph, out_file))
+}
+
+fn export_graph(
+    collection: &Collection,
+    graph: &Hnsw<'_, f32, DistCosine>,
+    out_file: &Path,
+) -> Result<usize, String> {
+    let m = collection.params.max_nb_connection;
+    let max_m0 = 2 * m;
+    let dims = collection.dimensions;
+
+    // Only live points are exported; stale graph nodes left behind by upserts
+    // and pending deletions are skipped along with any links pointing to them.
+    let mut points = Vec::new();
+    for point in graph.get_point_indexation() {
+        let internal_id = point.get_origin_id();
+        if let Some(uuid) = collection.reverse_map.get(&internal_id) {
+            if !collection.deleted_ids.contains(uuid) {
+                points.push(point);
+            }
+        }
+    }
+    points.sort_by_key(|p| p.get_origin_id());
+
+    let label_of: HashMap<usize, u32> = points
+        .iter()
+        .enumerate()
+        .map(|(label, p)| (p.get_origin_id(), label as u32))
+        .collect();
+
+    // The entry point is the first point on the highest populated layer.
+    // An empty index uses hnswlib's sentinels (-1 for both).
+    let (max_level, entry_point) = points
+        .iter()
+        .enumerate()
+        .map(|(label, p)| (p.get_point_id().0 as i32, label as u32))
+        .fold((-1i32, u32::MAX), |best, cur| if cur.0 > best.0 { cur } else { best });
+
+    let size_links_level0 = max_m0 * 4 + 4;
+    let size_links_per_element = m * 4 + 4;
+    let data_size = dims * 4;
+    let size_data_per_element = size_links_level0 + data_size + 8;
+    let offset_data = size_links_level0;
+    let label_offset = size_links_level0 + data_size;
+    let mult = 1.0 / (m as f64).ln();
+
+    if let Some(parent) = out_file.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory: {}", e))?;
+        }
+    }
+
+    let mut buf: Vec<u8> = Vec::new();
+
+    // Header
+    for v in [0, points.len(), points.len(), size_data_per_element, label_offset, offset_data] {
+        buf.extend_from_slice(&(v as u64).to_le_bytes());
+    }
+    buf.extend_from_slice(&max_level.to_le_bytes());
+    buf.extend_from_slice(&entry_point.to_le_bytes());
+    for v in [m, max_m0, m] {
+        buf.extend_from_slice(&(v as u64).to_le_bytes());
+    }
+    buf.extend_from_slice(&mult.to_le_bytes());
+    buf.extend_from_slice(&(collection.params.ef_construction as u64).to_le_bytes());
+
+    let neighbourhoods: Vec<_> = points.iter().map(|p| p.get_neighborhood_id()).collect();
+
+    // Level 0: links, vector data and label for every element
+    for (label, point) in points.iter().enumerate() {
+        write_links(&mut buf, &neighbourhoods[label][0], max_m0, &label_of);
+
+        // hnswlib's cosine space stores normalized vectors
+        let data = point.get_v();
+        let norm = data.iter().map(|x| x * x).sum::<f32>().sqrt();
+        for &x in data {
+            let v = if norm > 0.0 { x / norm } else { x };
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+        buf.extend_from_slice(&(label as u64).to_le_bytes());
+    }
+
+    // Upper levels: one link block per layer above 0
+    for (label, point) in points.iter().enumerate() {
+        let level = point.get_point_id().0 as usize;
+        let link_list_size = (size_links_per_element * level) as u32;
+        buf.extend_from_slice(&link_list_size.to_le_bytes());
+        for links in &neighbourhoods[label][1..=level] {
+            write_links(&mut buf, links, m, &label_of);
+        }
+    }
+
+    fs::write(out_file, &buf).map_err(|e| format!("Failed to write export file: {}", e))?;
+
+    let ids: Vec<&Id> = points
+        .iter()
+        .filter_map(|p| collection.reverse_map.get(&p.get_origin_id()))
+        .collect();
+    let ids_json = serde_json::to_string(&ids)
+        .map_err(|e| format!("Failed to serialize ids: {}", e))?;
+    fs::write(ids_file(out_file), ids_json)
+        .map_err(|e| format!("Failed to write ids file: {}", e))?;
+
+    Ok(points.len())
+}
+
+/// Path of the sidecar file mapping hnswlib labels back to string ids.
+fn ids_file(out_file: &Path) -> PathBuf {
+    let mut name = out_file.as_os_str().to_owned();
+    name.push(".ids.json");
+    PathBuf::from(name)
+}
+
+/// One hnswlib link list: a u32 count followed by `capacity` u32 slots.
+fn write_links(
+    buf: &mut Vec<u8>,
+    neighbours: &[Neighbour],
+    capacity: usize,
+    label_of: &HashMap<usize, u32>,
+) {
+    let links: Vec<u32> = neighbours
+        .iter()
+        .filter_map(|n| label_of.get(&n.d_id).copied())
+        .take(capacity)
+        .collect();
+
+    buf.extend_from_slice(&(links.len() as u32).to_le_bytes());
+    for slot in 0..capacity {
+        let v = links.get(slot).copied().unwrap_or(0);
+        buf.extend_from_slice(&v.to_le_bytes());
+    }
+}
@@ -0,0 +1,96 @@
+use rand::rngs::StdRng;
+use rand::{RngExt, SeedableRng};
+use std::collections::{HashMap, HashSet};
+
+/// Hash tables when `lshTables` isn't set
+pub const DEFAULT_TABLES: usize = 8;
+/// Hyperplanes per table when `lshBits` isn't set
+pub const DEFAULT_BITS: usize = 12;
+/// Bucket keys are u64s, one bit per hyperplane
+pub const MAX_BITS: usize = 64;
+
+/// Random-hyperplane LSH: each table files a point under the signs of its
+/// dot products with `bits` random hyperplanes, so points at a small angle
+/// tend to share a bucket. A search scores the points sharing a bucket
+/// with the query in any table. More tables find more neighbours; more
+/// bits make buckets smaller and searches faster but miss more.
+#[derive(Clone, Debug, Default)]
+pub struct HyperplaneIndex {
+    /// `planes[t][b]` is hyperplane b of table t
+    planes: Vec<Vec<Vec<f32>>>,
+    /// Internal ids by bucket key, per table. Replaced points stay until
+    /// the next rebuild, like dead graph nodes.
+    tables: Vec<HashMap<u64, Vec<usize>>>,
+    /// Points inserted, including replaced ones
+    len: usize,
+}
+
+impl HyperplaneIndex {
+    /// Hyperplanes with normally distributed components, so their normals
+    /// are uniform over directions. The same `seed` gives the same planes.
+    pub fn new(dimensions: usize, tables: usize, bits: usize, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let planes = (0..tables)
+            .map(|_| {
+                (0..bits)
+                    .map(|_| (0..dimensions).map(|_| gaussian(&mut rng)).collect())
+                    .collect()
+            })
+            .collect();
+        HyperplaneIndex {
+            planes,
+            tables: vec![HashMap::new(); tables],
+            len: 0,
+        }
+    }
+
+    pub fn insert(&mut self, internal_id: usize, vector: &[f32]) {
+        for (planes, table) in self.planes.iter().zip(&mut self.tables) {
+            table.entry(key(planes, vector)).or_default().push(internal_id);
+        }
+        self.len += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Bytes held by the hyperplanes and bucket entries
+    pub fn memory(&self) -> u64 {
+        let planes: usize = self.planes.iter().flatten().map(Vec::len).sum();
+        let entries = self.len * self.tables.len();
+        ((planes * std::mem::size_of::<f32>()) + entries * std::mem::size_of::<usize>()) as u64
+    }
+
+    /// Internal ids sharing a bucket with `query` in any table
+    pub fn candidates(&self, query: &[f32]) -> Vec<usize> {
+        let candidates: HashSet<usize> = self
+            .planes
+            .iter()
+            .zip(&self.tables)
+            .filter_map(|(planes, table)| table.get(&key(planes, query)))
+            .flatten()
+            .copied()
+            .collect();
+        candidates.into_iter().collect()
+    }
+}
+
+/// One bit per hyperplane: set when `vector` is on its positive side
+fn key(planes: &[Vec<f32>], vector: &[f32]) -> u64 {
+    planes.iter().enumerate().fold(0, |key, (bit, plane)| {
+        let dot: f32 = plane.iter().zip(vector).map(|(p, x)| p * x).sum();
+        if dot >= 0.0 {
+            key | 1 << bit
+        } else {
+            key
+        }
+    })
+}
+
+/// A standard normal sample by the Box-Muller transform
+fn gaussian(rng: &mut StdRng) -> f32 {
+    let u: f32 = 1.0 - rng.random::<f32>();
+    let v: f32 = rng.random::<f32>();
+    (-2.0 * u.ln()).sqrt() * (2.0 * std::f32::consts::PI * v).cos()
+}
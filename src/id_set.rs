@@ -0,0 +1,47 @@
+/// Set of internal ids as a bitmap, one bit per id below the largest. Free
+/// ids are reused, so internal ids stay dense and the bitmap stays about
+/// one bit per point however many of them are in the set.
+#[derive(Clone, Debug, Default)]
+pub struct IdSet {
+    bits: Vec<u64>,
+    len: usize,
+}
+
+impl IdSet {
+    pub fn insert(&mut self, internal_id: usize) {
+        let (word, bit) = (internal_id / 64, internal_id % 64);
+        if word >= self.bits.len() {
+            self.bits.resize(word + 1, 0);
+        }
+        if self.bits[word] & (1 << bit) == 0 {
+            self.bits[word] |= 1 << bit;
+            self.len += 1;
+        }
+    }
+
+    pub fn contains(&self, internal_id: usize) -> bool {
+        self.bits
+            .get(internal_id / 64)
+            .is_some_and(|word| word & (1 << (internal_id % 64)) != 0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.bits.iter().enumerate().flat_map(|(word, &bits)| {
+            (0..64).filter(move |bit| bits & (1 << bit) != 0).map(move |bit| word * 64 + bit)
+        })
+    }
+}
+
+impl FromIterator<usize> for IdSet {
+    fn from_iter<I: IntoIterator<Item = usize>>(ids: I) -> Self {
+        let mut set = IdSet::default();
+        for internal_id in ids {
+            set.insert(internal_id);
+        }
+        set
+    }
+}
@@ -0,0 +1,170 @@
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use crate::operations;
+use crate::WriteSummary;
+
+/// Ids and the caller's buffer of their vectors, read when the batch is
+/// inserted
+type Batch = (Vec<String>, Float32Array);
+
+#[napi(string_enum = "lowercase")]
+pub enum PushStatus {
+    /// The batch was queued
+    Ok,
+    /// The queue is full; retry the same batch later
+    Busy,
+}
+
+/// Shared between the stream handle and its worker thread
+#[derive(Default)]
+struct IngestState {
+    inserted: AtomicUsize,
+    /// Time spent inserting batches (µs)
+    insert_micros: AtomicU64,
+    /// First error hit by the worker; later batches are discarded
+    error: Mutex<Option<String>>,
+}
+
+/// Handle returned by `createIngestStream`. Batches pushed here are queued
+/// and inserted by a background thread, so `push` never waits on the graph.
+#[napi]
+pub struct IngestStream {
+    path: String,
+    dimensions: usize,
+    sender: Option<SyncSender<Batch>>,
+    worker: Option<JoinHandle<()>>,
+    state: Arc<IngestState>,
+}
+
+impl IngestStream {
+    pub(crate) fn start(path: String, dimensions: usize, queue_size: usize) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(queue_size);
+        let state = Arc::new(IngestState::default());
+
+        let worker = {
+            let path = path.clone();
+            let state = state.clone();
+            let operation = operations::start("ingest", &crate::collection_key(&path), None);
+            operation.running();
+            thread::spawn(move || run_worker(&path, dimensions, receiver, &state, &operation))
+        };
+
+        IngestStream {
+            path,
+            dimensions,
+            sender: Some(sender),
+            worker: Some(worker),
+            state,
+        }
+    }
+
+    fn worker_error(&self) -> Option<String> {
+        self.state.error.lock().ok().and_then(|e| e.clone())
+    }
+}
+
+#[napi]
+impl IngestStream {
+    /// Queue a batch. `vectors` holds `ids.length` vectors back to back.
+    /// Returns "busy" without queueing anything when the queue is full.
+    /// Otherwise `vectors` is read only when the batch is inserted, so leave
+    /// the buffer unchanged until `finish` returns.
+    #[napi]
+    pub fn push(&self, ids: Vec<String>, vectors: Float32Array) -> Result<PushStatus> {
+        let sender = self.sender.as_ref().ok_or_else(|| {
+            Error::from_reason(format!("Ingest stream for '{}' is finished", self.path))
+        })?;
+        if let Some(e) = self.worker_error() {
+            return Err(Error::from_reason(format!("Ingest failed: {}", e)));
+        }
+
+        if vectors.len() != ids.len() * self.dimensions {
+            return Err(Error::from_reason(format!(
+                "Dimension mismatch: expected {} floats for {} ids, got {}",
+                ids.len() * self.dimensions,
+                ids.len(),
+                vectors.len()
+            )));
+        }
+        if ids.is_empty() {
+            return Ok(PushStatus::Ok);
+        }
+
+        // Not copied here: the worker reads each vector from the buffer as
+        // it stores it
+        match sender.try_send((ids, vectors)) {
+            Ok(()) => Ok(PushStatus::Ok),
+            Err(TrySendError::Full(_)) => Ok(PushStatus::Busy),
+            Err(TrySendError::Disconnected(_)) => Err(Error::from_reason(format!(
+                "Ingest worker for '{}' stopped",
+                self.path
+            ))),
+        }
+    }
+
+    /// Wait for every queued batch to be inserted and close the stream.
+    /// Returns how many points were inserted and how long that took;
+    /// nothing is saved, so call `buildIndex` to persist.
+    #[napi]
+    pub fn finish(&mut self) -> Result<WriteSummary> {
+        // Dropping the sender ends the worker's loop once the queue drains
+        self.sender.take();
+        if let Some(worker) = self.worker.take() {
+            worker
+                .join()
+                .map_err(|_| Error::from_reason("Ingest worker panicked".to_string()))?;
+        }
+
+        if let Some(e) = self.worker_error() {
+            return Err(Error::from_reason(format!("Ingest failed: {}", e)));
+        }
+        Ok(WriteSummary {
+            points: self.state.inserted.load(Ordering::SeqCst) as u32,
+            index_ms: self.state.insert_micros.load(Ordering::SeqCst) as f64 / 1000.0,
+            persist_ms: 0.0,
+            bytes_written: 0.0,
+            committed_seq: None,
+        })
+    }
+}
+
+fn run_worker(
+    path: &str,
+    dimensions: usize,
+    receiver: Receiver<Batch>,
+    state: &IngestState,
+    operation: &operations::Handle,
+) {
+    for (ids, vectors) in receiver {
+        let failed = state.error.lock().map(|e| e.is_some()).unwrap_or(true);
+        if failed {
+            continue;
+        }
+
+        let _permit = crate::limits::acquire(&crate::collection_key(path));
+        let started = Instant::now();
+        let batch = ids.into_iter().zip(vectors.chunks_exact(dimensions)).collect();
+        let inserted = crate::insert_batch(path, batch);
+        state
+            .insert_micros
+            .fetch_add(started.elapsed().as_micros() as u64, Ordering::SeqCst);
+        match inserted {
+            Ok(count) => {
+                let inserted = state.inserted.fetch_add(count, Ordering::SeqCst) + count;
+                operation.progress(inserted);
+            }
+            Err(e) => {
+                operation.fail(&e.reason);
+                if let Ok(mut error) = state.error.lock() {
+                    *error = Some(e.reason.clone());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use hnsw_rs::prelude::*;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rayon::prelude::*;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::pq::ProductQuantizer;
+
+/// Lists probed per query when a search doesn't set `nprobe`
+pub const DEFAULT_NPROBE: usize = 8;
+/// Candidates per result rescored against full vectors when a search of a
+/// quantized collection doesn't set `rescore`
+pub const DEFAULT_RESCORE: usize = 4;
+/// Lloyd iterations per training
+const TRAIN_ITERATIONS: usize = 10;
+/// Points sampled per list to train the centroids on
+const TRAIN_SAMPLES_PER_LIST: usize = 256;
+
+/// How much of an IVF index a search visits
+#[derive(Clone, Copy, Debug)]
+pub struct Probe {
+    /// Lists scanned
+    pub lists: usize,
+    /// Quantized candidates per result kept for exact rescoring
+    pub rescore: usize,
+}
+
+impl Default for Probe {
+    fn default() -> Self {
+        Probe {
+            lists: DEFAULT_NPROBE,
+            rescore: DEFAULT_RESCORE,
+        }
+    }
+}
+
+/// Inverted file index: points are filed under their nearest k-means
+/// centroid, and a search only scores the points in the lists of the
+/// `nprobe` centroids nearest the query.
+#[derive(Clone, Debug, Default)]
+pub struct IvfIndex {
+    centroids: Vec<Vec<f32>>,
+    /// Internal ids filed under each centroid. Replaced points stay until
+    /// the next training, like dead graph nodes.
+    lists: Vec<Vec<usize>>,
+    /// Points inserted while there were no centroids; every search scans
+    /// them
+    unassigned: Vec<usize>,
+    /// Points the centroids were trained on
+    trained_on: usize,
+    /// Points inserted since
+    added: usize,
+    /// Set for IVF-PQ, which keeps a code per point to rank candidates by
+    pq: Option<ProductQuantizer>,
+    codes: HashMap<usize, Vec<u8>>,
+}
+
+impl IvfIndex {
+    /// Train `lists` centroids (√n by default) on the points by k-means and
+    /// file every point under its nearest one, encoding it with a product
+    /// quantizer of `pq_subvectors` subspaces if set. Points are read
+    /// through `vector`. The same points and `seed` give the same index.
+    pub fn train<'a>(
+        ids: &[usize],
+        vector: impl Fn(usize) -> Option<Cow<'a, [f32]>> + Sync,
+        lists: Option<usize>,
+        pq_subvectors: Option<usize>,
+        seed: u64,
+    ) -> Self {
+        let lists = lists
+            .unwrap_or_else(|| (ids.len() as f64).sqrt() as usize)
+            .clamp(1, ids.len().max(1));
+        if ids.is_empty() {
+            return IvfIndex::default();
+        }
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut sample_ids = ids.to_vec();
+        sample_ids.shuffle(&mut rng);
+        sample_ids.truncate(lists * TRAIN_SAMPLES_PER_LIST);
+        let sample: Vec<Cow<[f32]>> = sample_ids.into_iter().filter_map(&vector).collect();
+        let sample: Vec<&[f32]> = sample.iter().map(AsRef::as_ref).collect();
+        if sample.is_empty() {
+            return IvfIndex::default();
+        }
+        let centroids = kmeans(&sample, lists.min(sample.len()), |a, b| DistCosine.eval(a, b));
+        let pq = pq_subvectors.map(|subvectors| ProductQuantizer::train(&sample, subvectors));
+
+        let assigned: Vec<Option<(usize, Option<Vec<u8>>)>> = crate::pool::install(|| {
+            ids.par_iter()
+                .map(|&internal_id| {
+                    let vector = vector(internal_id)?;
+                    let code = pq.as_ref().map(|pq| pq.encode(&vector));
+                    Some((nearest(&centroids, &vector), code))
+                })
+                .collect()
+        });
+        let mut index = IvfIndex {
+            lists: vec![Vec::new(); centroids.len()],
+            centroids,
+            unassigned: Vec::new(),
+            trained_on: ids.len(),
+            added: 0,
+            pq,
+            codes: HashMap::new(),
+        };
+        for (&internal_id, assigned) in ids.iter().zip(assigned) {
+            if let Some((list, code)) = assigned {
+                index.lists[list].push(internal_id);
+                if let Some(code) = code {
+                    index.codes.insert(internal_id, code);
+                }
+            }
+        }
+        index
+    }
+
+    pub fn insert(&mut self, internal_id: usize, vector: &[f32]) {
+        if self.centroids.is_empty() {
+            self.unassigned.push(internal_id);
+        } else {
+            let list = nearest(&self.centroids, vector);
+            self.lists[list].push(internal_id);
+        }
+        if let Some(pq) = &self.pq {
+            self.codes.insert(internal_id, pq.encode(vector));
+        }
+        self.added += 1;
+    }
+
+    /// Entries in the lists, including replaced points
+    pub fn len(&self) -> usize {
+        self.lists.iter().map(Vec::len).sum::<usize>() + self.unassigned.len()
+    }
+
+    /// Bytes held by the centroids, lists and codes
+    pub fn memory(&self, dimensions: usize) -> u64 {
+        let centroids = self.centroids.len() * dimensions * std::mem::size_of::<f32>();
+        let entries = self.len() * std::mem::size_of::<usize>();
+        let codes = self.pq.as_ref().map_or(0, |pq| {
+            // 256 centroids per subspace spanning the dimensions between them
+            256 * dimensions * std::mem::size_of::<f32>()
+                + self.codes.len() * (pq.subvectors() + std::mem::size_of::<usize>())
+        });
+        (centroids + entries + codes) as u64
+    }
+
+    /// Whether more points have been inserted since the last training than
+    /// it was trained on, so the centroids may no longer fit the data
+    pub fn needs_training(&self) -> bool {
+        self.added > self.trained_on
+    }
+
+    /// Internal ids in the lists of the `nprobe` centroids nearest `query`,
+    /// plus any unassigned points
+    pub fn candidates(&self, query: &[f32], nprobe: usize) -> Vec<usize> {
+        let mut nearest: Vec<(usize, f32)> = self
+            .centroids
+            .iter()
+            .enumerate()
+            .map(|(list, centroid)| (list, DistCosine.eval(query, centroid)))
+            .collect();
+        nearest.sort_unstable_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        nearest
+            .into_iter()
+            .take(nprobe)
+            .flat_map(|(list, _)| self.lists[list].iter().copied())
+            .chain(self.unassigned.iter().copied())
+            .collect()
+    }
+
+    /// The `n` candidates whose codes score best against `query`, plus
+    /// any without a code. Without a quantizer every candidate is kept.
+    pub fn shortlist(&self, query: &[f32], candidates: Vec<usize>, n: usize) -> Vec<usize> {
+        let Some(pq) = &self.pq else {
+            return candidates;
+        };
+        let table = pq.table(query);
+        let (coded, mut uncoded): (Vec<usize>, Vec<usize>) =
+            candidates.into_iter().partition(|id| self.codes.contains_key(id));
+        let mut scored: Vec<(usize, f32)> = coded
+            .into_iter()
+            .map(|id| (id, ProductQuantizer::score(&table, &self.codes[&id])))
+            .collect();
+        scored.sort_unstable_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        scored.truncate(n);
+        uncoded.extend(scored.into_iter().map(|(id, _)| id));
+        uncoded
+    }
+}
+
+/// Lloyd's k-means over `sample`, which should be shuffled: the first `k`
+/// points are the initial centroids.
+pub fn kmeans(
+    sample: &[&[f32]],
+    k: usize,
+    distance: impl Fn(&[f32], &[f32]) -> f32 + Sync,
+) -> Vec<Vec<f32>> {
+    let mut centroids: Vec<Vec<f32>> = sample[..k].iter().map(|v| v.to_vec()).collect();
+
+    for _ in 0..TRAIN_ITERATIONS {
+        let assigned: Vec<usize> = crate::pool::install(|| {
+            sample.par_iter().map(|vector| closest(&centroids, vector, &distance)).collect()
+        });
+        let dims = centroids[0].len();
+        let mut sums = vec![vec![0.0f32; dims]; k];
+        let mut counts = vec![0usize; k];
+        for (vector, &list) in sample.iter().zip(&assigned) {
+            counts[list] += 1;
+            for (sum, x) in sums[list].iter_mut().zip(vector.iter()) {
+                *sum += x;
+            }
+        }
+        // A centroid nothing was assigned to keeps its place
+        for ((centroid, sum), count) in centroids.iter_mut().zip(sums).zip(counts) {
+            if count > 0 {
+                *centroid = sum.into_iter().map(|x| x / count as f32).collect();
+            }
+        }
+    }
+    centroids
+}
+
+fn nearest(centroids: &[Vec<f32>], vector: &[f32]) -> usize {
+    closest(centroids, vector, |a, b| DistCosine.eval(a, b))
+}
+
+fn closest(
+    centroids: &[Vec<f32>],
+    vector: &[f32],
+    distance: impl Fn(&[f32], &[f32]) -> f32,
+) -> usize {
+    centroids
+        .iter()
+        .map(|centroid| distance(vector, centroid))
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(0, |(list, _)| list)
+}
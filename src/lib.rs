@@ -1,224 +1,4886 @@
+mod alerts;
+mod audit;
+mod bloom;
+mod buffers;
+mod calibration;
 mod collection;
+mod expr;
+mod filter;
+mod hamming;
+mod hnswlib;
+mod hyperplane;
+mod id_set;
+mod ingest;
+mod ivf;
+mod limits;
+mod maintenance;
+mod mapped_graph;
+mod minhash;
+mod openapi;
+mod operations;
 mod persistence;
+mod pool;
+mod pq;
+mod projection;
+mod schema;
+mod server;
+mod text;
+mod unwind;
+mod vector_file;
+mod write_queue;
 
-use collection::Collection;
+use alerts::{AlertCallback, CapacityAlert, CapacityThresholds};
+use calibration::Calibration;
+use collection::{
+    Collection, CompactionPolicy, EntryLimits, HnswParams, Id, PendingBuild, PointFilter, Quota,
+    RetryPolicy, PointKind, SearchDefaults, DEFAULT_INDEX_TYPE, DEFAULT_METRIC, FLAT_INDEX_TYPE,
+    HAMMING_METRIC, IVF_INDEX_TYPE, JACCARD_METRIC, LSH_INDEX_TYPE, MINHASH_INDEX_TYPE,
+};
+use expr::ScoreExpr;
+use id_set::IdSet;
+use filter::{Boost, Decay, PayloadFilter};
+use ingest::IngestStream;
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
 use once_cell::sync::Lazy;
-use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::RwLock;
+use operations::Operation;
+use persistence::Durability;
+use projection::InputProjection;
+use schema::{FieldType, Payload, Projection, Schema};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::{Duration, Instant};
+use text::{Analyzer, Analyzers};
+use unwind::guard;
+use write_queue::WriteOp;
 
 static COLLECTIONS: Lazy<RwLock<HashMap<String, Collection>>> =
     Lazy::new(|| RwLock::new(HashMap::new()));
 
+/// Thrown by the call that finds a panic has poisoned `COLLECTIONS`, and
+/// recorded as `lastError` on every loaded collection
+const LOCK_POISONED: &str = "LOCK_POISONED: an operation panicked while holding the collection \
+     lock, so changes since the last save may be incomplete; reopen the collection to be safe";
+
+/// Shared access to the loaded collections. See `write_collections`.
+pub(crate) fn read_collections() -> Result<RwLockReadGuard<'static, HashMap<String, Collection>>> {
+    if COLLECTIONS.is_poisoned() {
+        drop(write_collections()?);
+    }
+    Ok(COLLECTIONS.read().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Exclusive access to the loaded collections. A panic while the lock was
+/// held poisons it. The call that finds it poisoned fails with
+/// `LOCK_POISONED`, having cleared the poison and flagged each collection
+/// as unhealthy, since the one being changed may have been left
+/// half-updated; later calls go through.
+pub(crate) fn write_collections() -> Result<RwLockWriteGuard<'static, HashMap<String, Collection>>>
+{
+    COLLECTIONS.write().map_err(|e| {
+        COLLECTIONS.clear_poison();
+        for coll in e.into_inner().values_mut() {
+            coll.record_error(LOCK_POISONED);
+        }
+        Error::from_reason(LOCK_POISONED)
+    })
+}
+
+/// Exclusive access for background threads, which have no caller to throw
+/// `LOCK_POISONED` to. They carry on, leaving the poison for the next call.
+pub(crate) fn background_collections() -> RwLockWriteGuard<'static, HashMap<String, Collection>> {
+    COLLECTIONS.write().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Shared access for background threads. See `background_collections`.
+pub(crate) fn background_read_collections(
+) -> RwLockReadGuard<'static, HashMap<String, Collection>> {
+    COLLECTIONS.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Panic while holding the collection lock, poisoning it. Only in debug
+/// builds, for testing the recovery.
+#[cfg(debug_assertions)]
+#[napi(js_name = "__panicHoldingLock")]
+pub fn panic_holding_lock() -> Result<()> {
+    guard("__panicHoldingLock", || {
+        let _collections = write_collections()?;
+        panic!("deliberate panic while holding the collection lock")
+    })
+}
+
+/// Held while a directory is being opened, so two opens of one collection
+/// never race for its lock while different collections load in parallel.
+static OPENING: Lazy<Mutex<HashMap<String, Arc<Mutex<()>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Configs registered with `registerTemplate`, by name
+static TEMPLATES: Lazy<Mutex<HashMap<String, TemplateConfig>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Config from `setAutoCreate` that inserts open unknown paths with
+static AUTO_CREATE: Lazy<Mutex<Option<TemplateConfig>>> = Lazy::new(|| Mutex::new(None));
+
+/// The `OPENING` lock of the collection at `key`
+fn opening(key: &str) -> Arc<Mutex<()>> {
+    OPENING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(key.to_string())
+        .or_default()
+        .clone()
+}
+
+/// Key of each path a loaded collection was opened by, as given, so calls
+/// with that path don't canonicalize it again. Entries go when the
+/// collection is closed or renamed.
+static KEYS: Lazy<RwLock<HashMap<String, String>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Key in `COLLECTIONS` for a user-supplied path. Every spelling of the
+/// same directory maps to one key so it is never loaded twice. Paths a
+/// collection was opened by are looked up in `KEYS`; any other is
+/// canonicalized, which touches the filesystem.
+fn collection_key(path: &str) -> String {
+    if let Some(key) = KEYS.read().unwrap_or_else(|e| e.into_inner()).get(path) {
+        return key.clone();
+    }
+    persistence::canonical_path(Path::new(path))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Remember that `path` names the collection loaded at `key`
+fn remember_key(path: &str, key: &str) {
+    KEYS.write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(path.to_string(), key.to_string());
+}
+
+/// Forget every path naming `key`, once no collection is loaded there
+fn forget_key(key: &str) {
+    KEYS.write()
+        .unwrap_or_else(|e| e.into_inner())
+        .retain(|_, cached| cached != key);
+}
+
+/// Define the config structs that share the optional settings, so each
+/// setting is listed once and templates can't leave one behind
+macro_rules! collection_configs {
+    ($($(#[$doc:meta])* $field:ident: $ty:ty,)*) => {
+        #[napi(object)]
+        pub struct CollectionConfig {
+            pub path: String,
+            pub dimensions: u32,
+            pub index_type: String,
+            pub metric: String,
+            $($(#[$doc])* pub $field: Option<$ty>,)*
+        }
+
+        /// The settings of a `CollectionConfig` without its path, all
+        /// optional, for `registerTemplate` and `setAutoCreate`
+        #[napi(object)]
+        #[derive(Clone, Default)]
+        pub struct TemplateConfig {
+            pub dimensions: Option<u32>,
+            pub index_type: Option<String>,
+            pub metric: Option<String>,
+            $($(#[$doc])* pub $field: Option<$ty>,)*
+        }
+
+        /// What `createCollection` takes: a `CollectionConfig`, or one
+        /// naming a template that it takes every setting it leaves unset
+        /// from
+        #[napi(object)]
+        pub struct TemplatedCollectionConfig {
+            pub template: Option<String>,
+            pub path: String,
+            pub dimensions: Option<u32>,
+            pub index_type: Option<String>,
+            pub metric: Option<String>,
+            $($(#[$doc])* pub $field: Option<$ty>,)*
+        }
+
+        impl TemplatedCollectionConfig {
+            /// This config, with the settings it leaves unset taken from
+            /// `template`. Without a template it must set the dimensions,
+            /// index type and metric itself
+            fn apply(self, template: TemplateConfig) -> Result<CollectionConfig> {
+                TemplateConfig {
+                    dimensions: self.dimensions.or(template.dimensions),
+                    index_type: self.index_type.or(template.index_type),
+                    metric: self.metric.or(template.metric),
+                    $($field: self.$field.or(template.$field),)*
+                }
+                .at(self.path)
+            }
+        }
+
+        impl TemplateConfig {
+            /// The config of a collection at `path` with these settings,
+            /// which must include the dimensions, index type and metric
+            fn at(self, path: String) -> Result<CollectionConfig> {
+                Ok(CollectionConfig {
+                    path,
+                    dimensions: required(self.dimensions, "dimensions")?,
+                    index_type: required(self.index_type, "indexType")?,
+                    metric: required(self.metric, "metric")?,
+                    $($field: self.$field,)*
+                })
+            }
+        }
+    };
+}
+
+collection_configs! {
+    /// HNSW M parameter (max connections per node), default 16
+    m: u32,
+    /// HNSW ef_construction, default 200
+    ef_construction: u32,
+    /// Expected number of elements, used to size the graph, default 100000
+    max_elements: u32,
+    /// Lists of `indexType: "ivf"` collections, default √n of the points
+    /// each time the centroids are trained. Saved with the collection
+    ivf_lists: u32,
+    /// Compress the points of an `indexType: "ivf"` collection to product
+    /// quantizer codes of this many bytes, keeping the full vectors on
+    /// disk for rescoring (IVF-PQ). Must divide `dimensions`
+    pq_subvectors: u32,
+    /// Hash tables of `indexType: "lsh"` collections, default 8; more find
+    /// more of the true neighbours
+    lsh_tables: u32,
+    /// Hyperplanes per LSH table, 1 to 64, default 12; more make buckets
+    /// smaller and searches faster but miss more neighbours
+    lsh_bits: u32,
+    /// Open without taking the writer lock; mutations are rejected and
+    /// `reloadCollection` picks up the writer's latest save
+    read_only: bool,
+    /// Also dump the graph and vectors as mmap-able segments on every save,
+    /// so other processes can `attachReadonly` without copying the vectors
+    shared_segments: bool,
+    /// Keep deleted points restorable for this many days; `buildIndex`
+    /// only purges older tombstones. Default: purge on every build
+    tombstone_retention_days: f64,
+    /// Make `insertVector` throw when the id already exists instead of
+    /// replacing the point. Saved with the collection
+    strict_insert: bool,
+    /// Record every mutating operation in an append-only audit log, read
+    /// back with `getAuditLog`. Saved with the collection
+    audit_log: bool,
+    /// How far each save is flushed to disk: "none" (default) leaves it to
+    /// the OS, "flush" syncs the metadata file before it replaces the old
+    /// one, "fsync" also syncs the segments and directory. Saved with the
+    /// collection
+    durability: String,
+    /// Check a bloom filter before the id map, so lookups of ids that were
+    /// never inserted mostly skip it. Saved with the collection
+    id_filter: bool,
+    /// Open without rebuilding the graph: searches scan every vector until
+    /// the graph, built in the background, is swapped in
+    lazy_index: bool,
+    /// Compact in the background when dead graph nodes cross these
+    /// thresholds. Saved with the collection
+    auto_compaction: AutoCompactionConfig,
+    /// Usage levels that raise capacity alerts, reported by `health` and
+    /// passed to the `onCapacityAlert` callback. Saved with the collection
+    capacity_alerts: CapacityAlertConfig,
+    /// Limits on the collection's size; inserts past them fail with a
+    /// `QUOTA_EXCEEDED` error. Saved with the collection
+    quota: QuotaConfig,
+    /// Limits on each point's id and payload; inserts and payload updates
+    /// past them fail with an `ID_TOO_LONG` or `PAYLOAD_TOO_LARGE` error.
+    /// Saved with the collection
+    entry_limits: EntryLimitsConfig,
+    /// Embedding model the vectors come from, e.g. "text-embedding-3-small".
+    /// Inserts tagged with a different model are rejected, so outputs of
+    /// incompatible models aren't mixed. Can be added to an untagged
+    /// collection but not changed. Saved with the collection
+    model_tag: String,
+    /// Payload field types, e.g. `{ title: "string", price: "float",
+    /// tags: "string[]" }`. Inserts whose payload doesn't conform are
+    /// rejected. Saved with the collection
+    schema: HashMap<String, String>,
+    /// How the text of payload fields is split into terms for lexical
+    /// matching, keyed by field. Saved with the collection
+    analyzers: HashMap<String, AnalyzerConfig>,
+    /// Build the graph deterministically, so the same inserts in the same
+    /// order give an identical index. Saved with the collection
+    seed: u32,
+    /// Re-insert points in shuffled order (by `seed` if set) when the graph
+    /// is rebuilt, for better recall on sorted or clustered data. Saved
+    /// with the collection
+    shuffle_on_rebuild: bool,
+    /// Scale inserted vectors and queries to unit length, so exact scoring
+    /// is a dot product instead of a full cosine. Only set on creation;
+    /// saved with the collection
+    normalize: bool,
+}
+
+#[napi(object)]
+#[derive(Clone)]
+pub struct AnalyzerConfig {
+    /// Lowercase terms, default true
+    pub lowercase: Option<bool>,
+    /// Snowball stemmer language, e.g. "english" or "german"
+    pub stemmer: Option<String>,
+    /// Words to drop; matched after lowercasing
+    pub stopwords: Option<Vec<String>>,
+    /// Index character n-grams of each term instead of the term itself
+    pub ngram: Option<NgramRange>,
+}
+
+#[napi(object)]
+#[derive(Clone)]
+pub struct NgramRange {
+    pub min: u32,
+    pub max: u32,
+}
+
+#[napi(object)]
+#[derive(Clone)]
+pub struct TextToken {
+    pub term: String,
+    /// Start of the source word in the text (JS string index)
+    pub start: u32,
+    /// End of the source word in the text (exclusive)
+    pub end: u32,
+}
+
+#[napi(object)]
+#[derive(Clone)]
+pub struct CapacityAlertConfig {
+    /// Fractions of `maxElements` (and of `memoryLimitBytes`), default
+    /// [0.8, 0.95]
+    pub levels: Option<Vec<f64>>,
+    /// Also raise alerts as the estimated memory of the vectors and graph
+    /// reaches the levels of this many bytes
+    pub memory_limit_bytes: Option<f64>,
+}
+
+#[napi(object)]
+#[derive(Clone)]
+pub struct QuotaConfig {
+    /// Live points; inserts of new ids past it fail
+    pub max_points: Option<u32>,
+    /// Estimated memory of the vectors and index, as in
+    /// `capacityAlerts.memoryLimitBytes`; inserts fail once it is reached
+    pub max_bytes: Option<f64>,
+}
+
+#[napi(object)]
+#[derive(Clone)]
+pub struct EntryLimitsConfig {
+    /// Id length in UTF-8 bytes
+    pub max_id_length: Option<u32>,
+    /// Payload size as the JSON it is saved as
+    pub max_payload_bytes: Option<u32>,
+}
+
+/// A collection's usage against its quota
+#[napi(object)]
+pub struct QuotaUsage {
+    pub points: u32,
+    pub max_points: Option<u32>,
+    /// Estimated memory of the vectors and index
+    pub bytes: f64,
+    pub max_bytes: Option<f64>,
+}
+
+#[napi(object)]
+#[derive(Clone)]
+pub struct AutoCompactionConfig {
+    /// Fraction of graph nodes that are dead before compacting, default 0.2
+    pub tombstone_ratio: Option<f64>,
+    /// Minimum number of dead graph nodes before compacting, default 1000
+    pub min_deleted: Option<u32>,
+}
+
+#[napi(object)]
+pub struct DeletedPoint {
+    pub id: String,
+    /// Deletion time (ms since epoch); 0 for tombstones saved before it was recorded
+    pub deleted_at: f64,
+}
+
+#[napi(object)]
+pub struct ChangeSet {
+    /// Ids inserted, updated or restored since then and live now
+    pub upserted: Vec<String>,
+    /// Ids deleted since then and still deleted
+    pub deleted: Vec<String>,
+    /// The collection's current sequence number, to pass next time
+    pub seq: f64,
+}
+
+#[napi(object)]
+pub struct ReindexConfig {
+    pub index_type: Option<String>,
+    pub metric: Option<String>,
+    pub m: Option<u32>,
+    pub ef_construction: Option<u32>,
+    pub max_elements: Option<u32>,
+    pub seed: Option<u32>,
+    pub shuffle_on_rebuild: Option<bool>,
+    pub ivf_lists: Option<u32>,
+    /// 0 turns product quantization off
+    pub pq_subvectors: Option<u32>,
+    pub lsh_tables: Option<u32>,
+    pub lsh_bits: Option<u32>,
+}
+
+#[napi(object)]
+pub struct ServerConfig {
+    /// Collections to expose, keyed by the name used in URLs
+    pub collections: HashMap<String, String>,
+    /// Interface to bind, default "127.0.0.1"
+    pub host: Option<String>,
+    /// When set, every request must send one of these tokens as
+    /// `Authorization: Bearer <token>` and may only do what it grants
+    pub tokens: Option<Vec<ApiToken>>,
+    /// Serve HTTPS with this certificate instead of plain HTTP
+    pub tls: Option<TlsConfig>,
+}
+
+#[napi(object)]
+pub struct TlsConfig {
+    /// PEM file with the certificate chain, leaf first
+    pub cert_path: String,
+    /// PEM file with the private key (PKCS#8, PKCS#1 or SEC1)
+    pub key_path: String,
+}
+
+#[napi(object)]
+pub struct ApiToken {
+    pub token: String,
+    /// URL names of the collections the token may use; "*" for all
+    pub collections: Vec<String>,
+    /// "read" (stats, health, search) or "write" (also inserts, deletes
+    /// and builds)
+    pub scope: String,
+}
+
+#[napi(object)]
+pub struct IngestOptions {
+    /// Batches that may wait for the worker before `push` returns "busy", default 16
+    pub queue_size: Option<u32>,
+    /// Embedding model of every vector pushed, checked against the
+    /// collection's `modelTag` when the stream is created
+    pub model_tag: Option<String>,
+}
+
+/// Default number of pending batches per ingest stream
+const DEFAULT_INGEST_QUEUE_SIZE: u32 = 16;
+
+/// Default over-fetch multiplier when reranking
+const DEFAULT_RERANK_FACTOR: u32 = 4;
+
+#[napi(object)]
+#[derive(Default)]
+pub struct SearchOptions<'a> {
+    /// HNSW ef at query time, default max(k * 10, 200)
+    pub ef_search: Option<u32>,
+    /// IVF lists scanned at query time, default 8; more is slower but
+    /// finds more of the true neighbours
+    pub nprobe: Option<u32>,
+    /// IVF-PQ candidates per result rescored against the full vectors,
+    /// default 4; more is slower but corrects more quantization error
+    pub pq_rescore: Option<u32>,
+    /// Called once with `k * rerankFactor` candidates (best first); must
+    /// return one score per candidate. Results are re-ordered by these
+    /// scores (higher is better) before slicing to k.
+    pub rerank: Option<Function<'a, Vec<SearchResult>, Vec<f64>>>,
+    /// Over-fetch multiplier for `rerank` and `boosts`, default 4
+    pub rerank_factor: Option<u32>,
+    /// Adjust the scores of results whose payload matches a filter, then
+    /// re-rank. Candidates are over-fetched by `rerankFactor` so boosted
+    /// points from just outside the top k can move up
+    pub boosts: Option<Vec<SearchBoost>>,
+    /// Favour recent points: scores are halved for every `halfLifeDays` of
+    /// age. Applied before `boosts`, with the same over-fetch
+    pub decay: Option<DecayOptions>,
+    /// Replace each score with an expression over it and the payload, e.g.
+    /// `0.8 * $similarity + 0.2 * log(1 + payload.popularity)`. Applied
+    /// after `decay` and `boosts`, with the same over-fetch. Nesting is
+    /// capped at 256 levels
+    pub score_expr: Option<String>,
+    /// Token vectors of the query for ColBERT-style late interaction: each
+    /// candidate is rescored by summing, over the query tokens, the best dot
+    /// product with its own token vectors from `setTokenVectors`. Applied
+    /// before `decay`, with the same over-fetch; candidates without token
+    /// vectors are dropped
+    pub late_interaction: Option<Vec<Float32Array>>,
+    /// Only points inserted at or after this time (ms since epoch)
+    pub inserted_after: Option<f64>,
+    /// Only points inserted before this time (ms since epoch)
+    pub inserted_before: Option<f64>,
+    /// Only these points, e.g. the ones an access-control system lets the
+    /// caller see. Unknown ids are ignored. A list no longer than
+    /// `efSearch` is scored exactly instead of searching the index
+    pub allow_ids: Option<Vec<String>>,
+    /// Like `allowIds`, from a set made once by `createIdSet`
+    pub allow_set: Option<u32>,
+    /// Leave out the points in a set made by `createIdSet`
+    pub deny_set: Option<u32>,
+    /// How many times to widen the search when deleted points leave fewer
+    /// than k results, default 8
+    pub max_retries: Option<u32>,
+    /// Candidate-count multiplier per retry, default 2
+    pub retry_growth: Option<u32>,
+    /// Report where the query's terms occur in a text payload field
+    pub highlight: Option<HighlightOptions>,
+    /// Return each result's payload
+    pub include_payload: Option<bool>,
+    /// Return only these payload keys; implies `includePayload`
+    pub payload_fields: Option<Vec<String>>,
+    /// Drop results scoring below this
+    pub score_threshold: Option<f64>,
+    /// Latency budget. When it runs out, search stops widening and returns
+    /// `{ results, partial }` instead of an array
+    pub timeout_ms: Option<u32>,
+    /// Return `{ ids, scores, insertedAt }` parallel arrays instead of one
+    /// object per result. Can't be combined with `highlight`
+    pub columnar: Option<bool>,
+    /// With `columnar`, return numeric `handles` in place of `ids`, skipping
+    /// a string per result. Map them back with `resolveIds`. Can't be
+    /// combined with `rerank`
+    pub handles: Option<bool>,
+    /// Map the final scores onto [0, 1] so results of different collections
+    /// can be merged: "unit" rescales cosine similarity's [-1, 1], "probit"
+    /// ranks each score against the others returned, and `{ min, max }`
+    /// rescales that range. `scoreThreshold` still applies to raw scores
+    pub calibration: Option<Either<String, CalibrationRange>>,
+}
+
+#[napi(object)]
+pub struct SearchBoost {
+    /// Payload conditions that must all hold, e.g.
+    /// `{ lang: "en", year: { gte: 2020 } }`. Operators: eq, ne, in, gt,
+    /// gte, lt, lte, exists
+    pub filter: Payload,
+    pub weight: f64,
+    /// "multiply" (default) or "add"
+    pub mode: Option<String>,
+}
+
+#[napi(object)]
+pub struct DecayOptions {
+    /// Payload field holding a timestamp in ms since epoch. Defaults to the
+    /// point's insertion time. Points without a numeric value keep their score
+    pub field: Option<String>,
+    pub half_life_days: f64,
+}
+
+#[napi(object)]
+pub struct CalibrationRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+#[napi(object)]
+pub struct TimedSearchResults {
+    pub results: Vec<SearchResult>,
+    /// The budget ran out before k results were found
+    pub partial: bool,
+}
+
+/// Search results as parallel arrays, best first, from `columnar: true`
+#[napi(object)]
+pub struct ColumnarSearchResults {
+    /// Result ids; absent with `handles`
+    pub ids: Option<Vec<String>>,
+    /// Result handles, with `handles`
+    pub handles: Option<BigUint64Array>,
+    pub scores: Float64Array,
+    pub inserted_at: Float64Array,
+    /// Each result's payload (null when it has none), when requested
+    pub payloads: Option<Vec<Option<Payload>>>,
+    /// The budget ran out before k results were found; only with `timeoutMs`
+    pub partial: Option<bool>,
+}
+
+#[napi(object)]
+pub struct HighlightOptions {
+    /// Payload field holding the text, analyzed with its field analyzer
+    pub field: String,
+    /// Text whose terms are looked for
+    pub query: String,
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct SearchStreamOptions {
+    /// Results per chunk, default 1000
+    pub chunk_size: Option<u32>,
+    /// HNSW ef at query time, default max(k * 10, 200)
+    pub ef_search: Option<u32>,
+    /// Only points inserted at or after this time (ms since epoch)
+    pub inserted_after: Option<f64>,
+    /// Only points inserted before this time (ms since epoch)
+    pub inserted_before: Option<f64>,
+    /// Return each result's payload
+    pub include_payload: Option<bool>,
+    /// Return only these payload keys; implies `includePayload`
+    pub payload_fields: Option<Vec<String>>,
+    /// Drop results scoring below this
+    pub score_threshold: Option<f64>,
+}
+
+/// Default number of results per `searchStream` chunk
+const DEFAULT_STREAM_CHUNK_SIZE: u32 = 1000;
+
+/// Iterator returned by `searchStream`. Each step yields the next chunk of
+/// results, best first, so a large k never crosses into JS as one array.
+#[napi(iterator)]
+pub struct SearchStream {
+    results: std::vec::IntoIter<SearchResult>,
+    chunk_size: usize,
+}
+
+#[napi]
+impl Generator for SearchStream {
+    type Yield = Vec<SearchResult>;
+    type Next = ();
+    type Return = ();
+
+    fn next(&mut self, _value: Option<()>) -> Option<Self::Yield> {
+        let chunk: Vec<SearchResult> = self.results.by_ref().take(self.chunk_size).collect();
+        if chunk.is_empty() {
+            None
+        } else {
+            Some(chunk)
+        }
+    }
+}
+
+#[napi(object)]
+pub struct KnnGraphOptions<'a> {
+    /// Write one JSON line `{ id, neighbors: [{ id, score }] }` per point
+    pub out_file: Option<String>,
+    /// Called with each batch of entries as it is computed
+    pub on_batch: Option<Function<'a, Vec<KnnEntry>, Unknown<'a>>>,
+    /// Points per batch, default 1000
+    pub batch_size: Option<u32>,
+    /// HNSW ef at query time, default max(k * 10, 200)
+    pub ef_search: Option<u32>,
+}
+
+#[napi(object)]
+pub struct KnnEntry {
+    pub id: String,
+    /// Nearest live neighbours, best first, excluding the point itself
+    pub neighbors: Vec<SearchResult>,
+}
+
+/// Default number of points per `knnGraph` batch
+const DEFAULT_KNN_BATCH_SIZE: u32 = 1000;
+
+#[napi(object)]
+pub struct DeleteFilter {
+    /// Points inserted at or after this time (ms since epoch)
+    pub inserted_after: Option<f64>,
+    /// Points inserted before this time (ms since epoch)
+    pub inserted_before: Option<f64>,
+}
+
+#[napi(object)]
+#[derive(Clone)]
+pub struct SearchResult {
+    pub id: String,
+    pub score: f64,
+    /// Insertion time (ms since epoch); 0 for points saved before it was recorded
+    pub inserted_at: f64,
+    /// Words of the `highlight` field matching the query, when requested
+    pub highlights: Option<Vec<TextToken>>,
+    /// The point's payload, or the requested keys of it, when requested
+    pub payload: Option<Payload>,
+}
+
+#[napi(object)]
+pub struct StoredVector {
+    pub id: String,
+    pub vector: Float32Array,
+    /// Insertion time (ms since epoch); 0 for points saved before it was recorded
+    pub inserted_at: f64,
+    pub payload: Option<Payload>,
+}
+
+#[napi(object)]
+pub struct CollectionStats {
+    pub count: u32,
+    pub dimensions: u32,
+    pub file_size_bytes: u32,
+    /// Inserts that replaced an existing point with the same id
+    pub upserts: u32,
+    /// Inserts that reused an internal id freed by a rebuild
+    pub reused_ids: u32,
+    /// Graph nodes no id maps to any more, left by upserts and purges
+    /// until the next rebuild
+    pub orphaned_nodes: u32,
+    /// Deleted points whose ids, vectors and graph nodes are still held
+    pub tombstones: u32,
+    /// Internal ids freed by rebuilds and not yet reused
+    pub free_ids: u32,
+    /// Id lookups by inserts, deletes and existence checks that found the id
+    pub id_hits: u32,
+    /// Id lookups that didn't find the id
+    pub id_misses: u32,
+    /// Misses answered by the id filter without reading the id map
+    pub id_filter_rejections: u32,
+    /// Sequence number of the latest change. Every insert, delete, payload
+    /// update or other change that would be saved bumps it.
+    pub seq: f64,
+    /// Sequence number of the latest saved change. Changes after it are
+    /// lost on a crash, and their numbers reused.
+    pub committed_seq: f64,
+    /// Usage against the quota, when one is set
+    pub quota: Option<QuotaUsage>,
+}
+
+fn validate_metric(metric: &str) -> Result<()> {
+    if metric != "cosine" && metric != HAMMING_METRIC && metric != JACCARD_METRIC {
+        return Err(Error::from_reason(format!(
+            "Unsupported metric '{}'. Only 'cosine', 'hamming' and 'jaccard' are supported.",
+            metric
+        )));
+    }
+    Ok(())
+}
+
+fn validate_index_type(index_type: &str) -> Result<()> {
+    let supported =
+        [DEFAULT_INDEX_TYPE, IVF_INDEX_TYPE, LSH_INDEX_TYPE, FLAT_INDEX_TYPE, MINHASH_INDEX_TYPE];
+    if !supported.contains(&index_type) {
+        return Err(Error::from_reason(format!(
+            "Unsupported index type '{}'. Only 'hnsw', 'ivf', 'lsh', 'flat' and 'minhash' are \
+             supported.",
+            index_type
+        )));
+    }
+    Ok(())
+}
+
+/// Hamming collections are always scanned and jaccard ones always bucketed
+/// by minhash; cosine ones have a graph, IVF lists or LSH buckets.
+fn validate_index_for_metric(metric: &str, index_type: &str) -> Result<()> {
+    let expected: &[&str] = match metric {
+        HAMMING_METRIC => &[FLAT_INDEX_TYPE],
+        JACCARD_METRIC => &[MINHASH_INDEX_TYPE],
+        _ => &[DEFAULT_INDEX_TYPE, IVF_INDEX_TYPE, LSH_INDEX_TYPE],
+    };
+    if !expected.contains(&index_type) {
+        return Err(Error::from_reason(format!(
+            "Unsupported index type '{}' for metric '{}'. Use 'flat' for 'hamming', \
+             'minhash' for 'jaccard' and 'hnsw', 'ivf' or 'lsh' for 'cosine'.",
+            index_type, metric
+        )));
+    }
+    Ok(())
+}
+
+/// Fails unless the collection holds `kind` points: hamming collections
+/// only take `insertBinary` and `searchBinary`, jaccard ones only
+/// `insertSet` and `searchSet`.
+fn check_kind(coll: &Collection, kind: PointKind) -> Result<()> {
+    if coll.kind() == kind {
+        return Ok(());
+    }
+    let path = coll.path.display();
+    Err(Error::from_reason(match (coll.kind(), kind) {
+        (PointKind::Binary, _) => format!(
+            "Collection at '{}' stores binary vectors; use insertBinary and searchBinary",
+            path
+        ),
+        (PointKind::Set, _) => format!(
+            "Collection at '{}' stores token sets; use insertSet and searchSet",
+            path
+        ),
+        (PointKind::Dense, PointKind::Binary) => format!(
+            "Collection at '{}' has metric '{}'; binary vectors need metric 'hamming'",
+            path, coll.metric
+        ),
+        (PointKind::Dense, _) => format!(
+            "Collection at '{}' has metric '{}'; token sets need metric 'jaccard'",
+            path, coll.metric
+        ),
+    }))
+}
+
+/// Fails for collections without an HNSW graph, which `api` works on.
+fn check_graph(coll: &Collection, api: &str) -> Result<()> {
+    if coll.index_type != DEFAULT_INDEX_TYPE {
+        return Err(Error::from_reason(format!(
+            "{} needs an HNSW graph; collection at '{}' has indexType '{}'",
+            api,
+            coll.path.display(),
+            coll.index_type
+        )));
+    }
+    Ok(())
+}
+
+/// Overlay user-supplied retry settings on the default policy.
+pub(crate) fn resolve_retry_policy(
+    max_retries: Option<u32>,
+    retry_growth: Option<u32>,
+) -> Result<RetryPolicy> {
+    let default = RetryPolicy::default();
+    let policy = RetryPolicy {
+        max_retries: max_retries.map(|v| v as usize).unwrap_or(default.max_retries),
+        growth: retry_growth.map(|v| v as usize).unwrap_or(default.growth),
+        ..default
+    };
+    if policy.growth < 2 {
+        return Err(Error::from_reason("retryGrowth must be >= 2".to_string()));
+    }
+    Ok(policy)
+}
+
+/// Overlay user-supplied HNSW parameters on top of `base`.
+fn resolve_params(
+    base: HnswParams,
+    m: Option<u32>,
+    ef_construction: Option<u32>,
+    max_elements: Option<u32>,
+    seed: Option<u32>,
+    shuffle_on_rebuild: Option<bool>,
+    ivf_lists: Option<u32>,
+) -> Result<HnswParams> {
+    let params = HnswParams {
+        max_nb_connection: m.map(|v| v as usize).unwrap_or(base.max_nb_connection),
+        ef_construction: ef_construction
+            .map(|v| v as usize)
+            .unwrap_or(base.ef_construction),
+        max_elements: max_elements
+            .map(|v| v as usize)
+            .unwrap_or(base.max_elements),
+        seed: seed.map(u64::from).or(base.seed),
+        shuffle_on_rebuild: shuffle_on_rebuild.unwrap_or(base.shuffle_on_rebuild),
+        ivf_lists: ivf_lists.map(|v| v as usize).or(base.ivf_lists),
+        ..base
+    };
+
+    // hnsw_rs aborts the process for M > 256, so reject it here
+    if !(2..=256).contains(&params.max_nb_connection) {
+        return Err(Error::from_reason(format!(
+            "Invalid m {}: must be between 2 and 256",
+            params.max_nb_connection
+        )));
+    }
+    if params.ef_construction == 0 {
+        return Err(Error::from_reason("efConstruction must be > 0".to_string()));
+    }
+    if params.max_elements == 0 {
+        return Err(Error::from_reason("maxElements must be > 0".to_string()));
+    }
+    if params.ivf_lists == Some(0) {
+        return Err(Error::from_reason("ivfLists must be > 0".to_string()));
+    }
+
+    Ok(params)
+}
+
+/// Overlay user-supplied LSH table settings on `params`.
+fn resolve_lsh(
+    mut params: HnswParams,
+    tables: Option<u32>,
+    bits: Option<u32>,
+) -> Result<HnswParams> {
+    if tables == Some(0) {
+        return Err(Error::from_reason("lshTables must be > 0".to_string()));
+    }
+    if bits.is_some_and(|bits| bits == 0 || bits as usize > hyperplane::MAX_BITS) {
+        return Err(Error::from_reason(format!(
+            "lshBits must be between 1 and {}",
+            hyperplane::MAX_BITS
+        )));
+    }
+    params.lsh_tables = tables.map(|v| v as usize).or(params.lsh_tables);
+    params.lsh_bits = bits.map(|v| v as usize).or(params.lsh_bits);
+    Ok(params)
+}
+
+/// Apply a requested `pqSubvectors`, where 0 turns quantization off, and
+/// check it suits the index type and dimensions. Leaving IVF drops it.
+fn resolve_pq(
+    mut params: HnswParams,
+    requested: Option<u32>,
+    index_type: &str,
+    dimensions: usize,
+) -> Result<HnswParams> {
+    match requested {
+        Some(0) => params.pq_subvectors = None,
+        Some(subvectors) => {
+            if index_type != IVF_INDEX_TYPE {
+                return Err(Error::from_reason(
+                    "pqSubvectors needs indexType 'ivf'".to_string(),
+                ));
+            }
+            if !dimensions.is_multiple_of(subvectors as usize) {
+                return Err(Error::from_reason(format!(
+                    "pqSubvectors must divide dimensions ({})",
+                    dimensions
+                )));
+            }
+            params.pq_subvectors = Some(subvectors as usize);
+        }
+        None if index_type != IVF_INDEX_TYPE => params.pq_subvectors = None,
+        None => {}
+    }
+    Ok(params)
+}
+
+/// Convert and check user-supplied analyzers. Fields declared in `schema`
+/// must be strings to be analyzed.
+fn resolve_analyzers(
+    configs: &HashMap<String, AnalyzerConfig>,
+    schema: Option<&schema::Schema>,
+) -> Result<Analyzers> {
+    let mut analyzers = Analyzers::new();
+    for (field, config) in configs {
+        if let Some(ty) = schema.and_then(|schema| schema.get(field)) {
+            if !matches!(ty, schema::FieldType::String | schema::FieldType::StringArray) {
+                return Err(Error::from_reason(format!(
+                    "Analyzer for field '{}' needs a string field, but the schema declares {}",
+                    field,
+                    ty.name()
+                )));
+            }
+        }
+        let default = Analyzer::default();
+        let analyzer = Analyzer {
+            lowercase: config.lowercase.unwrap_or(default.lowercase),
+            stemmer: config.stemmer.clone(),
+            stopwords: config.stopwords.clone().unwrap_or_default(),
+            ngram: config
+                .ngram
+                .as_ref()
+                .map(|range| (range.min as usize, range.max as usize)),
+        };
+        analyzer.validate(field).map_err(Error::from_reason)?;
+        analyzers.insert(field.clone(), analyzer);
+    }
+    Ok(analyzers)
+}
+
+/// Open or create a collection. A config naming a template registered
+/// with `registerTemplate` takes every setting it leaves unset from it.
+#[napi]
+pub fn create_collection(config: TemplatedCollectionConfig) -> Result<()> {
+    guard("createCollection", || {
+        let template = match &config.template {
+            Some(name) => find_template(name)?,
+            None => TemplateConfig::default(),
+        };
+        open_collection(&config.apply(template)?)
+    })
+}
+
+/// Register `config` as a template that `createCollection({ template,
+/// path })` fills in unset settings from, so collections created alike
+/// don't drift apart. Replaces any template of the same name. Templates
+/// live until the process exits; collections keep their settings either
+/// way.
+#[napi]
+pub fn register_template(name: String, config: TemplateConfig) -> Result<()> {
+    guard("registerTemplate", || {
+        if let Some(metric) = &config.metric {
+            validate_metric(metric)?;
+        }
+        if let Some(index_type) = &config.index_type {
+            validate_index_type(index_type)?;
+        }
+        TEMPLATES.lock().unwrap_or_else(|e| e.into_inner()).insert(name, config);
+        Ok(())
+    })
+}
+
+/// Make inserts to a path no collection is loaded from open or create one
+/// there instead of failing, for collections made on the fly (e.g. one per
+/// user). Takes the name of a template, looked up now, or the settings
+/// themselves; `indexType` defaults to "hnsw" and `metric` to "cosine".
+/// Pass null to go back to failing, the default.
+#[napi]
+pub fn set_auto_create(config: Option<Either<String, TemplateConfig>>) -> Result<()> {
+    guard("setAutoCreate", || {
+        let config = match config {
+            Some(config) => {
+                let mut config = match config {
+                    Either::A(name) => find_template(&name)?,
+                    Either::B(config) => config,
+                };
+                required(config.dimensions, "dimensions")?;
+                let metric = config.metric.get_or_insert_with(|| DEFAULT_METRIC.to_string());
+                validate_metric(metric)?;
+                let index_type =
+                    config.index_type.get_or_insert_with(|| DEFAULT_INDEX_TYPE.to_string());
+                validate_index_type(index_type)?;
+                validate_index_for_metric(metric, index_type)?;
+                Some(config)
+            }
+            None => None,
+        };
+        *AUTO_CREATE.lock().unwrap_or_else(|e| e.into_inner()) = config;
+        Ok(())
+    })
+}
+
+/// Open or create the collection at `path` with the `setAutoCreate` config,
+/// if there is one. Called by inserts that found no collection loaded.
+fn auto_create(path: &str) -> Result<()> {
+    let config = AUTO_CREATE.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    match config {
+        Some(config) => open_collection(&config.at(path.to_string())?),
+        None => Ok(()),
+    }
+}
+
+/// The template registered as `name`
+fn find_template(name: &str) -> Result<TemplateConfig> {
+    TEMPLATES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(name)
+        .cloned()
+        .ok_or_else(|| Error::from_reason(format!("Template '{}' not found", name)))
+}
+
+/// A setting every collection needs, from its config or template
+fn required<T>(value: Option<T>, name: &str) -> Result<T> {
+    value.ok_or_else(|| Error::from_reason(format!("{} is required", name)))
+}
+
+/// Body of `create_collection`, shared with `preload_collections`. The
+/// collection is read from disk without holding `COLLECTIONS`.
+fn open_collection(config: &CollectionConfig) -> Result<()> {
+    let config_path = config.path.as_str();
+    let dimensions = config.dimensions;
+    let index_type = config.index_type.as_str();
+    let metric = config.metric.as_str();
+    validate_metric(metric)?;
+    validate_index_type(index_type)?;
+    validate_index_for_metric(metric, index_type)?;
+    if dimensions == 0 {
+        return Err(Error::from_reason("Dimensions must be > 0".to_string()));
+    }
+    if metric == HAMMING_METRIC && !dimensions.is_multiple_of(8) {
+        return Err(Error::from_reason(
+            "Hamming collections need dimensions (bits) divisible by 8".to_string(),
+        ));
+    }
+    let params = resolve_params(
+        HnswParams::default(),
+        config.m,
+        config.ef_construction,
+        config.max_elements,
+        config.seed,
+        config.shuffle_on_rebuild,
+        config.ivf_lists,
+    )?;
+    let params = resolve_pq(params, config.pq_subvectors, index_type, dimensions as usize)?;
+    let params = resolve_lsh(params, config.lsh_tables, config.lsh_bits)?;
+    let schema = config
+        .schema
+        .as_ref()
+        .map(schema::parse_schema)
+        .transpose()
+        .map_err(Error::from_reason)?;
+    let analyzers = config
+        .analyzers
+        .as_ref()
+        .map(|configs| resolve_analyzers(configs, schema.as_ref()))
+        .transpose()?;
+    let durability = config
+        .durability
+        .as_deref()
+        .map(Durability::parse)
+        .transpose()
+        .map_err(Error::from_reason)?;
+
+    let key = collection_key(config_path);
+    let path = PathBuf::from(&key);
+
+    let opening = opening(&key);
+    let _opening = opening.lock().unwrap_or_else(|e| e.into_inner());
+
+    // Idempotent: if already loaded, possibly under another spelling of the
+    // same directory, only check that the config agrees
+    {
+        let collections = read_collections()?;
+        if let Some(existing) = collections.get(&key) {
+            check_config_matches(existing, config)?;
+            remember_key(config_path, &key);
+            return Ok(());
+        }
+    }
+
+    // Writers take the directory lock before reading so no other process
+    // can save underneath them
+    let read_only = config.read_only.unwrap_or(false);
+    let lock = if read_only {
+        None
+    } else {
+        Some(persistence::lock_collection_dir(&path).map_err(Error::from_reason)?)
+    };
+
+    // Try to load existing collection from disk. IVF lists train and LSH
+    // buckets fill quickly enough to do so while opening.
+    let graph_free = [IVF_INDEX_TYPE, LSH_INDEX_TYPE].contains(&index_type);
+    let lazy = config.lazy_index.unwrap_or(false) && !graph_free;
+    let mut coll = match persistence::load_collection(&path, !lazy) {
+        Ok(Some(existing)) => {
+            check_config_matches(&existing, config)?;
+            existing
+        }
+        Ok(None) => {
+            let mut coll = Collection::new(path, dimensions as usize, params);
+            coll.metric = metric.to_string();
+            coll.index_type = index_type.to_string();
+            coll.schema = schema;
+            coll.analyzers = analyzers.unwrap_or_default();
+            coll.normalize = config.normalize.unwrap_or(false);
+            if graph_free {
+                // IVF lists are untrained until `buildIndex`; searches scan
+                // meanwhile
+                coll.rebuild_from_vectors();
+            }
+            coll
+        }
+        Err(e) => {
+            return Err(Error::from_reason(format!(
+                "Failed to load collection: {}",
+                e
+            )));
+        }
+    };
+    coll.read_only = read_only;
+    coll.lock = lock;
+    if let Some(shared) = config.shared_segments {
+        if shared && graph_free {
+            return Err(Error::from_reason(
+                "sharedSegments needs indexType 'hnsw'; IVF lists and LSH buckets aren't dumped"
+                    .to_string(),
+            ));
+        }
+        coll.share_segments = shared;
+    }
+    if let Some(strict) = config.strict_insert {
+        coll.strict_insert = strict;
+    }
+    if let Some(audit_log) = config.audit_log {
+        coll.audit_log = audit_log;
+    }
+    if let Some(durability) = durability {
+        coll.durability = durability;
+    }
+    if let Some(enabled) = config.id_filter {
+        if enabled != coll.id_filter.is_some() {
+            coll.set_id_filter(enabled);
+        }
+    }
+    if let Some(auto) = &config.auto_compaction {
+        let default = CompactionPolicy::default();
+        let policy = CompactionPolicy {
+            tombstone_ratio: auto.tombstone_ratio.unwrap_or(default.tombstone_ratio),
+            min_deleted: auto
+                .min_deleted
+                .map(|v| v as usize)
+                .unwrap_or(default.min_deleted),
+        };
+        if !(policy.tombstone_ratio > 0.0 && policy.tombstone_ratio <= 1.0) {
+            return Err(Error::from_reason(
+                "autoCompaction.tombstoneRatio must be in (0, 1]".to_string(),
+            ));
+        }
+        coll.auto_compaction = Some(policy);
+    }
+    if coll.auto_compaction.is_some() {
+        maintenance::ensure_started();
+    }
+    if let Some(alerts) = &config.capacity_alerts {
+        let mut thresholds = CapacityThresholds::default();
+        if let Some(levels) = &alerts.levels {
+            if levels.iter().any(|&level| !(level > 0.0 && level <= 1.0)) {
+                return Err(Error::from_reason(
+                    "capacityAlerts.levels must be in (0, 1]".to_string(),
+                ));
+            }
+            thresholds.levels = levels.clone();
+            thresholds.levels.sort_by(f64::total_cmp);
+            thresholds.levels.dedup();
+        }
+        if let Some(bytes) = alerts.memory_limit_bytes {
+            if bytes.is_nan() || bytes < 1.0 {
+                return Err(Error::from_reason(
+                    "capacityAlerts.memoryLimitBytes must be > 0".to_string(),
+                ));
+            }
+            thresholds.memory_limit = Some(bytes as u64);
+        }
+        coll.capacity_thresholds = thresholds;
+    }
+    if let Some(quota) = &config.quota {
+        if quota.max_bytes.is_some_and(|bytes| bytes.is_nan() || bytes < 1.0) {
+            return Err(Error::from_reason("quota.maxBytes must be > 0".to_string()));
+        }
+        coll.quota = Some(Quota {
+            max_points: quota.max_points.map(|n| n as usize),
+            max_bytes: quota.max_bytes.map(|bytes| bytes as u64),
+        });
+    }
+    if let Some(limits) = &config.entry_limits {
+        if limits.max_id_length == Some(0) || limits.max_payload_bytes == Some(0) {
+            return Err(Error::from_reason(
+                "entryLimits.maxIdLength and maxPayloadBytes must be > 0".to_string(),
+            ));
+        }
+        coll.entry_limits = EntryLimits {
+            max_id_length: limits.max_id_length.map(|n| n as usize),
+            max_payload_bytes: limits.max_payload_bytes.map(|n| n as usize),
+        };
+    }
+    if let Some(tag) = &config.model_tag {
+        coll.model_tag = Some(tag.clone());
+    }
+    if let Some(days) = config.tombstone_retention_days {
+        if days.is_nan() || days < 0.0 {
+            return Err(Error::from_reason(
+                "tombstoneRetentionDays must be >= 0".to_string(),
+            ));
+        }
+        coll.tombstone_retention_ms = Some((days * 86_400_000.0) as i64);
+    }
+    // Only a collection loaded from disk has a graph to build
+    let pending = (lazy && !coll.vectors.is_empty()).then(|| coll.defer_build());
+    write_collections()?.insert(key.clone(), coll);
+    remember_key(config_path, &key);
+    if let Some(pending) = pending {
+        build_in_background(key, pending);
+    }
+
+    Ok(())
+}
+
+#[napi(object)]
+pub struct PreloadOptions<'a> {
+    /// Collections opened at once, default the worker pool's thread count
+    pub concurrency: Option<u32>,
+    /// Called on the JS thread as each collection finishes opening
+    pub on_progress: Option<Function<'a, PreloadProgress, Unknown<'a>>>,
+}
+
+#[napi(object)]
+pub struct PreloadProgress {
+    pub path: String,
+    /// Collections finished so far, including this one
+    pub done: u32,
+    pub total: u32,
+    /// Why the collection failed to open
+    pub error: Option<String>,
+}
+
+#[napi(object)]
+pub struct PreloadResult {
+    pub path: String,
+    /// Why the collection failed to open; unset if it is open
+    pub error: Option<String>,
+    pub elapsed_ms: f64,
+}
+
+/// Open many collections as `createCollection` would, several at a time
+/// on worker threads, for service startup. Returns once all have been
+/// tried, with one result per config in order. A collection that fails to
+/// open doesn't stop the others.
+#[napi]
+pub fn preload_collections(
+    configs: Vec<CollectionConfig>,
+    options: Option<PreloadOptions>,
+) -> Result<Vec<PreloadResult>> {
+    guard("preloadCollections", || {
+        let (concurrency, on_progress) = match options {
+            Some(options) => (options.concurrency, options.on_progress),
+            None => (None, None),
+        };
+        let concurrency = concurrency.map_or_else(pool::threads, |n| n as usize);
+        if concurrency == 0 {
+            return Err(Error::from_reason("concurrency must be > 0".to_string()));
+        }
+
+        let total = configs.len();
+        let next = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+        let mut results: Vec<Option<PreloadResult>> = (0..total).map(|_| None).collect();
+        let mut callback_error = None;
+
+        std::thread::scope(|scope| {
+            for _ in 0..concurrency.min(total) {
+                let sender = sender.clone();
+                let (configs, next) = (&configs, &next);
+                scope.spawn(move || loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(config) = configs.get(index) else {
+                        break;
+                    };
+                    let started = Instant::now();
+                    let outcome = open_collection(config);
+                    let _ = sender.send((index, outcome, started.elapsed()));
+                });
+            }
+            drop(sender);
+
+            for (done, (index, outcome, elapsed)) in receiver.iter().enumerate() {
+                let path = configs[index].path.clone();
+                let error = outcome.err().map(|e| e.reason.clone());
+                if let (Some(on_progress), None) = (&on_progress, &callback_error) {
+                    let progress = PreloadProgress {
+                        path: path.clone(),
+                        done: done as u32 + 1,
+                        total: total as u32,
+                        error: error.clone(),
+                    };
+                    // Keep draining so every worker finishes; throw afterwards
+                    if let Err(e) = on_progress.call(progress) {
+                        callback_error = Some(e);
+                    }
+                }
+                results[index] = Some(PreloadResult {
+                    path,
+                    error,
+                    elapsed_ms: elapsed.as_secs_f64() * 1000.0,
+                });
+            }
+        });
+
+        if let Some(e) = callback_error {
+            return Err(e);
+        }
+        Ok(results.into_iter().flatten().collect())
+    })
+}
+
+/// Reject reopening a collection with settings that differ from the ones it
+/// was saved with. HNSW parameters are only compared when passed explicitly;
+/// use `reindex` to change them.
+fn check_config_matches(existing: &Collection, config: &CollectionConfig) -> Result<()> {
+    if existing.dimensions != config.dimensions as usize {
+        return Err(Error::from_reason(format!(
+            "Dimension mismatch: existing collection has {} dims, requested {}",
+            existing.dimensions, config.dimensions
+        )));
+    }
+    if existing.metric != config.metric {
+        return Err(Error::from_reason(format!(
+            "Metric mismatch: existing collection uses '{}', requested '{}'",
+            existing.metric, config.metric
+        )));
+    }
+    if existing.index_type != config.index_type {
+        return Err(Error::from_reason(format!(
+            "Index type mismatch: existing collection uses '{}', requested '{}'",
+            existing.index_type, config.index_type
+        )));
+    }
+
+    let params = [
+        ("m", existing.params.max_nb_connection, config.m),
+        ("efConstruction", existing.params.ef_construction, config.ef_construction),
+        ("maxElements", existing.params.max_elements, config.max_elements),
+        (
+            "lshTables",
+            existing.params.lsh_tables.unwrap_or(hyperplane::DEFAULT_TABLES),
+            config.lsh_tables,
+        ),
+        (
+            "lshBits",
+            existing.params.lsh_bits.unwrap_or(hyperplane::DEFAULT_BITS),
+            config.lsh_bits,
+        ),
+    ];
+    for (name, saved, requested) in params {
+        if let Some(requested) = requested {
+            if requested as usize != saved {
+                return Err(Error::from_reason(format!(
+                    "{} mismatch: existing collection has {}, requested {}; use reindex to change it",
+                    name, saved, requested
+                )));
+            }
+        }
+    }
+
+    if let Some(requested) = config.seed {
+        if existing.params.seed != Some(requested as u64) {
+            let saved = existing.params.seed.map_or("none".to_string(), |s| s.to_string());
+            return Err(Error::from_reason(format!(
+                "seed mismatch: existing collection has {}, requested {}; use reindex to change it",
+                saved, requested
+            )));
+        }
+    }
+
+    if let Some(requested) = config.ivf_lists {
+        if existing.params.ivf_lists != Some(requested as usize) {
+            let saved = existing.params.ivf_lists.map_or("√n".to_string(), |n| n.to_string());
+            return Err(Error::from_reason(format!(
+                "ivfLists mismatch: existing collection has {}, requested {}; use reindex to change it",
+                saved, requested
+            )));
+        }
+    }
+
+    if let Some(requested) = config.pq_subvectors {
+        let requested = (requested > 0).then_some(requested as usize);
+        if existing.params.pq_subvectors != requested {
+            let describe = |n: Option<usize>| n.map_or("none".to_string(), |n| n.to_string());
+            return Err(Error::from_reason(format!(
+                "pqSubvectors mismatch: existing collection has {}, requested {}; use reindex to change it",
+                describe(existing.params.pq_subvectors),
+                describe(requested)
+            )));
+        }
+    }
+
+    if let Some(requested) = config.normalize {
+        if existing.normalize != requested {
+            return Err(Error::from_reason(format!(
+                "normalize mismatch: existing collection has {}, requested {}; it can only be \
+                 set when the collection is created",
+                existing.normalize, requested
+            )));
+        }
+    }
+
+    if let (Some(existing), Some(requested)) = (&existing.model_tag, &config.model_tag) {
+        if existing != requested {
+            return Err(Error::from_reason(format!(
+                "modelTag mismatch: existing collection holds embeddings from '{}', \
+                 requested '{}'; re-embed into a new collection to change models",
+                existing, requested
+            )));
+        }
+    }
+
+    if let Some(requested) = config.shuffle_on_rebuild {
+        if existing.params.shuffle_on_rebuild != requested {
+            return Err(Error::from_reason(format!(
+                "shuffleOnRebuild mismatch: existing collection has {}, requested {}; use reindex to change it",
+                existing.params.shuffle_on_rebuild, requested
+            )));
+        }
+    }
+
+    if let Some(fields) = &config.schema {
+        let requested = schema::parse_schema(fields).map_err(Error::from_reason)?;
+        if existing.schema.as_ref() != Some(&requested) {
+            return Err(Error::from_reason(
+                "Schema mismatch: existing collection was created with a different schema"
+                    .to_string(),
+            ));
+        }
+    }
+    if let Some(configs) = &config.analyzers {
+        let requested = resolve_analyzers(configs, existing.schema.as_ref())?;
+        if existing.analyzers != requested {
+            return Err(Error::from_reason(
+                "Analyzer mismatch: existing collection was created with different analyzers"
+                    .to_string(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Build the graph of a lazily opened collection on its own thread and swap
+/// it in. The build counts as a heavy operation against the limits, and is
+/// listed by `listOperations` as a "build".
+fn build_in_background(key: String, pending: PendingBuild) {
+    let operation = operations::start("build", &key, Some(pending.len()));
+    std::thread::spawn(move || {
+        let _permit = limits::acquire(&key);
+        operation.running();
+        let hnsw = pending.build(|processed| operation.progress(processed));
+        if let Some(coll) = background_collections().get_mut(&key) {
+            coll.finish_build(&pending, hnsw);
+        }
+    });
+}
+
+/// Open a collection read-only by memory-mapping the segments its writer
+/// dumps (see `sharedSegments`). Several processes attached to the same
+/// collection share one copy of the vectors in the OS page cache.
+#[napi]
+pub fn attach_readonly(path: String) -> Result<()> {
+    guard("attachReadonly", || {
+        let mut collections = write_collections()?;
+
+        let key = collection_key(&path);
+        if collections.contains_key(&key) {
+            return Ok(());
+        }
+
+        let coll = persistence::attach_collection(&PathBuf::from(&key))
+            .map_err(|e| Error::from_reason(format!("Failed to attach collection: {}", e)))?;
+        collections.insert(key.clone(), coll);
+        remember_key(&path, &key);
+
+        Ok(())
+    })
+}
+
+fn check_writable(coll: &Collection) -> Result<()> {
+    if coll.read_only {
+        return Err(Error::from_reason(format!(
+            "Collection at '{}' is open read-only",
+            coll.path.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Re-read a read-only collection from disk to pick up the writer's latest
+/// save. Returns false if nothing has been saved yet.
+#[napi]
+pub fn reload_collection(path: String) -> Result<bool> {
+    guard("reloadCollection", || {
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        if !coll.read_only {
+            return Err(Error::from_reason(format!(
+                "Collection at '{}' is the writer; only read-only collections can be reloaded",
+                path
+            )));
+        }
+
+        if coll.mapped_graph.is_some() {
+            *coll = persistence::attach_collection(&coll.path)
+                .map_err(|e| Error::from_reason(format!("Failed to attach collection: {}", e)))?;
+            return Ok(true);
+        }
+
+        match persistence::load_collection(&coll.path, true) {
+            Ok(Some(mut fresh)) => {
+                fresh.read_only = true;
+                *coll = fresh;
+                Ok(true)
+            }
+            Ok(None) => Ok(false),
+            Err(e) => Err(Error::from_reason(format!(
+                "Failed to load collection: {}",
+                e
+            ))),
+        }
+    })
+}
+
+/// Drop a collection from the in-process cache and release its writer
+/// lock. Unsaved changes are discarded; call `buildIndex` first to keep them.
+#[napi]
+pub fn close_collection(path: String) -> Result<bool> {
+    guard("closeCollection", || {
+        let mut collections = write_collections()?;
+
+        let key = collection_key(&path);
+        alerts::set_callback(key.clone(), None);
+        forget_key(&key);
+        Ok(collections.remove(&key).is_some())
+    })
+}
+
+/// Move a collection's directory to `newPath`, which must not exist yet. A
+/// loaded collection stays loaded, unsaved changes and all, under the new
+/// path; one that isn't loaded is just moved, unless another process holds
+/// its writer lock. Read-only collections can't be renamed, and readers
+/// attached to the old path must attach to the new one to see later saves.
+/// On Windows, where open files pin their directory, close the collection
+/// first.
+#[napi]
+pub fn rename_collection(old_path: String, new_path: String) -> Result<()> {
+    guard("renameCollection", || {
+        let old_key = collection_key(&old_path);
+        let new_key = collection_key(&new_path);
+        if old_key == new_key {
+            return Err(Error::from_reason(format!(
+                "'{}' and '{}' are the same directory",
+                old_path, new_path
+            )));
+        }
+        // Taken in a fixed order so two renames can't deadlock
+        let (first, second) = if old_key < new_key {
+            (opening(&old_key), opening(&new_key))
+        } else {
+            (opening(&new_key), opening(&old_key))
+        };
+        let _first = first.lock().unwrap_or_else(|e| e.into_inner());
+        let _second = second.lock().unwrap_or_else(|e| e.into_inner());
+
+        let mut collections = write_collections()?;
+        if collections.contains_key(&new_key) {
+            return Err(Error::from_reason(format!(
+                "A collection is already loaded at '{}'",
+                new_path
+            )));
+        }
+        let (from, to) = (PathBuf::from(&old_key), PathBuf::from(&new_key));
+        let Some(coll) = collections.get_mut(&old_key) else {
+            if !persistence::is_collection_dir(&from) {
+                return Err(Error::from_reason(format!("No collection at '{}'", old_path)));
+            }
+            // Only checks that no other writer has it open
+            drop(persistence::lock_collection_dir(&from).map_err(Error::from_reason)?);
+            return persistence::move_collection_dir(&from, &to, Durability::default())
+                .map_err(Error::from_reason);
+        };
+        if coll.read_only {
+            return Err(Error::from_reason(format!(
+                "Collection at '{}' is read-only; rename it through its writer",
+                old_path
+            )));
+        }
+        persistence::move_collection_dir(&from, &to, coll.durability)
+            .map_err(Error::from_reason)?;
+        coll.path = to;
+        if let Some(coll) = collections.remove(&old_key) {
+            collections.insert(new_key.clone(), coll);
+        }
+        forget_key(&old_key);
+        remember_key(&new_path, &new_key);
+        alerts::rename_callback(&old_key, new_key);
+        Ok(())
+    })
+}
+
+/// A vector passed in from JS. Float64Arrays and plain arrays are narrowed
+/// to f32 in one pass.
+type VectorInput = Either3<Float32Array, Float64Array, Vec<f64>>;
+
+fn vector_values(input: &VectorInput) -> Cow<'_, [f32]> {
+    match input {
+        Either3::A(vector) => Cow::Borrowed(vector.as_ref()),
+        Either3::B(vector) => Cow::Owned(vector.iter().map(|&v| v as f32).collect()),
+        Either3::C(vector) => Cow::Owned(vector.iter().map(|&v| v as f32).collect()),
+    }
+}
+
+/// Every reason inserting `vector` under `id` would fail, in the order
+/// insert checks them.
+fn point_problems(
+    coll: &Collection,
+    id: &str,
+    vector: &[f32],
+    payload: Option<&Payload>,
+    model_tag: Option<&str>,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if let Err(e) = check_kind(coll, PointKind::Dense) {
+        problems.push(e.reason.clone());
+    }
+    if let Err(e) = check_writable(coll) {
+        problems.push(e.reason.clone());
+    }
+    if vector.len() != coll.input_dimensions() {
+        problems.push(format!(
+            "Dimension mismatch: expected {}, got {}",
+            coll.input_dimensions(),
+            vector.len()
+        ));
+    }
+    if let Some(index) = vector.iter().position(|v| !v.is_finite()) {
+        problems.push(format!(
+            "Vector has a non-finite value ({}) at index {}",
+            vector[index], index
+        ));
+    }
+    problems.extend(model_tag_problem(coll, model_tag));
+    problems.extend(entry_problems(coll, id, payload));
+
+    problems
+}
+
+/// Why vectors from `model_tag` can't go into the collection, if they can't.
+/// Untagged inserts and collections accept anything.
+fn model_tag_problem(coll: &Collection, model_tag: Option<&str>) -> Option<String> {
+    match (coll.model_tag.as_deref(), model_tag) {
+        (Some(expected), Some(tag)) if expected != tag => Some(format!(
+            "MODEL_TAG_MISMATCH: collection at '{}' holds embeddings from '{}', got '{}'",
+            coll.path.display(),
+            expected,
+            tag
+        )),
+        _ => None,
+    }
+}
+
+/// The checks on a point's id and payload, shared by dense and binary
+/// inserts.
+fn entry_problems(coll: &Collection, id: &str, payload: Option<&Payload>) -> Vec<String> {
+    let mut problems = Vec::new();
+    if coll.strict_insert && coll.contains(id) {
+        problems.push(format!(
+            "Id '{}' already exists and strictInsert is enabled",
+            id
+        ));
+    }
+    if let (Some(schema), Some(payload)) = (&coll.schema, payload) {
+        problems.extend(schema::payload_errors(schema, payload));
+    }
+    problems.extend(coll.id_length_problem(id));
+    if let Some(payload) = payload {
+        problems.extend(coll.payload_size_problem(id, payload));
+    }
+    problems.extend(coll.quota_problem(usize::from(!coll.contains(id))));
+    problems
+}
+
+fn check_point(
+    coll: &Collection,
+    id: &str,
+    vector: &[f32],
+    payload: Option<&Payload>,
+    model_tag: Option<&str>,
+) -> Result<()> {
+    match point_problems(coll, id, vector, payload, model_tag).into_iter().next() {
+        Some(problem) => Err(Error::from_reason(problem)),
+        None => Ok(()),
+    }
+}
+
+#[napi(object)]
+pub struct PointValidation {
+    pub valid: bool,
+    /// Every check that failed, each worded like the error `insertVector`
+    /// would throw
+    pub errors: Vec<String>,
+}
+
+/// Run the checks `insertVector` would for this point without inserting it.
+#[napi]
+pub fn validate_point(
+    path: String,
+    id: String,
+    vector: VectorInput,
+    payload: Option<Payload>,
+    model_tag: Option<String>,
+) -> Result<PointValidation> {
+    guard("validatePoint", || {
+        let collections = read_collections()?;
+
+        let coll = collections
+            .get(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        let errors = point_problems(
+            coll,
+            &id,
+            &vector_values(&vector),
+            payload.as_ref(),
+            model_tag.as_deref(),
+        );
+
+        Ok(PointValidation {
+            valid: errors.is_empty(),
+            errors,
+        })
+    })
+}
+
+/// `payload` is an optional JSON object stored with the point and returned
+/// by `getVectors`. It is checked against the collection's schema, if any.
+/// Returns the insert's sequence number; it is durable once `committedSeq`
+/// in `stats` reaches it. `modelTag` names the embedding model the vector
+/// came from; it must match the collection's `modelTag`, if both are set.
+#[napi]
+pub fn insert_vector(
+    path: String,
+    id: String,
+    vector: VectorInput,
+    payload: Option<Payload>,
+    model_tag: Option<String>,
+) -> Result<f64> {
+    guard("insertVector", || {
+        insert_slice(&path, &id, &vector_values(&vector), payload, model_tag.as_deref())
+            .map(|seq| seq as f64)
+    })
+}
+
+/// Body of `insert_vector`, shared with the embedded server. Returns the
+/// insert's sequence number.
+pub(crate) fn insert_slice(
+    path: &str,
+    id: &str,
+    vector: &[f32],
+    payload: Option<Payload>,
+    model_tag: Option<&str>,
+) -> Result<u64> {
+    let mut collections = write_collections()?;
+    if !collections.contains_key(&collection_key(path)) {
+        drop(collections);
+        auto_create(path)?;
+        collections = write_collections()?;
+    }
+
+    let coll = collections
+        .get_mut(&collection_key(path))
+        .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+    insert_into(coll, id, vector, payload, model_tag)?;
+    Ok(coll.seq)
+}
+
+fn insert_into(
+    coll: &mut Collection,
+    id: &str,
+    vector: &[f32],
+    payload: Option<Payload>,
+    model_tag: Option<&str>,
+) -> Result<()> {
+    check_point(coll, id, vector, payload.as_ref(), model_tag)?;
+
+    let vector = coll.project(vector);
+    coll.insert_vector(id, vector, payload);
+    coll.audit("insert", vec![id.to_string()]);
+    raise_capacity_alerts(coll);
+
+    Ok(())
+}
+
+/// Insert a packed bit vector into a collection created with
+/// `metric: "hamming"`: `dimensions / 8` bytes, bit i in bit `i % 8` of
+/// byte `i / 8`. Returns the insert's sequence number.
+#[napi]
+pub fn insert_binary(
+    path: String,
+    id: String,
+    vector: Uint8Array,
+    payload: Option<Payload>,
+) -> Result<f64> {
+    guard("insertBinary", || {
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_kind(coll, PointKind::Binary)?;
+        check_writable(coll)?;
+        check_code_len(coll, &vector)?;
+        if let Some(problem) = entry_problems(coll, &id, payload.as_ref()).into_iter().next() {
+            return Err(Error::from_reason(problem));
+        }
+
+        coll.insert_code(&id, vector.to_vec(), payload);
+        coll.audit("insert", vec![id]);
+        raise_capacity_alerts(coll);
+
+        Ok(coll.seq as f64)
+    })
+}
+
+fn check_code_len(coll: &Collection, code: &[u8]) -> Result<()> {
+    let bytes = coll.dimensions / 8;
+    if code.len() != bytes {
+        return Err(Error::from_reason(format!(
+            "Dimension mismatch: expected {} bytes ({} bits), got {}",
+            bytes,
+            coll.dimensions,
+            code.len()
+        )));
+    }
+    Ok(())
+}
+
+#[napi(object)]
+pub struct BinarySearchOptions {
+    /// Return each result's payload
+    pub include_payload: Option<bool>,
+    /// Only consider points whose payload matches, written as for `boosts`
+    pub filter: Option<Payload>,
+}
+
+/// The `k` points of a hamming collection nearest `query`, a packed bit
+/// vector laid out as for `insertBinary`, found by scanning every point.
+/// Scores are `1 - distance / dimensions`, so identical vectors score 1.
+#[napi]
+pub fn search_binary(
+    path: String,
+    query: Uint8Array,
+    k: u32,
+    options: Option<BinarySearchOptions>,
+) -> Result<Vec<SearchResult>> {
+    guard("searchBinary", || {
+        let (include_payload, filter) = match options {
+            Some(options) => (options.include_payload, options.filter),
+            None => (None, None),
+        };
+        let filter = filter
+            .as_ref()
+            .map(PayloadFilter::parse)
+            .transpose()
+            .map_err(Error::from_reason)?;
+
+        let collections = read_collections()?;
+
+        let coll = collections
+            .get(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_kind(coll, PointKind::Binary)?;
+        check_code_len(coll, &query)?;
+        let include_payload = include_payload
+            .or(coll.search_defaults.include_payload)
+            .unwrap_or(false);
+
+        let hits = coll.search_codes(&query, k as usize, |internal_id| {
+            filter
+                .as_ref()
+                .is_none_or(|filter| filter.matches(coll.payloads.get(&internal_id)))
+        });
+        Ok(hits
+            .into_iter()
+            .map(|(internal_id, distance)| {
+                let score = 1.0 - distance as f64 / coll.dimensions as f64;
+                scan_result(coll, internal_id, score, include_payload)
+            })
+            .collect())
+    })
+}
+
+/// A `searchBinary`, `searchSet` or `discover` hit
+fn scan_result(
+    coll: &Collection,
+    internal_id: usize,
+    score: f64,
+    include_payload: bool,
+) -> SearchResult {
+    SearchResult {
+        id: coll.reverse_map[&internal_id].to_string(),
+        score,
+        inserted_at: coll.inserted_at_of(internal_id) as f64,
+        highlights: None,
+        payload: include_payload
+            .then(|| coll.payloads.get(&internal_id).cloned())
+            .flatten(),
+    }
+}
+
+/// Insert a set of tokens, e.g. hashed shingles, into a collection created
+/// with `metric: "jaccard"`. Order and duplicates don't matter. Returns
+/// the insert's sequence number.
+#[napi]
+pub fn insert_set(
+    path: String,
+    id: String,
+    tokens: Uint32Array,
+    payload: Option<Payload>,
+) -> Result<f64> {
+    guard("insertSet", || {
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_kind(coll, PointKind::Set)?;
+        check_writable(coll)?;
+        if tokens.is_empty() {
+            return Err(Error::from_reason("Token set must not be empty".to_string()));
+        }
+        if let Some(problem) = entry_problems(coll, &id, payload.as_ref()).into_iter().next() {
+            return Err(Error::from_reason(problem));
+        }
+
+        coll.insert_set(&id, minhash::normalize(tokens.to_vec()), payload);
+        coll.audit("insert", vec![id]);
+        raise_capacity_alerts(coll);
+
+        Ok(coll.seq as f64)
+    })
+}
+
+#[napi(object)]
+pub struct SetSearchOptions {
+    /// Return each result's payload
+    pub include_payload: Option<bool>,
+    /// Only consider points whose payload matches, written as for `boosts`
+    pub filter: Option<Payload>,
+    /// Compare the query with every set rather than only the LSH
+    /// candidates: exact, but slower
+    pub exhaustive: Option<bool>,
+}
+
+/// The `k` points of a jaccard collection most similar to the token set
+/// `query`, scored by exact Jaccard similarity. Candidates come from
+/// minhash LSH buckets, so sets less than about 0.4 similar are mostly
+/// missed unless `exhaustive` is set.
+#[napi]
+pub fn search_set(
+    path: String,
+    query: Uint32Array,
+    k: u32,
+    options: Option<SetSearchOptions>,
+) -> Result<Vec<SearchResult>> {
+    guard("searchSet", || {
+        let (include_payload, filter, exhaustive) = match options {
+            Some(options) => (options.include_payload, options.filter, options.exhaustive),
+            None => (None, None, None),
+        };
+        let filter = filter
+            .as_ref()
+            .map(PayloadFilter::parse)
+            .transpose()
+            .map_err(Error::from_reason)?;
+
+        let collections = read_collections()?;
+
+        let coll = collections
+            .get(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_kind(coll, PointKind::Set)?;
+        let include_payload = include_payload
+            .or(coll.search_defaults.include_payload)
+            .unwrap_or(false);
+
+        let query = minhash::normalize(query.to_vec());
+        let exhaustive = exhaustive.unwrap_or(false);
+        let hits = coll.search_sets(&query, k as usize, exhaustive, |internal_id| {
+            filter
+                .as_ref()
+                .is_none_or(|filter| filter.matches(coll.payloads.get(&internal_id)))
+        });
+        Ok(hits
+            .into_iter()
+            .map(|(internal_id, score)| scan_result(coll, internal_id, score, include_payload))
+            .collect())
+    })
+}
+
+/// Pass the capacity alerts the last insert raised to the collection's
+/// `onCapacityAlert` callback, if any.
+fn raise_capacity_alerts(coll: &mut Collection) {
+    let alerts = coll.new_capacity_alerts();
+    alerts::notify(&coll.path.to_string_lossy(), alerts);
+}
+
+/// Like `insertVector`, but queued behind the collection's other async
+/// writes and applied in a batch with them off the JS thread. Resolves once
+/// the point is inserted, to true.
+#[napi]
+pub fn insert_vector_async(
+    path: String,
+    id: String,
+    vector: VectorInput,
+    payload: Option<Payload>,
+    model_tag: Option<String>,
+) -> AsyncTask<QueuedWrite> {
+    let vector = vector_values(&vector).into_owned();
+    AsyncTask::new(QueuedWrite {
+        op: "insertVectorAsync",
+        path,
+        write: Some(WriteOp::Insert {
+            id,
+            vector,
+            payload,
+            model_tag,
+        }),
+    })
+}
+
+/// Like `deleteVector`, but queued like `insertVectorAsync`. Resolves to
+/// whether the point was deleted.
+#[napi]
+pub fn delete_vector_async(path: String, id: String) -> AsyncTask<QueuedWrite> {
+    AsyncTask::new(QueuedWrite {
+        op: "deleteVectorAsync",
+        path,
+        write: Some(WriteOp::Delete { id }),
+    })
+}
+
+pub struct QueuedWrite {
+    op: &'static str,
+    path: String,
+    write: Option<WriteOp>,
+}
+
+impl Task for QueuedWrite {
+    type Output = bool;
+    type JsValue = bool;
+
+    fn compute(&mut self) -> Result<bool> {
+        let write = self.write.take();
+        guard(self.op, || {
+            let write = write.ok_or_else(|| Error::from_reason("Write already applied"))?;
+            write_queue::submit(&collection_key(&self.path), write, |ops| {
+                apply_writes(&self.path, ops)
+            })
+            .map_err(Error::from_reason)
+        })
+    }
+
+    fn resolve(&mut self, _env: Env, output: bool) -> Result<bool> {
+        Ok(output)
+    }
+}
+
+/// Apply a batch of queued writes in order under one write lock. Each gets
+/// its own result, so one bad point doesn't fail the others.
+fn apply_writes(path: &str, ops: Vec<WriteOp>) -> Vec<std::result::Result<bool, String>> {
+    let fail_all = |error: &str| -> Vec<std::result::Result<bool, String>> {
+        ops.iter().map(|_| Err(error.to_string())).collect()
+    };
+    let mut collections = match write_collections() {
+        Ok(collections) => collections,
+        Err(e) => return fail_all(&e.reason),
+    };
+    let inserts = ops.iter().any(|op| matches!(op, WriteOp::Insert { .. }));
+    if inserts && !collections.contains_key(&collection_key(path)) {
+        drop(collections);
+        collections = match auto_create(path).and_then(|_| write_collections()) {
+            Ok(collections) => collections,
+            Err(e) => return fail_all(&e.reason),
+        };
+    }
+    let Some(coll) = collections.get_mut(&collection_key(path)) else {
+        return fail_all(&format!("Collection not found at '{}'", path));
+    };
+    ops.into_iter()
+        .map(|op| {
+            match op {
+                WriteOp::Insert {
+                    id,
+                    vector,
+                    payload,
+                    model_tag,
+                } => insert_into(coll, &id, &vector, payload, model_tag.as_deref()).map(|()| true),
+                WriteOp::Delete { id } => delete_from(coll, id),
+            }
+            .map_err(|e| e.reason.clone())
+        })
+        .collect()
+}
+
+/// Insert a batch under one write lock, adding the points to the graph in
+/// parallel. Used by ingest streams, whose vectors are read from the
+/// caller's buffer here. Returns the number of points inserted.
+pub(crate) fn insert_batch(path: &str, batch: Vec<(String, &[f32])>) -> Result<usize> {
+    let mut collections = write_collections()?;
+
+    let coll = collections
+        .get_mut(&collection_key(path))
+        .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+    let mut seen = HashSet::new();
+    for (id, vector) in &batch {
+        check_point(coll, id, vector, None, None)?;
+        if coll.strict_insert && !seen.insert(id.as_str()) {
+            return Err(Error::from_reason(format!(
+                "Id '{}' already exists and strictInsert is enabled",
+                id
+            )));
+        }
+    }
+    let new_ids: HashSet<&str> =
+        batch.iter().map(|(id, _)| id.as_str()).filter(|id| !coll.contains(id)).collect();
+    if let Some(problem) = coll.quota_problem(new_ids.len()) {
+        return Err(Error::from_reason(problem));
+    }
+
+    let batch: Vec<(String, Cow<[f32]>)> =
+        batch.into_iter().map(|(id, vector)| (id, coll.project(vector))).collect();
+
+    let count = batch.len();
+    let ids = if coll.audit_log {
+        batch.iter().map(|(id, _)| id.clone()).collect()
+    } else {
+        Vec::new()
+    };
+    coll.insert_batch(batch);
+    raise_capacity_alerts(coll);
+    coll.audit("insert", ids);
+
+    Ok(count)
+}
+
+#[napi(object)]
+pub struct ProjectionConfig {
+    /// One row per collection dimension, each as long as the vectors that
+    /// will be passed in
+    pub matrix: Vec<Float32Array>,
+    /// Subtracted from each vector before projecting, e.g. the PCA mean
+    pub mean: Option<Float32Array>,
+}
+
+/// Reduce the vectors passed to inserts and searches to the collection's
+/// dimensions by multiplying them with `projection.matrix`, so large
+/// embeddings can be stored as smaller projections. Vectors already stored
+/// are left as they are. Pass null to remove the projection. Saved with
+/// the collection on the next `buildIndex`.
+#[napi]
+pub fn set_projection(path: String, projection: Option<ProjectionConfig>) -> Result<()> {
+    guard("setProjection", || {
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_writable(coll)?;
+
+        coll.projection = projection
+            .map(|config| {
+                let rows = config.matrix.iter().map(|row| row.to_vec()).collect();
+                let mean = config.mean.map(|mean| mean.to_vec());
+                InputProjection::new(rows, mean, coll.dimensions)
+            })
+            .transpose()
+            .map_err(Error::from_reason)?;
+        coll.mark_dirty();
+
+        Ok(())
+    })
+}
+
+#[napi(object)]
+pub struct PayloadUpdate {
+    pub id: String,
+    /// Fields to set; fields not listed keep their values
+    pub payload: Payload,
+}
+
+/// Merge new fields into the payloads of many points without re-inserting
+/// their vectors, under one lock, then save once. Nothing is changed if any
+/// merged payload fails the schema. Missing and deleted ids are skipped.
+/// Returns the number of points updated.
+#[napi]
+pub fn update_payloads(path: String, entries: Vec<PayloadUpdate>) -> Result<u32> {
+    guard("updatePayloads", || {
+        let _permit = limits::try_acquire(&collection_key(&path)).map_err(Error::from_reason)?;
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_writable(coll)?;
+
+        let updates = entries.into_iter().map(|e| (e.id, e.payload)).collect();
+        let updated = coll.merge_payloads(updates).map_err(Error::from_reason)?;
+        let count = updated.len() as u32;
+        coll.audit("updatePayloads", updated);
+
+        persistence::save_collection(coll).map_err(|e| {
+            coll.record_error(&e);
+            Error::from_reason(e)
+        })?;
+
+        coll.dirty = false;
+
+        Ok(count)
+    })
+}
+
+/// Start a streaming bulk insert into a loaded collection. Batches are
+/// inserted by a background thread; `push` returns "busy" instead of
+/// blocking when `queueSize` batches are already waiting.
+#[napi]
+pub fn create_ingest_stream(path: String, options: Option<IngestOptions>) -> Result<IngestStream> {
+    guard("createIngestStream", || {
+        let (queue_size, model_tag) = match options {
+            Some(options) => (options.queue_size, options.model_tag),
+            None => (None, None),
+        };
+        let queue_size = queue_size.unwrap_or(DEFAULT_INGEST_QUEUE_SIZE);
+        if queue_size == 0 {
+            return Err(Error::from_reason("queueSize must be > 0".to_string()));
+        }
+
+        let collections = read_collections()?;
+
+        let coll = collections
+            .get(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_writable(coll)?;
+        if let Some(problem) = model_tag_problem(coll, model_tag.as_deref()) {
+            return Err(Error::from_reason(problem));
+        }
+
+        Ok(IngestStream::start(path, coll.input_dimensions(), queue_size as usize))
+    })
+}
+
+#[napi(object)]
+pub struct WriteSummary {
+    /// Points inserted, or saved by `buildIndex`
+    pub points: u32,
+    /// Time spent inserting into the index, or rebuilding it (ms)
+    pub index_ms: f64,
+    /// Time spent saving (ms)
+    pub persist_ms: f64,
+    pub bytes_written: f64,
+    /// Sequence number now committed, if anything was saved
+    pub committed_seq: Option<f64>,
+}
+
+/// Save the collection, purging expired tombstones first. IVF collections
+/// also retrain their centroids once more points have been inserted since
+/// the last training than it used. Returns what was saved and how long it
+/// took, with the sequence number now committed.
+#[napi]
+pub fn build_index(path: String) -> Result<WriteSummary> {
+    guard("buildIndex", || {
+        let _permit = limits::try_acquire(&collection_key(&path)).map_err(Error::from_reason)?;
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_writable(coll)?;
+
+        // If tombstones expired, rebuild the HNSW from scratch without them
+        let purged = if coll.deleted_ids.is_empty() {
+            Vec::new()
+        } else {
+            coll.purge_deleted()
+        };
+        let retrain = coll.ivf.as_ref().is_some_and(|ivf| ivf.needs_training());
+        let started = Instant::now();
+        if !purged.is_empty() || retrain {
+            coll.rebuild_from_vectors();
+        }
+        let index_ms = started.elapsed().as_secs_f64() * 1000.0;
+        coll.audit("build", purged);
+
+        // Persist to disk
+        let started = Instant::now();
+        let written = persistence::save_collection(coll).map_err(|e| {
+            coll.record_error(&e);
+            Error::from_reason(e)
+        })?;
+
+        coll.dirty = false;
+
+        Ok(WriteSummary {
+            points: coll.active_count() as u32,
+            index_ms,
+            persist_ms: started.elapsed().as_secs_f64() * 1000.0,
+            bytes_written: written as f64,
+            committed_seq: Some(coll.committed_seq as f64),
+        })
+    })
+}
+
+/// Purge expired tombstones and rebuild the graph without the nodes they
+/// and upserts left behind. Unlike `buildIndex` nothing is saved. Returns
+/// how many nodes were dropped.
+#[napi]
+pub fn compact(path: String) -> Result<u32> {
+    guard("compact", || {
+        let _permit = limits::try_acquire(&collection_key(&path)).map_err(Error::from_reason)?;
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_writable(coll)?;
+
+        Ok(coll.compact() as u32)
+    })
+}
+
+#[napi(object)]
+pub struct RepairOptions {
+    /// Also delete vectors no id maps to; otherwise they are only reported
+    pub drop_orphans: Option<bool>,
+}
+
+#[napi(object)]
+pub struct RepairReport {
+    /// Ids whose vector was missing; dropped, since they can't be served
+    pub missing_vectors: Vec<String>,
+    /// Ids that shared an internal id with another id; dropped
+    pub duplicate_ids: Vec<String>,
+    /// Vectors no id maps to
+    pub orphaned_vectors: u32,
+    /// Tombstones of ids that don't exist; dropped
+    pub stale_tombstones: Vec<String>,
+    /// Reverse map entries that disagreed with the id map; rebuilt
+    pub reverse_map_fixes: u32,
+    /// Timestamps, payloads and deletion times left without a point; dropped
+    pub stray_entries: u32,
+    /// Whether anything was changed and saved
+    pub repaired: bool,
+}
+
+/// Reconcile a collection whose id maps, vectors and tombstones disagree,
+/// e.g. after a crash, and save the consistent state. Reports what was
+/// found; a consistent collection is left untouched.
+#[napi]
+pub fn repair_collection(path: String, options: Option<RepairOptions>) -> Result<RepairReport> {
+    guard("repairCollection", || {
+        let drop_orphans = options.and_then(|o| o.drop_orphans).unwrap_or(false);
+        let _permit = limits::try_acquire(&collection_key(&path)).map_err(Error::from_reason)?;
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_writable(coll)?;
+
+        let report = coll.repair(drop_orphans);
+        if report.changed {
+            let dropped: Vec<Id> = report
+                .missing_vectors
+                .iter()
+                .chain(&report.duplicate_ids)
+                .cloned()
+                .collect();
+            coll.audit("repair", dropped);
+            persistence::save_collection(coll).map_err(|e| {
+                coll.record_error(&e);
+                Error::from_reason(e)
+            })?;
+            coll.dirty = false;
+        }
+
+        Ok(RepairReport {
+            missing_vectors: id_strings(&report.missing_vectors),
+            duplicate_ids: id_strings(&report.duplicate_ids),
+            orphaned_vectors: report.orphaned_vectors.len() as u32,
+            stale_tombstones: id_strings(&report.stale_tombstones),
+            reverse_map_fixes: report.reverse_map_fixes as u32,
+            stray_entries: report.stray_entries as u32,
+            repaired: report.changed,
+        })
+    })
+}
+
+fn id_strings(ids: &[Id]) -> Vec<String> {
+    ids.iter().map(|id| id.to_string()).collect()
+}
+
+#[napi]
+pub fn reindex(path: String, config: ReindexConfig) -> Result<()> {
+    guard("reindex", || {
+        if let Some(metric) = &config.metric {
+            validate_metric(metric)?;
+        }
+        if let Some(index_type) = &config.index_type {
+            validate_index_type(index_type)?;
+        }
+        let _permit = limits::try_acquire(&collection_key(&path)).map_err(Error::from_reason)?;
+
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_writable(coll)?;
+        if config.metric.as_ref().is_some_and(|metric| *metric != coll.metric) {
+            return Err(Error::from_reason(
+                "reindex can't change the metric; binary and float vectors don't convert"
+                    .to_string(),
+            ));
+        }
+        validate_index_for_metric(
+            &coll.metric,
+            config.index_type.as_deref().unwrap_or(&coll.index_type),
+        )?;
+
+        let params = resolve_params(
+            coll.params,
+            config.m,
+            config.ef_construction,
+            config.max_elements,
+            config.seed,
+            config.shuffle_on_rebuild,
+            config.ivf_lists,
+        )?;
+        let index_type = config.index_type.unwrap_or_else(|| coll.index_type.clone());
+        let params = resolve_pq(params, config.pq_subvectors, &index_type, coll.dimensions)?;
+        let params = resolve_lsh(params, config.lsh_tables, config.lsh_bits)?;
+
+        // Switching between a graph and IVF lists takes effect in the rebuild
+        coll.index_type = index_type;
+        // The new graph is built off to the side and swapped in once complete
+        coll.reindex(params);
+
+        persistence::save_collection(coll).map_err(|e| {
+            coll.record_error(&e);
+            Error::from_reason(e)
+        })?;
+
+        coll.dirty = false;
+
+        Ok(())
+    })
+}
+
+/// Remember the collection's current points under `name`, so a failed job
+/// can undo its own inserts and deletes with `rollbackTo`. Savepoints live
+/// in memory only and keep what each point held before its first change
+/// after them, so they cost only what the job changes.
+#[napi]
+pub fn savepoint(path: String, name: String) -> Result<()> {
+    guard("savepoint", || {
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_writable(coll)?;
+
+        coll.savepoint(&name);
+        Ok(())
+    })
+}
+
+/// Return the collection's points and staged vectors to savepoint `name`
+/// and rebuild the graph. Nothing on disk changes until the next
+/// `buildIndex`. Savepoints taken after `name` are dropped.
+#[napi]
+pub fn rollback_to(path: String, name: String) -> Result<()> {
+    guard("rollbackTo", || {
+        let _permit = limits::try_acquire(&collection_key(&path)).map_err(Error::from_reason)?;
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_writable(coll)?;
+
+        if !coll.rollback_to(&name) {
+            return Err(Error::from_reason(format!("No savepoint named '{}'", name)));
+        }
+        coll.audit("rollback", Vec::<String>::new());
+        Ok(())
+    })
+}
+
+/// Drop savepoint `name` and the ones taken after it. The savepoint before
+/// them, if any, still undoes their changes. Returns false if there is no
+/// such savepoint.
+#[napi]
+pub fn release_savepoint(path: String, name: String) -> Result<bool> {
+    guard("releaseSavepoint", || {
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        Ok(coll.release_savepoint(&name))
+    })
+}
+
+/// Export the graph and vectors as an hnswlib index file, plus
+/// `<outFile>.ids.json` mapping hnswlib labels to ids. Returns the number
+/// of exported points.
+#[napi]
+pub fn export_hnswlib(path: String, out_file: String) -> Result<u32> {
+    guard("exportHnswlib", || {
+        let collections = read_collections()?;
+
+        let coll = collections
+            .get(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_graph(coll, "exportHnswlib")?;
+        let count = hnswlib::export_collection(coll, &PathBuf::from(&out_file))
+            .map_err(Error::from_reason)?;
+
+        Ok(count as u32)
+    })
+}
+
+#[napi(object)]
+pub struct WarmupOptions {
+    /// Queries like real traffic, each run once to warm the part of the
+    /// graph it visits
+    pub sample_queries: Option<Vec<Float32Array>>,
+    /// Neighbours fetched per sample query, default 10
+    pub k: Option<u32>,
+}
+
+#[napi(object)]
+pub struct WarmupReport {
+    /// Graph nodes whose vectors were read
+    pub points: u32,
+    /// Sample queries run
+    pub queries: u32,
+    pub elapsed_ms: f64,
+}
+
+/// Read every vector once and run the sample queries, so the first real
+/// searches after opening don't pay for cold caches. Matters most for
+/// collections opened with `attachReadonly`, whose vectors are paged in
+/// from the segment files on first use.
+#[napi]
+pub fn warmup(path: String, options: Option<WarmupOptions>) -> Result<WarmupReport> {
+    guard("warmup", || {
+        let started = Instant::now();
+        let options = options.unwrap_or(WarmupOptions {
+            sample_queries: None,
+            k: None,
+        });
+        let k = options.k.unwrap_or(10);
+        if k == 0 {
+            return Err(Error::from_reason("k must be > 0".to_string()));
+        }
+
+        let points = {
+            let collections = read_collections()?;
+
+            let coll = collections
+                .get(&collection_key(&path))
+                .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+            coll.touch_vectors()
+        };
+
+        let queries = options.sample_queries.unwrap_or_default();
+        for query in &queries {
+            search_slice(&path, query.as_ref(), k, &QueryOptions::default())?;
+        }
+
+        Ok(WarmupReport {
+            points: points as u32,
+            queries: queries.len() as u32,
+            elapsed_ms: started.elapsed().as_secs_f64() * 1000.0,
+        })
+    })
+}
+
+#[napi(object)]
+pub struct IndexCheckOptions {
+    /// Live points searched for by their own vector, default 100
+    pub sample_size: Option<u32>,
+    /// HNSW ef for the sample searches, default 200
+    pub ef_search: Option<u32>,
+}
+
+#[napi(object)]
+pub struct IndexCheckReport {
+    /// No id is missing from the graph and every sampled point was found
+    pub ok: bool,
+    pub nodes: u32,
+    /// Ids, live or deleted, with no graph node holding their vector
+    pub missing_from_graph: Vec<String>,
+    /// Graph nodes matching no stored point: leftovers of upserts and
+    /// purges, which `compact` drops. Not an error
+    pub dead_nodes: u32,
+    pub sampled: u32,
+    /// Sampled live ids a search for their own vector didn't return as an
+    /// exact match
+    pub sample_misses: Vec<String>,
+}
+
+/// Check that the graph and the stored vectors agree, as a quick smoke
+/// test after upgrades or restores: every point has a graph node holding
+/// its vector, and searching for a random sample of points by their own
+/// vectors finds each of them at similarity ~1.
+#[napi]
+pub fn check_index(path: String, options: Option<IndexCheckOptions>) -> Result<IndexCheckReport> {
+    guard("checkIndex", || {
+        let (sample_size, ef_search) = options
+            .map(|o| (o.sample_size, o.ef_search))
+            .unwrap_or_default();
+        let ef_search = ef_search.unwrap_or(200);
+        if ef_search == 0 {
+            return Err(Error::from_reason("efSearch must be > 0".to_string()));
+        }
+        let collections = read_collections()?;
+
+        let coll = collections
+            .get(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+        check_kind(coll, PointKind::Dense)?;
+        check_graph(coll, "checkIndex")?;
+
+        if coll.pending_build.is_some() {
+            return Err(Error::from_reason(
+                "The index is still being built in the background; try again once it is ready"
+                    .to_string(),
+            ));
+        }
+
+        let check = coll.check_index(sample_size.unwrap_or(100) as usize, ef_search as usize);
+        Ok(IndexCheckReport {
+            ok: check.missing_from_graph.is_empty() && check.sample_misses.is_empty(),
+            nodes: check.nodes as u32,
+            missing_from_graph: check.missing_from_graph,
+            dead_nodes: check.dead_nodes as u32,
+            sampled: check.sampled as u32,
+            sample_misses: check.sample_misses,
+        })
+    })
+}
+
+/// Per-query settings of `search_slice`. Unset ones fall back to the
+/// collection's search defaults.
+#[derive(Default)]
+pub(crate) struct QueryOptions {
+    pub ef_search: Option<u32>,
+    pub nprobe: Option<u32>,
+    pub pq_rescore: Option<u32>,
+    pub filter: PointFilter,
+    /// Resolved into `filter.allowed` under the collection lock
+    pub allow_ids: Option<Vec<String>>,
+    /// `createIdSet` handles, resolved into `filter` under the lock
+    pub allow_set: Option<u32>,
+    pub deny_set: Option<u32>,
+    pub policy: RetryPolicy,
+    pub projection: Option<Projection>,
+    pub score_threshold: Option<f64>,
+    /// Budget for the graph searches, counted from the call
+    pub timeout: Option<Duration>,
+    /// Fill `SearchHits::handles` and leave each result's id empty
+    pub handles: bool,
+    /// Applied to each result's score, after `decay`; results are then
+    /// re-sorted
+    pub boosts: Vec<Boost>,
+    pub decay: Option<Decay>,
+    /// Applied last; see `SearchOptions::score_expr`
+    pub score_expr: Option<ScoreExpr>,
+    /// Query token rows; replaces each score before `decay`
+    pub late_interaction: Option<Vec<Vec<f32>>>,
+}
+
+impl QueryOptions {
+    /// Whether scores are adjusted after the graph search, which re-sorts
+    /// the results and makes `search` over-fetch
+    fn rescores(&self) -> bool {
+        !self.boosts.is_empty()
+            || self.decay.is_some()
+            || self.score_expr.is_some()
+            || self.late_interaction.is_some()
+    }
+}
+
+/// Results of `search_slice`
+pub(crate) struct SearchHits {
+    pub results: Vec<SearchResult>,
+    /// Handle of each result, when `QueryOptions::handles` is set
+    pub handles: Vec<u64>,
+    /// `QueryOptions::timeout` ran out before k results were found
+    pub partial: bool,
+}
+
+/// `options` is either `efSearch` as a number or a `SearchOptions` object.
+/// Returns `{ results, partial }` instead of an array when `timeoutMs` is set,
+/// and parallel arrays when `columnar` is set.
+#[napi]
+pub fn search(
+    path: String,
+    query: VectorInput,
+    k: u32,
+    options: Option<Either<u32, SearchOptions>>,
+) -> Result<SearchOutput> {
+    guard("search", || {
+        let options = match options {
+            Some(Either::A(ef_search)) => SearchOptions {
+                ef_search: Some(ef_search),
+                ..Default::default()
+            },
+            Some(Either::B(options)) => options,
+            None => SearchOptions::default(),
+        };
+
+        let filter = PointFilter {
+            inserted_after: options.inserted_after.map(|t| t as i64),
+            inserted_before: options.inserted_before.map(|t| t as i64),
+            allowed: None,
+            denied: None,
+        };
+
+        let query_options = QueryOptions {
+            ef_search: options.ef_search,
+            nprobe: options.nprobe,
+            pq_rescore: options.pq_rescore,
+            filter,
+            allow_ids: options.allow_ids,
+            allow_set: options.allow_set,
+            deny_set: options.deny_set,
+            policy: resolve_retry_policy(options.max_retries, options.retry_growth)?,
+            projection: Projection::new(options.include_payload, options.payload_fields),
+            score_threshold: options.score_threshold,
+            timeout: options.timeout_ms.map(|ms| Duration::from_millis(ms as u64)),
+            handles: options.handles.unwrap_or(false),
+            boosts: options
+                .boosts
+                .as_deref()
+                .unwrap_or_default()
+                .iter()
+                .map(resolve_boost)
+                .collect::<Result<_>>()?,
+            decay: options.decay.as_ref().map(resolve_decay).transpose()?,
+            score_expr: options
+                .score_expr
+                .as_deref()
+                .map(ScoreExpr::parse)
+                .transpose()
+                .map_err(Error::from_reason)?,
+            late_interaction: options
+                .late_interaction
+                .as_ref()
+                .map(|tokens| tokens.iter().map(|row| row.to_vec()).collect()),
+        };
+        let shape = ResultShape {
+            timed: options.timeout_ms.is_some(),
+            columnar: options.columnar.unwrap_or(false),
+            payloads: query_options.projection.is_some(),
+            handles: query_options.handles,
+            calibration: options.calibration.as_ref().map(resolve_calibration).transpose()?,
+        };
+        if shape.columnar && options.highlight.is_some() {
+            return Err(Error::from_reason(
+                "highlight can't be combined with columnar results",
+            ));
+        }
+        if shape.handles && (!shape.columnar || options.rerank.is_some()) {
+            return Err(Error::from_reason(
+                "handles requires columnar results and can't be combined with rerank",
+            ));
+        }
+        let query = vector_values(&query);
+        let factor = options.rerank_factor.unwrap_or(DEFAULT_RERANK_FACTOR).max(1);
+
+        let Some(rerank) = options.rerank else {
+            let rescored = query_options.rescores();
+            let mut hits = if !rescored {
+                search_slice(&path, &query, k, &query_options)?
+            } else {
+                let mut hits =
+                    search_slice(&path, &query, k.saturating_mul(factor), &query_options)?;
+                hits.results.truncate(k as usize);
+                hits.handles.truncate(k as usize);
+                hits
+            };
+            if let Some(highlight) = &options.highlight {
+                highlight_results(&path, &mut hits.results, highlight)?;
+            }
+            return Ok(wrap_results(hits, &shape));
+        };
+
+        let hits = search_slice(&path, &query, k.saturating_mul(factor), &query_options)?;
+        let candidates = hits.results;
+        if candidates.is_empty() {
+            return Ok(wrap_results(SearchHits { results: candidates, ..hits }, &shape));
+        }
+
+        // search_slice has released the collection lock, so the callback is free
+        // to call back into the addon
+        let scores = rerank.call(candidates.clone())?;
+        if scores.len() != candidates.len() {
+            return Err(Error::from_reason(format!(
+                "rerank returned {} scores for {} candidates",
+                scores.len(),
+                candidates.len()
+            )));
+        }
+
+        let mut reranked: Vec<SearchResult> = candidates
+            .into_iter()
+            .zip(scores)
+            .map(|(candidate, score)| SearchResult { score, ..candidate })
+            .collect();
+        reranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+        reranked.truncate(k as usize);
+        if let Some(highlight) = &options.highlight {
+            highlight_results(&path, &mut reranked, highlight)?;
+        }
+
+        let hits = SearchHits {
+            results: reranked,
+            ..hits
+        };
+        Ok(wrap_results(hits, &shape))
+    })
+}
+
+type SearchOutput = Either3<Vec<SearchResult>, TimedSearchResults, ColumnarSearchResults>;
+
+/// How `search` hands its results back, from the options
+struct ResultShape {
+    timed: bool,
+    columnar: bool,
+    payloads: bool,
+    handles: bool,
+    calibration: Option<Calibration>,
+}
+
+fn resolve_boost(boost: &SearchBoost) -> Result<Boost> {
+    let additive = match boost.mode.as_deref() {
+        None | Some("multiply") => false,
+        Some("add") => true,
+        Some(other) => {
+            return Err(Error::from_reason(format!(
+                "Invalid boost mode '{}'; expected 'multiply' or 'add'",
+                other
+            )))
+        }
+    };
+    Ok(Boost {
+        filter: PayloadFilter::parse(&boost.filter).map_err(Error::from_reason)?,
+        weight: boost.weight,
+        additive,
+    })
+}
+
+fn resolve_decay(decay: &DecayOptions) -> Result<Decay> {
+    if !(decay.half_life_days.is_finite() && decay.half_life_days > 0.0) {
+        return Err(Error::from_reason("halfLifeDays must be > 0".to_string()));
+    }
+    Ok(Decay {
+        field: decay.field.clone(),
+        half_life_ms: decay.half_life_days * 86_400_000.0,
+        now: collection::now_millis(),
+    })
+}
+
+fn resolve_calibration(calibration: &Either<String, CalibrationRange>) -> Result<Calibration> {
+    match calibration {
+        Either::A(name) => Calibration::parse(name),
+        Either::B(range) => Calibration::range(range.min, range.max),
+    }
+    .map_err(Error::from_reason)
+}
+
+fn wrap_results(mut hits: SearchHits, shape: &ResultShape) -> SearchOutput {
+    if let Some(calibration) = &shape.calibration {
+        let mut scores: Vec<f64> = hits.results.iter().map(|result| result.score).collect();
+        calibration.apply(&mut scores);
+        for (result, score) in hits.results.iter_mut().zip(scores) {
+            result.score = score;
+        }
+    }
+    if shape.columnar {
+        let count = hits.results.len();
+        let mut ids = Vec::with_capacity(if shape.handles { 0 } else { count });
+        let mut scores = Vec::with_capacity(count);
+        let mut inserted_at = Vec::with_capacity(count);
+        let mut payloads = Vec::with_capacity(if shape.payloads { count } else { 0 });
+        for result in hits.results {
+            if !shape.handles {
+                ids.push(result.id);
+            }
+            scores.push(result.score);
+            inserted_at.push(result.inserted_at);
+            if shape.payloads {
+                payloads.push(result.payload);
+            }
+        }
+        Either3::C(ColumnarSearchResults {
+            ids: (!shape.handles).then_some(ids),
+            handles: shape.handles.then(|| hits.handles.into()),
+            scores: scores.into(),
+            inserted_at: inserted_at.into(),
+            payloads: shape.payloads.then_some(payloads),
+            partial: shape.timed.then_some(hits.partial),
+        })
+    } else if shape.timed {
+        Either3::B(TimedSearchResults {
+            results: hits.results,
+            partial: hits.partial,
+        })
+    } else {
+        Either3::A(hits.results)
+    }
+}
+
+/// Like `search`, but returns an iterator over chunks of at most
+/// `chunkSize` results, for exporting neighbour lists with a very large k.
+#[napi]
+pub fn search_stream(
+    path: String,
+    query: VectorInput,
+    k: u32,
+    options: Option<SearchStreamOptions>,
+) -> Result<SearchStream> {
+    guard("searchStream", || {
+        let options = options.unwrap_or_default();
+        let chunk_size = options.chunk_size.unwrap_or(DEFAULT_STREAM_CHUNK_SIZE);
+        if chunk_size == 0 {
+            return Err(Error::from_reason("chunkSize must be > 0".to_string()));
+        }
+
+        let filter = PointFilter {
+            inserted_after: options.inserted_after.map(|t| t as i64),
+            inserted_before: options.inserted_before.map(|t| t as i64),
+            allowed: None,
+            denied: None,
+        };
+        let query_options = QueryOptions {
+            ef_search: options.ef_search,
+            filter,
+            projection: Projection::new(options.include_payload, options.payload_fields),
+            score_threshold: options.score_threshold,
+            ..Default::default()
+        };
+        let hits = search_slice(&path, &vector_values(&query), k, &query_options)?;
+
+        Ok(SearchStream {
+            results: hits.results.into_iter(),
+            chunk_size: chunk_size as usize,
+        })
+    })
+}
+
+/// Similarity of every pair of the points, scored as `search` would, for
+/// clustering diagnostics and deduplicating candidate sets. Returns an
+/// `ids.length` square matrix row by row: entry `i * ids.length + j` is
+/// the similarity of `ids[i]` and `ids[j]`. Computed in parallel on the
+/// worker pool; counts as a heavy operation. Throws on an id that isn't
+/// live.
+#[napi]
+pub fn pairwise_similarities(path: String, ids: Vec<String>) -> Result<Float32Array> {
+    guard("pairwiseSimilarities", || {
+        let _permit = limits::try_acquire(&collection_key(&path)).map_err(Error::from_reason)?;
+        let collections = read_collections()?;
+
+        let coll = collections
+            .get(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_kind(coll, PointKind::Dense)?;
+        let matrix = coll.pairwise_similarities(&ids).map_err(Error::from_reason)?;
+        Ok(Float32Array::new(matrix))
+    })
+}
+
+#[napi(object)]
+pub struct ContextPair {
+    /// An id of a live point or a vector the results should be near
+    pub positive: Either<String, Float32Array>,
+    /// An id of a live point or a vector the results should be away from
+    pub negative: Either<String, Float32Array>,
+}
+
+#[napi(object)]
+pub struct DiscoverOptions {
+    /// An id or vector to rank the points that fit the context by
+    pub target: Option<Either<String, Float32Array>>,
+    /// Return each result's payload
+    pub include_payload: Option<bool>,
+    /// Only consider points whose payload matches, written as for `boosts`
+    pub filter: Option<Payload>,
+}
+
+/// The vector of a `discover` example: a live point's, or the given one
+/// projected as a query would be
+fn example_vector<'a>(
+    coll: &'a Collection,
+    example: &'a Either<String, Float32Array>,
+    examples: &mut HashSet<usize>,
+) -> Result<Cow<'a, [f32]>> {
+    match example {
+        Either::A(id) => {
+            let internal_id = coll
+                .id_map
+                .get(id.as_str())
+                .copied()
+                .filter(|_| !coll.deleted_ids.contains(id.as_str()))
+                .ok_or_else(|| Error::from_reason(format!("Point '{}' not found", id)))?;
+            examples.insert(internal_id);
+            coll.vector(internal_id)
+                .ok_or_else(|| Error::from_reason(format!("Point '{}' has no stored vector", id)))
+        }
+        Either::B(vector) => {
+            if vector.len() != coll.input_dimensions() {
+                return Err(Error::from_reason(format!(
+                    "Query dimension mismatch: expected {}, got {}",
+                    coll.input_dimensions(),
+                    vector.len()
+                )));
+            }
+            Ok(coll.project(vector))
+        }
+    }
+}
+
+/// Discovery search: the `k` points that best fit the context pairs, for
+/// browsing a region of the space rather than the neighbourhood of one
+/// vector. A point fits a pair when it is more similar to the positive
+/// example than to the negative one. With a `target`, points fitting more
+/// pairs rank first and ties go to the points nearest the target; without
+/// one, points are ranked by how far they fall on the wrong side of the
+/// pairs, so those fitting every pair score 0. Examples given by id are
+/// left out of the results. Scans every point in parallel on the worker
+/// pool; counts as a heavy operation.
+#[napi]
+pub fn discover(
+    path: String,
+    context: Vec<ContextPair>,
+    k: u32,
+    options: Option<DiscoverOptions>,
+) -> Result<Vec<SearchResult>> {
+    guard("discover", || {
+        if context.is_empty() {
+            return Err(Error::from_reason("discover needs at least one context pair".to_string()));
+        }
+        let (target, include_payload, filter) = match options {
+            Some(options) => (options.target, options.include_payload, options.filter),
+            None => (None, None, None),
+        };
+        let filter = filter
+            .as_ref()
+            .map(PayloadFilter::parse)
+            .transpose()
+            .map_err(Error::from_reason)?;
+
+        let _permit = limits::try_acquire(&collection_key(&path)).map_err(Error::from_reason)?;
+        let collections = read_collections()?;
+
+        let coll = collections
+            .get(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_kind(coll, PointKind::Dense)?;
+        let include_payload = include_payload
+            .or(coll.search_defaults.include_payload)
+            .unwrap_or(false);
+
+        let mut examples = HashSet::new();
+        let target = target
+            .as_ref()
+            .map(|target| example_vector(coll, target, &mut examples))
+            .transpose()?;
+        let pairs = context
+            .iter()
+            .map(|pair| {
+                Ok((
+                    example_vector(coll, &pair.positive, &mut examples)?.into_owned(),
+                    example_vector(coll, &pair.negative, &mut examples)?.into_owned(),
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let hits = coll.discover(target.as_deref(), &pairs, k as usize, |internal_id| {
+            !examples.contains(&internal_id)
+                && filter
+                    .as_ref()
+                    .is_none_or(|filter| filter.matches(coll.payloads.get(&internal_id)))
+        });
+        Ok(hits
+            .into_iter()
+            .map(|(internal_id, score)| {
+                scan_result(coll, internal_id, score as f64, include_payload)
+            })
+            .collect())
+    })
+}
+
+/// Compute the k nearest neighbours of every live point and stream them to
+/// `outFile` and/or `onBatch`, batch by batch. Returns the number of points.
+#[napi]
+pub fn knn_graph(path: String, k: u32, options: KnnGraphOptions) -> Result<u32> {
+    guard("knnGraph", || {
+        if options.out_file.is_none() && options.on_batch.is_none() {
+            return Err(Error::from_reason("knnGraph needs outFile or onBatch".to_string()));
+        }
+        let batch_size = options.batch_size.unwrap_or(DEFAULT_KNN_BATCH_SIZE);
+        if batch_size == 0 {
+            return Err(Error::from_reason("batchSize must be > 0".to_string()));
+        }
+        let ef = options
+            .ef_search
+            .map(|v| v as usize)
+            .unwrap_or_else(|| std::cmp::max((k as usize).saturating_mul(10), 200));
+        let _permit = limits::try_acquire(&collection_key(&path)).map_err(Error::from_reason)?;
+
+        // Points live at the start; ones deleted meanwhile are skipped
+        let ids: Vec<String> = {
+            let collections = read_collections()?;
+            let coll = collections
+                .get(&collection_key(&path))
+                .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+            coll.live_ids()
+        };
+
+        let mut out = match &options.out_file {
+            Some(file) => Some(BufWriter::new(File::create(file).map_err(|e| {
+                Error::from_reason(format!("Failed to create '{}': {}", file, e))
+            })?)),
+            None => None,
+        };
+
+        let mut count = 0;
+        for batch in ids.chunks(batch_size as usize) {
+            // Hold the lock only while computing, so `onBatch` may call back in
+            let entries: Vec<KnnEntry> = {
+                let collections = read_collections()?;
+                let coll = collections
+                    .get(&collection_key(&path))
+                    .ok_or_else(|| {
+                        Error::from_reason(format!("Collection not found at '{}'", path))
+                    })?;
+
+                coll.knn_of(batch, k as usize, ef)
+                    .into_iter()
+                    .map(|(id, neighbours)| KnnEntry {
+                        id,
+                        neighbors: neighbours
+                            .into_iter()
+                            .filter_map(|(internal_id, score)| {
+                                coll.reverse_map.get(&internal_id).map(|id| SearchResult {
+                                    id: id.to_string(),
+                                    score: score as f64,
+                                    inserted_at: coll.inserted_at_of(internal_id) as f64,
+                                    highlights: None,
+                                    payload: None,
+                                })
+                            })
+                            .collect(),
+                    })
+                    .collect()
+            };
+            count += entries.len();
+
+            if let Some(out) = out.as_mut() {
+                for entry in &entries {
+                    let line = serde_json::json!({
+                        "id": entry.id,
+                        "neighbors": entry
+                            .neighbors
+                            .iter()
+                            .map(|n| serde_json::json!({ "id": n.id, "score": n.score }))
+                            .collect::<Vec<_>>(),
+                    });
+                    writeln!(out, "{}", line).map_err(|e| {
+                        Error::from_reason(format!("Failed to write knn graph: {}", e))
+                    })?;
+                }
+            }
+            if let Some(on_batch) = &options.on_batch {
+                on_batch.call(entries)?;
+            }
+        }
+
+        if let Some(out) = out.as_mut() {
+            out.flush()
+                .map_err(|e| Error::from_reason(format!("Failed to write knn graph: {}", e)))?;
+        }
+
+        Ok(count as u32)
+    })
+}
+
+/// Body of `search`, shared with the embedded server.
+pub(crate) fn search_slice(
+    path: &str,
+    query: &[f32],
+    k: u32,
+    options: &QueryOptions,
+) -> Result<SearchHits> {
+    if options.nprobe == Some(0) {
+        return Err(Error::from_reason("nprobe must be > 0".to_string()));
+    }
+    if options.pq_rescore == Some(0) {
+        return Err(Error::from_reason("pqRescore must be > 0".to_string()));
+    }
+    if options.allow_ids.is_some() && options.allow_set.is_some() {
+        return Err(Error::from_reason(
+            "allowIds and allowSet can't be combined".to_string(),
+        ));
+    }
+    let started = Instant::now();
+    let collections = read_collections()?;
+
+    let coll = collections
+        .get(&collection_key(path))
+        .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+    check_kind(coll, PointKind::Dense)?;
+    if query.len() != coll.input_dimensions() {
+        return Err(Error::from_reason(format!(
+            "Query dimension mismatch: expected {}, got {}",
+            coll.input_dimensions(),
+            query.len()
+        )));
+    }
+    let query = coll.project(query);
+    if let Some(tokens) = &options.late_interaction {
+        check_query_tokens(coll, tokens)?;
+    }
+
+    if coll.active_count() == 0 {
+        return Ok(SearchHits {
+            results: Vec::new(),
+            handles: Vec::new(),
+            partial: false,
+        });
+    }
+
+    let defaults = &coll.search_defaults;
+
+    // Default ef_search = max(k * 10, 200) — high enough for good recall at scale
+    let ef = options
+        .ef_search
+        .map(|v| v as usize)
+        .or(defaults.ef_search)
+        .unwrap_or_else(|| std::cmp::max((k as usize).saturating_mul(10), 200));
+    let threshold = options.score_threshold.or(defaults.score_threshold);
+    let projection = match &options.projection {
+        Some(projection) => projection,
+        None if defaults.include_payload == Some(true) => &Projection::All,
+        None => &Projection::None,
+    };
+
+    let policy = RetryPolicy {
+        deadline: options.timeout.map(|timeout| started + timeout),
+        ..options.policy
+    };
+    let probe = ivf::Probe {
+        lists: options.nprobe.map_or(ivf::DEFAULT_NPROBE, |n| n as usize),
+        rescore: options.pq_rescore.map_or(ivf::DEFAULT_RESCORE, |n| n as usize),
+    };
+    let id_set = |handle: u32| id_set(coll, handle);
+    let allowed = match &options.allow_ids {
+        Some(ids) => Some(Arc::new(ids.iter().filter_map(|id| coll.internal_id(id)).collect())),
+        None => options.allow_set.map(id_set).transpose()?,
+    };
+    let filter = PointFilter {
+        allowed: allowed.or_else(|| options.filter.allowed.clone()),
+        denied: options.deny_set.map(id_set).transpose()?.or_else(|| options.filter.denied.clone()),
+        ..options.filter.clone()
+    };
+    let neighbours = coll.search_vectors(&query, k as usize, ef, probe, &filter, &policy);
+
+    let mut hits = buffers::scored();
+    hits.extend(
+        neighbours
+            .hits
+            .iter()
+            .map(|&(internal_id, score)| (internal_id, score as f64))
+            .filter(|&(_, score)| threshold.is_none_or(|t| score >= t)),
+    );
+    buffers::recycle_candidates(neighbours.hits);
+    if options.rescores() {
+        if let Some(tokens) = &options.late_interaction {
+            hits.retain_mut(|(internal_id, score)| {
+                match coll.late_interaction_score(*internal_id, tokens) {
+                    Some(late) => {
+                        *score = late as f64;
+                        true
+                    }
+                    None => false,
+                }
+            });
+        }
+        for (internal_id, score) in &mut hits {
+            let payload = coll.payloads.get(internal_id);
+            let inserted_at = coll.inserted_at_of(*internal_id);
+            let similarity = *score;
+            if let Some(decay) = &options.decay {
+                *score = decay.apply(*score, payload, inserted_at);
+            }
+            let boosts = options.boosts.iter();
+            *score = boosts.fold(*score, |score, boost| boost.apply(score, payload));
+            if let Some(expr) = &options.score_expr {
+                let inputs = expr::Inputs {
+                    similarity,
+                    score: *score,
+                    inserted_at,
+                    payload,
+                };
+                // NaN would sort above every real score
+                *score = Some(expr.eval(&inputs)).filter(|v| !v.is_nan()).unwrap_or(f64::MIN);
+            }
+        }
+        hits.sort_by(|a, b| b.1.total_cmp(&a.1));
+    }
+
+    let mut handles = Vec::new();
+    let results = hits
+        .drain(..)
+        .filter_map(|(internal_id, score)| {
+            let id = coll.reverse_map.get(&internal_id)?;
+            let id = if options.handles {
+                handles.push(coll.handle(internal_id));
+                String::new()
+            } else {
+                id.to_string()
+            };
+            Some(SearchResult {
+                id,
+                score,
+                inserted_at: coll.inserted_at_of(internal_id) as f64,
+                highlights: None,
+                payload: projection.apply(coll.payloads.get(&internal_id)),
+            })
+        })
+        .collect();
+    buffers::recycle_scored(hits);
+
+    Ok(SearchHits {
+        results,
+        handles,
+        partial: neighbours.partial,
+    })
+}
+
+/// Check the query token rows of a late-interaction search against the
+/// collection's token vectors.
+fn check_query_tokens(coll: &Collection, tokens: &[Vec<f32>]) -> Result<()> {
+    let Some(dimensions) = coll.token_dimensions else {
+        return Err(Error::from_reason(format!(
+            "Collection at '{}' has no token vectors; store them with setTokenVectors",
+            coll.path.display()
+        )));
+    };
+    if tokens.is_empty() {
+        return Err(Error::from_reason(
+            "lateInteraction needs at least one query token".to_string(),
+        ));
+    }
+    if let Some(row) = tokens.iter().find(|row| row.len() != dimensions) {
+        return Err(Error::from_reason(format!(
+            "Query token dimension mismatch: expected {}, got {}",
+            dimensions,
+            row.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Fill in `highlights` on each result from its payload's `field` text.
+/// Results whose field is missing or not a string get an empty list.
+fn highlight_results(
+    path: &str,
+    results: &mut [SearchResult],
+    options: &HighlightOptions,
+) -> Result<()> {
+    let collections = read_collections()?;
+
+    let coll = collections
+        .get(&collection_key(path))
+        .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+    let default = Analyzer::default();
+    let analyzer = coll.analyzers.get(&options.field).unwrap_or(&default);
+
+    for result in results {
+        let text = coll
+            .id_map
+            .get(result.id.as_str())
+            .and_then(|internal_id| coll.payloads.get(internal_id))
+            .and_then(|payload| payload.get(&options.field))
+            .and_then(|value| value.as_str());
+        let matches = match text {
+            Some(text) => analyzer.matches(text, &options.query),
+            None => Vec::new(),
+        };
+        result.highlights = Some(
+            matches
+                .into_iter()
+                .map(|token| TextToken {
+                    term: token.term,
+                    start: token.start as u32,
+                    end: token.end as u32,
+                })
+                .collect(),
+        );
+    }
+
+    Ok(())
+}
+
+/// Map handles from a `handles: true` search back to ids, in order, with
+/// `null` for handles whose point has since been deleted or replaced.
+/// Handles carry the epoch they were returned in, so one whose point's
+/// internal id has since gone to another point resolves to `null` too, as
+/// does one from before the collection was opened.
+#[napi]
+pub fn resolve_ids(path: String, handles: BigUint64Array) -> Result<Vec<Option<String>>> {
+    guard("resolveIds", || {
+        let collections = read_collections()?;
+
+        let coll = collections
+            .get(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        Ok(handles
+            .iter()
+            .map(|&handle| coll.resolve_handle(handle).map(|id| id.to_string()))
+            .collect())
+    })
+}
+
+/// Resolve `ids` to a set of points once, for searches to pass as
+/// `allowSet` or `denySet` instead of sending the ids every time. Unknown
+/// ids are ignored. The set follows its points through upserts, but is
+/// dropped by the next compaction or rebuild, after which searches naming
+/// it fail; create it again then.
+#[napi]
+pub fn create_id_set(path: String, ids: Vec<String>) -> Result<u32> {
+    guard("createIdSet", || {
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        let set = ids.iter().filter_map(|id| coll.internal_id(id)).collect();
+        let handle = coll.next_id_set;
+        coll.next_id_set += 1;
+        coll.id_sets.insert(handle, Arc::new(set));
+        Ok(handle)
+    })
+}
+
+/// Free a set made by `createIdSet`. Returns false if it was already gone.
+#[napi]
+pub fn drop_id_set(path: String, handle: u32) -> Result<bool> {
+    guard("dropIdSet", || {
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        Ok(coll.id_sets.remove(&handle).is_some())
+    })
+}
+
+fn id_set(coll: &Collection, handle: u32) -> Result<Arc<IdSet>> {
+    coll.id_sets.get(&handle).cloned().ok_or_else(|| {
+        Error::from_reason(format!(
+            "Id set {} not found; sets are dropped when the collection is compacted or rebuilt",
+            handle
+        ))
+    })
+}
+
+/// Vector tolerance of `diffCollections` when none is passed
+const DEFAULT_DIFF_TOLERANCE: f64 = 1e-6;
+/// Common points whose vectors `diffCollections` copies at a time
+const DIFF_CHUNK_SIZE: usize = 4096;
+
+#[napi(object)]
+pub struct DiffOptions {
+    /// Largest difference in any component for vectors to count as equal,
+    /// default 1e-6
+    pub tolerance: Option<f64>,
+}
+
+#[napi(object)]
+pub struct CollectionDiff {
+    /// Live ids of A that B doesn't have live; every list is sorted
+    pub only_in_a: Vec<String>,
+    pub only_in_b: Vec<String>,
+    /// Ids in both whose vectors differ beyond the tolerance, or in length
+    pub vector_changed: Vec<String>,
+    pub payload_changed: Vec<String>,
+}
+
+/// Compare the live points of two loaded collections, e.g. to validate a
+/// migration or a blue/green reindex before switching over to the copy.
+/// Both collections are read under one lock, so neither changes midway.
+#[napi]
+pub fn diff_collections(
+    path_a: String,
+    path_b: String,
+    options: Option<DiffOptions>,
+) -> Result<CollectionDiff> {
+    guard("diffCollections", || {
+        let tolerance = options.and_then(|o| o.tolerance).unwrap_or(DEFAULT_DIFF_TOLERANCE);
+        if tolerance.is_nan() || tolerance < 0.0 {
+            return Err(Error::from_reason("tolerance must be >= 0".to_string()));
+        }
+
+        let collections = read_collections()?;
+        let [a, b] = [&path_a, &path_b].map(|path| {
+            collections
+                .get(&collection_key(path))
+                .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))
+        });
+        let (a, b) = (a?, b?);
+        check_kind(a, PointKind::Dense)?;
+        check_kind(b, PointKind::Dense)?;
+
+        let (ids_a, ids_b) = (a.live_ids(), b.live_ids());
+        let only_in_a = ids_a.iter().filter(|id| !b.contains(id)).cloned().collect();
+        let only_in_b = ids_b.iter().filter(|id| !a.contains(id)).cloned().collect();
+        let common: Vec<String> = ids_a.into_iter().filter(|id| b.contains(id)).collect();
+
+        let (mut vector_changed, mut payload_changed) = (Vec::new(), Vec::new());
+        // A chunk at a time, so the copies of the vectors stay small
+        for chunk in common.chunks(DIFF_CHUNK_SIZE) {
+            let (vectors_a, vectors_b) = (a.get_vectors(chunk), b.get_vectors(chunk));
+            for id in chunk {
+                let (internal_a, internal_b) = (a.id_map[id.as_str()], b.id_map[id.as_str()]);
+                let equal = match (vectors_a.get(&internal_a), vectors_b.get(&internal_b)) {
+                    (Some(x), Some(y)) => {
+                        x.len() == y.len()
+                            && x.iter().zip(y).all(|(p, q)| ((p - q).abs() as f64) <= tolerance)
+                    }
+                    (x, y) => x.is_none() && y.is_none(),
+                };
+                if !equal {
+                    vector_changed.push(id.clone());
+                }
+                if a.payloads.get(&internal_a) != b.payloads.get(&internal_b) {
+                    payload_changed.push(id.clone());
+                }
+            }
+        }
+
+        Ok(CollectionDiff {
+            only_in_a,
+            only_in_b,
+            vector_changed,
+            payload_changed,
+        })
+    })
+}
+
+/// Look up many points under one read lock. The result has one entry per
+/// id, in order, with `null` for ids that are missing or deleted.
+#[napi]
+pub fn get_vectors(path: String, ids: Vec<String>) -> Result<Vec<Option<StoredVector>>> {
+    guard("getVectors", || {
+        let collections = read_collections()?;
+
+        let coll = collections
+            .get(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        let vectors = coll.get_vectors(&ids);
+
+        Ok(ids
+            .into_iter()
+            .map(|id| {
+                let internal_id = *coll.id_map.get(id.as_str())?;
+                let vector = vectors.get(&internal_id)?;
+                Some(StoredVector {
+                    id,
+                    vector: Float32Array::new(vector.clone()),
+                    inserted_at: coll.inserted_at_of(internal_id) as f64,
+                    payload: coll.payloads.get(&internal_id).cloned(),
+                })
+            })
+            .collect())
+    })
+}
+
+/// Points per page when `retrieve` is given no limit
+const DEFAULT_RETRIEVE_LIMIT: u32 = 100;
+
+#[napi(object)]
+pub struct RetrieveOptions {
+    /// Only return points whose payload matches, written as for `boosts`
+    pub filter: Option<Payload>,
+    /// Points returned, default 100
+    pub limit: Option<u32>,
+    /// Matching points skipped first, for paging
+    pub offset: Option<u32>,
+    /// Order by a numeric payload field instead of by id
+    pub order_by: Option<OrderBy>,
+}
+
+#[napi(object)]
+pub struct OrderBy {
+    pub field: String,
+    /// Default "asc"
+    pub direction: Option<SortDirection>,
+}
+
+#[napi(string_enum = "lowercase")]
+#[derive(PartialEq)]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+#[napi(object)]
+pub struct RetrievedPoint {
+    pub id: String,
+    /// Insertion time (ms since epoch); 0 for points saved before it was recorded
+    pub inserted_at: f64,
+    pub payload: Option<Payload>,
+}
+
+/// Live points whose payload matches `filter`, ordered by id or by
+/// `orderBy`, without a vector query. Points whose `orderBy` field isn't a
+/// number come last in either direction; ties go by id. Pages taken with
+/// `offset` line up as long as the matching points don't change in between.
+#[napi]
+pub fn retrieve(path: String, options: Option<RetrieveOptions>) -> Result<Vec<RetrievedPoint>> {
+    guard("retrieve", || {
+        let (filter, limit, offset, order_by) = match options {
+            Some(options) => (options.filter, options.limit, options.offset, options.order_by),
+            None => (None, None, None, None),
+        };
+        let filter = filter
+            .as_ref()
+            .map(PayloadFilter::parse)
+            .transpose()
+            .map_err(Error::from_reason)?;
+
+        let collections = read_collections()?;
+
+        let coll = collections
+            .get(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        if let Some(order_by) = &order_by {
+            let declared = coll.schema.as_ref().and_then(|schema| schema.get(&order_by.field));
+            let numeric = |ty: &&FieldType| matches!(ty, FieldType::Int | FieldType::Float);
+            if let Some(ty) = declared.filter(|ty| !numeric(ty)) {
+                return Err(Error::from_reason(format!(
+                    "orderBy field '{}' is declared as {}; only numeric fields can be ordered by",
+                    order_by.field,
+                    ty.name()
+                )));
+            }
+        }
+
+        let mut matching: Vec<(&Id, usize)> = coll
+            .id_map
+            .iter()
+            .filter(|(id, _)| !coll.deleted_ids.contains(*id))
+            .filter(|(_, internal_id)| {
+                filter
+                    .as_ref()
+                    .is_none_or(|filter| filter.matches(coll.payloads.get(internal_id)))
+            })
+            .map(|(id, &internal_id)| (id, internal_id))
+            .collect();
+        matching.sort_unstable();
+        if let Some(order_by) = &order_by {
+            let descending = order_by.direction == Some(SortDirection::Desc);
+            let key = |internal_id: &usize| {
+                coll.payloads
+                    .get(internal_id)
+                    .and_then(|payload| payload.get(&order_by.field))
+                    .and_then(serde_json::Value::as_f64)
+            };
+            // Stable, so ties stay in id order
+            matching.sort_by(|(_, a), (_, b)| match (key(a), key(b)) {
+                (Some(a), Some(b)) if descending => b.total_cmp(&a),
+                (Some(a), Some(b)) => a.total_cmp(&b),
+                (a, b) => b.is_some().cmp(&a.is_some()),
+            });
+        }
+
+        Ok(matching
+            .into_iter()
+            .skip(offset.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_RETRIEVE_LIMIT) as usize)
+            .map(|(id, internal_id)| RetrievedPoint {
+                id: id.to_string(),
+                inserted_at: coll.inserted_at_of(internal_id) as f64,
+                payload: coll.payloads.get(&internal_id).cloned(),
+            })
+            .collect())
+    })
+}
+
+/// Distinct values returned when `payloadValues` is given no limit
+const DEFAULT_PAYLOAD_VALUES_LIMIT: u32 = 100;
+
+#[napi(object)]
+pub struct PayloadValuesOptions {
+    /// Distinct values returned, most common first; default 100
+    pub limit: Option<u32>,
+}
+
+#[napi(object)]
+pub struct PayloadValueCount {
+    pub value: serde_json::Value,
+    /// Live points holding the value
+    pub count: u32,
+}
+
+/// Distinct values of payload field `field` across the live points, with
+/// how many points hold each, most common first, e.g. to fill a filter
+/// dropdown. Each element of an array field counts as a value, as in
+/// filters. Ties go by the value's JSON text.
+#[napi]
+pub fn payload_values(
+    path: String,
+    field: String,
+    options: Option<PayloadValuesOptions>,
+) -> Result<Vec<PayloadValueCount>> {
+    guard("payloadValues", || {
+        let limit = options
+            .and_then(|options| options.limit)
+            .unwrap_or(DEFAULT_PAYLOAD_VALUES_LIMIT);
+
+        let collections = read_collections()?;
+
+        let coll = collections
+            .get(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        // Keyed by JSON text, since values aren't hashable
+        let mut counts: HashMap<String, (&serde_json::Value, u32)> = HashMap::new();
+        for (id, internal_id) in &coll.id_map {
+            if coll.deleted_ids.contains(id) {
+                continue;
+            }
+            let values = match coll.payloads.get(internal_id).and_then(|p| p.get(&field)) {
+                Some(serde_json::Value::Array(items)) => items.iter().collect(),
+                Some(value) => vec![value],
+                None => continue,
+            };
+            for value in values {
+                counts.entry(value.to_string()).or_insert((value, 0)).1 += 1;
+            }
+        }
+
+        let mut counts: Vec<(String, (&serde_json::Value, u32))> = counts.into_iter().collect();
+        counts.sort_unstable_by(|(a, (_, a_count)), (b, (_, b_count))| {
+            b_count.cmp(a_count).then_with(|| a.cmp(b))
+        });
+        Ok(counts
+            .into_iter()
+            .take(limit as usize)
+            .map(|(_, (value, count))| PayloadValueCount {
+                value: value.clone(),
+                count,
+            })
+            .collect())
+    })
+}
+
+#[napi]
+pub fn delete_vector(path: String, id: String) -> Result<bool> {
+    guard("deleteVector", || {
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        delete_from(coll, id)
+    })
+}
+
+fn delete_from(coll: &mut Collection, id: String) -> Result<bool> {
+    check_writable(coll)?;
+
+    let deleted = coll.delete_vector(&id);
+    if deleted {
+        coll.audit("delete", vec![id]);
+    }
+    Ok(deleted)
+}
+
+/// Undo a delete that has not been purged by `buildIndex` yet.
+#[napi]
+pub fn restore_vector(path: String, id: String) -> Result<bool> {
+    guard("restoreVector", || {
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_writable(coll)?;
+
+        let restored = coll.restore_vector(&id);
+        if restored {
+            coll.audit("restore", vec![id]);
+        }
+        Ok(restored)
+    })
+}
+
+/// Store a matrix of token vectors for live point `id`, e.g. a ColBERT
+/// model's per-token output, for `lateInteraction` rescoring. Every row of
+/// every point has the same length, set by the first matrix stored. Scores
+/// are dot products, so pass unit-length rows for cosine MaxSim. Replacing
+/// the point with an upsert drops its matrix. Returns false if `id` is not
+/// live.
+#[napi]
+pub fn set_token_vectors(path: String, id: String, tokens: Vec<Float32Array>) -> Result<bool> {
+    guard("setTokenVectors", || {
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_kind(coll, PointKind::Dense)?;
+        check_writable(coll)?;
+        let dimensions = match (tokens.first(), coll.token_dimensions) {
+            (None, _) => {
+                return Err(Error::from_reason("Token matrix has no rows".to_string()));
+            }
+            (Some(_), Some(dimensions)) => dimensions,
+            (Some(row), None) => row.len(),
+        };
+        if dimensions == 0 {
+            return Err(Error::from_reason("Token vectors are empty".to_string()));
+        }
+        if let Some(row) = tokens.iter().find(|row| row.len() != dimensions) {
+            return Err(Error::from_reason(format!(
+                "Token dimension mismatch: expected {}, got {}",
+                dimensions,
+                row.len()
+            )));
+        }
+        let rows: Vec<f32> = tokens.iter().flat_map(|row| row.iter().copied()).collect();
+        if let Some(index) = rows.iter().position(|v| !v.is_finite()) {
+            return Err(Error::from_reason(format!(
+                "Token vector has a non-finite value ({}) at row {}, index {}",
+                rows[index],
+                index / dimensions,
+                index % dimensions
+            )));
+        }
+        let Some(internal_id) = coll.internal_id(&id).filter(|_| coll.contains(&id)) else {
+            return Ok(false);
+        };
+
+        coll.set_token_vectors(internal_id, dimensions, rows);
+        coll.audit("setTokenVectors", vec![id]);
+        Ok(true)
+    })
+}
+
+/// Stage `vector` as live point `id`'s embedding from the model the
+/// collection is moving to, next to the vector searches use. The first
+/// staged vector sets the staging dimensions; `modelTag` becomes the
+/// collection's tag on promotion. Upserting or deleting the point drops its
+/// staged vector, so it has to be staged again. Returns false if `id` is
+/// not live.
+#[napi]
+pub fn stage_vector(
+    path: String,
+    id: String,
+    vector: VectorInput,
+    model_tag: Option<String>,
+) -> Result<bool> {
+    guard("stageVector", || {
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_kind(coll, PointKind::Dense)?;
+        check_writable(coll)?;
+        if coll.projection.is_some() || coll.quantized() {
+            return Err(Error::from_reason(format!(
+                "Collection at '{}' can't stage vectors: it has a projection or is IVF-PQ",
+                path
+            )));
+        }
+        let vector = vector_values(&vector);
+        if vector.is_empty() {
+            return Err(Error::from_reason("Staged vector is empty".to_string()));
+        }
+        if let Some(index) = vector.iter().position(|v| !v.is_finite()) {
+            return Err(Error::from_reason(format!(
+                "Vector has a non-finite value ({}) at index {}",
+                vector[index], index
+            )));
+        }
+        if let Some(staging) = &coll.staging {
+            if vector.len() != staging.dimensions {
+                return Err(Error::from_reason(format!(
+                    "Dimension mismatch: staged vectors have {}, got {}",
+                    staging.dimensions,
+                    vector.len()
+                )));
+            }
+            if let (Some(expected), Some(tag)) = (&staging.model_tag, &model_tag) {
+                if expected != tag {
+                    return Err(Error::from_reason(format!(
+                        "MODEL_TAG_MISMATCH: vectors staged at '{}' are from '{}', got '{}'",
+                        path, expected, tag
+                    )));
+                }
+            }
+        }
+        if !coll.contains(&id) {
+            return Ok(false);
+        }
+
+        let vector = coll.project(&vector).into_owned();
+        coll.stage_vector(&id, vector, model_tag);
+        coll.audit("stage", vec![id]);
+        Ok(true)
+    })
+}
+
+/// Swap the staged vectors in for the live ones and rebuild the index on
+/// them, under one write lock: searches see either the old model's vectors
+/// or the new one's, never a mix. Every live point needs a staged vector.
+/// Tombstones are purged, since they hold old-model vectors, and savepoints
+/// are dropped. Nothing is saved. Returns how many points were promoted.
+#[napi]
+pub fn promote_staging(path: String) -> Result<u32> {
+    guard("promoteStaging", || {
+        let _permit = limits::try_acquire(&collection_key(&path)).map_err(Error::from_reason)?;
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_writable(coll)?;
+
+        let promoted = coll.promote_staging().map_err(Error::from_reason)?;
+        coll.audit("promote", Vec::<String>::new());
+        Ok(promoted as u32)
+    })
+}
+
+/// Drop every staged vector, abandoning a migration. Returns false if none
+/// were staged.
+#[napi]
+pub fn clear_staging(path: String) -> Result<bool> {
+    guard("clearStaging", || {
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_writable(coll)?;
+
+        Ok(coll.clear_staging())
+    })
+}
+
 #[napi(object)]
-pub struct CollectionConfig {
-    pub path: String,
+pub struct StagingStatus {
     pub dimensions: u32,
-    pub index_type: String,
-    pub metric: String,
+    pub model_tag: Option<String>,
+    /// Live points with a staged vector
+    pub staged: u32,
+    /// Live points still to stage before `promoteStaging` can run
+    pub missing: u32,
+}
+
+/// Progress of a migration, or null if nothing is staged.
+#[napi]
+pub fn staging_status(path: String) -> Result<Option<StagingStatus>> {
+    guard("stagingStatus", || {
+        let collections = read_collections()?;
+
+        let coll = collections
+            .get(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        Ok(coll.staging.as_ref().map(|staging| StagingStatus {
+            dimensions: staging.dimensions as u32,
+            model_tag: staging.model_tag.clone(),
+            staged: staging.vectors.len() as u32,
+            missing: coll.unstaged().len() as u32,
+        }))
+    })
+}
+
+/// Ids changed after sequence number `seq`, in the order of their latest
+/// change, for syncing a downstream copy incrementally. Each id appears
+/// once, with its current state. A deleted id is reported until its
+/// tombstone is purged; asking for changes from before a purged deletion
+/// throws, and the downstream copy has to be rebuilt from a full read.
+#[napi]
+pub fn changes_since(path: String, seq: f64) -> Result<ChangeSet> {
+    guard("changesSince", || {
+        if seq.is_nan() || seq < 0.0 {
+            return Err(Error::from_reason("seq must be >= 0".to_string()));
+        }
+
+        let collections = read_collections()?;
+
+        let coll = collections
+            .get(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        let (upserted, deleted) = coll.changes_since(seq as u64).map_err(Error::from_reason)?;
+        Ok(ChangeSet {
+            upserted: id_strings(&upserted),
+            deleted: id_strings(&deleted),
+            seq: coll.seq as f64,
+        })
+    })
 }
 
 #[napi(object)]
-pub struct SearchResult {
-    pub id: String,
-    pub score: f64,
+pub struct UnsavedChanges {
+    /// Ids inserted, updated or restored since the last save and live now
+    pub inserts: u32,
+    /// Ids deleted since the last save and still deleted
+    pub deletes: u32,
+    /// Last successful save (ms since epoch), from this process or on disk
+    pub last_saved_at: Option<f64>,
+}
+
+/// How far the collection in memory has drifted from what's on disk, for
+/// health checks to alert on. Each changed id counts once, by its state
+/// now; changes undone by a rollback still count until the next save.
+#[napi]
+pub fn unsaved_changes(path: String) -> Result<UnsavedChanges> {
+    guard("unsavedChanges", || {
+        let collections = read_collections()?;
+
+        let coll = collections
+            .get(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        let (inserts, deletes) = coll.unsaved_changes();
+        Ok(UnsavedChanges {
+            inserts: inserts as u32,
+            deletes: deletes as u32,
+            last_saved_at: coll.last_saved_at.map(|t| t as f64),
+        })
+    })
+}
+
+/// Deleted points that can still be restored, oldest deletion first.
+#[napi]
+pub fn list_deleted(path: String) -> Result<Vec<DeletedPoint>> {
+    guard("listDeleted", || {
+        let collections = read_collections()?;
+
+        let coll = collections
+            .get(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        let mut deleted: Vec<DeletedPoint> = coll
+            .deleted_ids
+            .iter()
+            .map(|id| DeletedPoint {
+                id: id.to_string(),
+                deleted_at: coll.deleted_at.get(id).copied().unwrap_or(0) as f64,
+            })
+            .collect();
+        deleted.sort_by(|a, b| a.deleted_at.total_cmp(&b.deleted_at).then_with(|| a.id.cmp(&b.id)));
+
+        Ok(deleted)
+    })
+}
+
+/// Delete every point matching the filter. Returns how many were deleted.
+#[napi]
+pub fn delete_by_filter(path: String, filter: DeleteFilter) -> Result<u32> {
+    guard("deleteByFilter", || {
+        let filter = PointFilter {
+            inserted_after: filter.inserted_after.map(|t| t as i64),
+            inserted_before: filter.inserted_before.map(|t| t as i64),
+            allowed: None,
+            denied: None,
+        };
+        if filter.is_empty() {
+            return Err(Error::from_reason(
+                "Filter must set insertedAfter or insertedBefore".to_string(),
+            ));
+        }
+
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_writable(coll)?;
+
+        let deleted = coll.delete_matching(&filter);
+        let count = deleted.len() as u32;
+        coll.audit("deleteByFilter", deleted);
+        Ok(count)
+    })
 }
 
 #[napi(object)]
-pub struct CollectionStats {
-    pub count: u32,
-    pub dimensions: u32,
-    pub file_size_bytes: u32,
+pub struct AuditEntry {
+    /// When the operation ran (ms since epoch)
+    pub at: f64,
+    /// "insert", "delete", "restore", "deleteByFilter", "updatePayloads",
+    /// "build", "compact", "reindex", "rollback", "stage", "promote" or
+    /// "setTokenVectors"
+    pub op: String,
+    /// Point ids the operation changed. For builds, compactions and
+    /// reindexes, the tombstones they purged
+    pub ids: Vec<String>,
+    /// Set with `setAuditActor` in the process that ran the operation
+    pub actor: Option<String>,
+}
+
+#[napi(object)]
+pub struct AuditLogOptions {
+    /// Only entries at or after this time (ms since epoch)
+    pub since: Option<f64>,
 }
 
+/// Attribute this process's later audit entries for the collection to
+/// `actor`, e.g. a user or job name. Pass null to clear it.
 #[napi]
-pub fn create_collection(config: CollectionConfig) -> Result<()> {
-    if config.metric != "cosine" {
-        return Err(Error::from_reason(format!(
-            "Unsupported metric '{}'. Only 'cosine' is supported.",
-            config.metric
-        )));
+pub fn set_audit_actor(path: String, actor: Option<String>) -> Result<()> {
+    guard("setAuditActor", || {
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        coll.audit_actor = actor;
+        Ok(())
+    })
+}
+
+/// Entries in the collection's audit log, oldest first.
+#[napi]
+pub fn get_audit_log(path: String, options: Option<AuditLogOptions>) -> Result<Vec<AuditEntry>> {
+    guard("getAuditLog", || {
+        let collections = read_collections()?;
+
+        let coll = collections
+            .get(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        let since = options.and_then(|o| o.since).map(|t| t as i64);
+        let entries = audit::read(&coll.path, since).map_err(Error::from_reason)?;
+
+        Ok(entries
+            .into_iter()
+            .map(|entry| AuditEntry {
+                at: entry.at as f64,
+                op: entry.op,
+                ids: entry.ids,
+                actor: entry.actor,
+            })
+            .collect())
+    })
+}
+
+#[napi]
+pub fn stats(path: String) -> Result<CollectionStats> {
+    guard("stats", || {
+        let collections = read_collections()?;
+
+        let coll = collections
+            .get(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        let file_size = persistence::collection_file_size(&coll.path);
+
+        Ok(CollectionStats {
+            count: coll.active_count() as u32,
+            dimensions: coll.dimensions as u32,
+            file_size_bytes: file_size as u32,
+            upserts: coll.upserts as u32,
+            reused_ids: coll.reused_ids as u32,
+            orphaned_nodes: coll.orphaned_nodes() as u32,
+            tombstones: coll.deleted_ids.len() as u32,
+            free_ids: coll.free_ids.len() as u32,
+            id_hits: coll.id_lookups.hits.load(Ordering::Relaxed) as u32,
+            id_misses: coll.id_lookups.misses.load(Ordering::Relaxed) as u32,
+            id_filter_rejections: coll.id_lookups.filtered.load(Ordering::Relaxed) as u32,
+            seq: coll.seq as f64,
+            committed_seq: coll.committed_seq as f64,
+            quota: coll.quota.map(|quota| QuotaUsage {
+                points: coll.active_count() as u32,
+                max_points: quota.max_points.map(|n| n as u32),
+                bytes: coll.estimated_memory() as f64,
+                max_bytes: quota.max_bytes.map(|bytes| bytes as f64),
+            }),
+        })
+    })
+}
+
+/// The configuration a collection was created with, as persisted on disk,
+/// plus how it is opened in this process.
+#[napi]
+pub fn get_config(path: String) -> Result<CollectionConfig> {
+    guard("getConfig", || {
+        let collections = read_collections()?;
+
+        let coll = collections
+            .get(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        Ok(CollectionConfig {
+            path,
+            dimensions: coll.dimensions as u32,
+            index_type: coll.index_type.clone(),
+            metric: coll.metric.clone(),
+            m: Some(coll.params.max_nb_connection as u32),
+            ef_construction: Some(coll.params.ef_construction as u32),
+            max_elements: Some(coll.params.max_elements as u32),
+            ivf_lists: coll.params.ivf_lists.map(|n| n as u32),
+            pq_subvectors: coll.params.pq_subvectors.map(|n| n as u32),
+            lsh_tables: coll.params.lsh_tables.map(|n| n as u32),
+            lsh_bits: coll.params.lsh_bits.map(|n| n as u32),
+            read_only: Some(coll.read_only),
+            shared_segments: Some(coll.share_segments),
+            tombstone_retention_days: coll
+                .tombstone_retention_ms
+                .map(|ms| ms as f64 / 86_400_000.0),
+            strict_insert: Some(coll.strict_insert),
+            audit_log: Some(coll.audit_log),
+            durability: Some(coll.durability.name().to_string()),
+            id_filter: Some(coll.id_filter.is_some()),
+            lazy_index: None,
+            auto_compaction: coll.auto_compaction.map(|policy| AutoCompactionConfig {
+                tombstone_ratio: Some(policy.tombstone_ratio),
+                min_deleted: Some(policy.min_deleted as u32),
+            }),
+            capacity_alerts: Some(capacity_alert_config(&coll.capacity_thresholds)),
+            quota: coll.quota.map(quota_config),
+            entry_limits: Some(entry_limits_config(coll.entry_limits)),
+            model_tag: coll.model_tag.clone(),
+            schema: coll.schema.as_ref().map(schema_config),
+            analyzers: Some(analyzer_configs(&coll.analyzers)),
+            seed: coll.params.seed.map(|s| s as u32),
+            shuffle_on_rebuild: Some(coll.params.shuffle_on_rebuild),
+            normalize: Some(coll.normalize),
+        })
+    })
+}
+
+fn capacity_alert_config(thresholds: &CapacityThresholds) -> CapacityAlertConfig {
+    CapacityAlertConfig {
+        levels: Some(thresholds.levels.clone()),
+        memory_limit_bytes: thresholds.memory_limit.map(|bytes| bytes as f64),
     }
-    if config.index_type != "hnsw" {
-        return Err(Error::from_reason(format!(
-            "Unsupported index type '{}'. Only 'hnsw' is supported.",
-            config.index_type
-        )));
+}
+
+fn quota_config(quota: Quota) -> QuotaConfig {
+    QuotaConfig {
+        max_points: quota.max_points.map(|n| n as u32),
+        max_bytes: quota.max_bytes.map(|bytes| bytes as f64),
     }
-    if config.dimensions == 0 {
-        return Err(Error::from_reason("Dimensions must be > 0".to_string()));
+}
+
+fn entry_limits_config(limits: EntryLimits) -> EntryLimitsConfig {
+    EntryLimitsConfig {
+        max_id_length: limits.max_id_length.map(|n| n as u32),
+        max_payload_bytes: limits.max_payload_bytes.map(|n| n as u32),
     }
+}
 
-    let path = PathBuf::from(&config.path);
-    let key = config.path.clone();
+fn schema_config(schema: &Schema) -> HashMap<String, String> {
+    schema
+        .iter()
+        .map(|(field, ty)| (field.clone(), ty.name().to_string()))
+        .collect()
+}
 
-    let mut collections = COLLECTIONS
-        .write()
-        .map_err(|e| Error::from_reason(format!("Lock error: {}", e)))?;
+fn analyzer_configs(analyzers: &Analyzers) -> HashMap<String, AnalyzerConfig> {
+    analyzers
+        .iter()
+        .map(|(field, analyzer)| {
+            let config = AnalyzerConfig {
+                lowercase: Some(analyzer.lowercase),
+                stemmer: analyzer.stemmer.clone(),
+                stopwords: Some(analyzer.stopwords.clone()),
+                ngram: analyzer.ngram.map(|(min, max)| NgramRange {
+                    min: min as u32,
+                    max: max as u32,
+                }),
+            };
+            (field.clone(), config)
+        })
+        .collect()
+}
 
-    // Idempotent: if already loaded, skip
-    if collections.contains_key(&key) {
-        return Ok(());
-    }
+#[napi(object)]
+pub struct CollectionMetadata {
+    /// Live points at the last save
+    pub count: u32,
+    /// Deleted points not yet purged at the last save
+    pub tombstones: u32,
+    /// When the collection was last saved (ms since epoch)
+    pub last_saved_at: Option<f64>,
+    pub file_size_bytes: u32,
+    /// Settings the collection was saved with; `readOnly` and `lazyIndex`
+    /// are unset
+    pub config: CollectionConfig,
+}
 
-    // Try to load existing collection from disk
-    match persistence::load_collection(&path) {
-        Ok(Some(existing)) => {
-            if existing.dimensions != config.dimensions as usize {
-                return Err(Error::from_reason(format!(
-                    "Dimension mismatch: existing collection has {} dims, requested {}",
-                    existing.dimensions, config.dimensions
-                )));
-            }
-            collections.insert(key, existing);
-        }
-        Ok(None) => {
-            let coll = Collection::new(path, config.dimensions as usize);
-            collections.insert(key, coll);
-        }
-        Err(e) => {
-            return Err(Error::from_reason(format!(
-                "Failed to load collection: {}",
-                e
-            )));
+/// Read a saved collection's settings and counts from its metadata without
+/// opening it: no vectors are decoded, no graph is built and no lock is
+/// taken. Cheap enough for admin tooling to inspect many collections, and
+/// safe to call on collections another process is writing.
+#[napi]
+pub fn open_metadata(path: String) -> Result<CollectionMetadata> {
+    guard("openMetadata", || {
+        let dir = PathBuf::from(collection_key(&path));
+        let summary = persistence::read_summary(&dir)
+            .map_err(Error::from_reason)?
+            .ok_or_else(|| Error::from_reason(format!("No collection saved at '{}'", path)))?;
+
+        Ok(CollectionMetadata {
+            count: (summary.id_map.len() - summary.deleted_ids.len()) as u32,
+            tombstones: summary.deleted_ids.len() as u32,
+            last_saved_at: summary.saved_at.map(|t| t as f64),
+            file_size_bytes: persistence::collection_file_size(&dir) as u32,
+            config: CollectionConfig {
+                path,
+                dimensions: summary.dimensions as u32,
+                index_type: summary.index_type,
+                metric: summary.metric,
+                m: Some(summary.params.max_nb_connection as u32),
+                ef_construction: Some(summary.params.ef_construction as u32),
+                max_elements: Some(summary.params.max_elements as u32),
+                ivf_lists: summary.params.ivf_lists.map(|n| n as u32),
+                pq_subvectors: summary.params.pq_subvectors.map(|n| n as u32),
+                lsh_tables: summary.params.lsh_tables.map(|n| n as u32),
+                lsh_bits: summary.params.lsh_bits.map(|n| n as u32),
+                read_only: None,
+                shared_segments: Some(summary.share_segments),
+                tombstone_retention_days: summary
+                    .tombstone_retention_ms
+                    .map(|ms| ms as f64 / 86_400_000.0),
+                strict_insert: Some(summary.strict_insert),
+                audit_log: Some(summary.audit_log),
+                durability: Some(summary.durability.name().to_string()),
+                id_filter: Some(summary.id_filter),
+                lazy_index: None,
+                auto_compaction: summary.auto_compaction.map(|policy| AutoCompactionConfig {
+                    tombstone_ratio: Some(policy.tombstone_ratio),
+                    min_deleted: Some(policy.min_deleted as u32),
+                }),
+                capacity_alerts: Some(capacity_alert_config(&summary.capacity_thresholds)),
+                quota: summary.quota.map(quota_config),
+                entry_limits: Some(entry_limits_config(summary.entry_limits)),
+                model_tag: summary.model_tag.clone(),
+                schema: summary.schema.as_ref().map(schema_config),
+                analyzers: Some(analyzer_configs(&summary.analyzers)),
+                seed: summary.params.seed.map(|s| s as u32),
+                shuffle_on_rebuild: Some(summary.params.shuffle_on_rebuild),
+                normalize: Some(summary.normalize),
+            },
+        })
+    })
+}
+
+#[napi(object)]
+pub struct DefaultSearchOptions {
+    /// HNSW ef at query time
+    pub ef_search: Option<u32>,
+    /// Drop results scoring below this
+    pub score_threshold: Option<f64>,
+    /// Return each result's payload
+    pub include_payload: Option<bool>,
+}
+
+/// Replace the options every search on this collection uses when the query
+/// doesn't set them. Saved with the collection on the next `buildIndex`.
+#[napi]
+pub fn set_default_search_options(path: String, options: DefaultSearchOptions) -> Result<()> {
+    guard("setDefaultSearchOptions", || {
+        if options.ef_search == Some(0) {
+            return Err(Error::from_reason("efSearch must be > 0".to_string()));
         }
-    }
 
-    Ok(())
+        let mut collections = write_collections()?;
+
+        let coll = collections
+            .get_mut(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        check_writable(coll)?;
+
+        coll.search_defaults = SearchDefaults {
+            ef_search: options.ef_search.map(|v| v as usize),
+            score_threshold: options.score_threshold,
+            include_payload: options.include_payload,
+        };
+        coll.mark_dirty();
+
+        Ok(())
+    })
 }
 
 #[napi]
-pub fn insert_vector(path: String, id: String, vector: Float32Array) -> Result<()> {
-    let mut collections = COLLECTIONS
-        .write()
-        .map_err(|e| Error::from_reason(format!("Lock error: {}", e)))?;
+pub fn get_default_search_options(path: String) -> Result<DefaultSearchOptions> {
+    guard("getDefaultSearchOptions", || {
+        let collections = read_collections()?;
 
-    let coll = collections
-        .get_mut(&path)
-        .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+        let coll = collections
+            .get(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        let defaults = &coll.search_defaults;
+        Ok(DefaultSearchOptions {
+            ef_search: defaults.ef_search.map(|v| v as u32),
+            score_threshold: defaults.score_threshold,
+            include_payload: defaults.include_payload,
+        })
+    })
+}
 
-    if vector.len() != coll.dimensions {
+/// Run `text` through the analyzer configured for `field` (or the default
+/// one: split on word boundaries and lowercase), to see the terms a lexical
+/// index would store for it.
+#[napi]
+pub fn analyze(path: String, field: String, text: String) -> Result<Vec<TextToken>> {
+    guard("analyze", || {
+        let collections = read_collections()?;
+
+        let coll = collections
+            .get(&collection_key(&path))
+            .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+
+        let tokens = match coll.analyzers.get(&field) {
+            Some(analyzer) => analyzer.analyze(&text),
+            None => Analyzer::default().analyze(&text),
+        };
+
+        Ok(tokens
+            .into_iter()
+            .map(|token| TextToken {
+                term: token.term,
+                start: token.start as u32,
+                end: token.end as u32,
+            })
+            .collect())
+    })
+}
+
+fn check_same_dimensions(a: &[f32], b: &[f32]) -> Result<()> {
+    if a.len() != b.len() {
         return Err(Error::from_reason(format!(
             "Dimension mismatch: expected {}, got {}",
-            coll.dimensions,
-            vector.len()
+            a.len(),
+            b.len()
         )));
     }
-
-    let vec: Vec<f32> = vector.to_vec();
-    coll.insert_vector(&id, vec);
-
     Ok(())
 }
 
+/// `a + b`, element by element, for building queries in JS without a
+/// linear algebra library.
 #[napi]
-pub fn build_index(path: String) -> Result<()> {
-    let mut collections = COLLECTIONS
-        .write()
-        .map_err(|e| Error::from_reason(format!("Lock error: {}", e)))?;
+pub fn add(a: Float32Array, b: Float32Array) -> Result<Float32Array> {
+    guard("add", || {
+        check_same_dimensions(&a, &b)?;
+        Ok(Float32Array::new(a.iter().zip(b.iter()).map(|(x, y)| x + y).collect()))
+    })
+}
 
-    let coll = collections
-        .get_mut(&path)
-        .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+/// `a - b`, element by element, e.g. for analogy queries.
+#[napi]
+pub fn subtract(a: Float32Array, b: Float32Array) -> Result<Float32Array> {
+    guard("subtract", || {
+        check_same_dimensions(&a, &b)?;
+        Ok(Float32Array::new(a.iter().zip(b.iter()).map(|(x, y)| x - y).collect()))
+    })
+}
 
-    // If deletions are pending, rebuild the HNSW from scratch
-    if !coll.deleted_ids.is_empty() {
-        // Remove deleted vectors and id mappings
-        let deleted: Vec<String> = coll.deleted_ids.iter().cloned().collect();
-        for uuid in &deleted {
-            if let Some(&internal_id) = coll.id_map.get(uuid) {
-                coll.vectors.remove(&internal_id);
-                coll.reverse_map.remove(&internal_id);
+/// The centroid of the vectors.
+#[napi]
+pub fn mean(vectors: Vec<Float32Array>) -> Result<Float32Array> {
+    guard("mean", || {
+        let Some(first) = vectors.first() else {
+            return Err(Error::from_reason("mean needs at least one vector".to_string()));
+        };
+        let mut sum = vec![0.0f32; first.len()];
+        for vector in &vectors {
+            check_same_dimensions(first, vector)?;
+            for (total, x) in sum.iter_mut().zip(vector.iter()) {
+                *total += x;
             }
-            coll.id_map.remove(uuid);
         }
-        coll.deleted_ids.clear();
+        let count = vectors.len() as f32;
+        Ok(Float32Array::new(sum.into_iter().map(|total| total / count).collect()))
+    })
+}
 
-        coll.rebuild_from_vectors();
-    }
+/// The vector scaled to unit length. A zero vector is returned as is, as
+/// collections created with `normalize` store it.
+#[napi]
+pub fn normalize(vector: Float32Array) -> Float32Array {
+    let length = collection::norm(&vector);
+    let scale = if length > 0.0 { 1.0 / length } else { 1.0 };
+    Float32Array::new(vector.iter().map(|x| x * scale).collect())
+}
 
-    // Persist to disk
-    persistence::save_collection(coll)
-        .map_err(|e| Error::from_reason(e))?;
+/// Cosine similarity of two vectors, scored as a cosine collection's
+/// search would: a zero vector is similar to everything.
+#[napi]
+pub fn cosine_similarity(a: Float32Array, b: Float32Array) -> Result<f64> {
+    guard("cosineSimilarity", || {
+        check_same_dimensions(&a, &b)?;
+        let (norm_a, norm_b) = (collection::norm(&a), collection::norm(&b));
+        if norm_a == 0.0 || norm_b == 0.0 {
+            return Ok(1.0);
+        }
+        let dot: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
+        Ok((dot / (norm_a * norm_b)).min(1.0) as f64)
+    })
+}
 
-    coll.dirty = false;
+#[napi(object)]
+pub struct SaveSnapshot {
+    /// Save time (ms since epoch)
+    pub saved_at: f64,
+    pub count: u32,
+    /// Tombstones at the time of the save
+    pub deleted: u32,
+}
 
-    Ok(())
+#[napi(object)]
+pub struct HealthReport {
+    pub path: String,
+    /// Whether the collection is open in this process
+    pub loaded: bool,
+    /// Loaded, has capacity left, and no error since the last save
+    pub healthy: bool,
+    pub read_only: Option<bool>,
+    pub count: Option<u32>,
+    /// Last successful save (ms since epoch), from this process or on disk
+    pub last_saved_at: Option<f64>,
+    /// Changes made since the last save
+    pub unsaved_changes: Option<bool>,
+    /// Deleted points not yet purged
+    pub pending_tombstones: Option<u32>,
+    /// Graph nodes a `compact` would drop
+    pub dead_nodes: Option<u32>,
+    /// Graph nodes left before `maxElements`
+    pub capacity_remaining: Option<u32>,
+    /// Capacity alert levels usage is at, lowest first
+    pub alerts: Vec<CapacityAlert>,
+    /// False while the graph of a collection opened with `lazyIndex` is
+    /// still being built; searches are exact scans until then
+    pub index_ready: Option<bool>,
+    pub last_error: Option<String>,
+    /// When `lastError` happened (ms since epoch)
+    pub last_error_at: Option<f64>,
+    /// Sizes at the most recent saves in this process, oldest first
+    pub history: Vec<SaveSnapshot>,
 }
 
+/// Structured status of a collection, for orchestration deciding whether
+/// this process is fit to serve it. Never throws for an unloaded path.
 #[napi]
-pub fn search(
-    path: String,
-    query: Float32Array,
-    k: u32,
-    ef_search: Option<u32>,
-) -> Result<Vec<SearchResult>> {
-    let collections = COLLECTIONS
-        .read()
-        .map_err(|e| Error::from_reason(format!("Lock error: {}", e)))?;
+pub fn health(path: String) -> Result<HealthReport> {
+    guard("health", || {
+        let collections = read_collections()?;
 
-    let coll = collections
-        .get(&path)
-        .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+        let Some(coll) = collections.get(&collection_key(&path)) else {
+            return Ok(HealthReport {
+                path,
+                loaded: false,
+                healthy: false,
+                read_only: None,
+                count: None,
+                last_saved_at: None,
+                unsaved_changes: None,
+                pending_tombstones: None,
+                dead_nodes: None,
+                capacity_remaining: None,
+                alerts: Vec::new(),
+                index_ready: None,
+                last_error: None,
+                last_error_at: None,
+                history: Vec::new(),
+            });
+        };
 
-    if query.len() != coll.dimensions {
-        return Err(Error::from_reason(format!(
-            "Query dimension mismatch: expected {}, got {}",
-            coll.dimensions,
-            query.len()
-        )));
-    }
+        let capacity_remaining = coll.params.max_elements.saturating_sub(coll.index_len());
+        let error_since_save = match (&coll.last_error, coll.last_saved_at) {
+            (Some((error_at, _)), Some(saved_at)) => *error_at >= saved_at,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
 
-    if coll.active_count() == 0 {
-        return Ok(Vec::new());
-    }
+        Ok(HealthReport {
+            path,
+            loaded: true,
+            healthy: capacity_remaining > 0 && !error_since_save,
+            read_only: Some(coll.read_only),
+            count: Some(coll.active_count() as u32),
+            last_saved_at: coll.last_saved_at.map(|t| t as f64),
+            unsaved_changes: Some(coll.dirty),
+            pending_tombstones: Some(coll.deleted_ids.len() as u32),
+            dead_nodes: Some(coll.dead_nodes() as u32),
+            capacity_remaining: Some(capacity_remaining as u32),
+            alerts: coll.capacity_alerts(),
+            index_ready: Some(coll.pending_build.is_none()),
+            last_error: coll.last_error.as_ref().map(|(_, e)| e.clone()),
+            last_error_at: coll.last_error.as_ref().map(|(t, _)| *t as f64),
+            history: coll
+                .save_history
+                .iter()
+                .map(|r| SaveSnapshot {
+                    saved_at: r.saved_at as f64,
+                    count: r.count as u32,
+                    deleted: r.deleted as u32,
+                })
+                .collect(),
+        })
+    })
+}
 
-    // Default ef_search = max(k * 10, 200) — high enough for good recall at scale
-    let ef = ef_search
-        .map(|v| v as usize)
-        .unwrap_or_else(|| std::cmp::max((k as usize) * 10, 200));
+/// Register `callback` to be called with each capacity alert the collection
+/// at `path` raises from now on, replacing any earlier one; pass nothing to
+/// unregister. An alert is raised when an insert takes usage to a level it
+/// wasn't at, and again only after usage drops back below it. Callbacks run
+/// on the JS thread after the write that raised them returns. Closing the
+/// collection unregisters its callback.
+#[napi]
+pub fn on_capacity_alert(path: String, callback: Option<AlertCallback>) -> Result<()> {
+    guard("onCapacityAlert", || {
+        let key = collection_key(&path);
+        if callback.is_some() && !read_collections()?.contains_key(&key) {
+            return Err(Error::from_reason(format!("Collection not found at '{}'", path)));
+        }
+        alerts::set_callback(key, callback);
+        Ok(())
+    })
+}
 
-    let results = coll.search_vectors(query.as_ref(), k as usize, ef);
+#[napi(object)]
+pub struct OperationLimits {
+    /// Heavy operations allowed at once across all collections
+    pub max_concurrent: Option<u32>,
+    /// Heavy operations allowed at once on one collection
+    pub max_per_collection: Option<u32>,
+    /// Heavy operations running now (ignored by `setOperationLimits`)
+    pub running: Option<u32>,
+}
 
-    Ok(results
-        .into_iter()
-        .map(|(id, score)| SearchResult {
-            id,
-            score: score as f64,
+/// Cap how many heavy operations (buildIndex, reindex, compact, knnGraph,
+/// pairwiseSimilarities, discover, ingest batches, background compaction)
+/// run at once. Replaces the previous limits; omitted caps are unlimited.
+/// Calls from JS over a cap throw right away instead of queueing;
+/// background work waits for a free slot.
+#[napi]
+pub fn set_operation_limits(limits: OperationLimits) -> Result<()> {
+    guard("setOperationLimits", || {
+        if limits.max_concurrent == Some(0) || limits.max_per_collection == Some(0) {
+            return Err(Error::from_reason("Operation limits must be > 0".to_string()));
+        }
+        limits::set_limits(limits::Limits {
+            max_concurrent: limits.max_concurrent.map(|v| v as usize),
+            max_per_collection: limits.max_per_collection.map(|v| v as usize),
+        });
+        Ok(())
+    })
+}
+
+#[napi]
+pub fn get_operation_limits() -> OperationLimits {
+    let (limits, running) = limits::snapshot();
+    OperationLimits {
+        max_concurrent: limits.max_concurrent.map(|v| v as u32),
+        max_per_collection: limits.max_per_collection.map(|v| v as u32),
+        running: Some(running as u32),
+    }
+}
+
+/// Background operations running or recently finished, oldest first:
+/// graph builds of `lazyIndex` opens, automatic compactions and ingest
+/// streams. The last 100 finished ones are kept.
+#[napi]
+pub fn list_operations() -> Vec<Operation> {
+    operations::list()
+}
+
+/// One operation from `listOperations`, for polling its progress.
+#[napi]
+pub fn operation_status(id: u32) -> Result<Operation> {
+    guard("operationStatus", || {
+        operations::get(id).ok_or_else(|| {
+            Error::from_reason(format!(
+                "Operation {} not found; only the last 100 finished ones are kept",
+                id
+            ))
         })
-        .collect())
+    })
+}
+
+#[napi(object)]
+pub struct ThreadPoolConfig {
+    /// Worker threads; defaults to one per CPU
+    pub threads: Option<u32>,
 }
 
+/// Size the worker pool used for parallel graph work (ingest batches,
+/// knnGraph). It is separate from libuv's pool, so the two can be sized
+/// together to fit the host. Work already running finishes on the old pool.
 #[napi]
-pub fn delete_vector(path: String, id: String) -> Result<bool> {
-    let mut collections = COLLECTIONS
-        .write()
-        .map_err(|e| Error::from_reason(format!("Lock error: {}", e)))?;
+pub fn set_thread_pool(config: ThreadPoolConfig) -> Result<()> {
+    guard("setThreadPool", || {
+        if config.threads == Some(0) {
+            return Err(Error::from_reason("threads must be > 0".to_string()));
+        }
+        pool::set_threads(config.threads.map(|v| v as usize)).map_err(Error::from_reason)
+    })
+}
 
-    let coll = collections
-        .get_mut(&path)
-        .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+#[napi]
+pub fn get_thread_pool() -> ThreadPoolConfig {
+    ThreadPoolConfig {
+        threads: Some(pool::threads() as u32),
+    }
+}
 
-    Ok(coll.delete_vector(&id))
+fn resolve_token(config: ApiToken) -> Result<(String, server::Grant)> {
+    if config.token.is_empty() {
+        return Err(Error::from_reason("API tokens must not be empty".to_string()));
+    }
+    let write = match config.scope.as_str() {
+        "read" => false,
+        "write" => true,
+        other => {
+            return Err(Error::from_reason(format!(
+                "Invalid scope '{}' for API token; expected 'read' or 'write'",
+                other
+            )))
+        }
+    };
+    let grant = server::Grant {
+        collections: config.collections,
+        write,
+    };
+    Ok((config.token, grant))
 }
 
+/// Serve the configured collections over HTTP/JSON from a background thread.
+/// Pass port 0 to pick a free port; the bound port is returned.
 #[napi]
-pub fn stats(path: String) -> Result<CollectionStats> {
-    let collections = COLLECTIONS
-        .read()
-        .map_err(|e| Error::from_reason(format!("Lock error: {}", e)))?;
+pub fn start_server(port: u32, config: ServerConfig) -> Result<u32> {
+    guard("startServer", || {
+        let port = u16::try_from(port)
+            .map_err(|_| Error::from_reason(format!("Invalid port {}", port)))?;
+        let host = config.host.unwrap_or_else(|| "127.0.0.1".to_string());
+        let tokens = config
+            .tokens
+            .map(|tokens| tokens.into_iter().map(resolve_token).collect::<Result<Vec<_>>>())
+            .transpose()?;
+        let tls = config
+            .tls
+            .map(|tls| server::load_tls(&tls.cert_path, &tls.key_path))
+            .transpose()
+            .map_err(Error::from_reason)?;
 
-    let coll = collections
-        .get(&path)
-        .ok_or_else(|| Error::from_reason(format!("Collection not found at '{}'", path)))?;
+        let bound = server::start(&host, port, config.collections, tokens, tls)
+            .map_err(Error::from_reason)?;
 
-    let file_size = persistence::collection_file_size(&coll.path);
+        Ok(bound as u32)
+    })
+}
+
+#[napi]
+pub fn stop_server(port: u32) -> Result<bool> {
+    guard("stopServer", || {
+        let port = u16::try_from(port)
+            .map_err(|_| Error::from_reason(format!("Invalid port {}", port)))?;
 
-    Ok(CollectionStats {
-        count: coll.active_count() as u32,
-        dimensions: coll.dimensions as u32,
-        file_size_bytes: file_size as u32,
+        server::stop(port).map_err(Error::from_reason)
     })
 }
@@ -0,0 +1,109 @@
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+/// Caps on heavy operations (builds, reindexes, compactions, ingest
+/// batches, k-NN graph exports) running at once. `None` means unlimited.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Limits {
+    pub max_concurrent: Option<usize>,
+    pub max_per_collection: Option<usize>,
+}
+
+#[derive(Default)]
+struct State {
+    limits: Limits,
+    running: usize,
+    /// Running operations keyed by collection key
+    per_collection: HashMap<String, usize>,
+}
+
+impl State {
+    /// Why another operation on `key` can't start now, if it can't
+    fn refusal(&self, key: &str) -> Option<String> {
+        if let Some(max) = self.limits.max_concurrent {
+            if self.running >= max {
+                return Some(format!(
+                    "Too many concurrent heavy operations: limit is {}",
+                    max
+                ));
+            }
+        }
+        if let Some(max) = self.limits.max_per_collection {
+            if self.per_collection.get(key).copied().unwrap_or(0) >= max {
+                return Some(format!(
+                    "Too many concurrent heavy operations on '{}': limit is {}",
+                    key, max
+                ));
+            }
+        }
+        None
+    }
+
+    fn start(&mut self, key: &str) -> Permit {
+        self.running += 1;
+        *self.per_collection.entry(key.to_string()).or_insert(0) += 1;
+        Permit {
+            key: key.to_string(),
+        }
+    }
+}
+
+static STATE: Lazy<(Mutex<State>, Condvar)> =
+    Lazy::new(|| (Mutex::new(State::default()), Condvar::new()));
+
+fn state() -> MutexGuard<'static, State> {
+    // The state is plain counters, so a panic elsewhere can't leave it torn
+    STATE.0.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Held for the duration of a heavy operation; dropping it frees the slot.
+pub struct Permit {
+    key: String,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut state = state();
+        state.running -= 1;
+        if let Some(count) = state.per_collection.get_mut(&self.key) {
+            *count -= 1;
+            if *count == 0 {
+                state.per_collection.remove(&self.key);
+            }
+        }
+        STATE.1.notify_all();
+    }
+}
+
+/// Start a heavy operation on the collection with key `key`, or fail right
+/// away if a limit is reached. Used for calls made from JS.
+pub fn try_acquire(key: &str) -> Result<Permit, String> {
+    let mut state = state();
+    match state.refusal(key) {
+        Some(reason) => Err(reason),
+        None => Ok(state.start(key)),
+    }
+}
+
+/// Start a heavy operation, waiting for a free slot. Used by background
+/// threads, which must not drop their work.
+pub fn acquire(key: &str) -> Permit {
+    let mut state = state();
+    while state.refusal(key).is_some() {
+        state = STATE.1.wait(state).unwrap_or_else(|e| e.into_inner());
+    }
+    state.start(key)
+}
+
+pub fn set_limits(limits: Limits) {
+    state().limits = limits;
+    // Raised limits may let waiters through
+    STATE.1.notify_all();
+}
+
+/// Current limits and the number of heavy operations running.
+pub fn snapshot() -> (Limits, usize) {
+    let state = state();
+    (state.limits, state.running)
+}
@@ -0,0 +1,53 @@
+use std::sync::Once;
+use std::thread;
+use std::time::Duration;
+
+use crate::unwind::guard;
+use crate::{background_collections, background_read_collections, limits};
+
+/// How often the maintenance thread checks compaction thresholds
+const CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+static STARTED: Once = Once::new();
+
+/// Start the maintenance thread if it isn't running yet. It lives for the
+/// rest of the process and compacts every writable collection whose
+/// `auto_compaction` thresholds are crossed.
+pub fn ensure_started() {
+    STARTED.call_once(|| {
+        thread::spawn(|| loop {
+            thread::sleep(CHECK_INTERVAL);
+            run_once();
+        });
+    });
+}
+
+fn run_once() {
+    // Find candidates under the read lock so searches aren't held up
+    let due: Vec<String> = background_read_collections()
+        .iter()
+        .filter(|(_, coll)| !coll.read_only && coll.compaction_due())
+        .map(|(key, _)| key.clone())
+        .collect();
+
+    for key in due {
+        // Skip this round if heavy operations are at their limit
+        let Ok(_permit) = limits::try_acquire(&key) else {
+            continue;
+        };
+        let mut collections = background_collections();
+        // Re-check: the collection may have changed or closed meanwhile
+        if let Some(coll) = collections.get_mut(&key) {
+            if !coll.read_only && coll.compaction_due() {
+                // A panic would end this thread, and with it auto-compaction
+                // for the rest of the process; report it through `health`
+                if let Err(e) = guard("autoCompaction", || {
+                    coll.compact();
+                    Ok(())
+                }) {
+                    coll.record_error(&e.reason);
+                }
+            }
+        }
+    }
+}
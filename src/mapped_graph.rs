@@ -0,0 +1,80 @@
+use hnsw_rs::hnswio::{HnswIo, ReloadOptions};
+use hnsw_rs::prelude::*;
+use std::collections::HashMap;
+use std::mem::ManuallyDrop;
+use std::path::Path;
+use std::ptr::NonNull;
+
+/// A graph reloaded from a segment dump with its vectors memory-mapped,
+/// together with the `HnswIo` owning the mapping. hnsw_rs ties such a
+/// graph's lifetime to a borrow of the `HnswIo`, so the two can only be
+/// kept side by side in one owner that controls how the graph is lent
+/// out and in which order they are freed.
+///
+/// Invariant: `graph` points into `io`, which is freed only in `drop`,
+/// after the graph. The graph is never handed out for longer than a
+/// borrow of `self`: `with_graph` only passes it to a callback generic
+/// over its lifetime, so nothing taken from it can outlive the mapping.
+pub struct MappedGraph {
+    graph: ManuallyDrop<Hnsw<'static, f32, DistCosine>>,
+    /// Node of each internal id, for reading single vectors
+    nodes: HashMap<usize, PointId>,
+    /// Leaked box, so moving `MappedGraph` leaves the mapping in place
+    io: NonNull<HnswIo>,
+}
+
+// SAFETY: `io` is only read through the graph, which is itself Send and
+// Sync, and is freed only by the owner in `drop`
+unsafe impl Send for MappedGraph {}
+unsafe impl Sync for MappedGraph {}
+
+impl MappedGraph {
+    pub fn load(dir: &Path, basename: &str) -> Result<Self, String> {
+        let io = NonNull::from(Box::leak(Box::new(HnswIo::new_with_options(
+            dir,
+            basename,
+            ReloadOptions::default().set_mmap(true),
+        ))));
+        // SAFETY: `io` was just allocated and nothing else refers to it
+        let graph = match unsafe { (*io.as_ptr()).load_hnsw() } {
+            Ok(graph) => graph,
+            Err(e) => {
+                // SAFETY: the failed load borrowed nothing from `io`
+                drop(unsafe { Box::from_raw(io.as_ptr()) });
+                return Err(format!("Failed to load segments: {}", e));
+            }
+        };
+        let nodes = graph
+            .get_point_indexation()
+            .into_iter()
+            .map(|point| (point.get_origin_id(), point.get_point_id()))
+            .collect();
+        Ok(MappedGraph {
+            graph: ManuallyDrop::new(graph),
+            nodes,
+            io,
+        })
+    }
+
+    /// Run `f` on the graph
+    pub fn with_graph<R>(&self, f: impl for<'b> FnOnce(&Hnsw<'b, f32, DistCosine>) -> R) -> R {
+        f(&self.graph)
+    }
+
+    /// The vector of `internal_id`'s node, copied out of the mapping
+    pub fn vector(&self, internal_id: usize) -> Option<Vec<f32>> {
+        let node = self.nodes.get(&internal_id)?;
+        self.graph.get_point_indexation().get_point_data(node)
+    }
+}
+
+impl Drop for MappedGraph {
+    fn drop(&mut self) {
+        // SAFETY: the graph is dropped first and never used again, and
+        // nothing borrowed from it outlives `self`
+        unsafe {
+            ManuallyDrop::drop(&mut self.graph);
+            drop(Box::from_raw(self.io.as_ptr()));
+        }
+    }
+}
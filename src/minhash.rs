@@ -0,0 +1,108 @@
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, HashSet};
+
+/// LSH bands per signature. Two sets become candidates when all rows of
+/// any band agree, which for `BANDS` = 32 and `ROWS` = 4 is likely above a
+/// Jaccard similarity of about (1/32)^(1/4) ≈ 0.42 and rare well below it.
+pub const BANDS: usize = 32;
+/// Minhash values per band
+pub const ROWS: usize = 4;
+
+/// Seed of each minhash function
+static SEEDS: Lazy<Vec<u64>> =
+    Lazy::new(|| (0..BANDS * ROWS).map(|i| mix(i as u64 + 1)).collect());
+
+/// splitmix64's finalizer: a cheap, well-mixed 64-bit hash
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Sorted, without duplicates, as sets are stored and compared
+pub fn normalize(mut tokens: Vec<u32>) -> Vec<u32> {
+    tokens.sort_unstable();
+    tokens.dedup();
+    tokens
+}
+
+/// |a ∩ b| / |a ∪ b| of two normalized sets
+pub fn jaccard(a: &[u32], b: &[u32]) -> f64 {
+    let (mut i, mut j, mut shared) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                shared += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    let union = a.len() + b.len() - shared;
+    if union == 0 {
+        1.0
+    } else {
+        shared as f64 / union as f64
+    }
+}
+
+/// One bucket key per band of the set's minhash signature
+fn band_keys(tokens: &[u32]) -> Vec<u64> {
+    let mut signature = vec![u64::MAX; BANDS * ROWS];
+    for &token in tokens {
+        for (min, seed) in signature.iter_mut().zip(SEEDS.iter()) {
+            *min = (*min).min(mix(u64::from(token) ^ seed));
+        }
+    }
+    signature
+        .chunks_exact(ROWS)
+        .enumerate()
+        .map(|(band, rows)| rows.iter().fold(mix(band as u64), |key, &row| mix(key ^ row)))
+        .collect()
+}
+
+/// Buckets of internal ids by band key. Kept in memory only and rebuilt
+/// from the sets when a collection is loaded.
+#[derive(Clone, Debug, Default)]
+pub struct LshIndex {
+    buckets: HashMap<u64, Vec<usize>>,
+}
+
+impl LshIndex {
+    pub fn build(sets: &HashMap<usize, Vec<u32>>) -> Self {
+        let mut index = LshIndex::default();
+        for (&internal_id, tokens) in sets {
+            index.insert(internal_id, tokens);
+        }
+        index
+    }
+
+    pub fn insert(&mut self, internal_id: usize, tokens: &[u32]) {
+        for key in band_keys(tokens) {
+            self.buckets.entry(key).or_default().push(internal_id);
+        }
+    }
+
+    pub fn remove(&mut self, internal_id: usize, tokens: &[u32]) {
+        for key in band_keys(tokens) {
+            if let Some(bucket) = self.buckets.get_mut(&key) {
+                bucket.retain(|&id| id != internal_id);
+                if bucket.is_empty() {
+                    self.buckets.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// Internal ids sharing at least one bucket with `tokens`
+    pub fn candidates(&self, tokens: &[u32]) -> HashSet<usize> {
+        band_keys(tokens)
+            .into_iter()
+            .filter_map(|key| self.buckets.get(&key))
+            .flatten()
+            .copied()
+            .collect()
+    }
+}
@@ -0,0 +1,248 @@
+use serde_json::{json, Map, Value};
+
+/// OpenAPI 3.0 description of the HTTP server's endpoints, served at
+/// `GET /openapi.json`. `secured` adds the bearer token scheme.
+pub fn document(secured: bool) -> Value {
+    let mut doc = json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "zvec-native",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Vector collections served by a zvec-native process",
+        },
+        "paths": {
+            "/collections": {
+                "get": operation(
+                    "listCollections",
+                    "List served collections",
+                    None,
+                    "CollectionList",
+                ),
+            },
+            "/collections/{name}/stats": {
+                "get": operation("getStats", "Collection statistics", None, "Stats"),
+                "parameters": [name_parameter()],
+            },
+            "/collections/{name}/health": {
+                "get": operation("getHealth", "Collection health report", None, "Health"),
+                "parameters": [name_parameter()],
+            },
+            "/collections/{name}/points": {
+                "post": operation("insertPoint", "Insert or replace a point", Some("Point"), "Ok"),
+                "parameters": [name_parameter()],
+            },
+            "/collections/{name}/points/{id}": {
+                "delete": operation("deletePoint", "Delete a point", None, "Deleted"),
+                "parameters": [
+                    name_parameter(),
+                    {
+                        "name": "id",
+                        "in": "path",
+                        "required": true,
+                        "description": "Point id",
+                        "schema": { "type": "string" },
+                    },
+                ],
+            },
+            "/collections/{name}/search": {
+                "post": operation(
+                    "search",
+                    "Nearest neighbour search",
+                    Some("SearchRequest"),
+                    "SearchResponse",
+                ),
+                "parameters": [name_parameter()],
+            },
+            "/collections/{name}/build": {
+                "post": operation("buildIndex", "Rebuild the index and save it", None, "Ok"),
+                "parameters": [name_parameter()],
+            },
+        },
+        "components": {
+            "schemas": schemas(),
+        },
+    });
+
+    if secured {
+        doc["components"]["securitySchemes"] = json!({
+            "bearerAuth": { "type": "http", "scheme": "bearer" },
+        });
+        doc["security"] = json!([{ "bearerAuth": [] }]);
+    }
+    doc
+}
+
+fn operation(id: &str, summary: &str, request: Option<&str>, response: &str) -> Value {
+    let error = json!({
+        "description": "Error",
+        "content": { "application/json": { "schema": schema_ref("Error") } },
+    });
+    let mut op = json!({
+        "operationId": id,
+        "summary": summary,
+        "responses": {
+            "200": {
+                "description": "Success",
+                "content": { "application/json": { "schema": schema_ref(response) } },
+            },
+            "400": error.clone(),
+            "401": error.clone(),
+            "403": error.clone(),
+            "404": error,
+        },
+    });
+    if let Some(request) = request {
+        op["requestBody"] = json!({
+            "required": true,
+            "content": { "application/json": { "schema": schema_ref(request) } },
+        });
+    }
+    op
+}
+
+fn name_parameter() -> Value {
+    json!({
+        "name": "name",
+        "in": "path",
+        "required": true,
+        "description": "Collection name from the server config",
+        "schema": { "type": "string" },
+    })
+}
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+/// Object schema requiring every property not in `optional`
+fn object(properties: Value, optional: &[&str]) -> Value {
+    let required: Vec<&String> = properties
+        .as_object()
+        .map(Map::keys)
+        .into_iter()
+        .flatten()
+        .filter(|key| !optional.contains(&key.as_str()))
+        .collect();
+    json!({ "type": "object", "properties": properties, "required": required })
+}
+
+fn schemas() -> Value {
+    let integer = json!({ "type": "integer" });
+    let number = json!({ "type": "number" });
+    let boolean = json!({ "type": "boolean" });
+    let vector = json!({ "type": "array", "items": { "type": "number" } });
+    let payload = json!({ "type": "object", "additionalProperties": true });
+
+    json!({
+        "Error": object(json!({ "error": { "type": "string" } }), &[]),
+        "Ok": object(json!({ "ok": boolean }), &[]),
+        "Deleted": object(json!({ "deleted": boolean }), &[]),
+        "CollectionList": object(
+            json!({ "collections": { "type": "array", "items": { "type": "string" } } }),
+            &[],
+        ),
+        "Stats": object(
+            json!({
+                "count": integer,
+                "dimensions": integer,
+                "fileSizeBytes": integer,
+                "upserts": integer,
+                "reusedIds": integer,
+                "orphanedNodes": integer,
+                "tombstones": integer,
+                "freeIds": integer,
+                "idHits": integer,
+                "idMisses": integer,
+                "idFilterRejections": integer,
+                "seq": integer,
+                "committedSeq": integer,
+            }),
+            &[],
+        ),
+        "Health": object(
+            json!({
+                "loaded": boolean,
+                "healthy": boolean,
+                "count": integer,
+                "lastSavedAt": { "type": "number", "nullable": true },
+                "unsavedChanges": integer,
+                "pendingTombstones": integer,
+                "deadNodes": integer,
+                "capacityRemaining": integer,
+                "alerts": {
+                    "type": "array",
+                    "items": object(
+                        json!({
+                            "kind": { "type": "string", "enum": ["elements", "memory"] },
+                            "level": number,
+                            "usage": number,
+                            "limit": number,
+                        }),
+                        &[],
+                    ),
+                },
+                "indexReady": boolean,
+                "lastError": { "type": "string", "nullable": true },
+            }),
+            &[],
+        ),
+        "Point": object(
+            json!({
+                "id": { "type": "string" },
+                "vector": vector,
+                "payload": payload,
+                "modelTag": { "type": "string" },
+            }),
+            &["payload", "modelTag"],
+        ),
+        "SearchRequest": object(
+            json!({
+                "vector": vector,
+                "k": { "type": "integer", "default": 10 },
+                "efSearch": integer,
+                "nprobe": integer,
+                "pqRescore": integer,
+                "insertedAfter": integer,
+                "insertedBefore": integer,
+                "allowIds": { "type": "array", "items": { "type": "string" } },
+                "maxRetries": integer,
+                "retryGrowth": integer,
+                "includePayload": boolean,
+                "payloadFields": { "type": "array", "items": { "type": "string" } },
+                "scoreThreshold": number,
+                "timeoutMs": integer,
+            }),
+            &[
+                "k",
+                "efSearch",
+                "nprobe",
+                "pqRescore",
+                "insertedAfter",
+                "insertedBefore",
+                "allowIds",
+                "maxRetries",
+                "retryGrowth",
+                "includePayload",
+                "payloadFields",
+                "scoreThreshold",
+                "timeoutMs",
+            ],
+        ),
+        "SearchResult": object(
+            json!({
+                "id": { "type": "string" },
+                "score": number,
+                "insertedAt": integer,
+                "payload": payload,
+            }),
+            &["payload"],
+        ),
+        "SearchResponse": object(
+            json!({
+                "results": { "type": "array", "items": schema_ref("SearchResult") },
+                "partial": boolean,
+            }),
+            &[],
+        ),
+    })
+}
@@ -0,0 +1,132 @@
+use napi_derive::napi;
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::collection::now_millis;
+
+/// Finished operations kept for polling, oldest dropped first
+const FINISHED_KEPT: usize = 100;
+
+#[napi(string_enum = "lowercase")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OperationPhase {
+    /// Waiting for a slot under the operation limits
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct Operation {
+    pub id: u32,
+    /// "build" for the graph build of a `lazyIndex` open, "compaction" for
+    /// an automatic compaction, "ingest" for an ingest stream
+    pub kind: String,
+    pub path: String,
+    pub phase: OperationPhase,
+    /// Points handled so far
+    pub processed: f64,
+    /// Points to handle, if known up front
+    pub total: Option<f64>,
+    /// Set when `phase` is "failed"
+    pub error: Option<String>,
+    /// ms since epoch
+    pub started_at: f64,
+    pub finished_at: Option<f64>,
+}
+
+#[derive(Default)]
+struct Registry {
+    next_id: u32,
+    /// Oldest first; running ones stay until they finish
+    operations: VecDeque<Operation>,
+}
+
+static REGISTRY: Lazy<Mutex<Registry>> = Lazy::new(|| Mutex::new(Registry::default()));
+
+fn registry() -> MutexGuard<'static, Registry> {
+    // Entries are replaced whole, so a panic elsewhere can't leave one torn
+    REGISTRY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Every operation running or recently finished, oldest first
+pub fn list() -> Vec<Operation> {
+    registry().operations.iter().cloned().collect()
+}
+
+pub fn get(id: u32) -> Option<Operation> {
+    registry().operations.iter().find(|op| op.id == id).cloned()
+}
+
+/// Register a background operation on `path`, queued until `running` is
+/// called. Dropping the handle finishes it, as done unless `fail` was
+/// called.
+pub fn start(kind: &str, path: &str, total: Option<usize>) -> Handle {
+    let mut registry = registry();
+    registry.next_id += 1;
+    let id = registry.next_id;
+    registry.operations.push_back(Operation {
+        id,
+        kind: kind.to_string(),
+        path: path.to_string(),
+        phase: OperationPhase::Queued,
+        processed: 0.0,
+        total: total.map(|n| n as f64),
+        error: None,
+        started_at: now_millis() as f64,
+        finished_at: None,
+    });
+    Handle { id }
+}
+
+/// Registry entry of one running operation
+pub struct Handle {
+    id: u32,
+}
+
+impl Handle {
+    fn update(&self, f: impl FnOnce(&mut Operation)) {
+        if let Some(op) = registry().operations.iter_mut().find(|op| op.id == self.id) {
+            f(op);
+        }
+    }
+
+    pub fn running(&self) {
+        self.update(|op| op.phase = OperationPhase::Running);
+    }
+
+    pub fn progress(&self, processed: usize) {
+        self.update(|op| op.processed = processed as f64);
+    }
+
+    pub fn fail(&self, error: &str) {
+        self.update(|op| {
+            op.phase = OperationPhase::Failed;
+            op.error = Some(error.to_string());
+        });
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        self.update(|op| {
+            if op.phase != OperationPhase::Failed {
+                op.phase = OperationPhase::Done;
+            }
+            op.finished_at = Some(now_millis() as f64);
+        });
+
+        let mut registry = registry();
+        let finished = registry.operations.iter().filter(|op| op.finished_at.is_some()).count();
+        if finished > FINISHED_KEPT {
+            if let Some(oldest) =
+                registry.operations.iter().position(|op| op.finished_at.is_some())
+            {
+                registry.operations.remove(oldest);
+            }
+        }
+    }
+}
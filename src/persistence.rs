@@ -1,54 +1,770 @@
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD as BASE64;
+use hnsw_rs::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
-use std::fs;
-use std::path::Path;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{ErrorKind, Write};
+use std::path::{Component, Path, PathBuf};
 
-use crate::collection::Collection;
+use crate::alerts::CapacityThresholds;
+use crate::collection::{
+    now_millis, Collection, CompactionPolicy, EntryLimits, HnswParams, Id, Quota, ReverseMap,
+    SearchDefaults, Staging, DEFAULT_INDEX_TYPE, DEFAULT_METRIC,
+};
+use crate::mapped_graph::MappedGraph;
+use crate::minhash::LshIndex;
+use crate::projection::InputProjection;
+use crate::schema::{Payload, Schema};
+use crate::text::Analyzers;
+use crate::vector_file::{VectorFile, VECTOR_FILE_PREFIX};
+
+/// How far `save_collection` pushes a save towards stable storage before
+/// returning. Stronger levels survive more kinds of failure but make every
+/// save slower.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Durability {
+    /// Leave the save in the OS page cache. A process crash loses nothing,
+    /// but a power loss can lose the save or leave an empty metadata file
+    #[default]
+    None,
+    /// Flush the metadata file to disk before renaming it into place, so a
+    /// power loss leaves either the old save or the new one
+    Flush,
+    /// Also flush the segment dumps and the directory entry, so the save
+    /// itself survives a power loss once this returns
+    Fsync,
+}
+
+impl Durability {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "none" => Ok(Durability::None),
+            "flush" => Ok(Durability::Flush),
+            "fsync" => Ok(Durability::Fsync),
+            _ => Err(format!(
+                "Invalid durability '{}'; expected 'none', 'flush' or 'fsync'",
+                name
+            )),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Durability::None => "none",
+            Durability::Flush => "flush",
+            Durability::Fsync => "fsync",
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize)]
 struct Metadata {
     dimensions: usize,
+    #[serde(default = "default_metric")]
+    metric: String,
+    #[serde(default = "default_index_type")]
+    index_type: String,
     next_id: usize,
-    id_map: HashMap<String, usize>,
-    deleted_ids: HashSet<String>,
+    id_map: HashMap<Id, usize>,
+    /// Id of each internal id (absent in collections saved before it was
+    /// kept, which derive it from `id_map`)
+    #[serde(default)]
+    reverse_map: Option<ReverseMap>,
+    deleted_ids: HashSet<Id>,
+    /// Deletion time (ms since epoch) of each tombstone
+    #[serde(default)]
+    deleted_at: HashMap<Id, i64>,
+    #[serde(default)]
+    tombstone_retention_ms: Option<i64>,
     /// Vectors stored as base64-encoded f32 arrays keyed by internal ID
     vectors: HashMap<String, String>,
+    /// Packed bit vectors of hamming collections, base64-encoded
+    #[serde(default)]
+    codes: HashMap<String, String>,
+    /// Sorted token sets of jaccard collections
+    #[serde(default)]
+    sets: HashMap<String, Vec<u32>>,
+    /// Late-interaction token matrices, base64-encoded like `vectors`
+    #[serde(default)]
+    token_vectors: HashMap<String, String>,
+    #[serde(default)]
+    token_dimensions: Option<usize>,
+    /// Insertion time (ms since epoch) keyed by internal ID
+    #[serde(default)]
+    inserted_at: HashMap<String, i64>,
+    /// Payloads keyed by internal ID
+    #[serde(default)]
+    payloads: HashMap<String, Payload>,
+    #[serde(default)]
+    schema: Option<Schema>,
+    #[serde(default)]
+    analyzers: Analyzers,
+    #[serde(default)]
+    search_defaults: SearchDefaults,
+    /// HNSW construction parameters (absent in collections saved before they were configurable)
+    #[serde(default)]
+    params: HnswParams,
+    #[serde(default)]
+    share_segments: bool,
+    #[serde(default)]
+    strict_insert: bool,
+    #[serde(default)]
+    audit_log: bool,
+    #[serde(default)]
+    durability: Durability,
+    #[serde(default)]
+    id_filter: bool,
+    #[serde(default)]
+    projection: Option<InputProjection>,
+    #[serde(default)]
+    normalize: bool,
+    #[serde(default)]
+    upserts: u64,
+    #[serde(default)]
+    reused_ids: u64,
+    #[serde(default)]
+    seq: u64,
+    /// Sequence number of each id's latest change
+    #[serde(default)]
+    change_seqs: HashMap<Id, u64>,
+    #[serde(default)]
+    purged_seq: u64,
+    #[serde(default)]
+    auto_compaction: Option<CompactionPolicy>,
+    #[serde(default)]
+    capacity_thresholds: CapacityThresholds,
+    #[serde(default)]
+    quota: Option<Quota>,
+    #[serde(default)]
+    entry_limits: EntryLimits,
+    #[serde(default)]
+    model_tag: Option<String>,
+    #[serde(default)]
+    staging: Option<SavedStaging>,
+    /// When this file was written (ms since epoch)
+    #[serde(default)]
+    saved_at: Option<i64>,
+    /// Basename of the current graph + data segment dump
+    #[serde(default)]
+    segments: Option<String>,
+    /// File IVF-PQ collections keep their vectors in, instead of `vectors`
+    #[serde(default)]
+    vector_file: Option<String>,
+    /// Record of each point in `vector_file`, keyed by internal ID
+    #[serde(default)]
+    vector_slots: HashMap<String, u64>,
+}
+
+/// `Staging` with its vectors base64-encoded like `Metadata::vectors`
+#[derive(Serialize, Deserialize)]
+struct SavedStaging {
+    dimensions: usize,
+    #[serde(default)]
+    model_tag: Option<String>,
+    vectors: HashMap<Id, String>,
+}
+
+/// Everything in `Metadata` except the vectors, for opens that don't need them
+#[derive(Deserialize)]
+struct MetadataHeader {
+    dimensions: usize,
+    #[serde(default = "default_metric")]
+    metric: String,
+    #[serde(default = "default_index_type")]
+    index_type: String,
+    next_id: usize,
+    id_map: HashMap<Id, usize>,
+    #[serde(default)]
+    reverse_map: Option<ReverseMap>,
+    deleted_ids: HashSet<Id>,
+    #[serde(default)]
+    deleted_at: HashMap<Id, i64>,
+    #[serde(default)]
+    inserted_at: HashMap<String, i64>,
+    #[serde(default)]
+    payloads: HashMap<String, Payload>,
+    #[serde(default)]
+    schema: Option<Schema>,
+    #[serde(default)]
+    analyzers: Analyzers,
+    #[serde(default)]
+    search_defaults: SearchDefaults,
+    #[serde(default)]
+    params: HnswParams,
+    #[serde(default)]
+    strict_insert: bool,
+    #[serde(default)]
+    audit_log: bool,
+    #[serde(default)]
+    durability: Durability,
+    #[serde(default)]
+    id_filter: bool,
+    #[serde(default)]
+    projection: Option<InputProjection>,
+    #[serde(default)]
+    normalize: bool,
+    #[serde(default)]
+    upserts: u64,
+    #[serde(default)]
+    reused_ids: u64,
+    #[serde(default)]
+    seq: u64,
+    #[serde(default)]
+    change_seqs: HashMap<Id, u64>,
+    #[serde(default)]
+    purged_seq: u64,
+    #[serde(default)]
+    saved_at: Option<i64>,
+    #[serde(default)]
+    segments: Option<String>,
+    #[serde(default)]
+    token_vectors: HashMap<String, String>,
+    #[serde(default)]
+    token_dimensions: Option<usize>,
+    #[serde(default)]
+    model_tag: Option<String>,
+    #[serde(default)]
+    staging: Option<SavedStaging>,
+}
+
+/// The settings and bookkeeping of a saved collection, read without the
+/// vectors, payloads or timestamps, for inspecting collections that are
+/// not open
+#[derive(Deserialize)]
+pub struct Summary {
+    pub dimensions: usize,
+    #[serde(default = "default_metric")]
+    pub metric: String,
+    #[serde(default = "default_index_type")]
+    pub index_type: String,
+    pub id_map: HashMap<String, usize>,
+    pub deleted_ids: HashSet<String>,
+    #[serde(default)]
+    pub tombstone_retention_ms: Option<i64>,
+    #[serde(default)]
+    pub schema: Option<Schema>,
+    #[serde(default)]
+    pub analyzers: Analyzers,
+    #[serde(default)]
+    pub params: HnswParams,
+    #[serde(default)]
+    pub share_segments: bool,
+    #[serde(default)]
+    pub strict_insert: bool,
+    #[serde(default)]
+    pub audit_log: bool,
+    #[serde(default)]
+    pub durability: Durability,
+    #[serde(default)]
+    pub id_filter: bool,
+    #[serde(default)]
+    pub normalize: bool,
+    #[serde(default)]
+    pub auto_compaction: Option<CompactionPolicy>,
+    #[serde(default)]
+    pub capacity_thresholds: CapacityThresholds,
+    #[serde(default)]
+    pub quota: Option<Quota>,
+    #[serde(default)]
+    pub entry_limits: EntryLimits,
+    #[serde(default)]
+    pub model_tag: Option<String>,
+    #[serde(default)]
+    pub saved_at: Option<i64>,
+}
+
+fn default_metric() -> String {
+    DEFAULT_METRIC.to_string()
+}
+
+fn default_index_type() -> String {
+    DEFAULT_INDEX_TYPE.to_string()
 }
 
 const METADATA_FILE: &str = "metadata.json";
+const LOCK_FILE: &str = "LOCK";
+const SEGMENT_PREFIX: &str = "graph-";
+/// Sidecar of vector norms: the `saved_at` of the metadata written with
+/// it, then an (internal ID as u64, norm as f32) record per vector, all
+/// little-endian
+const NORMS_FILE: &str = "norms.bin";
+
+/// Resolve `path` to an absolute path with symlinks, `.` and `..` resolved,
+/// so every spelling of the same directory gives the same result. Components
+/// that don't exist yet are appended to the deepest existing ancestor.
+pub fn canonical_path(path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+
+    let mut missing = Vec::new();
+    let mut existing = absolute.as_path();
+    loop {
+        if let Ok(resolved) = fs::canonicalize(existing) {
+            let mut out = resolved;
+            for component in missing.iter().rev() {
+                match component {
+                    Component::ParentDir => {
+                        out.pop();
+                    }
+                    Component::CurDir => {}
+                    other => out.push(other),
+                }
+            }
+            return out;
+        }
+        match (existing.parent(), existing.components().next_back()) {
+            (Some(parent), Some(last)) => {
+                missing.push(last);
+                existing = parent;
+            }
+            _ => return absolute,
+        }
+    }
+}
+
+/// Take the single-writer lock on a collection directory, creating the
+/// directory if needed. The lock is held for as long as the file stays open
+/// and is released by the OS if the process dies.
+pub fn lock_collection_dir(path: &Path) -> Result<File, String> {
+    fs::create_dir_all(path).map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path.join(LOCK_FILE))
+        .map_err(|e| format!("Failed to open lock file: {}", e))?;
+
+    match file.try_lock() {
+        Ok(()) => Ok(file),
+        Err(TryLockError::WouldBlock) => Err(format!(
+            "Collection at '{}' is locked by another writer; open it with readOnly: true",
+            path.display()
+        )),
+        Err(TryLockError::Error(e)) => Err(format!("Failed to lock collection: {}", e)),
+    }
+}
+
+/// Whether `path` holds a saved collection
+pub fn is_collection_dir(path: &Path) -> bool {
+    path.join(METADATA_FILE).is_file()
+}
+
+/// Move a collection directory to `to`, which must not exist yet. Within
+/// one filesystem this is a single rename, so a crash leaves the collection
+/// at one path or the other, never split between them.
+pub fn move_collection_dir(from: &Path, to: &Path, durability: Durability) -> Result<(), String> {
+    if to.exists() {
+        return Err(format!("'{}' already exists", to.display()));
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    fs::rename(from, to).map_err(|e| format!("Failed to move collection: {}", e))?;
+    if durability != Durability::None {
+        for dir in [from.parent(), to.parent()].into_iter().flatten() {
+            sync_dir(dir)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the bytes written.
+pub fn save_collection(collection: &mut Collection) -> Result<u64, String> {
+    // Attached readers need the graph now, not when the background build lands
+    if collection.share_segments && collection.pending_build.is_some() {
+        collection.rebuild_from_vectors();
+    }
+    if !collection.quantized() {
+        collection.unspill();
+    }
 
-pub fn save_collection(collection: &Collection) -> Result<(), String> {
     let path = &collection.path;
     fs::create_dir_all(path).map_err(|e| format!("Failed to create directory: {}", e))?;
 
+    let mut written = 0;
+
+    // Dump segments under a fresh name so attached readers keep their mapping
+    let segments = if collection.share_segments {
+        let seq = collection
+            .segments
+            .as_deref()
+            .and_then(|s| s.strip_prefix(SEGMENT_PREFIX))
+            .and_then(|n| n.parse::<u64>().ok())
+            .map_or(0, |n| n + 1);
+        let basename = collection
+            .hnsw
+            .file_dump(path, &format!("{}{}", SEGMENT_PREFIX, seq))
+            .map_err(|e| format!("Failed to dump segments: {}", e))?;
+        for suffix in [".hnsw.graph", ".hnsw.data"] {
+            written += fs::metadata(path.join(format!("{}{}", basename, suffix)))
+                .map_or(0, |m| m.len());
+        }
+        Some(basename)
+    } else {
+        None
+    };
+
+    // IVF-PQ collections move their vectors to the vector file instead
+    let spilled = if collection.quantized() {
+        let (file, appended) = spill_vectors(collection)?;
+        written += appended;
+        Some(file)
+    } else {
+        None
+    };
+    let in_file = spilled.is_some();
+
     // Encode vectors as base64
     let mut encoded_vectors: HashMap<String, String> = HashMap::new();
-    for (&internal_id, vec) in &collection.vectors {
-        let bytes: Vec<u8> = vec.iter().flat_map(|f| f.to_le_bytes()).collect();
-        encoded_vectors.insert(internal_id.to_string(), BASE64.encode(&bytes));
+    for (&internal_id, vec) in collection.vectors.iter().filter(|_| !in_file) {
+        encoded_vectors.insert(internal_id.to_string(), encode_vector(vec));
     }
 
+    let saved_at = now_millis();
     let metadata = Metadata {
         dimensions: collection.dimensions,
+        metric: collection.metric.clone(),
+        index_type: collection.index_type.clone(),
         next_id: collection.next_id,
         id_map: collection.id_map.clone(),
+        reverse_map: Some(collection.reverse_map.clone()),
         deleted_ids: collection.deleted_ids.clone(),
+        deleted_at: collection.deleted_at.clone(),
+        tombstone_retention_ms: collection.tombstone_retention_ms,
         vectors: encoded_vectors,
+        codes: collection
+            .codes
+            .iter()
+            .map(|(internal_id, code)| (internal_id.to_string(), BASE64.encode(code)))
+            .collect(),
+        sets: collection
+            .sets
+            .iter()
+            .map(|(internal_id, tokens)| (internal_id.to_string(), tokens.clone()))
+            .collect(),
+        token_vectors: collection
+            .token_vectors
+            .iter()
+            .map(|(internal_id, rows)| (internal_id.to_string(), encode_vector(rows)))
+            .collect(),
+        token_dimensions: collection.token_dimensions,
+        inserted_at: collection
+            .inserted_at
+            .iter()
+            .map(|(internal_id, &ts)| (internal_id.to_string(), ts))
+            .collect(),
+        payloads: collection
+            .payloads
+            .iter()
+            .map(|(internal_id, payload)| (internal_id.to_string(), payload.clone()))
+            .collect(),
+        schema: collection.schema.clone(),
+        analyzers: collection.analyzers.clone(),
+        search_defaults: collection.search_defaults.clone(),
+        params: collection.params,
+        share_segments: collection.share_segments,
+        strict_insert: collection.strict_insert,
+        audit_log: collection.audit_log,
+        durability: collection.durability,
+        id_filter: collection.id_filter.is_some(),
+        projection: collection.projection.clone(),
+        normalize: collection.normalize,
+        upserts: collection.upserts,
+        reused_ids: collection.reused_ids,
+        seq: collection.seq,
+        change_seqs: collection.change_seqs.clone(),
+        purged_seq: collection.purged_seq,
+        auto_compaction: collection.auto_compaction,
+        capacity_thresholds: collection.capacity_thresholds.clone(),
+        quota: collection.quota,
+        entry_limits: collection.entry_limits,
+        model_tag: collection.model_tag.clone(),
+        staging: collection.staging.as_ref().map(|staging| SavedStaging {
+            dimensions: staging.dimensions,
+            model_tag: staging.model_tag.clone(),
+            vectors: staging
+                .vectors
+                .iter()
+                .map(|(id, vector)| (id.clone(), encode_vector(vector)))
+                .collect(),
+        }),
+        saved_at: Some(saved_at),
+        segments: segments.clone(),
+        vector_file: spilled.as_ref().map(|file| file.name.clone()),
+        vector_slots: spilled
+            .iter()
+            .flat_map(|file| file.slots())
+            .map(|(internal_id, &slot)| (internal_id.to_string(), slot))
+            .collect(),
     };
 
     let json = serde_json::to_string_pretty(&metadata)
         .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
 
+    written += write_norms(collection, saved_at)?;
+
+    // Write to a temp file and rename so readers never see a partial file
+    let durability = collection.durability;
     let metadata_path = path.join(METADATA_FILE);
-    fs::write(&metadata_path, json)
+    let tmp_path = path.join(format!("{}.tmp", METADATA_FILE));
+    let mut tmp = File::create(&tmp_path)
+        .map_err(|e| format!("Failed to write metadata: {}", e))?;
+    tmp.write_all(json.as_bytes())
         .map_err(|e| format!("Failed to write metadata: {}", e))?;
+    written += json.len() as u64;
+    if durability != Durability::None {
+        tmp.sync_all()
+            .map_err(|e| format!("Failed to flush metadata: {}", e))?;
+    }
+    drop(tmp);
+    if durability == Durability::Fsync {
+        if let Some(basename) = &segments {
+            for suffix in [".hnsw.graph", ".hnsw.data"] {
+                sync_file(&path.join(format!("{}{}", basename, suffix)))?;
+            }
+        }
+    }
+    fs::rename(&tmp_path, &metadata_path)
+        .map_err(|e| format!("Failed to replace metadata: {}", e))?;
+    if durability == Durability::Fsync {
+        sync_dir(path)?;
+    }
+
+    remove_stale_segments(path, segments.as_deref());
+    remove_stale_vector_files(path, spilled.as_ref().map(|file| file.name.as_str()));
+    collection.segments = segments;
+    if let Some(spilled) = spilled {
+        collection.vectors.clear();
+        collection.spilled = Some(spilled);
+    }
+    collection.record_save();
+
+    Ok(written)
+}
 
+/// The vector file for this save, left out of the collection until the
+/// metadata naming it is written. New vectors are appended to the current
+/// file, or all of them copied to a fresh one once most of its records are
+/// dead. Also returns the bytes written to it.
+fn spill_vectors(collection: &Collection) -> Result<(VectorFile, u64), String> {
+    let (mut file, start) = match &collection.spilled {
+        Some(current) if current.records() <= 2 * current.len() as u64 => {
+            (current.try_clone()?, current.bytes())
+        }
+        current => {
+            let seq = current
+                .as_ref()
+                .and_then(|file| file.name.strip_prefix(VECTOR_FILE_PREFIX))
+                .and_then(|name| name.strip_suffix(".f32"))
+                .and_then(|n| n.parse::<u64>().ok())
+                .map_or(0, |n| n + 1);
+            let name = format!("{}{}.f32", VECTOR_FILE_PREFIX, seq);
+            let mut file = VectorFile::open(&collection.path, &name, collection.dimensions)?;
+            if let Some(current) = current.as_ref() {
+                let mut ids: Vec<usize> = current.ids().collect();
+                ids.sort_unstable_by_key(|internal_id| current.slots()[internal_id]);
+                for chunk in ids.chunks(4096) {
+                    let vectors: Vec<(usize, Vec<f32>)> = chunk
+                        .iter()
+                        .filter_map(|&internal_id| {
+                            current.read(internal_id).map(|vector| (internal_id, vector))
+                        })
+                        .collect();
+                    file.append(vectors.iter().map(|(id, vector)| (*id, vector.as_slice())))?;
+                }
+            }
+            (file, 0)
+        }
+    };
+    file.append(collection.vectors.iter().map(|(id, vector)| (*id, vector.as_slice())))?;
+    if collection.durability != Durability::None {
+        file.sync()?;
+    }
+    let appended = file.bytes() - start;
+    Ok((file, appended))
+}
+
+/// Replace the norms sidecar, or remove it from collections without float
+/// vectors. Goes through a temp file like the metadata. Returns the bytes
+/// written.
+fn write_norms(collection: &Collection, saved_at: i64) -> Result<u64, String> {
+    let norms_path = collection.path.join(NORMS_FILE);
+    if collection.norms.is_empty() {
+        let _ = fs::remove_file(&norms_path);
+        return Ok(0);
+    }
+    let mut bytes = Vec::with_capacity(8 + collection.norms.len() * 12);
+    bytes.extend(saved_at.to_le_bytes());
+    for (&internal_id, norm) in &collection.norms {
+        bytes.extend((internal_id as u64).to_le_bytes());
+        bytes.extend(norm.to_le_bytes());
+    }
+    let tmp_path = collection.path.join(format!("{}.tmp", NORMS_FILE));
+    let mut tmp =
+        File::create(&tmp_path).map_err(|e| format!("Failed to write norms: {}", e))?;
+    tmp.write_all(&bytes)
+        .map_err(|e| format!("Failed to write norms: {}", e))?;
+    if collection.durability != Durability::None {
+        tmp.sync_all()
+            .map_err(|e| format!("Failed to flush norms: {}", e))?;
+    }
+    drop(tmp);
+    fs::rename(&tmp_path, &norms_path).map_err(|e| format!("Failed to replace norms: {}", e))?;
+    Ok(bytes.len() as u64)
+}
+
+/// Norms saved along with the metadata stamped `saved_at`. Empty if the
+/// sidecar is missing, torn or left by another save; the caller then
+/// recomputes them.
+fn read_norms(path: &Path, saved_at: Option<i64>) -> HashMap<usize, f32> {
+    let Ok(bytes) = fs::read(path.join(NORMS_FILE)) else {
+        return HashMap::new();
+    };
+    let Some((stamp, records)) = bytes.split_first_chunk::<8>() else {
+        return HashMap::new();
+    };
+    if saved_at != Some(i64::from_le_bytes(*stamp)) || records.len() % 12 != 0 {
+        return HashMap::new();
+    }
+    records
+        .chunks_exact(12)
+        .map(|record| {
+            let (internal_id, norm) = record.split_at(8);
+            (
+                u64::from_le_bytes(internal_id.try_into().expect("8-byte id")) as usize,
+                f32::from_le_bytes(norm.try_into().expect("4-byte norm")),
+            )
+        })
+        .collect()
+}
+
+fn sync_file(path: &Path) -> Result<(), String> {
+    File::open(path)
+        .and_then(|file| file.sync_all())
+        .map_err(|e| format!("Failed to flush '{}': {}", path.display(), e))
+}
+
+/// Flush the directory entry so a rename in `dir` survives a power loss.
+/// Windows doesn't allow opening directories and commits renames itself.
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> Result<(), String> {
+    sync_file(dir)
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> Result<(), String> {
     Ok(())
 }
 
-pub fn load_collection(path: &Path) -> Result<Option<Collection>, String> {
+/// Best-effort removal of segment dumps other than `current`. Readers that
+/// still map an old dump keep it alive on Unix; on Windows the delete fails
+/// while it is mapped and is retried on the next save.
+fn remove_stale_segments(path: &Path, current: Option<&str>) {
+    let Ok(entries) = fs::read_dir(path) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some(basename) = name
+            .strip_suffix(".hnsw.data")
+            .or_else(|| name.strip_suffix(".hnsw.graph"))
+        else {
+            continue;
+        };
+        if basename.starts_with(SEGMENT_PREFIX) && Some(basename) != current {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// Best-effort removal of vector files other than `current`
+fn remove_stale_vector_files(path: &Path, current: Option<&str>) {
+    let Ok(entries) = fs::read_dir(path) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with(VECTOR_FILE_PREFIX) && Some(name.as_str()) != current {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// Read the summary of the collection saved in `path`, or None if nothing
+/// has been saved there. Takes no lock, so it works while another process
+/// writes the collection.
+pub fn read_summary(path: &Path) -> Result<Option<Summary>, String> {
+    let json = match fs::read_to_string(path.join(METADATA_FILE)) {
+        Ok(json) => json,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read metadata: {}", e)),
+    };
+    serde_json::from_str(&json)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse metadata: {}", e))
+}
+
+/// Open a collection read-only from its segment dump, with vector data
+/// memory-mapped instead of decoded from metadata.
+pub fn attach_collection(path: &Path) -> Result<Collection, String> {
+    let json = fs::read_to_string(path.join(METADATA_FILE))
+        .map_err(|e| format!("Failed to read metadata: {}", e))?;
+    let header: MetadataHeader = serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse metadata: {}", e))?;
+
+    let basename = header.segments.ok_or_else(|| {
+        format!(
+            "Collection at '{}' has no shared segments; open the writer with sharedSegments: true and call buildIndex",
+            path.display()
+        )
+    })?;
+
+    let graph = MappedGraph::load(path, &basename)?;
+
+    let mut collection = Collection::new(path.to_path_buf(), header.dimensions, header.params);
+    collection.mapped_graph = Some(graph);
+    collection.metric = header.metric;
+    collection.index_type = header.index_type;
+    collection.segments = Some(basename);
+    collection.read_only = true;
+    collection.next_id = header.next_id;
+    collection.set_ids(header.id_map, header.reverse_map, header.deleted_ids, header.deleted_at);
+    collection.inserted_at = decode_timestamps(&header.inserted_at)?;
+    collection.payloads = decode_payloads(header.payloads)?;
+    collection.schema = header.schema;
+    collection.analyzers = header.analyzers;
+    collection.search_defaults = header.search_defaults;
+    collection.strict_insert = header.strict_insert;
+    collection.audit_log = header.audit_log;
+    collection.durability = header.durability;
+    collection.set_id_filter(header.id_filter);
+    collection.projection = header.projection;
+    collection.normalize = header.normalize;
+    collection.upserts = header.upserts;
+    collection.reused_ids = header.reused_ids;
+    collection.seq = header.seq;
+    collection.committed_seq = header.seq;
+    collection.change_seqs = header
+        .change_seqs
+        .into_iter()
+        .map(|(id, seq)| (collection.intern(&id), seq))
+        .collect();
+    collection.purged_seq = header.purged_seq;
+    collection.last_saved_at = header.saved_at;
+    // Not in the segment dump: lateInteraction searches need the token
+    // matrices, and stagingStatus the staged vectors
+    collection.token_vectors = decode_token_vectors(&header.token_vectors)?;
+    collection.token_dimensions = header.token_dimensions;
+    collection.model_tag = header.model_tag;
+    collection.staging = decode_staging(&collection, header.staging)?;
+    Ok(collection)
+}
+
+/// Load the collection saved in `path`. Without `build_graph` the graph is
+/// left empty for the caller to build, e.g. with `Collection::defer_build`.
+pub fn load_collection(path: &Path, build_graph: bool) -> Result<Option<Collection>, String> {
     let metadata_path = path.join(METADATA_FILE);
 
     if !metadata_path.exists() {
@@ -61,38 +777,173 @@ pub fn load_collection(path: &Path) -> Result<Option<Collection>, String> {
     let metadata: Metadata = serde_json::from_str(&json)
         .map_err(|e| format!("Failed to parse metadata: {}", e))?;
 
-    let mut collection = Collection::new(path.to_path_buf(), metadata.dimensions);
+    let mut collection = Collection::new(path.to_path_buf(), metadata.dimensions, metadata.params);
+    collection.metric = metadata.metric;
+    collection.index_type = metadata.index_type;
     collection.next_id = metadata.next_id;
-    collection.id_map = metadata.id_map;
-    collection.deleted_ids = metadata.deleted_ids;
+    collection.set_ids(
+        metadata.id_map,
+        metadata.reverse_map,
+        metadata.deleted_ids,
+        metadata.deleted_at,
+    );
+    collection.tombstone_retention_ms = metadata.tombstone_retention_ms;
+    collection.share_segments = metadata.share_segments;
+    collection.strict_insert = metadata.strict_insert;
+    collection.audit_log = metadata.audit_log;
+    collection.durability = metadata.durability;
+    collection.set_id_filter(metadata.id_filter);
+    collection.projection = metadata.projection;
+    collection.normalize = metadata.normalize;
+    collection.upserts = metadata.upserts;
+    collection.reused_ids = metadata.reused_ids;
+    collection.seq = metadata.seq;
+    collection.committed_seq = metadata.seq;
+    collection.change_seqs = metadata
+        .change_seqs
+        .into_iter()
+        .map(|(id, seq)| (collection.intern(&id), seq))
+        .collect();
+    collection.purged_seq = metadata.purged_seq;
+    collection.auto_compaction = metadata.auto_compaction;
+    collection.capacity_thresholds = metadata.capacity_thresholds;
+    collection.quota = metadata.quota;
+    collection.entry_limits = metadata.entry_limits;
+    collection.model_tag = metadata.model_tag;
+    collection.last_saved_at = metadata.saved_at;
+    collection.inserted_at = decode_timestamps(&metadata.inserted_at)?;
+    collection.payloads = decode_payloads(metadata.payloads)?;
+    collection.schema = metadata.schema;
+    collection.analyzers = metadata.analyzers;
+    collection.search_defaults = metadata.search_defaults;
+    collection.segments = metadata.segments;
 
     // Decode vectors from base64
     for (id_str, b64) in &metadata.vectors {
         let internal_id: usize = id_str.parse()
             .map_err(|e| format!("Invalid internal ID '{}': {}", id_str, e))?;
 
-        let bytes = BASE64.decode(b64)
-            .map_err(|e| format!("Failed to decode vector: {}", e))?;
-
-        let vec: Vec<f32> = bytes
-            .chunks_exact(4)
-            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-            .collect();
-
-        collection.vectors.insert(internal_id, vec);
+        collection.vectors.insert(internal_id, decode_vector(b64)?);
     }
-
-    // Build reverse map from id_map
-    for (uuid, &internal_id) in &collection.id_map {
-        collection.reverse_map.insert(internal_id, uuid.clone());
+    collection.staging = decode_staging(&collection, metadata.staging)?;
+    if let Some(name) = &metadata.vector_file {
+        if !path.join(name).exists() {
+            return Err(format!("Vector file '{}' is missing", name));
+        }
+        let mut file = VectorFile::open(path, name, metadata.dimensions)?;
+        let slots = metadata
+            .vector_slots
+            .iter()
+            .map(|(id_str, &slot)| {
+                id_str
+                    .parse::<usize>()
+                    .map(|internal_id| (internal_id, slot))
+                    .map_err(|e| format!("Invalid internal ID '{}': {}", id_str, e))
+            })
+            .collect::<Result<_, _>>()?;
+        file.set_slots(slots);
+        collection.spilled = Some(file);
+    }
+    collection.norms = read_norms(path, metadata.saved_at);
+    collection.check_norms();
+    for (id_str, b64) in &metadata.codes {
+        let internal_id: usize = id_str
+            .parse()
+            .map_err(|e| format!("Invalid internal ID '{}': {}", id_str, e))?;
+        let code = BASE64
+            .decode(b64)
+            .map_err(|e| format!("Failed to decode binary vector: {}", e))?;
+        collection.codes.insert(internal_id, code);
     }
+    for (id_str, tokens) in &metadata.sets {
+        let internal_id: usize = id_str
+            .parse()
+            .map_err(|e| format!("Invalid internal ID '{}': {}", id_str, e))?;
+        collection.sets.insert(internal_id, tokens.clone());
+    }
+    collection.token_vectors = decode_token_vectors(&metadata.token_vectors)?;
+    collection.token_dimensions = metadata.token_dimensions;
+    collection.lsh = LshIndex::build(&collection.sets);
 
     // Rebuild HNSW from stored vectors
-    collection.rebuild_from_vectors();
+    if build_graph {
+        collection.rebuild_from_vectors();
+    }
 
     Ok(Some(collection))
 }
 
+fn decode_token_vectors(
+    saved: &HashMap<String, String>,
+) -> Result<HashMap<usize, Vec<f32>>, String> {
+    saved
+        .iter()
+        .map(|(id_str, b64)| {
+            let internal_id: usize = id_str
+                .parse()
+                .map_err(|e| format!("Invalid internal ID '{}': {}", id_str, e))?;
+            Ok((internal_id, decode_vector(b64)?))
+        })
+        .collect()
+}
+
+fn decode_staging(
+    collection: &Collection,
+    saved: Option<SavedStaging>,
+) -> Result<Option<Staging>, String> {
+    let Some(saved) = saved else {
+        return Ok(None);
+    };
+    let vectors = saved
+        .vectors
+        .iter()
+        .map(|(id, b64)| Ok((collection.intern(id), decode_vector(b64)?)))
+        .collect::<Result<_, String>>()?;
+    Ok(Some(Staging {
+        dimensions: saved.dimensions,
+        model_tag: saved.model_tag,
+        vectors,
+    }))
+}
+
+fn encode_vector(vector: &[f32]) -> String {
+    let bytes: Vec<u8> = vector.iter().flat_map(|f| f.to_le_bytes()).collect();
+    BASE64.encode(&bytes)
+}
+
+fn decode_vector(b64: &str) -> Result<Vec<f32>, String> {
+    let bytes = BASE64.decode(b64)
+        .map_err(|e| format!("Failed to decode vector: {}", e))?;
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect())
+}
+
+fn decode_timestamps(encoded: &HashMap<String, i64>) -> Result<HashMap<usize, i64>, String> {
+    encoded
+        .iter()
+        .map(|(id_str, &ts)| {
+            id_str
+                .parse::<usize>()
+                .map(|internal_id| (internal_id, ts))
+                .map_err(|e| format!("Invalid internal ID '{}': {}", id_str, e))
+        })
+        .collect()
+}
+
+fn decode_payloads(encoded: HashMap<String, Payload>) -> Result<HashMap<usize, Payload>, String> {
+    encoded
+        .into_iter()
+        .map(|(id_str, payload)| {
+            id_str
+                .parse::<usize>()
+                .map(|internal_id| (internal_id, payload))
+                .map_err(|e| format!("Invalid internal ID '{}': {}", id_str, e))
+        })
+        .collect()
+}
+
 pub fn collection_file_size(path: &Path) -> u64 {
     let metadata_path = path.join(METADATA_FILE);
     if metadata_path.exists() {
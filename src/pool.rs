@@ -0,0 +1,46 @@
+use once_cell::sync::Lazy;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::sync::{Arc, RwLock};
+
+/// Worker threads for parallel graph work (batch inserts, k-NN graphs),
+/// kept apart from rayon's global pool and from libuv's.
+static POOL: Lazy<RwLock<Arc<ThreadPool>>> = Lazy::new(|| {
+    let pool = build(default_threads()).expect("Failed to start the worker thread pool");
+    RwLock::new(Arc::new(pool))
+});
+
+fn default_threads() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+fn build(threads: usize) -> Result<ThreadPool, String> {
+    ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|i| format!("zvec-worker-{}", i))
+        .build()
+        .map_err(|e| format!("Failed to start the worker thread pool: {}", e))
+}
+
+fn current() -> Arc<ThreadPool> {
+    Arc::clone(&POOL.read().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Run `op` inside the worker pool, so rayon work it starts uses the pool's
+/// threads.
+pub fn install<R: Send>(op: impl FnOnce() -> R + Send) -> R {
+    current().install(op)
+}
+
+/// Replace the pool with one of `threads` threads, or one per CPU. Work
+/// already running finishes on the old pool.
+pub fn set_threads(threads: Option<usize>) -> Result<(), String> {
+    let pool = build(threads.unwrap_or_else(default_threads))?;
+    *POOL.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(pool);
+    Ok(())
+}
+
+pub fn threads() -> usize {
+    current().current_num_threads()
+}
@@ -0,0 +1,76 @@
+use crate::ivf::kmeans;
+
+/// Centroids per subspace, so each code is one byte per subvector
+const CODEBOOK_SIZE: usize = 256;
+
+/// Product quantizer: vectors are cut into `subvectors` equal slices and
+/// each slice is replaced by the index of its nearest codebook centroid,
+/// so a point takes one byte per subvector instead of four per dimension.
+#[derive(Clone, Debug)]
+pub struct ProductQuantizer {
+    sub_dims: usize,
+    /// `codebooks[j][c]` is centroid c of subspace j
+    codebooks: Vec<Vec<Vec<f32>>>,
+}
+
+impl ProductQuantizer {
+    /// Train one codebook per subspace on `sample` by k-means.
+    pub fn train(sample: &[&[f32]], subvectors: usize) -> Self {
+        let sub_dims = sample[0].len() / subvectors;
+        let codebooks = (0..subvectors)
+            .map(|j| {
+                let slices: Vec<&[f32]> =
+                    sample.iter().map(|v| &v[j * sub_dims..(j + 1) * sub_dims]).collect();
+                kmeans(&slices, CODEBOOK_SIZE.min(slices.len()), squared_l2)
+            })
+            .collect();
+        ProductQuantizer {
+            sub_dims,
+            codebooks,
+        }
+    }
+
+    pub fn subvectors(&self) -> usize {
+        self.codebooks.len()
+    }
+
+    pub fn encode(&self, vector: &[f32]) -> Vec<u8> {
+        self.codebooks
+            .iter()
+            .zip(vector.chunks_exact(self.sub_dims))
+            .map(|(codebook, slice)| {
+                codebook
+                    .iter()
+                    .map(|centroid| squared_l2(slice, centroid))
+                    .enumerate()
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .map_or(0, |(c, _)| c as u8)
+            })
+            .collect()
+    }
+
+    /// Dot products of each query slice with each centroid of its
+    /// subspace, computed once per query so scoring a code is a sum of
+    /// lookups (asymmetric distance computation)
+    pub fn table(&self, query: &[f32]) -> Vec<Vec<f32>> {
+        self.codebooks
+            .iter()
+            .zip(query.chunks_exact(self.sub_dims))
+            .map(|(codebook, slice)| codebook.iter().map(|c| dot(slice, c)).collect())
+            .collect()
+    }
+
+    /// Approximate similarity of the query `table` was made for to the
+    /// point encoded as `code`
+    pub fn score(table: &[Vec<f32>], code: &[u8]) -> f32 {
+        table.iter().zip(code).map(|(row, &c)| row[c as usize]).sum()
+    }
+}
+
+fn squared_l2(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+/// Linear map applied to incoming vectors before they are stored or
+/// searched, e.g. a PCA projection of large embeddings down to the
+/// collection's dimensions.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InputProjection {
+    /// One row per output dimension, each `input_dimensions` long
+    rows: Vec<Vec<f32>>,
+    /// Subtracted from each input vector first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mean: Option<Vec<f32>>,
+}
+
+impl InputProjection {
+    /// Check the matrix is `output_dimensions` rows of one common, non-zero
+    /// length with finite values, and that `mean` matches that length.
+    pub fn new(
+        rows: Vec<Vec<f32>>,
+        mean: Option<Vec<f32>>,
+        output_dimensions: usize,
+    ) -> Result<Self, String> {
+        if rows.len() != output_dimensions {
+            return Err(format!(
+                "Projection matrix has {} rows; expected one per collection dimension ({})",
+                rows.len(),
+                output_dimensions
+            ));
+        }
+        let input_dimensions = rows[0].len();
+        if input_dimensions == 0 {
+            return Err("Projection matrix rows must not be empty".to_string());
+        }
+        if let Some(row) = rows.iter().position(|row| row.len() != input_dimensions) {
+            return Err(format!(
+                "Projection matrix row {} has {} values; expected {} like row 0",
+                row,
+                rows[row].len(),
+                input_dimensions
+            ));
+        }
+        if let Some(mean) = &mean {
+            if mean.len() != input_dimensions {
+                return Err(format!(
+                    "Projection mean has {} values; expected {}",
+                    mean.len(),
+                    input_dimensions
+                ));
+            }
+        }
+        let values = rows.iter().flatten().chain(mean.iter().flatten());
+        if values.into_iter().any(|v| !v.is_finite()) {
+            return Err("Projection has a non-finite value".to_string());
+        }
+        Ok(InputProjection { rows, mean })
+    }
+
+    pub fn input_dimensions(&self) -> usize {
+        self.rows[0].len()
+    }
+
+    /// Project `vector`, which must be `input_dimensions` long.
+    pub fn apply(&self, vector: &[f32]) -> Vec<f32> {
+        let centered: Vec<f32> = match &self.mean {
+            Some(mean) => vector.iter().zip(mean).map(|(v, m)| v - m).collect(),
+            None => vector.to_vec(),
+        };
+        self.rows
+            .iter()
+            .map(|row| row.iter().zip(&centered).map(|(w, v)| w * v).sum())
+            .collect()
+    }
+}
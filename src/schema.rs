@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Arbitrary JSON object stored alongside a point
+pub type Payload = serde_json::Map<String, Value>;
+
+/// Type of a payload field declared in a collection schema
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FieldType {
+    #[serde(rename = "string")]
+    String,
+    #[serde(rename = "int")]
+    Int,
+    #[serde(rename = "float")]
+    Float,
+    #[serde(rename = "bool")]
+    Bool,
+    #[serde(rename = "string[]")]
+    StringArray,
+    #[serde(rename = "int[]")]
+    IntArray,
+    #[serde(rename = "float[]")]
+    FloatArray,
+}
+
+impl FieldType {
+    pub fn parse(name: &str) -> Option<FieldType> {
+        serde_json::from_value(Value::String(name.to_string())).ok()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FieldType::String => "string",
+            FieldType::Int => "int",
+            FieldType::Float => "float",
+            FieldType::Bool => "bool",
+            FieldType::StringArray => "string[]",
+            FieldType::IntArray => "int[]",
+            FieldType::FloatArray => "float[]",
+        }
+    }
+
+    fn accepts(&self, value: &Value) -> bool {
+        match self {
+            FieldType::String => value.is_string(),
+            FieldType::Int => is_int(value),
+            FieldType::Float => value.is_number(),
+            FieldType::Bool => value.is_boolean(),
+            FieldType::StringArray => is_array_of(value, Value::is_string),
+            FieldType::IntArray => is_array_of(value, is_int),
+            FieldType::FloatArray => is_array_of(value, Value::is_number),
+        }
+    }
+}
+
+fn is_int(value: &Value) -> bool {
+    match value {
+        Value::Number(n) => n.is_i64() || n.is_u64() || n.as_f64().is_some_and(|f| f.fract() == 0.0),
+        _ => false,
+    }
+}
+
+fn is_array_of(value: &Value, element: impl Fn(&Value) -> bool) -> bool {
+    value.as_array().is_some_and(|items| items.iter().all(element))
+}
+
+/// JSON type of `value` as named in error messages
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(_) if is_int(value) => "int",
+        Value::Number(_) => "float",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Declared payload field types. Fields not in the schema are accepted
+/// as-is, and declared fields may be missing or null.
+pub type Schema = BTreeMap<String, FieldType>;
+
+/// Parse a user-supplied `{ field: "type" }` map.
+pub fn parse_schema<'a>(
+    fields: impl IntoIterator<Item = (&'a String, &'a String)>,
+) -> Result<Schema, String> {
+    fields
+        .into_iter()
+        .map(|(field, type_name)| {
+            FieldType::parse(type_name)
+                .map(|ty| (field.clone(), ty))
+                .ok_or_else(|| {
+                    format!(
+                        "Unknown type '{}' for schema field '{}'; expected one of string, int, float, bool, string[], int[], float[]",
+                        type_name, field
+                    )
+                })
+        })
+        .collect()
+}
+
+/// One message per field of `payload` that doesn't match `schema`.
+pub fn payload_errors(schema: &Schema, payload: &Payload) -> Vec<String> {
+    schema
+        .iter()
+        .filter_map(|(field, ty)| {
+            let value = payload.get(field).filter(|v| !v.is_null())?;
+            if ty.accepts(value) {
+                None
+            } else {
+                Some(format!(
+                    "Payload field '{}': expected {}, got {}",
+                    field,
+                    ty.name(),
+                    json_type(value)
+                ))
+            }
+        })
+        .collect()
+}
+
+/// Which part of each payload a read returns
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Projection {
+    #[default]
+    None,
+    All,
+    /// Only these keys; the rest are never copied out
+    Fields(Vec<String>),
+}
+
+impl Projection {
+    /// `fields` wins over `include`. Returns None when neither is set, so
+    /// the caller can fall back to a default.
+    pub fn new(include: Option<bool>, fields: Option<Vec<String>>) -> Option<Projection> {
+        match (include, fields) {
+            (_, Some(fields)) => Some(Projection::Fields(fields)),
+            (Some(true), None) => Some(Projection::All),
+            (Some(false), None) => Some(Projection::None),
+            (None, None) => None,
+        }
+    }
+
+    pub fn apply(&self, payload: Option<&Payload>) -> Option<Payload> {
+        let payload = payload?;
+        match self {
+            Projection::None => None,
+            Projection::All => Some(payload.clone()),
+            Projection::Fields(fields) => Some(
+                fields
+                    .iter()
+                    .filter_map(|field| payload.get(field).map(|v| (field.clone(), v.clone())))
+                    .collect(),
+            ),
+        }
+    }
+}
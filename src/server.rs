@@ -0,0 +1,631 @@
+use once_cell::sync::Lazy;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{ServerConnection, StreamOwned};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::collection::PointFilter;
+use crate::schema::Projection;
+
+/// Largest request body accepted (a batch of large vectors as JSON)
+const MAX_BODY_BYTES: usize = 64 * 1024 * 1024;
+/// Largest request line and headers accepted, together
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+/// Connections one server handles at once; more are closed right away
+const MAX_CONNECTIONS: usize = 128;
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+struct ServerHandle {
+    addr: SocketAddr,
+    shutdown: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+static SERVERS: Lazy<Mutex<HashMap<u16, ServerHandle>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+struct Request {
+    method: String,
+    path: String,
+    /// Bearer token from the Authorization header
+    token: Option<String>,
+    body: Vec<u8>,
+}
+
+/// What requests bearing one API token may do
+#[derive(Clone, Debug)]
+pub struct Grant {
+    /// URL names of the collections it covers; "*" covers every one
+    pub collections: Vec<String>,
+    /// Also allow inserts, deletes and builds, not just reads
+    pub write: bool,
+}
+
+impl Grant {
+    fn covers(&self, name: &str) -> bool {
+        self.collections.iter().any(|c| c == "*" || c == name)
+    }
+}
+
+struct Config {
+    /// URL name -> collection path
+    collections: HashMap<String, String>,
+    /// API tokens and their grants; None serves without authentication
+    tokens: Option<Vec<(String, Grant)>>,
+    /// Certificate and key to terminate TLS with; None serves plain HTTP
+    tls: Option<Arc<rustls::ServerConfig>>,
+}
+
+/// Load a PEM certificate chain and private key for serving HTTPS.
+pub fn load_tls(cert_path: &str, key_path: &str) -> Result<Arc<rustls::ServerConfig>, String> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to read TLS certificate '{}': {}", cert_path, e))?;
+    if certs.is_empty() {
+        return Err(format!("No certificates found in '{}'", cert_path));
+    }
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .map_err(|e| format!("Failed to read TLS key '{}': {}", key_path, e))?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+        .map_err(|e| format!("Invalid TLS certificate or key: {}", e))?;
+    Ok(Arc::new(config))
+}
+
+/// Bind `host:port` and serve the given collections (URL name -> collection
+/// path) on a background thread. With `tokens`, every request must bear one
+/// of them and is limited to its grant; with `tls`, connections are HTTPS.
+/// Returns the bound port, which differs from `port` when 0 is requested.
+pub fn start(
+    host: &str,
+    port: u16,
+    collections: HashMap<String, String>,
+    tokens: Option<Vec<(String, Grant)>>,
+    tls: Option<Arc<rustls::ServerConfig>>,
+) -> Result<u16, String> {
+    let listener = TcpListener::bind((host, port))
+        .map_err(|e| format!("Failed to bind {}:{}: {}", host, port, e))?;
+    let addr = listener
+        .local_addr()
+        .map_err(|e| format!("Failed to read bound address: {}", e))?;
+
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let config = Arc::new(Config {
+        collections,
+        tokens,
+        tls,
+    });
+
+    let flag = shutdown.clone();
+    let active = Arc::new(AtomicUsize::new(0));
+    let thread = thread::spawn(move || {
+        for stream in listener.incoming() {
+            if flag.load(Ordering::SeqCst) {
+                break;
+            }
+            let Ok(stream) = stream else {
+                continue;
+            };
+            // Over the cap, drop the connection rather than queue it
+            let Some(slot) = Slot::take(&active) else {
+                continue;
+            };
+            let config = config.clone();
+            thread::spawn(move || {
+                handle_connection(stream, &config);
+                drop(slot);
+            });
+        }
+    });
+
+    let mut servers = SERVERS.lock().unwrap_or_else(|e| e.into_inner());
+    servers.insert(
+        addr.port(),
+        ServerHandle {
+            addr,
+            shutdown,
+            thread,
+        },
+    );
+
+    Ok(addr.port())
+}
+
+/// Stop the server listening on `port`. Returns false if none was running.
+pub fn stop(port: u16) -> Result<bool, String> {
+    let handle = {
+        let mut servers = SERVERS.lock().unwrap_or_else(|e| e.into_inner());
+        servers.remove(&port)
+    };
+
+    let Some(handle) = handle else {
+        return Ok(false);
+    };
+
+    handle.shutdown.store(true, Ordering::SeqCst);
+    // Wake the accept loop so it observes the shutdown flag
+    let _ = TcpStream::connect(handle.addr);
+    let _ = handle.thread.join();
+
+    Ok(true)
+}
+
+/// One of a server's `MAX_CONNECTIONS` connection slots, freed on drop
+struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+    fn take(active: &Arc<AtomicUsize>) -> Option<Slot> {
+        if active.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            active.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(Slot(active.clone()))
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn handle_connection(stream: TcpStream, config: &Config) {
+    let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+
+    let Some(tls) = &config.tls else {
+        return respond(stream, config);
+    };
+    let Ok(conn) = ServerConnection::new(tls.clone()) else {
+        return;
+    };
+    let mut stream = StreamOwned::new(conn, stream);
+    respond(&mut stream, config);
+    stream.conn.send_close_notify();
+    let _ = stream.conn.complete_io(&mut stream.sock);
+}
+
+/// Read one request from `stream` and write its response. The token is
+/// checked as soon as the headers are in, so a client without one can't
+/// make the server read and buffer a body.
+fn respond(mut stream: impl Read + Write, config: &Config) {
+    let mut reader = BufReader::new(&mut stream);
+    let (status, body) = match read_head(&mut reader) {
+        Err(e) => (400, json!({ "error": e })),
+        Ok((mut req, content_length)) => match authorize(&req, config) {
+            Err(refusal) => refusal,
+            Ok(grant) => match read_body(&mut reader, content_length) {
+                Ok(body) => {
+                    req.body = body;
+                    route(&req, grant, config)
+                }
+                Err(e) => (400, json!({ "error": e })),
+            },
+        },
+    };
+    drop(reader);
+
+    let payload = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason_phrase(status),
+        payload.len(),
+        payload
+    );
+    let _ = stream.write_all(response.as_bytes());
+    let _ = stream.flush();
+}
+
+/// Read the request line and headers, leaving the body unread. Returns
+/// the request without its body, and the body's length.
+fn read_head(reader: &mut impl BufRead) -> Result<(Request, usize), String> {
+    let mut budget = MAX_HEAD_BYTES;
+
+    let request_line = read_line(reader, &mut budget)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().ok_or("Malformed request line")?.to_string();
+    let path = parts.next().ok_or("Malformed request line")?.to_string();
+
+    let mut content_length = 0usize;
+    let mut token = None;
+    loop {
+        let line = read_line(reader, &mut budget)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| "Invalid Content-Length".to_string())?;
+            } else if name.trim().eq_ignore_ascii_case("authorization") {
+                token = value.trim().strip_prefix("Bearer ").map(|t| t.trim().to_string());
+            }
+        }
+    }
+
+    let req = Request {
+        method,
+        path,
+        token,
+        body: Vec::new(),
+    };
+    Ok((req, content_length))
+}
+
+fn read_body(reader: &mut impl Read, content_length: usize) -> Result<Vec<u8>, String> {
+    if content_length > MAX_BODY_BYTES {
+        return Err(format!(
+            "Request body too large: {} bytes (max {})",
+            content_length, MAX_BODY_BYTES
+        ));
+    }
+
+    let mut body = vec![0u8; content_length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|e| format!("Failed to read body: {}", e))?;
+    Ok(body)
+}
+
+/// Read one line of the request head, counting it against `budget` so a
+/// client can't make the server buffer an endless line
+fn read_line(reader: &mut impl BufRead, budget: &mut usize) -> Result<String, String> {
+    let mut line = String::new();
+    let read = reader
+        .take(*budget as u64)
+        .read_line(&mut line)
+        .map_err(|e| format!("Failed to read request: {}", e))?;
+    if read == *budget && !line.ends_with('\n') {
+        return Err(format!("Request headers too large (max {} bytes)", MAX_HEAD_BYTES));
+    }
+    *budget -= read;
+    Ok(line)
+}
+
+/// The grant of the token `req` bears, if it is one of `tokens`. Every
+/// token is compared in full, so timing doesn't reveal partial matches.
+fn authenticate<'a>(req: &Request, tokens: &'a [(String, Grant)]) -> Option<&'a Grant> {
+    let presented = req.token.as_deref()?.as_bytes();
+    let mut found = None;
+    for (token, grant) in tokens {
+        let token = token.as_bytes();
+        let differences = token
+            .iter()
+            .zip(presented)
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+        if token.len() == presented.len() && differences == 0 {
+            found = Some(grant);
+        }
+    }
+    found
+}
+
+/// The API description is public, so clients can be generated before they
+/// have a token
+fn is_public(req: &Request) -> bool {
+    req.path.split('?').next() == Some("/openapi.json")
+}
+
+/// The grant `req` is limited to, or the response refusing it. Without
+/// tokens, or for a public path, nothing is checked and there is no grant.
+fn authorize<'a>(req: &Request, config: &'a Config) -> Result<Option<&'a Grant>, (u16, Value)> {
+    match &config.tokens {
+        Some(tokens) if !is_public(req) => match authenticate(req, tokens) {
+            Some(grant) => Ok(Some(grant)),
+            None => Err((401, json!({ "error": "Missing or unknown API token" }))),
+        },
+        _ => Ok(None),
+    }
+}
+
+fn route(req: &Request, grant: Option<&Grant>, config: &Config) -> (u16, Value) {
+    if is_public(req) {
+        return match req.method.as_str() {
+            "GET" => (200, crate::openapi::document(config.tokens.is_some())),
+            _ => method_not_allowed(),
+        };
+    }
+
+    let collections = &config.collections;
+
+    let path = req.path.split('?').next().unwrap_or("");
+    let segments: Vec<String> = path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(percent_decode)
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+    if segments == ["collections"] {
+        return match req.method.as_str() {
+            "GET" => {
+                let mut names: Vec<&String> = collections
+                    .keys()
+                    .filter(|name| grant.is_none_or(|g| g.covers(name)))
+                    .collect();
+                names.sort();
+                (200, json!({ "collections": names }))
+            }
+            _ => method_not_allowed(),
+        };
+    }
+
+    let (name, rest) = match segments.as_slice() {
+        ["collections", name, rest @ ..] => (*name, rest),
+        _ => return (404, json!({ "error": "Not found" })),
+    };
+
+    if let Some(grant) = grant {
+        if !grant.covers(name) {
+            let error = format!("API token has no access to collection '{}'", name);
+            return (403, json!({ "error": error }));
+        }
+        let writes = matches!(
+            (req.method.as_str(), rest),
+            ("POST", ["points"]) | ("DELETE", ["points", _]) | ("POST", ["build"])
+        );
+        if writes && !grant.write {
+            return (403, json!({ "error": "API token is read-only" }));
+        }
+    }
+
+    let Some(coll_path) = collections.get(name) else {
+        return (404, json!({ "error": format!("Unknown collection '{}'", name) }));
+    };
+
+    let result = match (req.method.as_str(), rest) {
+        ("GET", ["stats"]) => crate::stats(coll_path.clone()).map(|s| {
+            json!({
+                "count": s.count,
+                "dimensions": s.dimensions,
+                "fileSizeBytes": s.file_size_bytes,
+                "upserts": s.upserts,
+                "reusedIds": s.reused_ids,
+                "orphanedNodes": s.orphaned_nodes,
+                "tombstones": s.tombstones,
+                "freeIds": s.free_ids,
+                "idHits": s.id_hits,
+                "idMisses": s.id_misses,
+                "idFilterRejections": s.id_filter_rejections,
+                "seq": s.seq,
+                "committedSeq": s.committed_seq,
+            })
+        }),
+        ("GET", ["health"]) => crate::health(coll_path.clone()).map(|h| {
+            json!({
+                "loaded": h.loaded,
+                "healthy": h.healthy,
+                "count": h.count,
+                "lastSavedAt": h.last_saved_at,
+                "unsavedChanges": h.unsaved_changes,
+                "pendingTombstones": h.pending_tombstones,
+                "deadNodes": h.dead_nodes,
+                "capacityRemaining": h.capacity_remaining,
+                "alerts": h.alerts.iter().map(|a| json!({
+                    "kind": a.kind,
+                    "level": a.level,
+                    "usage": a.usage,
+                    "limit": a.limit,
+                })).collect::<Vec<_>>(),
+                "indexReady": h.index_ready,
+                "lastError": h.last_error,
+            })
+        }),
+        ("POST", ["points"]) => match parse_body(&req.body) {
+            Ok(body) => insert(coll_path, &body),
+            Err(e) => return e,
+        },
+        ("DELETE", ["points", id]) => crate::delete_vector(coll_path.clone(), id.to_string())
+            .map(|deleted| json!({ "deleted": deleted })),
+        ("POST", ["search"]) => match parse_body(&req.body) {
+            Ok(body) => search(coll_path, &body),
+            Err(e) => return e,
+        },
+        ("POST", ["build"]) => crate::build_index(coll_path.clone()).map(|_| json!({ "ok": true })),
+        (_, ["stats"]) | (_, ["health"]) | (_, ["points"]) | (_, ["points", _]) | (_, ["search"]) | (_, ["build"]) => {
+            return method_not_allowed()
+        }
+        _ => return (404, json!({ "error": "Not found" })),
+    };
+
+    match result {
+        Ok(value) => (200, value),
+        Err(e) => (400, json!({ "error": e.reason })),
+    }
+}
+
+fn insert(coll_path: &str, body: &Value) -> napi::Result<Value> {
+    let id = body["id"]
+        .as_str()
+        .ok_or_else(|| napi::Error::from_reason("Missing string field 'id'".to_string()))?;
+    let vector = parse_vector(&body["vector"])?;
+    let payload = match &body["payload"] {
+        Value::Null => None,
+        Value::Object(payload) => Some(payload.clone()),
+        _ => {
+            return Err(napi::Error::from_reason(
+                "Field 'payload' must be an object".to_string(),
+            ))
+        }
+    };
+
+    let model_tag = match &body["modelTag"] {
+        Value::Null => None,
+        Value::String(tag) => Some(tag.as_str()),
+        _ => {
+            return Err(napi::Error::from_reason(
+                "Field 'modelTag' must be a string".to_string(),
+            ))
+        }
+    };
+
+    crate::insert_slice(coll_path, id, &vector, payload, model_tag)?;
+    Ok(json!({ "ok": true }))
+}
+
+fn search(coll_path: &str, body: &Value) -> napi::Result<Value> {
+    let vector = parse_vector(&body["vector"])?;
+    let k = u32_field(body, "k")?.unwrap_or(10);
+    let filter = PointFilter {
+        inserted_after: body["insertedAfter"].as_f64().map(|t| t as i64),
+        inserted_before: body["insertedBefore"].as_f64().map(|t| t as i64),
+        allowed: None,
+        denied: None,
+    };
+
+    let policy = crate::resolve_retry_policy(
+        u32_field(body, "maxRetries")?,
+        u32_field(body, "retryGrowth")?,
+    )?;
+
+    let payload_fields = match &body["payloadFields"] {
+        Value::Null => None,
+        Value::Array(fields) => Some(
+            fields
+                .iter()
+                .map(|f| f.as_str().map(str::to_string))
+                .collect::<Option<Vec<String>>>()
+                .ok_or_else(|| {
+                    napi::Error::from_reason(
+                        "Field 'payloadFields' must contain only strings".to_string(),
+                    )
+                })?,
+        ),
+        _ => {
+            return Err(napi::Error::from_reason(
+                "Field 'payloadFields' must be an array".to_string(),
+            ))
+        }
+    };
+    let allow_ids = match &body["allowIds"] {
+        Value::Null => None,
+        Value::Array(ids) => Some(
+            ids.iter()
+                .map(|id| id.as_str().map(str::to_string))
+                .collect::<Option<Vec<String>>>()
+                .ok_or_else(|| {
+                    napi::Error::from_reason(
+                        "Field 'allowIds' must contain only strings".to_string(),
+                    )
+                })?,
+        ),
+        _ => {
+            return Err(napi::Error::from_reason(
+                "Field 'allowIds' must be an array".to_string(),
+            ))
+        }
+    };
+    let options = crate::QueryOptions {
+        ef_search: u32_field(body, "efSearch")?,
+        nprobe: u32_field(body, "nprobe")?,
+        pq_rescore: u32_field(body, "pqRescore")?,
+        filter,
+        allow_ids,
+        allow_set: None,
+        deny_set: None,
+        policy,
+        projection: Projection::new(body["includePayload"].as_bool(), payload_fields),
+        score_threshold: body["scoreThreshold"].as_f64(),
+        timeout: body["timeoutMs"].as_u64().map(Duration::from_millis),
+        handles: false,
+        boosts: Vec::new(),
+        decay: None,
+        score_expr: None,
+        late_interaction: None,
+    };
+
+    let hits = crate::search_slice(coll_path, &vector, k, &options)?;
+    let results: Vec<Value> = hits
+        .results
+        .into_iter()
+        .map(|r| {
+            let mut result = json!({ "id": r.id, "score": r.score, "insertedAt": r.inserted_at });
+            if let Some(payload) = r.payload {
+                result["payload"] = Value::Object(payload);
+            }
+            result
+        })
+        .collect();
+    Ok(json!({ "results": results, "partial": hits.partial }))
+}
+
+fn parse_body(body: &[u8]) -> Result<Value, (u16, Value)> {
+    serde_json::from_slice(body)
+        .map_err(|e| (400, json!({ "error": format!("Invalid JSON body: {}", e) })))
+}
+
+/// An optional integer field, which must fit a u32
+fn u32_field(body: &Value, name: &str) -> napi::Result<Option<u32>> {
+    match &body[name] {
+        Value::Null => Ok(None),
+        value => value.as_u64().and_then(|v| u32::try_from(v).ok()).map(Some).ok_or_else(|| {
+            napi::Error::from_reason(format!(
+                "Field '{}' must be an integer from 0 to {}",
+                name,
+                u32::MAX
+            ))
+        }),
+    }
+}
+
+fn parse_vector(value: &Value) -> napi::Result<Vec<f32>> {
+    let items = value
+        .as_array()
+        .ok_or_else(|| napi::Error::from_reason("Missing array field 'vector'".to_string()))?;
+    items
+        .iter()
+        .map(|v| {
+            v.as_f64().map(|f| f as f32).ok_or_else(|| {
+                napi::Error::from_reason("Field 'vector' must contain only numbers".to_string())
+            })
+        })
+        .collect()
+}
+
+fn method_not_allowed() -> (u16, Value) {
+    (405, json!({ "error": "Method not allowed" }))
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    }
+}
+
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(byte) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
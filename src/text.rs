@@ -0,0 +1,150 @@
+use rust_stemmers::{Algorithm, Stemmer};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Languages with a Snowball stemmer, by the name used in configs
+const STEMMERS: &[(&str, Algorithm)] = &[
+    ("arabic", Algorithm::Arabic),
+    ("danish", Algorithm::Danish),
+    ("dutch", Algorithm::Dutch),
+    ("english", Algorithm::English),
+    ("finnish", Algorithm::Finnish),
+    ("french", Algorithm::French),
+    ("german", Algorithm::German),
+    ("greek", Algorithm::Greek),
+    ("hungarian", Algorithm::Hungarian),
+    ("italian", Algorithm::Italian),
+    ("norwegian", Algorithm::Norwegian),
+    ("portuguese", Algorithm::Portuguese),
+    ("romanian", Algorithm::Romanian),
+    ("russian", Algorithm::Russian),
+    ("spanish", Algorithm::Spanish),
+    ("swedish", Algorithm::Swedish),
+    ("tamil", Algorithm::Tamil),
+    ("turkish", Algorithm::Turkish),
+];
+
+/// How the text of one payload field is split into terms
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Analyzer {
+    pub lowercase: bool,
+    /// Snowball stemmer language, e.g. "english"
+    pub stemmer: Option<String>,
+    /// Terms dropped before stemming, compared after lowercasing
+    pub stopwords: Vec<String>,
+    /// Emit character n-grams of each term instead of the term itself
+    pub ngram: Option<(usize, usize)>,
+}
+
+impl Default for Analyzer {
+    fn default() -> Self {
+        Analyzer {
+            lowercase: true,
+            stemmer: None,
+            stopwords: Vec::new(),
+            ngram: None,
+        }
+    }
+}
+
+/// Analyzers keyed by payload field
+pub type Analyzers = BTreeMap<String, Analyzer>;
+
+/// One term and where it came from in the source text
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    pub term: String,
+    /// Offsets in UTF-16 code units, as JS string indices
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Analyzer {
+    /// Check the settings are usable. Called when a config is accepted.
+    pub fn validate(&self, field: &str) -> Result<(), String> {
+        if let Some(language) = &self.stemmer {
+            stemmer_for(language).ok_or_else(|| {
+                format!(
+                    "Unknown stemmer '{}' for field '{}'; expected one of {}",
+                    language,
+                    field,
+                    STEMMERS.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")
+                )
+            })?;
+        }
+        if let Some((min, max)) = self.ngram {
+            if min == 0 || min > max {
+                return Err(format!(
+                    "Invalid ngram range {}..{} for field '{}': need 0 < min <= max",
+                    min, max, field
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn analyze(&self, text: &str) -> Vec<Token> {
+        let stemmer = self.stemmer.as_deref().and_then(stemmer_for);
+        let stopwords: HashSet<String> = self
+            .stopwords
+            .iter()
+            .map(|w| if self.lowercase { w.to_lowercase() } else { w.clone() })
+            .collect();
+
+        let mut tokens = Vec::new();
+        let mut utf16_pos = 0;
+        let mut byte_pos = 0;
+        for (offset, word) in text.unicode_word_indices() {
+            utf16_pos += text[byte_pos..offset].encode_utf16().count();
+            byte_pos = offset;
+            let start = utf16_pos;
+            let end = start + word.encode_utf16().count();
+
+            let term = if self.lowercase { word.to_lowercase() } else { word.to_string() };
+            if stopwords.contains(&term) {
+                continue;
+            }
+            let term = match &stemmer {
+                Some(stemmer) => stemmer.stem(&term).into_owned(),
+                None => term,
+            };
+
+            match self.ngram {
+                Some((min, max)) => {
+                    let chars: Vec<char> = term.chars().collect();
+                    for n in min..=max.min(chars.len()) {
+                        for gram in chars.windows(n) {
+                            tokens.push(Token {
+                                term: gram.iter().collect(),
+                                start,
+                                end,
+                            });
+                        }
+                    }
+                }
+                None => tokens.push(Token { term, start, end }),
+            }
+        }
+        tokens
+    }
+
+    /// Words of `text` that share a term with `query`, in order, each once.
+    pub fn matches(&self, text: &str, query: &str) -> Vec<Token> {
+        let query_terms: HashSet<String> =
+            self.analyze(query).into_iter().map(|t| t.term).collect();
+
+        let mut seen = HashSet::new();
+        self.analyze(text)
+            .into_iter()
+            .filter(|t| query_terms.contains(&t.term) && seen.insert((t.start, t.end)))
+            .collect()
+    }
+}
+
+fn stemmer_for(language: &str) -> Option<Stemmer> {
+    STEMMERS
+        .iter()
+        .find(|(name, _)| *name == language)
+        .map(|(_, algorithm)| Stemmer::create(*algorithm))
+}
@@ -0,0 +1,54 @@
+use napi::{Error, Result};
+use std::any::Any;
+use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+static HOOK: Once = Once::new();
+
+thread_local! {
+    /// Where the latest panic on this thread happened, saved by the hook
+    static LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Run the body of exported function `op`, turning a panic into a JS error
+/// instead of letting it abort the Node process. The error message reads
+/// `PANIC in <op> at <file:line:col>: <panic message>`.
+pub fn guard<T>(op: &str, body: impl FnOnce() -> Result<T>) -> Result<T> {
+    install_hook();
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        let location = LOCATION
+            .with(|location| location.borrow_mut().take())
+            .unwrap_or_else(|| "unknown location".to_string());
+        Err(Error::from_reason(format!(
+            "PANIC in {} at {}: {}",
+            op,
+            location,
+            message(payload.as_ref())
+        )))
+    })
+}
+
+fn message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "non-string panic payload".to_string()
+    }
+}
+
+/// Chain a hook in front of the current one that records the panic's
+/// location, which the payload caught by `catch_unwind` doesn't carry.
+fn install_hook() {
+    HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if let Some(location) = info.location() {
+                LOCATION.with(|saved| *saved.borrow_mut() = Some(location.to_string()));
+            }
+            previous(info);
+        }));
+    });
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+/// Prefix of the files quantized collections keep their full vectors in
+pub const VECTOR_FILE_PREFIX: &str = "vectors-";
+
+/// Full vectors of an IVF-PQ collection, kept on disk rather than in memory
+/// as fixed-size records of little-endian f32s. Records are only appended;
+/// a replaced or purged point's record stays until the file is rewritten.
+#[derive(Debug)]
+pub struct VectorFile {
+    pub name: String,
+    file: File,
+    dimensions: usize,
+    /// Record index of each stored point, by internal ID
+    slots: HashMap<usize, u64>,
+}
+
+impl VectorFile {
+    pub fn open(dir: &Path, name: &str, dimensions: usize) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(dir.join(name))
+            .map_err(|e| format!("Failed to open vector file '{}': {}", name, e))?;
+        Ok(VectorFile {
+            name: name.to_string(),
+            file,
+            dimensions,
+            slots: HashMap::new(),
+        })
+    }
+
+    /// A second handle on the same file with its own slot map, for a save
+    /// to append to without touching the live one until it succeeds
+    pub fn try_clone(&self) -> Result<Self, String> {
+        let file = self
+            .file
+            .try_clone()
+            .map_err(|e| format!("Failed to open vector file '{}': {}", self.name, e))?;
+        Ok(VectorFile {
+            name: self.name.clone(),
+            file,
+            dimensions: self.dimensions,
+            slots: self.slots.clone(),
+        })
+    }
+
+    fn record_bytes(&self) -> u64 {
+        (self.dimensions * std::mem::size_of::<f32>()) as u64
+    }
+
+    /// Size of the file, including dead records
+    pub fn bytes(&self) -> u64 {
+        self.file.metadata().map_or(0, |m| m.len())
+    }
+
+    /// Records in the file, including dead ones
+    pub fn records(&self) -> u64 {
+        self.bytes() / self.record_bytes()
+    }
+
+    pub fn slots(&self) -> &HashMap<usize, u64> {
+        &self.slots
+    }
+
+    pub fn set_slots(&mut self, slots: HashMap<usize, u64>) {
+        self.slots = slots;
+    }
+
+    pub fn contains(&self, internal_id: usize) -> bool {
+        self.slots.contains_key(&internal_id)
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = usize> + '_ {
+        self.slots.keys().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Forget the point; its record is dropped when the file is rewritten
+    pub fn remove(&mut self, internal_id: usize) {
+        self.slots.remove(&internal_id);
+    }
+
+    /// The point's vector, or None if it isn't stored here or can't be read
+    pub fn read(&self, internal_id: usize) -> Option<Vec<f32>> {
+        let slot = *self.slots.get(&internal_id)?;
+        let mut bytes = vec![0u8; self.record_bytes() as usize];
+        read_at(&self.file, &mut bytes, slot * self.record_bytes()).ok()?;
+        Some(
+            bytes
+                .chunks_exact(4)
+                .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                .collect(),
+        )
+    }
+
+    /// Append the vectors, replacing any earlier records of the same points.
+    /// A partial record left by a failed write is padded out first.
+    pub fn append<'a>(
+        &mut self,
+        vectors: impl Iterator<Item = (usize, &'a [f32])>,
+    ) -> Result<(), String> {
+        let len = self
+            .file
+            .metadata()
+            .map_err(|e| format!("Failed to read vector file '{}': {}", self.name, e))?
+            .len();
+        let record_bytes = self.record_bytes();
+        let mut bytes = vec![0u8; (len.next_multiple_of(record_bytes) - len) as usize];
+        for (slot, (internal_id, vector)) in (len.div_ceil(record_bytes)..).zip(vectors) {
+            bytes.extend(vector.iter().flat_map(|x| x.to_le_bytes()));
+            self.slots.insert(internal_id, slot);
+        }
+        self.file
+            .write_all(&bytes)
+            .map_err(|e| format!("Failed to write vector file '{}': {}", self.name, e))
+    }
+
+    pub fn sync(&self) -> Result<(), String> {
+        self.file
+            .sync_all()
+            .map_err(|e| format!("Failed to flush '{}': {}", self.name, e))
+    }
+}
+
+#[cfg(unix)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> std::io::Result<()> {
+    std::os::unix::fs::FileExt::read_exact_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+fn read_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> std::io::Result<()> {
+    while !buf.is_empty() {
+        match std::os::windows::fs::FileExt::seek_read(file, buf, offset)? {
+            0 => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+            n => {
+                buf = &mut buf[n..];
+                offset += n as u64;
+            }
+        }
+    }
+    Ok(())
+}
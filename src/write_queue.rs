@@ -0,0 +1,74 @@
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex};
+
+use crate::schema::Payload;
+
+/// A write submitted through `insertVectorAsync` or `deleteVectorAsync`
+pub enum WriteOp {
+    Insert {
+        id: String,
+        vector: Vec<f32>,
+        payload: Option<Payload>,
+        model_tag: Option<String>,
+    },
+    Delete {
+        id: String,
+    },
+}
+
+struct Write {
+    op: WriteOp,
+    ack: SyncSender<Result<bool, String>>,
+}
+
+/// Writes to one collection, applied in the order they were submitted
+#[derive(Default)]
+struct Queue {
+    pending: Mutex<VecDeque<Write>>,
+    /// Held by the thread applying a batch
+    applying: Mutex<()>,
+}
+
+static QUEUES: Lazy<Mutex<HashMap<String, Arc<Queue>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Queue `op` on the collection under `key` and block until it is applied.
+/// Whichever waiting thread gets to apply next takes every write queued so
+/// far and applies them with `apply` as one batch under one collection
+/// lock, so concurrent writers share lock acquisitions and are served in
+/// submission order rather than in whatever order they win the lock.
+pub fn submit(
+    key: &str,
+    op: WriteOp,
+    apply: impl FnOnce(Vec<WriteOp>) -> Vec<Result<bool, String>>,
+) -> Result<bool, String> {
+    let queue = QUEUES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(key.to_string())
+        .or_default()
+        .clone();
+
+    let (ack, done) = mpsc::sync_channel(1);
+    queue.pending.lock().unwrap_or_else(|e| e.into_inner()).push_back(Write { op, ack });
+
+    let _applying = queue.applying.lock().unwrap_or_else(|e| e.into_inner());
+    // An earlier thread may have applied this write in its batch
+    if let Ok(result) = done.try_recv() {
+        return result;
+    }
+
+    let batch: Vec<Write> = {
+        let mut pending = queue.pending.lock().unwrap_or_else(|e| e.into_inner());
+        pending.drain(..).collect()
+    };
+    let (ops, acks): (Vec<WriteOp>, Vec<_>) = batch.into_iter().map(|w| (w.op, w.ack)).unzip();
+    for (ack, result) in acks.into_iter().zip(apply(ops)) {
+        // The submitter is blocked on its receiver, so the send can't fail
+        let _ = ack.send(result);
+    }
+
+    done.recv()
+        .unwrap_or_else(|_| Err("Queued write was dropped before it was applied".to_string()))
+}
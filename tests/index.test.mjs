@@ -1,8 +1,13 @@
 import { describe, it, beforeEach, afterEach } from "node:test";
 import assert from "node:assert/strict";
-import { mkdtempSync, rmSync } from "node:fs";
-import { join } from "node:path";
+import { mkdtempSync, readdirSync, readFileSync, rmSync, symlinkSync, writeFileSync } from "node:fs";
+import { join, relative } from "node:path";
 import { tmpdir } from "node:os";
+import { spawnSync } from "node:child_process";
+import { request } from "node:https";
+import { connect } from "node:net";
+import { fileURLToPath } from "node:url";
+import native from "../index.js";
 import {
   createCollection,
   insertVector,
@@ -10,6 +15,75 @@ import {
   search,
   deleteVector,
   stats,
+  reindex,
+  exportHnswlib,
+  startServer,
+  stopServer,
+  reloadCollection,
+  closeCollection,
+  attachReadonly,
+  deleteByFilter,
+  restoreVector,
+  listDeleted,
+  getConfig,
+  getVectors,
+  createIngestStream,
+  searchStream,
+  knnGraph,
+  compact,
+  health,
+  validatePoint,
+  analyze,
+  setDefaultSearchOptions,
+  getDefaultSearchOptions,
+  setOperationLimits,
+  getOperationLimits,
+  setThreadPool,
+  getThreadPool,
+  setAuditActor,
+  getAuditLog,
+  savepoint,
+  rollbackTo,
+  releaseSavepoint,
+  updatePayloads,
+  warmup,
+  preloadCollections,
+  openMetadata,
+  repairCollection,
+  checkIndex,
+  setProjection,
+  resolveIds,
+  insertVectorAsync,
+  deleteVectorAsync,
+  changesSince,
+  onCapacityAlert,
+  insertBinary,
+  searchBinary,
+  insertSet,
+  searchSet,
+  createIdSet,
+  dropIdSet,
+  registerTemplate,
+  setAutoCreate,
+  renameCollection,
+  unsavedChanges,
+  listOperations,
+  operationStatus,
+  add,
+  subtract,
+  mean,
+  normalize,
+  cosineSimilarity,
+  pairwiseSimilarities,
+  diffCollections,
+  stageVector,
+  stagingStatus,
+  promoteStaging,
+  clearStaging,
+  retrieve,
+  payloadValues,
+  setTokenVectors,
+  discover,
 } from "../index.js";
 
 /** Generate a random normalized vector of given dimensions */
@@ -36,6 +110,21 @@ function basisVector(dims, idx) {
 
 const DIMS = 384;
 
+/** Block for `ms` milliseconds so consecutive inserts get distinct timestamps */
+function sleepMs(ms) {
+  Atomics.wait(new Int32Array(new SharedArrayBuffer(4)), 0, 0, ms);
+}
+
+const ADDON = fileURLToPath(new URL("../index.js", import.meta.url));
+
+/** Run a snippet in a separate Node process with the addon bound to `zvec` */
+function runInChild(code) {
+  const script = `const zvec = require(${JSON.stringify(ADDON)});\n${code}`;
+  const res = spawnSync(process.execPath, ["-e", script], { encoding: "utf8" });
+  if (res.error) throw res.error;
+  return (res.stdout + res.stderr).trim();
+}
+
 describe("createCollection", () => {
   let tmpDir;
 
@@ -44,6 +133,7 @@ describe("createCollection", () => {
   });
 
   afterEach(() => {
+    closeCollection(join(tmpDir, "coll1"));
     rmSync(tmpDir, { recursive: true, force: true });
   });
 
@@ -74,6 +164,60 @@ describe("createCollection", () => {
     assert.equal(stats(collPath).count, 0);
   });
 
+  it("should treat every spelling of a path as the same collection", () => {
+    const collPath = join(tmpDir, "coll1");
+    const config = {
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    };
+    createCollection(config);
+    insertVector(collPath, "a", basisVector(DIMS, 0));
+
+    const link = join(tmpDir, "link");
+    symlinkSync(collPath, link);
+    const aliases = [
+      join(tmpDir, ".", "sub", "..", "coll1"),
+      relative(process.cwd(), collPath),
+      link,
+    ];
+    for (const alias of aliases) {
+      createCollection({ ...config, path: alias });
+      assert.equal(stats(alias).count, 1);
+    }
+    assert.throws(
+      () => createCollection({ ...config, path: link, dimensions: 128 }),
+      /Dimension mismatch/
+    );
+    closeCollection(link);
+    assert.throws(() => stats(collPath), /Collection not found/);
+  });
+
+  it("should resolve a path afresh once its collection is closed", () => {
+    const config = { dimensions: DIMS, indexType: "hnsw", metric: "cosine" };
+    const first = join(tmpDir, "first");
+    const second = join(tmpDir, "second");
+    createCollection({ ...config, path: first });
+    closeCollection(first);
+    createCollection({ ...config, path: second });
+    insertVector(second, "a", basisVector(DIMS, 0));
+
+    const link = join(tmpDir, "link");
+    symlinkSync(first, link);
+    createCollection({ ...config, path: link });
+    assert.equal(stats(link).count, 0);
+    closeCollection(link);
+
+    // Retargeted while closed: the link must not keep naming the old directory
+    rmSync(link);
+    symlinkSync(second, link);
+    createCollection({ ...config, path: link });
+    assert.equal(stats(link).count, 1);
+    closeCollection(link);
+    assert.throws(() => stats(second), /Collection not found/);
+  });
+
   it("should reject invalid metric", () => {
     const collPath = join(tmpDir, "coll1");
     assert.throws(
@@ -119,6 +263,7 @@ describe("insertVector", () => {
   });
 
   afterEach(() => {
+    closeCollection(collPath);
     rmSync(tmpDir, { recursive: true, force: true });
   });
 
@@ -134,11 +279,326 @@ describe("insertVector", () => {
     );
   });
 
+  it("should fetch many vectors by id in one call", () => {
+    const a = randomVector(DIMS);
+    const b = randomVector(DIMS);
+    insertVector(collPath, "a", a);
+    insertVector(collPath, "b", b);
+    insertVector(collPath, "gone", randomVector(DIMS));
+    deleteVector(collPath, "gone");
+
+    const got = getVectors(collPath, ["b", "missing", "a", "gone", "b"]);
+    assert.deepEqual(
+      got.map((p) => p && p.id),
+      ["b", null, "a", null, "b"]
+    );
+    assert.deepEqual(got[0].vector, b);
+    assert.deepEqual(got[2].vector, a);
+    assert.ok(got[0].insertedAt > 0);
+  });
+
+  it("should project vectors down to the collection's dimensions", () => {
+    const path = join(tmpDir, "projected");
+    const config = { path, dimensions: 2, indexType: "hnsw", metric: "cosine" };
+    createCollection(config);
+    const matrix = [new Float32Array([1, 0, 0, 0]), new Float32Array([0, 0, 1, 0])];
+    assert.throws(() => setProjection(path, { matrix: [matrix[0]] }), /has 1 rows/);
+    assert.throws(
+      () => setProjection(path, { matrix, mean: new Float32Array(3) }),
+      /mean has 3 values; expected 4/
+    );
+    setProjection(path, { matrix, mean: new Float32Array([0, 0, 0, 1]) });
+
+    insertVector(path, "x", new Float32Array([2, 5, 0, 1]));
+    insertVector(path, "z", new Float32Array([0, 5, 3, 1]));
+    assert.throws(() => insertVector(path, "y", new Float32Array(2)), /expected 4, got 2/);
+    assert.deepEqual([...getVectors(path, ["x"])[0].vector], [2, 0]);
+    assert.equal(search(path, new Float32Array([0, 0, 1, 0.5]), 1)[0].id, "z");
+
+    const stream = createIngestStream(path);
+    stream.push(["w"], new Float32Array([1, 1, 1, 1]));
+    assert.equal(stream.finish().points, 1);
+    assert.deepEqual([...getVectors(path, ["w"])[0].vector], [1, 1]);
+
+    buildIndex(path);
+    closeCollection(path);
+    createCollection(config);
+    assert.equal(search(path, new Float32Array([3, 0, 0, 0]), 1)[0].id, "x");
+    setProjection(path, null);
+    assert.equal(search(path, new Float32Array([0, 1]), 1)[0].id, "z");
+    closeCollection(path);
+  });
+
+  it("should accept Float64Array and plain array vectors", () => {
+    insertVector(collPath, "f64", new Float64Array(basisVector(DIMS, 0)));
+    insertVector(collPath, "arr", Array.from(basisVector(DIMS, 1)));
+    assert.deepEqual([...getVectors(collPath, ["f64"])[0].vector], [...basisVector(DIMS, 0)]);
+    assert.equal(search(collPath, new Float64Array(basisVector(DIMS, 1)), 1)[0].id, "arr");
+    assert.equal(search(collPath, Array.from(basisVector(DIMS, 0)), 1)[0].id, "f64");
+    assert.equal(validatePoint(collPath, "x", [1, 2]).valid, false);
+    assert.throws(
+      () => insertVector(collPath, "big", new Float64Array(DIMS).fill(1e300)),
+      /non-finite value/
+    );
+    assert.throws(() => insertVector(collPath, "bad", "nope"));
+  });
+
+  it("should ingest batches through a stream", () => {
+    const stream = createIngestStream(collPath, { queueSize: 1 });
+    let busy = 0;
+    for (let b = 0; b < 20; b++) {
+      const ids = Array.from({ length: 50 }, (_, i) => `s-${b}-${i}`);
+      const vectors = new Float32Array(ids.length * DIMS);
+      ids.forEach((_, i) => vectors.set(randomVector(DIMS), i * DIMS));
+      while (stream.push(ids, vectors) === "busy") {
+        busy++;
+        sleepMs(1);
+      }
+    }
+    assert.throws(() => stream.push(["x"], new Float32Array(3)), /Dimension mismatch/);
+    const ingested = stream.finish();
+    assert.equal(ingested.points, 1000);
+    assert.ok(ingested.indexMs > 0);
+    assert.equal(ingested.bytesWritten, 0);
+    assert.equal(ingested.committedSeq, undefined);
+    assert.throws(() => stream.push([], new Float32Array(0)), /finished/);
+    assert.ok(busy > 0, "A one-batch queue should push back at least once");
+
+    const op = listOperations().findLast((o) => o.kind === "ingest");
+    assert.equal(op.phase, "done");
+    assert.equal(op.processed, 1000);
+    assert.ok(op.finishedAt >= op.startedAt);
+    assert.deepEqual(operationStatus(op.id), op);
+    assert.throws(() => operationStatus(0), /Operation 0 not found/);
+
+    const built = buildIndex(collPath);
+    assert.equal(built.points, 1000);
+    assert.ok(built.bytesWritten > 1000 * DIMS * 4);
+    assert.ok(built.persistMs > 0);
+    assert.equal(built.committedSeq, stats(collPath).committedSeq);
+
+    assert.equal(stats(collPath).count, 1000);
+    const probe = getVectors(collPath, ["s-7-3"])[0].vector;
+    assert.equal(search(collPath, probe, 1)[0].id, "s-7-3");
+  });
+
+  it("should read pushed vectors from views into a larger buffer", () => {
+    // Two ids' vectors in the middle of a shared buffer
+    const shared = new Float32Array(4 * DIMS);
+    shared.set(basisVector(DIMS, 0), DIMS);
+    shared.set(basisVector(DIMS, 1), 2 * DIMS);
+    const stream = createIngestStream(collPath);
+    assert.equal(stream.push(["v0", "v1"], shared.subarray(DIMS, 3 * DIMS)), "ok");
+    assert.equal(stream.finish().points, 2);
+    assert.deepEqual([...getVectors(collPath, ["v1"])[0].vector], [...basisVector(DIMS, 1)]);
+    assert.equal(search(collPath, basisVector(DIMS, 0), 1)[0].id, "v0");
+  });
+
+  it("should reject non-finite values", () => {
+    const v = randomVector(DIMS);
+    v[5] = NaN;
+    assert.throws(() => insertVector(collPath, "nan", v), /non-finite value \(NaN\) at index 5/);
+  });
+
+  it("should dry-run insert checks without inserting", () => {
+    assert.deepEqual(validatePoint(collPath, "a", randomVector(DIMS)), { valid: true, errors: [] });
+
+    const bad = new Float32Array(10);
+    bad[2] = Infinity;
+    const { valid, errors } = validatePoint(collPath, "a", bad);
+    assert.equal(valid, false);
+    assert.equal(errors.length, 2);
+    assert.match(errors[0], /Dimension mismatch/);
+    assert.match(errors[1], /non-finite value \(inf\) at index 2/);
+    assert.equal(stats(collPath).count, 0);
+  });
+
   it("should handle upsert (same ID)", () => {
     insertVector(collPath, "vec-1", randomVector(DIMS));
     insertVector(collPath, "vec-1", randomVector(DIMS));
     assert.equal(stats(collPath).count, 1);
   });
+
+  it("should store payloads and replace them on upsert", () => {
+    insertVector(collPath, "a", randomVector(DIMS), { title: "A", tags: ["x", "y"] });
+    insertVector(collPath, "b", randomVector(DIMS));
+    buildIndex(collPath);
+    closeCollection(collPath);
+    createCollection({ path: collPath, dimensions: DIMS, indexType: "hnsw", metric: "cosine" });
+
+    const [a, b] = getVectors(collPath, ["a", "b"]);
+    assert.deepEqual(a.payload, { title: "A", tags: ["x", "y"] });
+    assert.equal(b.payload, undefined);
+
+    insertVector(collPath, "a", randomVector(DIMS));
+    assert.equal(getVectors(collPath, ["a"])[0].payload, undefined);
+  });
+
+  it("should backfill payload fields in bulk and save them", () => {
+    insertVector(collPath, "a", randomVector(DIMS), { title: "A" });
+    insertVector(collPath, "b", randomVector(DIMS));
+    insertVector(collPath, "gone", randomVector(DIMS));
+    deleteVector(collPath, "gone");
+
+    const updated = updatePayloads(collPath, [
+      { id: "a", payload: { lang: "en" } },
+      { id: "b", payload: { lang: "de", title: "B" } },
+      { id: "gone", payload: { lang: "fr" } },
+      { id: "missing", payload: { lang: "fr" } },
+    ]);
+    assert.equal(updated, 2);
+
+    // Saved without a buildIndex
+    closeCollection(collPath);
+    createCollection({ path: collPath, dimensions: DIMS, indexType: "hnsw", metric: "cosine" });
+    const [a, b] = getVectors(collPath, ["a", "b"]);
+    assert.deepEqual(a.payload, { title: "A", lang: "en" });
+    assert.deepEqual(b.payload, { lang: "de", title: "B" });
+  });
+
+  it("should reject payloads that don't match the schema", () => {
+    const schemaPath = join(tmpDir, "typed");
+    const schema = { title: "string", price: "float", stock: "int", tags: "string[]" };
+    const config = { path: schemaPath, dimensions: DIMS, indexType: "hnsw", metric: "cosine" };
+    assert.throws(
+      () => createCollection({ ...config, schema: { title: "text" } }),
+      /Unknown type 'text' for schema field 'title'/
+    );
+    createCollection({ ...config, schema });
+
+    try {
+      insertVector(schemaPath, "ok", randomVector(DIMS), { title: "T", price: 3, tags: [], extra: {} });
+      insertVector(schemaPath, "nulls", randomVector(DIMS), { title: null });
+      assert.throws(
+        () => insertVector(schemaPath, "bad", randomVector(DIMS), { price: "free" }),
+        /Payload field 'price': expected float, got string/
+      );
+
+      const { errors } = validatePoint(schemaPath, "bad", randomVector(DIMS), {
+        price: 1,
+        stock: 1.5,
+        tags: ["a", 2],
+      });
+      assert.deepEqual(errors, [
+        "Payload field 'stock': expected int, got float",
+        "Payload field 'tags': expected string[], got array",
+      ]);
+      assert.equal(stats(schemaPath).count, 2);
+
+      assert.throws(
+        () =>
+          updatePayloads(schemaPath, [
+            { id: "ok", payload: { stock: 4 } },
+            { id: "nulls", payload: { price: "free" } },
+          ]),
+        /Point 'nulls': Payload field 'price': expected float, got string/
+      );
+      assert.equal(getVectors(schemaPath, ["ok"])[0].payload.stock, undefined);
+
+      buildIndex(schemaPath);
+      closeCollection(schemaPath);
+      createCollection(config);
+      assert.deepEqual(getConfig(schemaPath).schema, schema);
+      closeCollection(schemaPath);
+      assert.throws(
+        () => createCollection({ ...config, schema: { title: "string" } }),
+        /Schema mismatch/
+      );
+    } finally {
+      closeCollection(schemaPath);
+    }
+  });
+});
+
+describe("retrieve", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should retrieve points by payload filter without a query", () => {
+    for (let i = 0; i < 6; i++) {
+      insertVector(collPath, `doc-${i}`, basisVector(DIMS, i), { lang: i % 2 ? "de" : "en", year: 2018 + i });
+    }
+    insertVector(collPath, "bare", basisVector(DIMS, 7));
+    deleteVector(collPath, "doc-4");
+
+    const english = retrieve(collPath, { filter: { lang: "en" } });
+    assert.deepEqual(english.map((p) => p.id), ["doc-0", "doc-2"]);
+    assert.deepEqual(english[1].payload, { lang: "en", year: 2020 });
+    assert.ok(english[0].insertedAt > 0);
+
+    const recent = { filter: { year: { gte: 2019 } }, limit: 2 };
+    assert.deepEqual(retrieve(collPath, recent).map((p) => p.id), ["doc-1", "doc-2"]);
+    assert.deepEqual(retrieve(collPath, { ...recent, offset: 2 }).map((p) => p.id), ["doc-3", "doc-5"]);
+    assert.equal(retrieve(collPath).length, 6);
+    assert.equal(retrieve(collPath)[0].payload, undefined);
+    assert.throws(() => retrieve(collPath, { filter: { year: { gte: "x" } } }), /needs a number/);
+
+    const byYear = (direction) => retrieve(collPath, { orderBy: { field: "year", direction } }).map((p) => p.id);
+    assert.deepEqual(byYear("desc"), ["doc-5", "doc-3", "doc-2", "doc-1", "doc-0", "bare"]);
+    assert.deepEqual(byYear(), ["doc-0", "doc-1", "doc-2", "doc-3", "doc-5", "bare"]);
+    const latest = { filter: { lang: "de" }, orderBy: { field: "year", direction: "desc" }, limit: 2 };
+    assert.deepEqual(retrieve(collPath, latest).map((p) => p.id), ["doc-5", "doc-3"]);
+
+    const typed = join(tmpDir, "typed");
+    createCollection({ path: typed, dimensions: DIMS, indexType: "hnsw", metric: "cosine", schema: { lang: "string" } });
+    assert.throws(() => retrieve(typed, { orderBy: { field: "lang" } }), /declared as string/);
+    closeCollection(typed);
+    assert.throws(() => retrieve(join(tmpDir, "missing")), /Collection not found/);
+  });
+});
+
+describe("payloadValues", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should count the distinct values of a payload field", () => {
+    insertVector(collPath, "a", basisVector(DIMS, 0), { lang: "en", tags: ["news", "tech"] });
+    insertVector(collPath, "b", basisVector(DIMS, 1), { lang: "de", tags: ["news"] });
+    insertVector(collPath, "c", basisVector(DIMS, 2), { lang: "en" });
+    insertVector(collPath, "d", basisVector(DIMS, 3), { lang: "fr" });
+    insertVector(collPath, "e", basisVector(DIMS, 4));
+    deleteVector(collPath, "d");
+
+    assert.deepEqual(payloadValues(collPath, "lang"), [
+      { value: "en", count: 2 },
+      { value: "de", count: 1 },
+    ]);
+    assert.deepEqual(payloadValues(collPath, "tags", { limit: 1 }), [{ value: "news", count: 2 }]);
+    assert.deepEqual(payloadValues(collPath, "missing"), []);
+    assert.throws(() => payloadValues(join(tmpDir, "missing"), "lang"), /Collection not found/);
+  });
 });
 
 describe("search", () => {
@@ -157,6 +617,7 @@ describe("search", () => {
   });
 
   afterEach(() => {
+    closeCollection(collPath);
     rmSync(tmpDir, { recursive: true, force: true });
   });
 
@@ -165,6 +626,228 @@ describe("search", () => {
     assert.equal(results.length, 0);
   });
 
+  it("should return only the requested payload fields", () => {
+    insertVector(collPath, "a", basisVector(DIMS, 0), { title: "A", url: "/a", body: "x".repeat(1000) });
+    insertVector(collPath, "b", basisVector(DIMS, 1));
+
+    const query = basisVector(DIMS, 0);
+    assert.equal(search(collPath, query, 1)[0].payload, undefined);
+    assert.equal(search(collPath, query, 1, { includePayload: true })[0].payload.body.length, 1000);
+
+    // A tiny HNSW graph can miss "b" entirely, so only check it when returned
+    const results = search(collPath, query, 2, { payloadFields: ["title", "url", "missing"] });
+    assert.equal(results[0].id, "a");
+    assert.deepEqual(results[0].payload, { title: "A", url: "/a" });
+    for (const r of results.slice(1)) assert.equal(r.payload, undefined);
+
+    const [chunk] = searchStream(collPath, query, 1, { payloadFields: ["url"] });
+    assert.deepEqual(chunk[0].payload, { url: "/a" });
+  });
+
+  it("should return results as parallel arrays when columnar", () => {
+    insertVector(collPath, "a", basisVector(DIMS, 0), { title: "A" });
+    insertVector(collPath, "b", basisVector(DIMS, 1));
+    const query = basisVector(DIMS, 0);
+
+    const rows = search(collPath, query, 2, { includePayload: true });
+    const columns = search(collPath, query, 2, { includePayload: true, columnar: true });
+    assert.ok(columns.scores instanceof Float64Array);
+    assert.ok(columns.insertedAt instanceof Float64Array);
+    assert.deepEqual(columns.ids, rows.map((r) => r.id));
+    assert.deepEqual([...columns.scores], rows.map((r) => r.score));
+    assert.deepEqual([...columns.insertedAt], rows.map((r) => r.insertedAt));
+    assert.deepEqual(columns.payloads[0], { title: "A" });
+    assert.equal(columns.partial, undefined);
+
+    const plain = search(collPath, query, 1, { columnar: true, timeoutMs: 60_000 });
+    assert.equal(plain.payloads, undefined);
+    assert.equal(plain.partial, false);
+
+    assert.throws(
+      () => search(collPath, query, 1, { columnar: true, highlight: { field: "title", query: "a" } }),
+      /highlight/
+    );
+  });
+
+  it("should boost results whose payload matches a filter", () => {
+    const close = basisVector(DIMS, 0);
+    close[0] = 0.9;
+    close[1] = Math.sqrt(1 - 0.81);
+    insertVector(collPath, "a", basisVector(DIMS, 0), { lang: "de", year: 2019 });
+    insertVector(collPath, "b", close, { lang: "en", tags: ["news"], year: 2024 });
+    const query = basisVector(DIMS, 0);
+
+    const ids = (options) => search(collPath, query, 1, options).map((r) => r.id);
+    assert.deepEqual(ids(), ["a"]);
+    assert.deepEqual(ids({ boosts: [{ filter: { lang: "en" }, weight: 2 }] }), ["b"]);
+    assert.deepEqual(ids({ boosts: [{ filter: { tags: "news" }, weight: 0.5, mode: "add" }] }), ["b"]);
+    assert.deepEqual(ids({ boosts: [{ filter: { year: { lt: 2020 } }, weight: 0.5 }] }), ["b"]);
+    assert.deepEqual(
+      ids({ boosts: [{ filter: { lang: { in: ["fr", "en"] }, missing: { exists: true } }, weight: 2 }] }),
+      ["a"]
+    );
+
+    const [top] = search(collPath, query, 1, { boosts: [{ filter: { lang: "en" }, weight: 2 }] });
+    assert.ok(Math.abs(top.score - 1.8) < 1e-5);
+
+    assert.throws(() => ids({ boosts: [{ filter: { year: { near: 1 } }, weight: 2 }] }), /Unknown filter operator 'near'/);
+    assert.throws(() => ids({ boosts: [{ filter: {}, weight: 2, mode: "pow" }] }), /Invalid boost mode/);
+  });
+
+  it("should decay scores of older points", () => {
+    const DAY = 86_400_000;
+    const close = basisVector(DIMS, 0);
+    close[0] = 0.9;
+    close[1] = Math.sqrt(1 - 0.81);
+    insertVector(collPath, "old", basisVector(DIMS, 0), { publishedAt: Date.now() - 60 * DAY });
+    insertVector(collPath, "new", close, { publishedAt: Date.now() });
+    insertVector(collPath, "undated", basisVector(DIMS, 1));
+    const query = basisVector(DIMS, 0);
+
+    assert.equal(search(collPath, query, 1)[0].id, "old");
+    const results = search(collPath, query, 2, { decay: { field: "publishedAt", halfLifeDays: 30 } });
+    assert.deepEqual(results.map((r) => r.id), ["new", "old"]);
+    assert.ok(Math.abs(results[1].score - 0.25) < 1e-3);
+
+    // Insertion times are all recent, so decaying on them changes little
+    const byInsert = search(collPath, query, 1, { decay: { halfLifeDays: 30 } });
+    assert.equal(byInsert[0].id, "old");
+
+    assert.throws(() => search(collPath, query, 1, { decay: { halfLifeDays: 0 } }), /halfLifeDays/);
+  });
+
+  it("should rank by a score expression", () => {
+    const close = basisVector(DIMS, 0);
+    close[0] = 0.9;
+    close[1] = Math.sqrt(1 - 0.81);
+    insertVector(collPath, "a", basisVector(DIMS, 0), { stats: { views: 1 } });
+    insertVector(collPath, "b", close, { stats: { views: 1000 }, pinned: true });
+    const query = basisVector(DIMS, 0);
+
+    const top = (scoreExpr) => search(collPath, query, 1, { scoreExpr })[0];
+    assert.equal(top("$similarity").id, "a");
+    assert.equal(top("0.5 * $similarity + 0.1 * log10(payload.stats.views)").id, "b");
+    assert.equal(top("$score - payload.pinned").id, "a");
+    assert.ok(Math.abs(top("2 ^ 3 ^ 2 - -1 + payload.missing").score - 513) < 1e-9);
+    assert.ok(Math.abs(top("max($similarity, 0.95) * (1 + 1) % 3").score - 2) < 1e-5);
+    assert.equal(top("sqrt(-payload.stats.views)").score, -Number.MAX_VALUE);
+
+    assert.throws(() => top("$similarity +"), /Invalid scoreExpr at position 13/);
+    assert.throws(() => top("$rank"), /unknown variable/);
+    assert.throws(() => top("sigmoid($similarity)"), /unknown function 'sigmoid'/);
+    assert.throws(() => top("min($similarity)"), /min takes 2 argument/);
+
+    const nested = (depth) => "(".repeat(depth) + "1" + ")".repeat(depth);
+    assert.equal(top(nested(200)).score, 1);
+    assert.throws(() => top(nested(200000)), /Invalid scoreExpr at position 256: nested too deeply/);
+    assert.throws(() => top("-".repeat(200000) + "1"), /nested too deeply/);
+    assert.throws(() => top(Array(200000).fill("1").join(" + ")), /nested too deeply/);
+  });
+
+  it("should calibrate scores onto [0, 1]", () => {
+    insertVector(collPath, "a", basisVector(DIMS, 0));
+    const near = basisVector(DIMS, 0);
+    near[0] = near[1] = Math.SQRT1_2;
+    insertVector(collPath, "b", near);
+    const query = basisVector(DIMS, 0);
+
+    const raw = search(collPath, query, 2);
+    const unit = search(collPath, query, 2, { calibration: "unit" });
+    assert.deepEqual(unit.map((r) => r.id), raw.map((r) => r.id));
+    unit.forEach((r, i) => assert.ok(Math.abs(r.score - (raw[i].score + 1) / 2) < 1e-6));
+
+    const range = search(collPath, query, 2, { calibration: { min: 0.5, max: 1 } });
+    assert.ok(Math.abs(range[0].score - 1) < 1e-6);
+
+    if (raw.length === 2) {
+      const [top, second] = search(collPath, query, 2, { calibration: "probit" });
+      assert.ok(top.score > 0.5 && second.score < 0.5);
+    }
+
+    assert.throws(() => search(collPath, query, 1, { calibration: "sigmoid" }), /Invalid calibration/);
+    assert.throws(() => search(collPath, query, 1, { calibration: { min: 1, max: 0 } }), /min < max/);
+  });
+
+  it("should return numeric handles that resolve back to ids", () => {
+    insertVector(collPath, "a", basisVector(DIMS, 0));
+    insertVector(collPath, "b", basisVector(DIMS, 1));
+    const query = basisVector(DIMS, 0);
+
+    const { ids } = search(collPath, query, 2, { columnar: true });
+    const result = search(collPath, query, 2, { columnar: true, handles: true });
+    assert.equal(result.ids, undefined);
+    assert.ok(result.handles instanceof BigUint64Array);
+    assert.deepEqual(resolveIds(collPath, result.handles), ids);
+
+    deleteVector(collPath, "a");
+    assert.deepEqual(resolveIds(collPath, result.handles.subarray(0, 1)), [null]);
+    assert.deepEqual(resolveIds(collPath, new BigUint64Array([999n])), [null]);
+
+    assert.throws(() => search(collPath, query, 1, { handles: true }), /columnar/);
+  });
+
+  it("should not resolve a stale handle to the point that took its slot", () => {
+    insertVector(collPath, "a", basisVector(DIMS, 0));
+    insertVector(collPath, "b", basisVector(DIMS, 1));
+    const query = basisVector(DIMS, 0);
+    const [stale] = search(collPath, query, 1, { columnar: true, handles: true }).handles;
+
+    // The upsert frees the slot of "a", and compacting hands it to "c"
+    insertVector(collPath, "a", basisVector(DIMS, 2));
+    compact(collPath);
+    insertVector(collPath, "c", basisVector(DIMS, 0));
+    const [fresh] = search(collPath, query, 1, { columnar: true, handles: true }).handles;
+    assert.equal(fresh & 0xffffffffn, stale & 0xffffffffn);
+    assert.deepEqual(resolveIds(collPath, new BigUint64Array([stale, fresh])), [null, "c"]);
+
+    closeCollection(collPath);
+    createCollection({ path: collPath, dimensions: DIMS, indexType: "hnsw", metric: "cosine" });
+    assert.deepEqual(resolveIds(collPath, new BigUint64Array([fresh])), [null]);
+  });
+
+  it("should apply queued async writes in order and ack each one", async () => {
+    const writes = [];
+    for (let i = 0; i < 20; i++) {
+      writes.push(insertVectorAsync(collPath, `p${i}`, basisVector(DIMS, i % DIMS), { i }));
+    }
+    writes.push(deleteVectorAsync(collPath, "p0"), deleteVectorAsync(collPath, "missing"));
+    const acks = await Promise.all(writes);
+    assert.equal(acks.at(-2), true);
+    assert.equal(acks.at(-1), false);
+    assert.equal(stats(collPath).count, 19);
+    assert.deepEqual(getVectors(collPath, ["p0", "p5"]).map((p) => p && p.payload), [null, { i: 5 }]);
+
+    await assert.rejects(insertVectorAsync(collPath, "bad", [1, 2]), /imension/);
+    await assert.rejects(deleteVectorAsync("/tmp/no-such-collection", "a"), /Collection not found/);
+  });
+
+  it("should apply collection search defaults unless the query overrides them", () => {
+    insertVector(collPath, "a", basisVector(DIMS, 0), { title: "A" });
+    insertVector(collPath, "b", basisVector(DIMS, 1), { title: "B" });
+    const query = basisVector(DIMS, 0);
+
+    assert.deepEqual(
+      search(collPath, query, 2, { scoreThreshold: 0.5 }).map((r) => r.id),
+      ["a"]
+    );
+
+    setDefaultSearchOptions(collPath, { scoreThreshold: 0.5, includePayload: true });
+    let results = search(collPath, query, 2);
+    assert.deepEqual(results.map((r) => [r.id, r.payload]), [["a", { title: "A" }]]);
+
+    // A tiny HNSW graph can miss "b" entirely, so only check it when returned
+    results = search(collPath, query, 2, { scoreThreshold: -1, includePayload: false });
+    assert.deepEqual([results[0].id, results[0].payload], ["a", undefined]);
+    for (const r of results.slice(1)) assert.deepEqual([r.id, r.payload], ["b", undefined]);
+    assert.deepEqual(search(collPath, query, 2, { scoreThreshold: 1.5 }), []);
+
+    buildIndex(collPath);
+    closeCollection(collPath);
+    createCollection({ path: collPath, dimensions: DIMS, indexType: "hnsw", metric: "cosine" });
+    assert.deepEqual(getDefaultSearchOptions(collPath), { scoreThreshold: 0.5, includePayload: true });
+    assert.equal(search(collPath, query, 2).length, 1);
+  });
+
   it("should find nearest neighbors with correct ordering", () => {
     // Insert basis vectors pointing in different directions
     const v1 = basisVector(DIMS, 0);
@@ -201,6 +884,56 @@ describe("search", () => {
     const results = search(collPath, randomVector(DIMS), 10);
     assert.equal(results.length, 1);
   });
+
+  it("should accept efSearch as a number or in options", () => {
+    insertVector(collPath, "a", basisVector(DIMS, 0));
+    assert.equal(search(collPath, basisVector(DIMS, 0), 1, 50)[0].id, "a");
+    assert.equal(search(collPath, basisVector(DIMS, 0), 1, { efSearch: 50 })[0].id, "a");
+  });
+
+  it("should rerank over-fetched candidates with the callback", () => {
+    for (let i = 0; i < 10; i++) {
+      insertVector(collPath, `v-${i}`, basisVector(DIMS, i));
+    }
+
+    let seen = [];
+    const results = search(collPath, basisVector(DIMS, 0), 2, {
+      rerankFactor: 3,
+      rerank: (candidates) => {
+        seen = candidates;
+        // Promote the candidate the vector search ranked last
+        return candidates.map((c, i) => (i === candidates.length - 1 ? 10 : c.score));
+      },
+    });
+
+    assert.equal(seen.length, 6);
+    assert.equal(results.length, 2);
+    assert.equal(results[0].id, seen[5].id);
+    assert.equal(results[0].score, 10);
+  });
+
+  it("should stream results in chunks", () => {
+    for (let i = 0; i < 25; i++) {
+      insertVector(collPath, `v-${i}`, randomVector(DIMS));
+    }
+    const query = randomVector(DIMS);
+
+    const chunks = [...searchStream(collPath, query, 20, { chunkSize: 8 })];
+    assert.deepEqual(chunks.map((c) => c.length), [8, 8, 4]);
+    assert.deepEqual(
+      chunks.flat().map((r) => r.id),
+      search(collPath, query, 20).map((r) => r.id)
+    );
+    assert.throws(() => searchStream(collPath, query, 5, { chunkSize: 0 }), /chunkSize/);
+  });
+
+  it("should reject a rerank callback returning the wrong number of scores", () => {
+    insertVector(collPath, "a", basisVector(DIMS, 0));
+    assert.throws(
+      () => search(collPath, basisVector(DIMS, 0), 1, { rerank: () => [] }),
+      /rerank returned 0 scores/
+    );
+  });
 });
 
 describe("deleteVector", () => {
@@ -219,6 +952,7 @@ describe("deleteVector", () => {
   });
 
   afterEach(() => {
+    closeCollection(collPath);
     rmSync(tmpDir, { recursive: true, force: true });
   });
 
@@ -231,6 +965,160 @@ describe("deleteVector", () => {
     assert.equal(deleteVector(collPath, "nonexistent"), false);
   });
 
+  it("should widen the search past tombstones clustered near the query", () => {
+    // 5 live points, then 50 deleted ones much closer to the query
+    const query = randomVector(DIMS);
+    for (let i = 0; i < 5; i++) {
+      const noise = randomVector(DIMS);
+      insertVector(collPath, `far-${i}`, query.map((x, j) => x + noise[j]));
+    }
+    for (let i = 0; i < 50; i++) {
+      const noise = randomVector(DIMS);
+      insertVector(collPath, `near-${i}`, query.map((x, j) => x + 0.1 * noise[j]));
+      deleteVector(collPath, `near-${i}`);
+    }
+
+    const results = search(collPath, query, 3);
+    assert.equal(results.length, 3);
+    assert.ok(results.every((r) => r.id.startsWith("far-")));
+
+    assert.equal(search(collPath, query, 3, { maxRetries: 0 }).length, 0);
+    assert.throws(() => search(collPath, query, 3, { retryGrowth: 1 }), /retryGrowth/);
+
+    // An exhausted latency budget stops the widening and flags the result
+    assert.deepEqual(search(collPath, query, 3, { timeoutMs: 0 }), { results: [], partial: true });
+    const timed = search(collPath, query, 3, { timeoutMs: 60_000 });
+    assert.equal(timed.partial, false);
+    assert.equal(timed.results.length, 3);
+  });
+
+  it("should compact dead nodes on demand and in the background", async () => {
+    for (let i = 0; i < 10; i++) {
+      insertVector(collPath, `v-${i}`, randomVector(DIMS));
+    }
+    insertVector(collPath, "v-0", randomVector(DIMS)); // upsert leaves a dead node
+    deleteVector(collPath, "v-1");
+    assert.equal(compact(collPath), 2);
+    assert.equal(compact(collPath), 0);
+
+    const autoPath = join(tmpDir, "auto");
+    createCollection({
+      path: autoPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+      autoCompaction: { tombstoneRatio: 0.5, minDeleted: 3 },
+    });
+    for (let i = 0; i < 10; i++) {
+      insertVector(autoPath, `v-${i}`, randomVector(DIMS));
+    }
+    for (let i = 0; i < 6; i++) {
+      deleteVector(autoPath, `v-${i}`);
+    }
+    for (let i = 0; i < 40 && listDeleted(autoPath).length > 0; i++) {
+      await new Promise((resolve) => setTimeout(resolve, 50));
+    }
+    assert.deepEqual(listDeleted(autoPath), []);
+    assert.equal(stats(autoPath).count, 4);
+    assert.deepEqual(getConfig(autoPath).autoCompaction, { tombstoneRatio: 0.5, minDeleted: 3 });
+    closeCollection(autoPath);
+  });
+
+  it("should reuse internal ids freed by compaction", () => {
+    const vectors = new Map();
+    const upsert = (id) => {
+      vectors.set(id, randomVector(DIMS));
+      insertVector(collPath, id, vectors.get(id));
+    };
+    for (let round = 0; round < 5; round++) {
+      for (let i = 0; i < 10; i++) upsert(`v-${i}`);
+      if (round === 1) assert.equal(stats(collPath).orphanedNodes, 10);
+      compact(collPath);
+      if (round === 1) assert.equal(stats(collPath).freeIds, 10);
+    }
+    const churn = ({ upserts, reusedIds, orphanedNodes, tombstones, freeIds }) =>
+      ({ upserts, reusedIds, orphanedNodes, tombstones, freeIds });
+    assert.deepEqual(churn(stats(collPath)), {
+      upserts: 40,
+      reusedIds: 20,
+      orphanedNodes: 0,
+      tombstones: 0,
+      freeIds: 0,
+    });
+
+    deleteVector(collPath, "v-0");
+    assert.equal(stats(collPath).tombstones, 1);
+    compact(collPath);
+    upsert("new");
+    assert.deepEqual(churn(stats(collPath)), {
+      upserts: 40,
+      reusedIds: 21,
+      orphanedNodes: 0,
+      tombstones: 0,
+      freeIds: 0,
+    });
+    buildIndex(collPath);
+
+    // Update-heavy churn keeps the id space bounded by the live points
+    const saved = JSON.parse(readFileSync(join(collPath, "metadata.json"), "utf8"));
+    assert.equal(saved.next_id, 10);
+    assert.deepEqual(Object.values(saved.id_map).sort((a, b) => a - b), [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+    closeCollection(collPath);
+    createCollection({ path: collPath, dimensions: DIMS, indexType: "hnsw", metric: "cosine" });
+    assert.equal(stats(collPath).count, 10);
+    assert.equal(stats(collPath).reusedIds, 21);
+    for (const [id, vector] of vectors) {
+      if (id !== "v-0") assert.deepEqual(getVectors(collPath, [id])[0].vector, vector);
+    }
+    assert.equal(search(collPath, vectors.get("new"), 1)[0].id, "new");
+  });
+
+  it("should restore a deleted vector before the next build", () => {
+    insertVector(collPath, "vec-1", basisVector(DIMS, 0));
+    deleteVector(collPath, "vec-1");
+    assert.deepEqual(listDeleted(collPath).map((d) => d.id), ["vec-1"]);
+
+    assert.equal(restoreVector(collPath, "vec-1"), true);
+    assert.equal(restoreVector(collPath, "vec-1"), false);
+    assert.deepEqual(listDeleted(collPath), []);
+    assert.equal(search(collPath, basisVector(DIMS, 0), 1)[0].id, "vec-1");
+  });
+
+  it("should purge tombstones on build without a retention window", () => {
+    insertVector(collPath, "vec-1", randomVector(DIMS));
+    deleteVector(collPath, "vec-1");
+    buildIndex(collPath);
+
+    assert.deepEqual(listDeleted(collPath), []);
+    assert.equal(restoreVector(collPath, "vec-1"), false);
+  });
+
+  it("should keep tombstones within the retention window across builds", () => {
+    const retainedPath = join(tmpDir, "retained");
+    createCollection({
+      path: retainedPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+      tombstoneRetentionDays: 1,
+    });
+    insertVector(retainedPath, "keep", basisVector(DIMS, 1));
+    insertVector(retainedPath, "vec-1", basisVector(DIMS, 0));
+    deleteVector(retainedPath, "vec-1");
+    buildIndex(retainedPath);
+
+    const [tombstone] = listDeleted(retainedPath);
+    assert.equal(tombstone.id, "vec-1");
+    assert.ok(tombstone.deletedAt > 0);
+    assert.notEqual(search(retainedPath, basisVector(DIMS, 0), 1)[0].id, "vec-1");
+
+    assert.equal(restoreVector(retainedPath, "vec-1"), true);
+    assert.equal(search(retainedPath, basisVector(DIMS, 0), 1)[0].id, "vec-1");
+    assert.equal(stats(retainedPath).count, 2);
+    closeCollection(retainedPath);
+  });
+
   it("should exclude deleted vectors from search after rebuild", () => {
     const target = basisVector(DIMS, 0);
     insertVector(collPath, "keep", basisVector(DIMS, 1));
@@ -246,38 +1134,2331 @@ describe("deleteVector", () => {
   });
 });
 
-describe("stats", () => {
+describe("savepoints", () => {
   let tmpDir;
   let collPath;
 
   beforeEach(() => {
     tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
     collPath = join(tmpDir, "coll1");
-    createCollection({
-      path: collPath,
-      dimensions: DIMS,
-      indexType: "hnsw",
-      metric: "cosine",
+    createCollection({ path: collPath, dimensions: 4, indexType: "hnsw", metric: "cosine" });
+    insertVector(collPath, "a", basisVector(4, 0), { v: 1 });
+    insertVector(collPath, "b", basisVector(4, 1));
+    buildIndex(collPath);
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  const ids = () => search(collPath, basisVector(4, 0), 10).map((r) => r.id).sort();
+
+  it("should undo inserts, upserts and deletes since the savepoint", () => {
+    savepoint(collPath, "job");
+    insertVector(collPath, "c", basisVector(4, 2));
+    insertVector(collPath, "a", basisVector(4, 3), { v: 2 });
+    deleteVector(collPath, "b");
+    assert.deepEqual(ids(), ["a", "c"]);
+
+    rollbackTo(collPath, "job");
+    assert.deepEqual(ids(), ["a", "b"]);
+    assert.deepEqual(getVectors(collPath, ["a"])[0].payload, { v: 1 });
+    assert.equal(stats(collPath).upserts, 0);
+
+    // The savepoint survives a rollback and can be used again
+    deleteVector(collPath, "a");
+    rollbackTo(collPath, "job");
+    assert.deepEqual(ids(), ["a", "b"]);
+  });
+
+  it("should drop later savepoints on rollback and release", () => {
+    savepoint(collPath, "first");
+    insertVector(collPath, "c", basisVector(4, 2));
+    savepoint(collPath, "second");
+    insertVector(collPath, "d", basisVector(4, 3));
+
+    rollbackTo(collPath, "first");
+    assert.deepEqual(ids(), ["a", "b"]);
+    assert.throws(() => rollbackTo(collPath, "second"), /No savepoint named 'second'/);
+
+    assert.equal(releaseSavepoint(collPath, "first"), true);
+    assert.equal(releaseSavepoint(collPath, "first"), false);
+    assert.throws(() => rollbackTo(collPath, "first"), /No savepoint named 'first'/);
+  });
+
+  it("should leave data saved earlier alone", () => {
+    savepoint(collPath, "job");
+    deleteVector(collPath, "a");
+    rollbackTo(collPath, "job");
+    closeCollection(collPath);
+    createCollection({ path: collPath, dimensions: 4, indexType: "hnsw", metric: "cosine" });
+    assert.deepEqual(ids(), ["a", "b"]);
+  });
+
+  it("should restore staged vectors", () => {
+    stageVector(collPath, "a", basisVector(8, 0), "model-v2");
+    savepoint(collPath, "job");
+    stageVector(collPath, "b", basisVector(8, 1));
+    assert.equal(stagingStatus(collPath).staged, 2);
+    // The upsert drops the staged vector of "a"
+    insertVector(collPath, "a", basisVector(4, 3));
+    deleteVector(collPath, "b");
+    assert.deepEqual(stagingStatus(collPath), { dimensions: 8, modelTag: "model-v2", staged: 0, missing: 1 });
+
+    rollbackTo(collPath, "job");
+    assert.deepEqual(stagingStatus(collPath), { dimensions: 8, modelTag: "model-v2", staged: 1, missing: 1 });
+    assert.deepEqual(getVectors(collPath, ["a"])[0].vector, basisVector(4, 0));
+
+    clearStaging(collPath);
+    rollbackTo(collPath, "job");
+    assert.equal(stagingStatus(collPath).staged, 1);
+  });
+
+  it("should keep what a released savepoint logged for the one before", () => {
+    savepoint(collPath, "first");
+    savepoint(collPath, "second");
+    deleteVector(collPath, "a");
+    assert.equal(releaseSavepoint(collPath, "second"), true);
+    rollbackTo(collPath, "first");
+    assert.deepEqual(ids(), ["a", "b"]);
+  });
+});
+
+describe("stats", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should return correct count and dimensions", () => {
+    insertVector(collPath, "a", randomVector(DIMS));
+    insertVector(collPath, "b", randomVector(DIMS));
+    const s = stats(collPath);
+    assert.equal(s.count, 2);
+    assert.equal(s.dimensions, DIMS);
+  });
+
+  it("should show file size after build", () => {
+    insertVector(collPath, "a", randomVector(DIMS));
+    buildIndex(collPath);
+    const s = stats(collPath);
+    assert.ok(s.fileSizeBytes > 0, "File size should be > 0 after build");
+  });
+});
+
+describe("checkIndex", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should find the graph and stored vectors in agreement", () => {
+    for (let i = 0; i < 50; i++) {
+      insertVector(collPath, `p${i}`, randomVector(DIMS));
+    }
+    insertVector(collPath, "p0", randomVector(DIMS));
+    deleteVector(collPath, "p1");
+
+    const report = checkIndex(collPath);
+    assert.equal(report.ok, true);
+    assert.equal(report.nodes, 51);
+    assert.equal(report.deadNodes, 1);
+    assert.deepEqual(report.missingFromGraph, []);
+    assert.equal(report.sampled, 49);
+    assert.deepEqual(report.sampleMisses, []);
+    assert.equal(checkIndex(collPath, { sampleSize: 5 }).sampled, 5);
+    assert.throws(() => checkIndex(collPath, { efSearch: 0 }), /efSearch must be > 0/);
+  });
+});
+
+describe("strictInsert", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should count upserts and reject them in strict mode", () => {
+    insertVector(collPath, "a", randomVector(DIMS));
+    insertVector(collPath, "a", randomVector(DIMS));
+    deleteVector(collPath, "a");
+    insertVector(collPath, "a", randomVector(DIMS)); // re-insert after delete
+    assert.equal(stats(collPath).upserts, 1);
+
+    const strictPath = join(tmpDir, "strict");
+    createCollection({
+      path: strictPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+      strictInsert: true,
+    });
+    insertVector(strictPath, "a", randomVector(DIMS));
+    assert.throws(() => insertVector(strictPath, "a", randomVector(DIMS)), /already exists/);
+    deleteVector(strictPath, "a");
+    insertVector(strictPath, "a", randomVector(DIMS));
+    assert.equal(stats(strictPath).upserts, 0);
+    assert.equal(getConfig(strictPath).strictInsert, true);
+    closeCollection(strictPath);
+  });
+});
+
+describe("health", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should report health and save history", () => {
+    assert.equal(health(join(tmpDir, "nope")).loaded, false);
+
+    insertVector(collPath, "a", randomVector(DIMS));
+    insertVector(collPath, "b", randomVector(DIMS));
+    let h = health(collPath);
+    assert.equal(h.loaded, true);
+    assert.equal(h.healthy, true);
+    assert.equal(h.unsavedChanges, true);
+    assert.equal(h.lastSavedAt, undefined);
+    assert.equal(h.capacityRemaining, 100000 - 2);
+
+    const before = Date.now();
+    buildIndex(collPath);
+    deleteVector(collPath, "a");
+    h = health(collPath);
+    assert.equal(h.unsavedChanges, true);
+    assert.equal(h.pendingTombstones, 1);
+    assert.equal(h.deadNodes, 1);
+    assert.ok(h.lastSavedAt >= before);
+    assert.deepEqual(
+      h.history.map(({ count, deleted }) => ({ count, deleted })),
+      [{ count: 2, deleted: 0 }]
+    );
+  });
+
+  // The hook that poisons the lock only exists in debug builds
+
+  it("should throw LOCK_POISONED once after a panic under the lock", { skip: !native.__panicHoldingLock }, () => {
+    insertVector(collPath, "a", randomVector(DIMS));
+    assert.throws(() => native.__panicHoldingLock(), /PANIC in __panicHoldingLock/);
+    assert.throws(() => search(collPath, randomVector(DIMS), 1), { message: /^LOCK_POISONED: / });
+
+    assert.equal(search(collPath, randomVector(DIMS), 1).length, 1);
+    const h = health(collPath);
+    assert.equal(h.healthy, false);
+    assert.match(h.lastError, /^LOCK_POISONED: /);
+  });
+});
+
+describe("change sequence numbers", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should number changes and commit them on save", () => {
+    const first = insertVector(collPath, "a", basisVector(DIMS, 0));
+    const second = insertVector(collPath, "b", basisVector(DIMS, 1));
+    assert.ok(second > first);
+    deleteVector(collPath, "a");
+    updatePayloads(collPath, [{ id: "b", payload: { lang: "en" } }]);
+    assert.ok(stats(collPath).seq > second);
+    const committed = stats(collPath).committedSeq;
+    assert.equal(committed, stats(collPath).seq);
+
+    const unsaved = insertVector(collPath, "c", basisVector(DIMS, 2));
+    assert.equal(unsaved, committed + 1);
+    assert.equal(stats(collPath).committedSeq, committed);
+    assert.equal(buildIndex(collPath).committedSeq, unsaved);
+
+    closeCollection(collPath);
+    createCollection({ path: collPath, dimensions: DIMS, indexType: "hnsw", metric: "cosine" });
+    assert.equal(stats(collPath).seq, unsaved);
+    assert.equal(stats(collPath).committedSeq, unsaved);
+    assert.ok(insertVector(collPath, "d", basisVector(DIMS, 3)) > unsaved);
+  });
+});
+
+describe("capacity alerts", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should raise capacity alerts as inserts reach each level", async () => {
+    const alertPath = join(tmpDir, "alerts");
+    const config = { path: alertPath, dimensions: DIMS, indexType: "hnsw", metric: "cosine" };
+    createCollection({ ...config, maxElements: 10, capacityAlerts: { levels: [0.5, 0.8] } });
+    const raised = [];
+    onCapacityAlert(alertPath, (alert) => raised.push(alert));
+
+    for (let i = 0; i < 4; i++) insertVector(alertPath, `v${i}`, basisVector(DIMS, i));
+    assert.deepEqual(health(alertPath).alerts, []);
+    for (let i = 4; i < 9; i++) insertVector(alertPath, `v${i}`, basisVector(DIMS, i));
+
+    const alerts = health(alertPath).alerts;
+    assert.deepEqual(alerts.map((a) => [a.kind, a.level]), [["elements", 0.5], ["elements", 0.8]]);
+    assert.equal(alerts[0].usage, 0.9);
+    assert.equal(alerts[0].limit, 10);
+    assert.deepEqual(getConfig(alertPath).capacityAlerts.levels, [0.5, 0.8]);
+
+    await new Promise((resolve) => setImmediate(resolve));
+    assert.deepEqual(raised.map((a) => a.level), [0.5, 0.8]);
+
+    onCapacityAlert(alertPath);
+    assert.throws(() => onCapacityAlert(join(tmpDir, "missing"), () => {}), /Collection not found/);
+    closeCollection(alertPath);
+    assert.throws(
+      () => createCollection({ ...config, capacityAlerts: { levels: [2] } }),
+      /levels must be in \(0, 1\]/,
+    );
+  });
+});
+
+describe("quotas", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should enforce quotas at insert time", () => {
+    const quotaPath = join(tmpDir, "quota");
+    const config = { path: quotaPath, dimensions: DIMS, indexType: "hnsw", metric: "cosine" };
+    createCollection({ ...config, quota: { maxPoints: 2 } });
+    assert.equal(stats(quotaPath).quota.maxPoints, 2);
+    insertVector(quotaPath, "a", basisVector(DIMS, 0));
+    insertVector(quotaPath, "b", basisVector(DIMS, 1));
+    assert.throws(() => insertVector(quotaPath, "c", basisVector(DIMS, 2)), /^Error: QUOTA_EXCEEDED/);
+    // Replacing a point doesn't add one
+    insertVector(quotaPath, "a", basisVector(DIMS, 2));
+    assert.equal(stats(quotaPath).quota.points, 2);
+
+    buildIndex(quotaPath);
+    closeCollection(quotaPath);
+    createCollection(config);
+    assert.deepEqual(getConfig(quotaPath).quota, { maxPoints: 2 });
+    deleteVector(quotaPath, "b");
+    insertVector(quotaPath, "c", basisVector(DIMS, 3));
+
+    closeCollection(quotaPath);
+    createCollection({ ...config, quota: { maxBytes: 1 } });
+    assert.ok(stats(quotaPath).quota.bytes >= 1);
+    assert.throws(() => insertVector(quotaPath, "d", basisVector(DIMS, 4)), /maxBytes is 1/);
+    closeCollection(quotaPath);
+  });
+});
+
+describe("entry limits", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should enforce id length and payload size limits", () => {
+    const limited = join(tmpDir, "limited");
+    const config = { path: limited, dimensions: DIMS, indexType: "hnsw", metric: "cosine" };
+    createCollection({ ...config, entryLimits: { maxIdLength: 8, maxPayloadBytes: 32 } });
+    insertVector(limited, "a", basisVector(DIMS, 0), { lang: "en" });
+    assert.throws(
+      () => insertVector(limited, "much-too-long", basisVector(DIMS, 1)),
+      /^Error: ID_TOO_LONG: .* is 13 bytes; maxIdLength is 8/
+    );
+    assert.throws(
+      () => insertVector(limited, "b", basisVector(DIMS, 1), { text: "x".repeat(64) }),
+      /^Error: PAYLOAD_TOO_LARGE: payload of 'b' is \d+ bytes; maxPayloadBytes is 32/
+    );
+    assert.equal(validatePoint(limited, "much-too-long", basisVector(DIMS, 1)).errors.length, 1);
+    assert.throws(
+      () => updatePayloads(limited, [{ id: "a", payload: { text: "x".repeat(64) } }]),
+      /PAYLOAD_TOO_LARGE/
+    );
+    assert.deepEqual(getVectors(limited, ["a"])[0].payload, { lang: "en" });
+    assert.equal(stats(limited).count, 1);
+
+    buildIndex(limited);
+    closeCollection(limited);
+    createCollection(config);
+    assert.deepEqual(getConfig(limited).entryLimits, { maxIdLength: 8, maxPayloadBytes: 32 });
+    assert.throws(() => createCollection({ ...config, path: join(tmpDir, "zero"), entryLimits: { maxIdLength: 0 } }), /must be > 0/);
+    closeCollection(limited);
+  });
+});
+
+describe("templates", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should create collections from a template", () => {
+    registerTemplate("small", {
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+      m: 8,
+      quota: { maxPoints: 5 },
+    });
+    const first = join(tmpDir, "tenant-1");
+    const second = join(tmpDir, "tenant-2");
+    createCollection({ template: "small", path: first });
+    createCollection({ template: "small", path: second, m: 12 });
+    assert.equal(getConfig(first).m, 8);
+    assert.equal(getConfig(first).dimensions, DIMS);
+    assert.equal(getConfig(first).quota.maxPoints, 5);
+    assert.equal(getConfig(second).m, 12);
+    const third = join(tmpDir, "tenant-3");
+    createCollection({ template: "small", path: third, dimensions: DIMS, indexType: "hnsw", metric: "cosine" });
+    assert.equal(getConfig(third).m, 8);
+    closeCollection(third);
+
+    buildIndex(second);
+    closeCollection(second);
+    assert.throws(() => createCollection({ template: "small", path: second }), /m mismatch/);
+    closeCollection(first);
+
+    assert.throws(() => createCollection({ template: "missing", path: first }), /Template 'missing' not found/);
+    registerTemplate("partial", { metric: "cosine" });
+    assert.throws(() => createCollection({ template: "partial", path: first }), /dimensions is required/);
+    assert.throws(() => createCollection({ path: first, dimensions: DIMS, metric: "cosine" }), /indexType is required/);
+    createCollection({ template: "partial", path: first, dimensions: DIMS, indexType: "hnsw" });
+    assert.equal(getConfig(first).m, 16);
+    closeCollection(first);
+  });
+});
+
+describe("autoCreate", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should create collections on first insert only with autoCreate", async () => {
+    const userPath = join(tmpDir, "user-1");
+    assert.throws(() => insertVector(userPath, "a", basisVector(DIMS, 0)), /Collection not found/);
+    assert.throws(() => setAutoCreate({ metric: "cosine" }), /dimensions is required/);
+
+    setAutoCreate({ dimensions: DIMS, metric: "cosine" });
+    try {
+      insertVector(userPath, "a", basisVector(DIMS, 0));
+      assert.equal(await insertVectorAsync(join(tmpDir, "user-2"), "b", basisVector(DIMS, 1)), true);
+      assert.equal(getConfig(userPath).indexType, "hnsw");
+      assert.equal(stats(userPath).count, 1);
+      assert.equal(stats(join(tmpDir, "user-2")).count, 1);
+
+      registerTemplate("user", { dimensions: DIMS, metric: "cosine", m: 8 });
+      setAutoCreate("user");
+      insertVector(join(tmpDir, "user-4"), "a", basisVector(DIMS, 0));
+      assert.equal(getConfig(join(tmpDir, "user-4")).m, 8);
+      assert.throws(() => setAutoCreate("missing"), /Template 'missing' not found/);
+    } finally {
+      setAutoCreate(null);
+    }
+    assert.throws(() => insertVector(join(tmpDir, "user-3"), "a", basisVector(DIMS, 0)), /Collection not found/);
+    closeCollection(userPath);
+    closeCollection(join(tmpDir, "user-2"));
+    closeCollection(join(tmpDir, "user-4"));
+  });
+});
+
+describe("renameCollection", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should rename a collection on disk", () => {
+    const from = join(tmpDir, "from");
+    const to = join(tmpDir, "nested", "to");
+    const config = { dimensions: DIMS, indexType: "hnsw", metric: "cosine" };
+    createCollection({ ...config, path: from });
+    insertVector(from, "a", basisVector(DIMS, 0));
+    buildIndex(from);
+    insertVector(from, "b", basisVector(DIMS, 1));
+
+    renameCollection(from, to);
+    assert.throws(() => stats(from), /Collection not found/);
+    assert.equal(stats(to).count, 2);
+    buildIndex(to);
+    assert.ok(!readdirSync(tmpDir).includes("from"));
+
+    // Not loaded: just moved
+    closeCollection(to);
+    renameCollection(to, from);
+    createCollection({ ...config, path: from });
+    assert.deepEqual(search(from, basisVector(DIMS, 1), 1).map((r) => r.id), ["b"]);
+
+    createCollection({ ...config, path: to });
+    assert.throws(() => renameCollection(from, to), /already loaded/);
+    closeCollection(to);
+    assert.throws(() => renameCollection(from, to), /already exists/);
+    assert.throws(() => renameCollection(join(tmpDir, "missing"), join(tmpDir, "x")), /No collection/);
+    closeCollection(from);
+  });
+});
+
+describe("changesSince", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should report ids changed since a sequence number", () => {
+    insertVector(collPath, "a", basisVector(DIMS, 0));
+    insertVector(collPath, "b", basisVector(DIMS, 1));
+    const all = changesSince(collPath, 0);
+    assert.deepEqual(all.upserted, ["a", "b"]);
+    assert.deepEqual(all.deleted, []);
+
+    insertVector(collPath, "c", basisVector(DIMS, 2));
+    deleteVector(collPath, "a");
+    updatePayloads(collPath, [{ id: "b", payload: { lang: "en" } }]);
+    const changes = changesSince(collPath, all.seq);
+    assert.deepEqual(changes.upserted, ["c", "b"]);
+    assert.deepEqual(changes.deleted, ["a"]);
+    assert.equal(changes.seq, stats(collPath).seq);
+    assert.deepEqual(changesSince(collPath, changes.seq), { upserted: [], deleted: [], seq: changes.seq });
+
+    closeCollection(collPath);
+    createCollection({ path: collPath, dimensions: DIMS, indexType: "hnsw", metric: "cosine" });
+    assert.deepEqual(changesSince(collPath, all.seq), changes);
+    assert.throws(() => changesSince(collPath, -1), /seq must be >= 0/);
+  });
+});
+
+describe("unsavedChanges", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should count changes made since the last save", () => {
+    assert.deepEqual(unsavedChanges(collPath), { inserts: 0, deletes: 0 });
+    insertVector(collPath, "a", basisVector(DIMS, 0));
+    insertVector(collPath, "b", basisVector(DIMS, 1));
+    buildIndex(collPath);
+    const { lastSavedAt } = unsavedChanges(collPath);
+    assert.ok(lastSavedAt > 0);
+
+    insertVector(collPath, "c", basisVector(DIMS, 2));
+    insertVector(collPath, "c", basisVector(DIMS, 3));
+    deleteVector(collPath, "a");
+    assert.deepEqual(unsavedChanges(collPath), { inserts: 1, deletes: 1, lastSavedAt });
+
+    buildIndex(collPath);
+    assert.equal(unsavedChanges(collPath).inserts, 0);
+    assert.equal(unsavedChanges(collPath).deletes, 0);
+    assert.throws(() => unsavedChanges(join(tmpDir, "missing")), /Collection not found/);
+  });
+});
+
+describe("diffCollections", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should diff the live points of two collections", () => {
+    const other = join(tmpDir, "other");
+    createCollection({ path: other, dimensions: DIMS, indexType: "hnsw", metric: "cosine" });
+    const nudged = basisVector(DIMS, 1);
+    nudged[0] = 1e-7;
+    for (const [path, b] of [[collPath, basisVector(DIMS, 1)], [other, nudged]]) {
+      insertVector(path, "same", basisVector(DIMS, 0), { lang: "en" });
+      insertVector(path, "close", b);
+      insertVector(path, "moved", basisVector(DIMS, path === other ? 3 : 2));
+      insertVector(path, "tagged", basisVector(DIMS, 4), { lang: path === other ? "fr" : "en" });
+    }
+    insertVector(collPath, "gone", basisVector(DIMS, 5));
+    insertVector(other, "new", basisVector(DIMS, 6));
+    insertVector(other, "dropped", basisVector(DIMS, 7));
+    deleteVector(other, "dropped");
+
+    assert.deepEqual(diffCollections(collPath, other), {
+      onlyInA: ["gone"],
+      onlyInB: ["new"],
+      vectorChanged: ["moved"],
+      payloadChanged: ["tagged"],
+    });
+    assert.deepEqual(diffCollections(collPath, other, { tolerance: 0 }).vectorChanged, ["close", "moved"]);
+    assert.deepEqual(diffCollections(other, other).onlyInA, []);
+    assert.throws(() => diffCollections(collPath, other, { tolerance: -1 }), /tolerance must be >= 0/);
+    assert.throws(() => diffCollections(collPath, join(tmpDir, "missing")), /Collection not found/);
+    closeCollection(other);
+  });
+});
+
+describe("modelTag", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should reject inserts tagged with another embedding model", () => {
+    const tagged = join(tmpDir, "tagged");
+    const config = { path: tagged, dimensions: DIMS, indexType: "hnsw", metric: "cosine" };
+    createCollection({ ...config, modelTag: "model-v1" });
+    insertVector(tagged, "a", basisVector(DIMS, 0), null, "model-v1");
+    insertVector(tagged, "b", basisVector(DIMS, 1));
+    assert.throws(
+      () => insertVector(tagged, "c", basisVector(DIMS, 2), null, "model-v2"),
+      /MODEL_TAG_MISMATCH: .* holds embeddings from 'model-v1', got 'model-v2'/
+    );
+    assert.equal(validatePoint(tagged, "c", basisVector(DIMS, 2), null, "model-v2").valid, false);
+    assert.throws(() => createIngestStream(tagged, { modelTag: "model-v2" }), /MODEL_TAG_MISMATCH/);
+    assert.equal(stats(tagged).count, 2);
+
+    buildIndex(tagged);
+    closeCollection(tagged);
+    assert.equal(openMetadata(tagged).config.modelTag, "model-v1");
+    assert.throws(() => createCollection({ ...config, modelTag: "model-v2" }), /modelTag mismatch/);
+    createCollection(config);
+    assert.equal(getConfig(tagged).modelTag, "model-v1");
+    closeCollection(tagged);
+  });
+});
+
+describe("staging", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should promote staged vectors from a new model", () => {
+    const migrating = join(tmpDir, "migrating");
+    const config = { path: migrating, dimensions: DIMS, indexType: "hnsw", metric: "cosine" };
+    createCollection({ ...config, modelTag: "model-v1" });
+    insertVector(migrating, "a", basisVector(DIMS, 0));
+    insertVector(migrating, "b", basisVector(DIMS, 1));
+    insertVector(migrating, "gone", basisVector(DIMS, 2));
+    deleteVector(migrating, "gone");
+    assert.equal(stagingStatus(migrating), null);
+
+    assert.equal(stageVector(migrating, "a", basisVector(8, 0), "model-v2"), true);
+    assert.equal(stageVector(migrating, "gone", basisVector(8, 2)), false);
+    assert.throws(() => stageVector(migrating, "b", basisVector(4, 1)), /staged vectors have 8, got 4/);
+    assert.throws(() => stageVector(migrating, "b", basisVector(8, 1), "model-v3"), /MODEL_TAG_MISMATCH/);
+    assert.equal(search(migrating, basisVector(DIMS, 0), 1)[0].id, "a");
+    assert.deepEqual(stagingStatus(migrating), { dimensions: 8, modelTag: "model-v2", staged: 1, missing: 1 });
+    assert.throws(() => promoteStaging(migrating), /1 live points have no staged vector, e.g. 'b'/);
+
+    // An upsert drops the staged vector made from the old contents
+    stageVector(migrating, "b", basisVector(8, 1));
+    insertVector(migrating, "b", basisVector(DIMS, 3));
+    assert.equal(stagingStatus(migrating).missing, 1);
+    stageVector(migrating, "b", basisVector(8, 1));
+
+    buildIndex(migrating);
+    closeCollection(migrating);
+    createCollection(config);
+    assert.equal(stagingStatus(migrating).staged, 2);
+
+    assert.equal(promoteStaging(migrating), 2);
+    assert.equal(stagingStatus(migrating), null);
+    assert.equal(search(migrating, basisVector(8, 1), 1)[0].id, "b");
+    assert.deepEqual(listDeleted(migrating), []);
+    assert.equal(getConfig(migrating).modelTag, "model-v2");
+    assert.throws(() => insertVector(migrating, "c", basisVector(DIMS, 0)), /Dimension mismatch/);
+    assert.equal(clearStaging(migrating), false);
+
+    buildIndex(migrating);
+    closeCollection(migrating);
+    createCollection({ ...config, dimensions: 8 });
+    assert.equal(stats(migrating).count, 2);
+    closeCollection(migrating);
+  });
+});
+
+describe("late interaction", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll");
+    createCollection({ path: collPath, dimensions: DIMS, indexType: "hnsw", metric: "cosine" });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should rescore candidates by MaxSim over token vectors", () => {
+    // Same pooled vector, so only the token vectors tell them apart
+    for (const id of ["a", "b", "c", "none"]) {
+      insertVector(collPath, id, basisVector(DIMS, 0));
+    }
+    setTokenVectors(collPath, "a", [basisVector(4, 0), basisVector(4, 1)]);
+    setTokenVectors(collPath, "b", [basisVector(4, 0), basisVector(4, 2)]);
+    assert.equal(setTokenVectors(collPath, "c", [basisVector(4, 3)]), true);
+    assert.equal(setTokenVectors(collPath, "missing", [basisVector(4, 0)]), false);
+    assert.throws(() => setTokenVectors(collPath, "a", [basisVector(8, 0)]), /Token dimension mismatch: expected 4, got 8/);
+    assert.throws(() => setTokenVectors(collPath, "a", []), /no rows/);
+
+    const queryTokens = [basisVector(4, 0), basisVector(4, 2)];
+    const results = search(collPath, basisVector(DIMS, 0), 3, { lateInteraction: queryTokens });
+    assert.deepEqual(results.map((r) => r.id), ["b", "a", "c"]);
+    assert.ok(Math.abs(results[0].score - 2) < 1e-6);
+    assert.ok(Math.abs(results[1].score - 1) < 1e-6);
+    assert.throws(
+      () => search(collPath, basisVector(DIMS, 0), 3, { lateInteraction: [basisVector(3, 0)] }),
+      /Query token dimension mismatch/
+    );
+
+    buildIndex(collPath);
+    closeCollection(collPath);
+    createCollection({ path: collPath, dimensions: DIMS, indexType: "hnsw", metric: "cosine" });
+    assert.equal(search(collPath, basisVector(DIMS, 0), 1, { lateInteraction: queryTokens })[0].id, "b");
+
+    // An upsert drops the matrix
+    insertVector(collPath, "b", basisVector(DIMS, 0));
+    assert.deepEqual(
+      search(collPath, basisVector(DIMS, 0), 3, { lateInteraction: queryTokens }).map((r) => r.id),
+      ["a", "c"]
+    );
+  });
+
+  it("should reject late interaction on a collection without token vectors", () => {
+    insertVector(collPath, "a", basisVector(DIMS, 0));
+    assert.throws(
+      () => search(collPath, basisVector(DIMS, 0), 1, { lateInteraction: [basisVector(4, 0)] }),
+      /has no token vectors/
+    );
+  });
+});
+
+describe("discovery search", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll");
+    createCollection({ path: collPath, dimensions: DIMS, indexType: "hnsw", metric: "cosine" });
+    const mix = (weights) => {
+      const vec = new Float32Array(DIMS);
+      weights.forEach((w, i) => (vec[i] = w));
+      return vec;
+    };
+    insertVector(collPath, "pos", basisVector(DIMS, 0));
+    insertVector(collPath, "neg", basisVector(DIMS, 1));
+    insertVector(collPath, "near", mix([1, 0, 1]), { keep: true });
+    insertVector(collPath, "far", mix([2, 0, 1]), { keep: true });
+    insertVector(collPath, "wrong", mix([0, 1, 1]), { keep: false });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should rank by pairs fitted, then by the target", () => {
+    const context = [{ positive: "pos", negative: "neg" }];
+    const results = discover(collPath, context, 10, { target: basisVector(DIMS, 2), includePayload: true });
+    assert.deepEqual(results.map((r) => r.id), ["near", "far", "wrong"]);
+    assert.ok(results[0].score > 1 && results[1].score > 1 && results[2].score < 0);
+    assert.deepEqual(results[0].payload, { keep: true });
+
+    const filtered = discover(collPath, context, 10, { target: basisVector(DIMS, 2), filter: { keep: false } });
+    assert.deepEqual(filtered.map((r) => r.id), ["wrong"]);
+  });
+
+  it("should score the wrong side of the pairs without a target", () => {
+    const context = [{ positive: basisVector(DIMS, 0), negative: basisVector(DIMS, 1) }];
+    const results = discover(collPath, context, 2);
+    assert.deepEqual(results.map((r) => r.id), ["pos", "near"]);
+    assert.equal(results[0].score, 0);
+    const all = discover(collPath, context, 10);
+    assert.ok(Math.abs(all.at(-1).score + 1) < 1e-6);
+    assert.equal(all.at(-1).id, "neg");
+  });
+
+  it("should reject bad context", () => {
+    assert.throws(() => discover(collPath, [], 5), /at least one context pair/);
+    assert.throws(() => discover(collPath, [{ positive: "missing", negative: "neg" }], 5), /Point 'missing' not found/);
+    assert.throws(
+      () => discover(collPath, [{ positive: basisVector(8, 0), negative: "neg" }], 5),
+      /Query dimension mismatch: expected 384, got 8/
+    );
+  });
+});
+
+describe("vector arithmetic", () => {
+  it("should add, subtract, average and normalize vectors", () => {
+    const a = Float32Array.from([1, 2, 3]);
+    const b = Float32Array.from([3, 2, 1]);
+    assert.deepEqual(Array.from(add(a, b)), [4, 4, 4]);
+    assert.deepEqual(Array.from(subtract(a, b)), [-2, 0, 2]);
+    assert.deepEqual(Array.from(mean([a, b, Float32Array.from([2, 8, 5])])), [2, 4, 3]);
+    assert.deepEqual(Array.from(normalize(Float32Array.from([3, 0, 4]))), [0.6000000238418579, 0, 0.800000011920929]);
+    assert.deepEqual(Array.from(normalize(new Float32Array(3))), [0, 0, 0]);
+    assert.deepEqual(Array.from(a), [1, 2, 3]);
+
+    assert.throws(() => add(a, new Float32Array(2)), /Dimension mismatch: expected 3, got 2/);
+    assert.throws(() => mean([a, new Float32Array(4)]), /Dimension mismatch/);
+    assert.throws(() => mean([]), /at least one vector/);
+  });
+
+  it("should score cosine similarity like a cosine collection", () => {
+    assert.ok(Math.abs(cosineSimilarity(basisVector(4, 0), basisVector(4, 0)) - 1) < 1e-6);
+    assert.equal(cosineSimilarity(basisVector(4, 0), basisVector(4, 1)), 0);
+    assert.ok(Math.abs(cosineSimilarity(Float32Array.from([1, 1]), Float32Array.from([-2, -2])) + 1) < 1e-6);
+    assert.equal(cosineSimilarity(new Float32Array(4), basisVector(4, 1)), 1);
+    assert.throws(() => cosineSimilarity(basisVector(4, 0), basisVector(3, 0)), /Dimension mismatch/);
+  });
+});
+
+describe("binary vectors", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "hashes");
+    createCollection({ path: collPath, dimensions: 64, indexType: "flat", metric: "hamming" });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  const code = (...bytes) => Uint8Array.from({ length: 8 }, (_, i) => bytes[i] ?? 0);
+
+  it("should search by Hamming distance", () => {
+    insertBinary(collPath, "zero", code());
+    insertBinary(collPath, "one", code(0b1), { tag: "a" });
+    insertBinary(collPath, "many", code(0xff, 0xff), { tag: "b" });
+
+    const results = searchBinary(collPath, code(0b11), 3);
+    assert.deepEqual(results.map((r) => r.id), ["one", "zero", "many"]);
+    assert.equal(results[0].score, 1 - 1 / 64);
+    assert.equal(results[2].score, 1 - 14 / 64);
+
+    const filtered = searchBinary(collPath, code(), 3, { filter: { tag: "b" }, includePayload: true });
+    assert.deepEqual(filtered.map((r) => [r.id, r.payload]), [["many", { tag: "b" }]]);
+
+    deleteVector(collPath, "one");
+    insertBinary(collPath, "zero", code(0xff));
+    assert.deepEqual(searchBinary(collPath, code(), 3).map((r) => r.id), ["zero", "many"]);
+  });
+
+  it("should persist codes and keep dense and binary APIs apart", () => {
+    insertBinary(collPath, "a", code(0xaa));
+    buildIndex(collPath);
+    closeCollection(collPath);
+    createCollection({ path: collPath, dimensions: 64, indexType: "flat", metric: "hamming" });
+    assert.equal(searchBinary(collPath, code(0xaa), 1)[0].score, 1);
+
+    assert.throws(() => insertBinary(collPath, "b", new Uint8Array(4)), /expected 8 bytes/);
+    assert.throws(() => insertVector(collPath, "b", new Float32Array(64)), /use insertBinary/);
+    assert.throws(() => search(collPath, new Float32Array(64), 1), /use insertBinary and searchBinary/);
+
+    const densePath = join(tmpDir, "dense");
+    createCollection({ path: densePath, dimensions: DIMS, indexType: "hnsw", metric: "cosine" });
+    assert.throws(() => insertBinary(densePath, "a", code()), /need metric 'hamming'/);
+    closeCollection(densePath);
+
+    const config = { path: join(tmpDir, "bad"), dimensions: 64, metric: "hamming" };
+    assert.throws(() => createCollection({ ...config, indexType: "hnsw" }), /Use 'flat' for 'hamming'/);
+    assert.throws(
+      () => createCollection({ ...config, indexType: "flat", dimensions: 12 }),
+      /divisible by 8/,
+    );
+  });
+});
+
+describe("token sets", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "docs");
+    createCollection({ path: collPath, dimensions: 1, indexType: "minhash", metric: "jaccard" });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  const tokens = (from, to) => Uint32Array.from({ length: to - from }, (_, i) => from + i);
+
+  it("should search by Jaccard similarity among LSH candidates", () => {
+    insertSet(collPath, "same", tokens(0, 100), { tag: "a" });
+    insertSet(collPath, "near", tokens(10, 110), { tag: "b" });
+    insertSet(collPath, "far", tokens(95, 195));
+
+    const results = searchSet(collPath, tokens(0, 100), 3);
+    assert.deepEqual(results.map((r) => r.id), ["same", "near"]);
+    assert.equal(results[0].score, 1);
+    assert.equal(results[1].score, 90 / 110);
+
+    const exhaustive = searchSet(collPath, tokens(0, 100), 3, { exhaustive: true });
+    assert.deepEqual(exhaustive.map((r) => r.id), ["same", "near", "far"]);
+    assert.equal(exhaustive[2].score, 5 / 195);
+
+    const filtered = searchSet(collPath, tokens(0, 100), 3, { filter: { tag: "b" }, includePayload: true });
+    assert.deepEqual(filtered.map((r) => [r.id, r.payload]), [["near", { tag: "b" }]]);
+
+    // Order and duplicates don't matter
+    insertSet(collPath, "same", Uint32Array.from([150, 120, 120, 199]));
+    deleteVector(collPath, "near");
+    assert.deepEqual(searchSet(collPath, tokens(0, 100), 3).map((r) => r.id), []);
+    assert.equal(searchSet(collPath, Uint32Array.from([7, 120, 150, 199]), 1)[0].score, 3 / 4);
+  });
+
+  it("should persist sets and keep the set APIs to jaccard collections", () => {
+    insertSet(collPath, "a", tokens(0, 50));
+    buildIndex(collPath);
+    closeCollection(collPath);
+    createCollection({ path: collPath, dimensions: 1, indexType: "minhash", metric: "jaccard" });
+    assert.equal(searchSet(collPath, tokens(0, 50), 1)[0].id, "a");
+
+    assert.throws(() => insertSet(collPath, "b", new Uint32Array()), /must not be empty/);
+    assert.throws(() => insertVector(collPath, "b", new Float32Array(1)), /use insertSet/);
+    assert.throws(() => searchBinary(collPath, new Uint8Array(1), 1), /use insertSet and searchSet/);
+
+    const densePath = join(tmpDir, "dense");
+    createCollection({ path: densePath, dimensions: DIMS, indexType: "hnsw", metric: "cosine" });
+    assert.throws(() => searchSet(densePath, tokens(0, 5), 1), /need metric 'jaccard'/);
+    closeCollection(densePath);
+
+    const config = { path: join(tmpDir, "bad"), dimensions: 1, metric: "jaccard" };
+    assert.throws(() => createCollection({ ...config, indexType: "flat" }), /'minhash' for 'jaccard'/);
+  });
+});
+
+describe("ivf index", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  const config = () => ({ path: collPath, dimensions: 32, indexType: "ivf", metric: "cosine" });
+
+  it("should train lists on buildIndex and search the nearest ones", () => {
+    createCollection({ ...config(), ivfLists: 4 });
+    const vectors = Array.from({ length: 200 }, () => randomVector(32));
+    vectors.forEach((v, i) => insertVector(collPath, `p${i}`, v));
+
+    // Untrained: every point is scanned
+    assert.equal(search(collPath, vectors[7], 1)[0].id, "p7");
+    buildIndex(collPath);
+
+    const exact = search(collPath, vectors[42], 5, { nprobe: 4 });
+    assert.equal(exact[0].id, "p42");
+    assert.ok(exact[0].score > 0.999);
+    const probed = search(collPath, vectors[42], 5, { nprobe: 1 });
+    assert.equal(probed[0].id, "p42");
+    assert.ok(probed.length <= 5);
+
+    deleteVector(collPath, "p42");
+    assert.notEqual(search(collPath, vectors[42], 1)[0].id, "p42");
+    assert.throws(() => search(collPath, vectors[0], 1, { nprobe: 0 }), /nprobe must be > 0/);
+    assert.throws(() => checkIndex(collPath), /needs an HNSW graph/);
+
+    closeCollection(collPath);
+    createCollection(config());
+    assert.equal(getConfig(collPath).ivfLists, 4);
+    assert.equal(search(collPath, vectors[9], 1)[0].id, "p9");
+    assert.throws(() => createCollection({ ...config(), ivfLists: 8 }), /ivfLists mismatch/);
+  });
+
+  it("should convert to and from HNSW with reindex", () => {
+    createCollection({ ...config(), indexType: "hnsw" });
+    const vectors = Array.from({ length: 50 }, () => randomVector(32));
+    vectors.forEach((v, i) => insertVector(collPath, `p${i}`, v));
+
+    reindex(collPath, { indexType: "ivf", ivfLists: 5 });
+    assert.equal(getConfig(collPath).indexType, "ivf");
+    assert.equal(search(collPath, vectors[3], 1, { nprobe: 5 })[0].id, "p3");
+
+    reindex(collPath, { indexType: "hnsw" });
+    assert.equal(search(collPath, vectors[3], 1)[0].id, "p3");
+    assert.equal(checkIndex(collPath).ok, true);
+  });
+
+  it("should quantize with pqSubvectors and rescore from the vector file", () => {
+    createCollection({ ...config(), ivfLists: 4, pqSubvectors: 8 });
+    const vectors = Array.from({ length: 200 }, () => randomVector(32));
+    vectors.forEach((v, i) => insertVector(collPath, `p${i}`, v));
+    buildIndex(collPath);
+    assert.deepEqual(readdirSync(collPath).filter((f) => f.startsWith("vectors-")), ["vectors-0.f32"]);
+
+    const hits = search(collPath, vectors[42], 5, { nprobe: 4, pqRescore: 2 });
+    assert.equal(hits[0].id, "p42");
+    assert.ok(hits[0].score > 0.999);
+    assert.equal(getVectors(collPath, ["p42"])[0].vector.length, 32);
+    assert.throws(() => search(collPath, vectors[0], 1, { pqRescore: 0 }), /pqRescore must be > 0/);
+
+    closeCollection(collPath);
+    createCollection(config());
+    assert.equal(getConfig(collPath).pqSubvectors, 8);
+    assert.equal(search(collPath, vectors[9], 1, { nprobe: 4 })[0].id, "p9");
+    assert.throws(() => createCollection({ ...config(), pqSubvectors: 4 }), /pqSubvectors mismatch/);
+
+    reindex(collPath, { pqSubvectors: 0 });
+    assert.equal(getConfig(collPath).pqSubvectors, undefined);
+    assert.equal(search(collPath, vectors[9], 1, { nprobe: 4 })[0].id, "p9");
+    assert.deepEqual(readdirSync(collPath).filter((f) => f.startsWith("vectors-")), []);
+
+    const bad = { path: join(tmpDir, "bad"), dimensions: 30, metric: "cosine" };
+    assert.throws(
+      () => createCollection({ ...bad, indexType: "ivf", pqSubvectors: 8 }),
+      /pqSubvectors must divide dimensions \(30\)/,
+    );
+    assert.throws(
+      () => createCollection({ ...bad, indexType: "hnsw", pqSubvectors: 5 }),
+      /pqSubvectors needs indexType 'ivf'/,
+    );
+  });
+});
+
+describe("lsh index", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  const config = () => ({ path: collPath, dimensions: 32, indexType: "lsh", metric: "cosine" });
+
+  it("should find points sharing a bucket and keep its settings", () => {
+    createCollection({ ...config(), lshTables: 4, lshBits: 6 });
+    const vectors = Array.from({ length: 100 }, () => randomVector(32));
+    vectors.forEach((v, i) => insertVector(collPath, `p${i}`, v));
+
+    const hits = search(collPath, vectors[17], 3);
+    assert.equal(hits[0].id, "p17");
+    assert.ok(hits[0].score > 0.999);
+    deleteVector(collPath, "p17");
+    assert.ok(search(collPath, vectors[17], 3).every((hit) => hit.id !== "p17"));
+    assert.throws(() => checkIndex(collPath), /needs an HNSW graph/);
+
+    buildIndex(collPath);
+    closeCollection(collPath);
+    createCollection(config());
+    assert.equal(getConfig(collPath).lshBits, 6);
+    assert.equal(search(collPath, vectors[5], 1)[0].id, "p5");
+    assert.throws(() => createCollection({ ...config(), lshTables: 8 }), /lshTables mismatch/);
+
+    reindex(collPath, { indexType: "hnsw" });
+    assert.equal(search(collPath, vectors[5], 1)[0].id, "p5");
+    assert.throws(() => reindex(collPath, { lshBits: 65 }), /lshBits must be between 1 and 64/);
+  });
+});
+
+describe("normalize", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should store unit vectors and score queries of any length", () => {
+    const config = { path: collPath, dimensions: 2, indexType: "lsh", metric: "cosine", normalize: true };
+    createCollection(config);
+    insertVector(collPath, "a", [3, 4]);
+    insertVector(collPath, "b", [0, 5]);
+    const [stored] = getVectors(collPath, ["a"]);
+    assert.ok(Math.abs(stored.vector[0] - 0.6) < 1e-6 && Math.abs(stored.vector[1] - 0.8) < 1e-6);
+
+    const [hit] = search(collPath, [30, 40], 1);
+    assert.equal(hit.id, "a");
+    assert.ok(Math.abs(hit.score - 1) < 1e-6);
+
+    buildIndex(collPath);
+    closeCollection(collPath);
+    createCollection(config);
+    assert.equal(getConfig(collPath).normalize, true);
+    assert.throws(() => createCollection({ ...config, normalize: false }), /normalize mismatch/);
+  });
+
+  it("should keep norms in a sidecar and recompute a stale one", () => {
+    const config = { path: collPath, dimensions: 2, indexType: "lsh", metric: "cosine" };
+    createCollection(config);
+    insertVector(collPath, "a", [3, 4]);
+    insertVector(collPath, "b", [0, 5]);
+    buildIndex(collPath);
+    assert.ok(readdirSync(collPath).includes("norms.bin"));
+
+    closeCollection(collPath);
+    createCollection(config);
+    assert.ok(Math.abs(search(collPath, [6, 8], 1)[0].score - 1) < 1e-6);
+
+    closeCollection(collPath);
+    writeFileSync(join(collPath, "norms.bin"), Buffer.alloc(20));
+    createCollection(config);
+    const [hit] = search(collPath, [0, 1], 1);
+    assert.equal(hit.id, "b");
+    assert.ok(Math.abs(hit.score - 1) < 1e-6);
+  });
+});
+
+describe("insertion time", () => {
+  let tmpDir;
+  let collPath;
+  let cutoff;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+    insertVector(collPath, "old", basisVector(DIMS, 0));
+    sleepMs(5);
+    cutoff = Date.now();
+    sleepMs(5);
+    insertVector(collPath, "new", basisVector(DIMS, 0));
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should report insertedAt in search results", () => {
+    const [hit] = search(collPath, basisVector(DIMS, 0), 1, { insertedAfter: cutoff });
+    assert.equal(hit.id, "new");
+    assert.ok(hit.insertedAt >= cutoff && hit.insertedAt <= Date.now());
+  });
+
+  it("should filter search by insertion time", () => {
+    const after = search(collPath, basisVector(DIMS, 0), 5, { insertedAfter: cutoff });
+    assert.deepEqual(after.map((r) => r.id), ["new"]);
+
+    const before = search(collPath, basisVector(DIMS, 0), 5, { insertedBefore: cutoff });
+    assert.deepEqual(before.map((r) => r.id), ["old"]);
+  });
+
+  it("should restrict search to allowIds", () => {
+    const query = basisVector(DIMS, 0);
+    const allowed = search(collPath, query, 5, { allowIds: ["old", "missing"] });
+    assert.deepEqual(allowed.map((r) => r.id), ["old"]);
+    assert.deepEqual(search(collPath, query, 5, { allowIds: [] }), []);
+
+    // Longer than efSearch, so filtered inside the graph search
+    const graph = search(collPath, query, 5, {
+      allowIds: ["old", "new"],
+      efSearch: 1,
+      insertedAfter: cutoff,
+    });
+    assert.deepEqual(graph.map((r) => r.id), ["new"]);
+  });
+
+  it("should restrict search to id sets until the next compaction", () => {
+    const query = basisVector(DIMS, 0);
+    const old = createIdSet(collPath, ["old", "missing"]);
+    assert.deepEqual(search(collPath, query, 5, { allowSet: old }).map((r) => r.id), ["old"]);
+    assert.deepEqual(search(collPath, query, 5, { denySet: old }).map((r) => r.id), ["new"]);
+    assert.throws(
+      () => search(collPath, query, 5, { allowSet: old, allowIds: ["old"] }),
+      /can't be combined/,
+    );
+
+    // An upserted point stays in the set
+    insertVector(collPath, "old", basisVector(DIMS, 1));
+    assert.deepEqual(search(collPath, query, 5, { allowSet: old }).map((r) => r.id), ["old"]);
+
+    compact(collPath);
+    assert.throws(() => search(collPath, query, 5, { allowSet: old }), /Id set \d+ not found/);
+    assert.equal(dropIdSet(collPath, old), false);
+    const again = createIdSet(collPath, ["new"]);
+    assert.notEqual(again, old);
+    assert.equal(dropIdSet(collPath, again), true);
+  });
+
+  it("should delete by insertion time", () => {
+    assert.equal(deleteByFilter(collPath, { insertedBefore: cutoff }), 1);
+    assert.deepEqual(search(collPath, basisVector(DIMS, 0), 5).map((r) => r.id), ["new"]);
+    assert.throws(() => deleteByFilter(collPath, {}), /must set insertedAfter or insertedBefore/);
+  });
+
+  it("should persist timestamps", () => {
+    const [before] = search(collPath, basisVector(DIMS, 0), 1, { insertedAfter: cutoff });
+    buildIndex(collPath);
+    closeCollection(collPath);
+    createCollection({ path: collPath, dimensions: DIMS, indexType: "hnsw", metric: "cosine" });
+
+    const [after] = search(collPath, basisVector(DIMS, 0), 1, { insertedAfter: cutoff });
+    assert.equal(after.id, "new");
+    assert.equal(after.insertedAt, before.insertedAt);
+  });
+});
+
+describe("audit log", () => {
+  let tmpDir;
+  let collPath;
+
+  const open = (auditLog) =>
+    createCollection({
+      path: collPath,
+      dimensions: 4,
+      indexType: "hnsw",
+      metric: "cosine",
+      auditLog,
+    });
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should record mutating operations with the actor", () => {
+    open(true);
+    insertVector(collPath, "a", basisVector(4, 0));
+    setAuditActor(collPath, "importer");
+    insertVector(collPath, "b", basisVector(4, 1));
+    assert.equal(deleteVector(collPath, "missing"), false);
+    deleteVector(collPath, "a");
+    restoreVector(collPath, "a");
+    sleepMs(5);
+    const cutoff = Date.now();
+    sleepMs(5);
+    setAuditActor(collPath, null);
+    deleteByFilter(collPath, { insertedBefore: cutoff });
+    buildIndex(collPath);
+
+    const log = getAuditLog(collPath);
+    assert.deepEqual(
+      log.map(({ op, ids, actor }) => ({ op, ids: [...ids].sort(), actor })),
+      [
+        { op: "insert", ids: ["a"], actor: undefined },
+        { op: "insert", ids: ["b"], actor: "importer" },
+        { op: "delete", ids: ["a"], actor: "importer" },
+        { op: "restore", ids: ["a"], actor: "importer" },
+        { op: "deleteByFilter", ids: ["a", "b"], actor: undefined },
+        { op: "build", ids: ["a", "b"], actor: undefined },
+      ]
+    );
+    assert.deepEqual(
+      getAuditLog(collPath, { since: cutoff }).map((e) => e.op),
+      ["deleteByFilter", "build"]
+    );
+  });
+
+  it("should be off unless enabled and persist the setting", () => {
+    open();
+    insertVector(collPath, "a", basisVector(4, 0));
+    assert.deepEqual(getAuditLog(collPath), []);
+    assert.equal(getConfig(collPath).auditLog, false);
+
+    closeCollection(collPath);
+    open(true);
+    buildIndex(collPath);
+    closeCollection(collPath);
+    open();
+    assert.equal(getConfig(collPath).auditLog, true);
+    insertVector(collPath, "b", basisVector(4, 1));
+    assert.deepEqual(getAuditLog(collPath).map((e) => e.op), ["build", "insert"]);
+  });
+});
+
+describe("reindex", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should keep ids and search results after reindex", () => {
+    insertVector(collPath, "a", basisVector(DIMS, 0));
+    insertVector(collPath, "b", basisVector(DIMS, 1));
+    buildIndex(collPath);
+
+    reindex(collPath, { m: 32, efConstruction: 100 });
+
+    assert.equal(stats(collPath).count, 2);
+    const results = search(collPath, basisVector(DIMS, 1), 1);
+    assert.equal(results[0].id, "b");
+  });
+
+  it("should drop pending deletions", () => {
+    insertVector(collPath, "keep", basisVector(DIMS, 0));
+    insertVector(collPath, "remove", basisVector(DIMS, 1));
+    deleteVector(collPath, "remove");
+
+    reindex(collPath, {});
+
+    const ids = search(collPath, basisVector(DIMS, 1), 5).map((r) => r.id);
+    assert.ok(!ids.includes("remove"));
+    assert.equal(stats(collPath).count, 1);
+  });
+
+  it("should reject invalid parameters", () => {
+    assert.throws(() => reindex(collPath, { m: 1000 }), /Invalid m/);
+    assert.throws(() => reindex(collPath, { metric: "euclidean" }), /Unsupported metric/);
+  });
+
+  it("should rebuild in seeded shuffled order when asked", () => {
+    // Sorted input: each point a little further round from the last
+    const sorted = (i) => {
+      const v = basisVector(DIMS, 0);
+      v[1] = i / 10;
+      return v;
+    };
+    const exportAfterReindex = (name, config) => {
+      const path = join(tmpDir, name);
+      createCollection({ path, dimensions: DIMS, indexType: "hnsw", metric: "cosine", seed: 7 });
+      for (let i = 0; i < 40; i++) {
+        insertVector(path, `v-${i}`, sorted(i));
+      }
+      reindex(path, config);
+      exportHnswlib(path, join(tmpDir, `${name}.bin`));
+      return readFileSync(join(tmpDir, `${name}.bin`));
+    };
+
+    try {
+      const inOrder = exportAfterReindex("plain", {});
+      const shuffled = exportAfterReindex("shuffled1", { shuffleOnRebuild: true });
+      assert.ok(shuffled.equals(exportAfterReindex("shuffled2", { shuffleOnRebuild: true })));
+      assert.ok(!shuffled.equals(inOrder));
+      assert.equal(search(join(tmpDir, "shuffled1"), sorted(20), 1)[0].id, "v-20");
+
+      const path = join(tmpDir, "shuffled1");
+      assert.equal(getConfig(path).shuffleOnRebuild, true);
+      buildIndex(path);
+      closeCollection(path);
+      assert.throws(
+        () =>
+          createCollection({
+            path,
+            dimensions: DIMS,
+            indexType: "hnsw",
+            metric: "cosine",
+            shuffleOnRebuild: false,
+          }),
+        /shuffleOnRebuild mismatch/
+      );
+    } finally {
+      for (const name of ["plain", "shuffled1", "shuffled2"]) {
+        closeCollection(join(tmpDir, name));
+      }
+    }
+  });
+});
+
+describe("analyze", () => {
+  let tmpDir;
+  let collPath;
+  const config = () => ({ path: collPath, dimensions: DIMS, indexType: "hnsw", metric: "cosine" });
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should apply per-field analyzers and persist them", () => {
+    const analyzers = {
+      title: { stemmer: "english", stopwords: ["The"] },
+      sku: { lowercase: false, ngram: { min: 2, max: 3 } },
+    };
+    createCollection({ ...config(), analyzers });
+
+    assert.deepEqual(analyze(collPath, "title", "The running Dogs"), [
+      { term: "run", start: 4, end: 11 },
+      { term: "dog", start: 12, end: 16 },
+    ]);
+    assert.deepEqual(
+      analyze(collPath, "sku", "AbC").map((t) => t.term),
+      ["Ab", "bC", "AbC"]
+    );
+    // Offsets are JS string indices, also past astral characters
+    const text = "😀 Straße";
+    const [token] = analyze(collPath, "other", text);
+    assert.equal(token.term, "straße");
+    assert.equal(text.slice(token.start, token.end), "Straße");
+
+    buildIndex(collPath);
+    closeCollection(collPath);
+    createCollection(config());
+    assert.deepEqual(getConfig(collPath).analyzers.title, {
+      lowercase: true,
+      stemmer: "english",
+      stopwords: ["The"],
+    });
+    closeCollection(collPath);
+    assert.throws(
+      () => createCollection({ ...config(), analyzers: { title: { stemmer: "french" } } }),
+      /Analyzer mismatch/
+    );
+  });
+
+  it("should highlight query terms in search results", () => {
+    createCollection({ ...config(), analyzers: { body: { stemmer: "english" } } });
+    insertVector(collPath, "a", basisVector(DIMS, 0), { body: "Running with dogs; a dog runs" });
+    insertVector(collPath, "b", basisVector(DIMS, 1), { body: 42 });
+    insertVector(collPath, "c", basisVector(DIMS, 2));
+
+    const results = search(collPath, basisVector(DIMS, 0), 3, {
+      highlight: { field: "body", query: "dog running" },
+    });
+    const byId = Object.fromEntries(results.map((r) => [r.id, r.highlights]));
+    assert.deepEqual(byId.a, [
+      { term: "run", start: 0, end: 7 },
+      { term: "dog", start: 13, end: 17 },
+      { term: "dog", start: 21, end: 24 },
+      { term: "run", start: 25, end: 29 },
+    ]);
+    // A tiny HNSW graph can miss "b" or "c" entirely, so only check them when returned
+    for (const id of ["b", "c"]) {
+      if (id in byId) assert.deepEqual(byId[id], []);
+    }
+    assert.equal(search(collPath, basisVector(DIMS, 0), 1)[0].highlights, undefined);
+  });
+
+  it("should reject invalid analyzers", () => {
+    assert.throws(
+      () => createCollection({ ...config(), analyzers: { title: { stemmer: "klingon" } } }),
+      /Unknown stemmer 'klingon'/
+    );
+    assert.throws(
+      () => createCollection({ ...config(), analyzers: { title: { ngram: { min: 3, max: 2 } } } }),
+      /Invalid ngram range/
+    );
+    assert.throws(
+      () =>
+        createCollection({
+          ...config(),
+          schema: { price: "float" },
+          analyzers: { price: {} },
+        }),
+      /needs a string field/
+    );
+  });
+});
+
+describe("exportHnswlib", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should write an hnswlib index and id mapping", () => {
+    for (let i = 0; i < 50; i++) {
+      insertVector(collPath, `v-${i}`, randomVector(DIMS));
+    }
+    deleteVector(collPath, "v-0");
+
+    const outFile = join(tmpDir, "export", "index.bin");
+    assert.equal(exportHnswlib(collPath, outFile), 49);
+
+    const ids = JSON.parse(readFileSync(`${outFile}.ids.json`, "utf8"));
+    assert.equal(ids.length, 49);
+    assert.ok(!ids.includes("v-0"));
+
+    // Walk the file the way hnswlib's loadIndex does
+    const buf = readFileSync(outFile);
+    const count = Number(buf.readBigUInt64LE(16));
+    const sizePerElement = Number(buf.readBigUInt64LE(24));
+    const m = Number(buf.readBigUInt64LE(72));
+    assert.equal(count, 49);
+    assert.equal(sizePerElement, (2 * m + 1) * 4 + DIMS * 4 + 8);
+
+    const headerSize = 8 * 6 + 4 + 4 + 8 * 3 + 8 + 8;
+    let offset = headerSize + count * sizePerElement;
+    for (let i = 0; i < count; i++) {
+      const linkListSize = buf.readUInt32LE(offset);
+      assert.equal(linkListSize % ((m + 1) * 4), 0);
+      offset += 4 + linkListSize;
+    }
+    assert.equal(offset, buf.length);
+  });
+
+  it("should export identical indexes for seeded collections", () => {
+    const vectors = new Float32Array(60 * DIMS);
+    const ids = Array.from({ length: 60 }, (_, i) => {
+      vectors.set(randomVector(DIMS), i * DIMS);
+      return `v-${i}`;
+    });
+    const exportSeeded = (name) => {
+      const path = join(tmpDir, name);
+      createCollection({ path, dimensions: DIMS, indexType: "hnsw", metric: "cosine", seed: 7 });
+      const stream = createIngestStream(path, { queueSize: 4 });
+      for (let i = 0; i < ids.length; i += 20) {
+        stream.push(ids.slice(i, i + 20), vectors.subarray(i * DIMS, (i + 20) * DIMS));
+      }
+      stream.finish();
+      deleteVector(path, "v-3");
+      buildIndex(path);
+      const outFile = join(tmpDir, `${name}.bin`);
+      exportHnswlib(path, outFile);
+      return readFileSync(outFile);
+    };
+
+    try {
+      const first = exportSeeded("seeded1");
+      assert.ok(first.equals(exportSeeded("seeded2")));
+
+      // Loading rebuilds the graph in the original order
+      closeCollection(join(tmpDir, "seeded1"));
+      const path = join(tmpDir, "seeded1");
+      createCollection({ path, dimensions: DIMS, indexType: "hnsw", metric: "cosine" });
+      assert.equal(getConfig(path).seed, 7);
+      exportHnswlib(path, join(tmpDir, "reloaded.bin"));
+      assert.ok(first.equals(readFileSync(join(tmpDir, "reloaded.bin"))));
+      closeCollection(path);
+      assert.throws(
+        () => createCollection({ path, dimensions: DIMS, indexType: "hnsw", metric: "cosine", seed: 8 }),
+        /seed mismatch: existing collection has 7, requested 8/
+      );
+    } finally {
+      closeCollection(join(tmpDir, "seeded1"));
+      closeCollection(join(tmpDir, "seeded2"));
+    }
+  });
+});
+
+describe("knnGraph", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should write neighbours of every live point to a file and callback", () => {
+    for (let i = 0; i < 20; i++) {
+      insertVector(collPath, `v-${i}`, randomVector(DIMS));
+    }
+    const a = randomVector(DIMS);
+    insertVector(collPath, "a", a);
+    insertVector(collPath, "a-twin", a.map((x, j) => x + (j === 0 ? 0.01 : 0)));
+    deleteVector(collPath, "v-0");
+
+    const outFile = join(tmpDir, "knn.jsonl");
+    const batches = [];
+    const count = knnGraph(collPath, 3, {
+      outFile,
+      batchSize: 8,
+      onBatch: (entries) => batches.push(entries),
+    });
+    assert.equal(count, 21);
+    assert.deepEqual(batches.map((b) => b.length), [8, 8, 5]);
+
+    const lines = readFileSync(outFile, "utf8").trim().split("\n").map((l) => JSON.parse(l));
+    assert.equal(lines.length, 21);
+    for (const { id, neighbors } of lines) {
+      assert.equal(neighbors.length, 3);
+      assert.ok(neighbors.every((n) => n.id !== id && n.id !== "v-0"));
+    }
+    assert.equal(lines.find((l) => l.id === "a").neighbors[0].id, "a-twin");
+    assert.throws(() => knnGraph(collPath, 3, {}), /outFile or onBatch/);
+  });
+});
+
+describe("pairwiseSimilarities", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should compute the similarity matrix of a set of points", () => {
+    insertVector(collPath, "x", basisVector(DIMS, 0));
+    insertVector(collPath, "y", basisVector(DIMS, 1));
+    const diagonal = new Float32Array(DIMS);
+    diagonal[0] = diagonal[1] = 1;
+    insertVector(collPath, "xy", diagonal);
+
+    const ids = ["x", "y", "xy"];
+    const matrix = pairwiseSimilarities(collPath, ids);
+    assert.ok(matrix instanceof Float32Array);
+    assert.equal(matrix.length, 9);
+    const xy = search(collPath, basisVector(DIMS, 0), 3).find((r) => r.id === "xy").score;
+    assert.ok(Math.abs(matrix[0 * 3 + 2] - xy) < 1e-6);
+    assert.ok(Math.abs(matrix[2 * 3 + 0] - xy) < 1e-6);
+    assert.equal(matrix[0 * 3 + 1], 0);
+    for (let i = 0; i < 3; i++) {
+      assert.ok(Math.abs(matrix[i * 3 + i] - 1) < 1e-6);
+    }
+
+    assert.equal(pairwiseSimilarities(collPath, []).length, 0);
+    deleteVector(collPath, "y");
+    assert.throws(() => pairwiseSimilarities(collPath, ids), /Point 'y' not found/);
+  });
+});
+
+describe("operation limits", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
     });
   });
 
   afterEach(() => {
+    closeCollection(collPath);
     rmSync(tmpDir, { recursive: true, force: true });
   });
 
-  it("should return correct count and dimensions", () => {
-    insertVector(collPath, "a", randomVector(DIMS));
-    insertVector(collPath, "b", randomVector(DIMS));
-    const s = stats(collPath);
-    assert.equal(s.count, 2);
-    assert.equal(s.dimensions, DIMS);
+  it("should refuse heavy operations over the configured limits", () => {
+    for (let i = 0; i < 4; i++) {
+      insertVector(collPath, `v-${i}`, randomVector(DIMS));
+    }
+    const otherPath = join(tmpDir, "coll2");
+    createCollection({ path: otherPath, dimensions: DIMS, indexType: "hnsw", metric: "cosine" });
+    assert.throws(() => setOperationLimits({ maxConcurrent: 0 }), /must be > 0/);
+
+    // Start heavy operations from inside a running knnGraph
+    const during = (limits, fn) => {
+      setOperationLimits(limits);
+      let outcome;
+      knnGraph(collPath, 1, {
+        onBatch: () => {
+          assert.equal(getOperationLimits().running, 1);
+          try {
+            outcome = fn();
+          } catch (e) {
+            outcome = e;
+          }
+        },
+      });
+      return outcome;
+    };
+    try {
+      assert.match(
+        during({ maxConcurrent: 1 }, () => compact(otherPath)).message,
+        /Too many concurrent heavy operations: limit is 1/
+      );
+      assert.match(
+        during({ maxPerCollection: 1 }, () => buildIndex(collPath)).message,
+        /Too many concurrent heavy operations on '.*coll1': limit is 1/
+      );
+      assert.equal(during({ maxPerCollection: 1 }, () => compact(otherPath)), 0);
+      assert.deepEqual(getOperationLimits(), { maxPerCollection: 1, running: 0 });
+    } finally {
+      setOperationLimits({});
+      closeCollection(otherPath);
+    }
   });
+});
 
-  it("should show file size after build", () => {
-    insertVector(collPath, "a", randomVector(DIMS));
-    buildIndex(collPath);
-    const s = stats(collPath);
-    assert.ok(s.fileSizeBytes > 0, "File size should be > 0 after build");
+describe("thread pool", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  it("should run parallel work on a resizable worker pool", () => {
+    const defaultThreads = getThreadPool().threads;
+    assert.ok(defaultThreads >= 1);
+    assert.throws(() => setThreadPool({ threads: 0 }), /threads must be > 0/);
+
+    try {
+      setThreadPool({ threads: 3 });
+      assert.deepEqual(getThreadPool(), { threads: 3 });
+      for (let i = 0; i < 10; i++) {
+        insertVector(collPath, `v-${i}`, randomVector(DIMS));
+      }
+      const entries = [];
+      assert.equal(knnGraph(collPath, 2, { onBatch: (batch) => entries.push(...batch) }), 10);
+      assert.ok(entries.every((e) => e.neighbors.length === 2));
+    } finally {
+      setThreadPool({});
+    }
+    assert.equal(getThreadPool().threads, defaultThreads);
+  });
+});
+
+describe("server", () => {
+  let tmpDir;
+  let collPath;
+  let port;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+    createCollection({
+      path: collPath,
+      dimensions: 4,
+      indexType: "hnsw",
+      metric: "cosine",
+    });
+    port = startServer(0, { collections: { docs: collPath } });
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    stopServer(port);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  const call = async (method, path, body, token) => {
+    const res = await fetch(`http://127.0.0.1:${port}${path}`, {
+      method,
+      headers: token ? { Authorization: `Bearer ${token}` } : {},
+      body: body && JSON.stringify(body),
+    });
+    return { status: res.status, body: await res.json() };
+  };
+
+  // Send `data` over a bare socket and collect everything the server answers
+  const rawCall = (data) =>
+    new Promise((resolve, reject) => {
+      const socket = connect(port, "127.0.0.1", () => socket.end(data));
+      let response = "";
+      socket.on("data", (chunk) => (response += chunk));
+      socket.on("end", () => resolve(response));
+      socket.on("error", reject);
+    });
+
+  it("should insert, search, delete and report stats", async () => {
+    let res = await call("POST", "/collections/docs/points", { id: "a", vector: [1, 0, 0, 0] });
+    assert.equal(res.status, 200);
+    await call("POST", "/collections/docs/points", { id: "b", vector: [0, 1, 0, 0] });
+
+    res = await call("POST", "/collections/docs/search", { vector: [1, 0, 0, 0], k: 1 });
+    assert.equal(res.body.results[0].id, "a");
+
+    res = await call("DELETE", "/collections/docs/points/a");
+    assert.equal(res.body.deleted, true);
+
+    res = await call("GET", "/collections/docs/stats");
+    assert.equal(res.body.count, 1);
+    assert.equal(stats(collPath).count, 1);
+  });
+
+  it("should report errors as JSON", async () => {
+    let res = await call("POST", "/collections/docs/points", { id: "a", vector: [1, 0] });
+    assert.equal(res.status, 400);
+    assert.match(res.body.error, /Dimension mismatch/);
+
+    res = await call("GET", "/collections/missing/stats");
+    assert.equal(res.status, 404);
+  });
+
+  it("should check API tokens and their scopes", async () => {
+    const logsPath = join(tmpDir, "coll2");
+    createCollection({ path: logsPath, dimensions: 4, indexType: "hnsw", metric: "cosine" });
+    stopServer(port);
+    port = startServer(0, {
+      collections: { docs: collPath, logs: logsPath },
+      tokens: [
+        { token: "reader", collections: ["docs"], scope: "read" },
+        { token: "writer", collections: ["*"], scope: "write" },
+      ],
+    });
+    const point = { id: "a", vector: [1, 0, 0, 0] };
+
+    try {
+      assert.equal((await call("GET", "/collections")).status, 401);
+      assert.equal((await call("GET", "/collections", undefined, "guess")).status, 401);
+
+      let res = await call("GET", "/collections", undefined, "reader");
+      assert.deepEqual(res.body.collections, ["docs"]);
+      res = await call("POST", "/collections/docs/search", { vector: [1, 0, 0, 0] }, "reader");
+      assert.equal(res.status, 200);
+      res = await call("POST", "/collections/docs/points", point, "reader");
+      assert.equal(res.status, 403);
+      assert.match(res.body.error, /read-only/);
+      res = await call("GET", "/collections/logs/stats", undefined, "reader");
+      assert.equal(res.status, 403);
+
+      res = await call("GET", "/collections", undefined, "writer");
+      assert.deepEqual(res.body.collections, ["docs", "logs"]);
+      res = await call("POST", "/collections/logs/points", point, "writer");
+      assert.equal(res.status, 200);
+      assert.equal(stats(logsPath).count, 1);
+      assert.equal(stats(collPath).count, 0);
+
+      // Refused from the headers alone: the announced body is never sent,
+      // so a server waiting for it would hang until its read timeout
+      const response = await rawCall("POST /collections/docs/points HTTP/1.1\r\nContent-Length: 60000000\r\n\r\n");
+      assert.match(response, /^HTTP\/1.1 401/);
+
+      assert.throws(
+        () => startServer(0, { collections: {}, tokens: [{ token: "t", collections: [], scope: "admin" }] }),
+        /Invalid scope 'admin'/
+      );
+    } finally {
+      closeCollection(logsPath);
+    }
+  });
+
+  it("should serve HTTPS with the configured certificate", async () => {
+    const certPath = fileURLToPath(new URL("fixtures/server.crt", import.meta.url));
+    const keyPath = fileURLToPath(new URL("fixtures/server.key", import.meta.url));
+    stopServer(port);
+    port = startServer(0, { collections: { docs: collPath }, tls: { certPath, keyPath } });
+
+    const body = await new Promise((resolve, reject) => {
+      const req = request(
+        { host: "127.0.0.1", port, path: "/collections", ca: readFileSync(certPath) },
+        (res) => {
+          let data = "";
+          res.on("data", (chunk) => (data += chunk));
+          res.on("end", () => resolve(JSON.parse(data)));
+        }
+      );
+      req.on("error", reject);
+      req.end();
+    });
+    assert.deepEqual(body.collections, ["docs"]);
+    await assert.rejects(() => fetch(`http://127.0.0.1:${port}/collections`));
+
+    assert.throws(
+      () => startServer(0, { collections: {}, tls: { certPath: keyPath, keyPath } }),
+      /No certificates found/
+    );
+  });
+
+  it("should reject oversized request heads and out-of-range integers", async () => {
+    // Exactly the 16 KiB head budget with no end of line, so the server
+    // reads everything sent before answering
+    const head = "GET /collections HTTP/1.1\r\nX-Filler: ";
+    const response = await rawCall(head.padEnd(16 * 1024, "a"));
+    assert.match(response, /^HTTP\/1.1 400/);
+    assert.match(response, /Request headers too large/);
+
+    const res = await call("POST", "/collections/docs/search", { vector: [1, 0, 0, 0], k: 2 ** 32 });
+    assert.equal(res.status, 400);
+    assert.match(res.body.error, /Field 'k' must be an integer/);
+  });
+
+  it("should answer searches asking for more neighbours than the collection has", async () => {
+    await call("POST", "/collections/docs/points", { id: "a", vector: [1, 0, 0, 0] });
+    await call("POST", "/collections/docs/points", { id: "b", vector: [0, 1, 0, 0] });
+
+    let res = await call("POST", "/collections/docs/search", { vector: [1, 0, 0, 0], k: 2 ** 32 - 1 });
+    assert.equal(res.status, 200);
+    assert.deepEqual(res.body.results.map((r) => r.id), ["a", "b"]);
+
+    res = await call("POST", "/collections/docs/search", { vector: [1, 0, 0, 0], k: 1, efSearch: 2 ** 32 - 1 });
+    assert.equal(res.status, 200);
+    assert.equal(res.body.results[0].id, "a");
+  });
+
+  it("should describe its endpoints in an OpenAPI document", async () => {
+    let res = await call("GET", "/openapi.json");
+    assert.equal(res.status, 200);
+    assert.equal(res.body.openapi, "3.0.3");
+    assert.ok(res.body.paths["/collections/{name}/search"].post);
+    assert.equal(res.body.security, undefined);
+
+    stopServer(port);
+    port = startServer(0, {
+      collections: { docs: collPath },
+      tokens: [{ token: "reader", collections: ["*"], scope: "read" }],
+    });
+    res = await call("GET", "/openapi.json");
+    assert.equal(res.status, 200);
+    assert.deepEqual(res.body.security, [{ bearerAuth: [] }]);
+  });
+
+  it("should stop serving after stopServer", async () => {
+    assert.equal(stopServer(port), true);
+    assert.equal(stopServer(port), false);
+    await assert.rejects(() => fetch(`http://127.0.0.1:${port}/collections`));
+    port = startServer(0, { collections: {} });
+  });
+});
+
+describe("multi-process access", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  const childWriter = (ids) => `
+    const config = { path: ${JSON.stringify(collPath)}, dimensions: 4, indexType: "hnsw", metric: "cosine" };
+    try {
+      zvec.createCollection(config);
+      for (const id of ${JSON.stringify(ids)}) zvec.insertVector(config.path, id, new Float32Array([1, 0, 0, 0]));
+      zvec.buildIndex(config.path);
+      console.log("ok");
+    } catch (e) {
+      console.log(e.message);
+    }`;
+
+  const open = (readOnly) =>
+    createCollection({
+      path: collPath,
+      dimensions: 4,
+      indexType: "hnsw",
+      metric: "cosine",
+      readOnly,
+    });
+
+  it("should reject a second writer process", () => {
+    open(false);
+    assert.match(runInChild(childWriter(["a"])), /locked by another writer/);
+  });
+
+  it("should release the writer lock on close", () => {
+    open(false);
+    assert.equal(closeCollection(collPath), true);
+    assert.equal(runInChild(childWriter(["a"])), "ok");
+  });
+
+  it("should serve a read-only view that reloads the writer's saves", () => {
+    assert.equal(runInChild(childWriter(["a"])), "ok");
+
+    open(true);
+    assert.equal(stats(collPath).count, 1);
+    assert.throws(
+      () => insertVector(collPath, "b", new Float32Array([0, 1, 0, 0])),
+      /read-only/
+    );
+
+    // Readers don't block the writer
+    assert.equal(runInChild(childWriter(["b", "c"])), "ok");
+    assert.equal(stats(collPath).count, 1);
+    assert.equal(reloadCollection(collPath), true);
+    assert.equal(stats(collPath).count, 3);
+  });
+});
+
+describe("attachReadonly", () => {
+  let tmpDir;
+  let collPath;
+
+  beforeEach(() => {
+    tmpDir = mkdtempSync(join(tmpdir(), "zvec-test-"));
+    collPath = join(tmpDir, "coll1");
+  });
+
+  afterEach(() => {
+    closeCollection(collPath);
+    rmSync(tmpDir, { recursive: true, force: true });
+  });
+
+  const childWriter = (sharedSegments, ids) => `
+    const config = { path: ${JSON.stringify(collPath)}, dimensions: 4, indexType: "hnsw", metric: "cosine", sharedSegments: ${sharedSegments} };
+    zvec.createCollection(config);
+    ${JSON.stringify(ids)}.forEach((id, i) => {
+      const v = new Float32Array(4);
+      v[i % 4] = 1;
+      zvec.insertVector(config.path, id, v);
+    });
+    zvec.buildIndex(config.path);
+    console.log("ok");`;
+
+  it("should search over segments dumped by the writer", () => {
+    assert.equal(runInChild(childWriter(true, ["a", "b", "c"])), "ok");
+
+    attachReadonly(collPath);
+    assert.equal(stats(collPath).count, 3);
+    assert.equal(search(collPath, basisVector(4, 1), 1)[0].id, "b");
+    assert.throws(() => deleteVector(collPath, "a"), /read-only/);
+    assert.deepEqual([...getVectors(collPath, ["c"])[0].vector], [0, 0, 1, 0]);
+    assert.equal(checkIndex(collPath).ok, true);
+  });
+
+  it("should pick up a newer dump on reload", () => {
+    assert.equal(runInChild(childWriter(true, ["a"])), "ok");
+    attachReadonly(collPath);
+
+    assert.equal(runInChild(childWriter(true, ["b", "c", "d"])), "ok");
+    assert.equal(reloadCollection(collPath), true);
+    assert.equal(stats(collPath).count, 4);
+
+    const dumps = readdirSync(collPath).filter((f) => f.endsWith(".hnsw.data"));
+    assert.equal(dumps.length, 1, "Stale segment dumps should be removed");
+  });
+
+  it("should warm up attached vectors and sample queries", () => {
+    assert.equal(runInChild(childWriter(true, ["a", "b", "c"])), "ok");
+    attachReadonly(collPath);
+
+    const report = warmup(collPath, { sampleQueries: [basisVector(4, 0), basisVector(4, 1)] });
+    assert.equal(report.points, 3);
+    assert.equal(report.queries, 2);
+    assert.ok(report.elapsedMs >= 0);
+    assert.equal(warmup(collPath).queries, 0);
+    assert.throws(
+      () => warmup(collPath, { sampleQueries: [new Float32Array(2)] }),
+      /Query dimension mismatch/
+    );
+  });
+
+  it("should restrict an attached search to allowIds", () => {
+    assert.equal(runInChild(childWriter(true, ["a", "b", "c", "d"])), "ok");
+    attachReadonly(collPath);
+
+    const results = search(collPath, basisVector(4, 0), 2, { allowIds: ["b", "c"] });
+    assert.deepEqual(results.map((r) => r.id).sort(), ["b", "c"]);
+    assert.equal(search(collPath, basisVector(4, 2), 1, { allowIds: ["b", "c"] })[0].id, "c");
+  });
+
+  it("should discover over an attached collection", () => {
+    assert.equal(runInChild(childWriter(true, ["a", "b", "c", "d"])), "ok");
+    attachReadonly(collPath);
+
+    const byId = discover(collPath, [{ positive: "a", negative: "b" }], 2, { target: basisVector(4, 2) });
+    assert.deepEqual(byId.map((r) => r.id), ["c", "d"]);
+    const byVector = discover(collPath, [{ positive: basisVector(4, 2), negative: basisVector(4, 3) }], 4);
+    assert.deepEqual(byVector.map((r) => r.id), ["a", "b", "c", "d"]);
+    assert.ok(Math.abs(byVector[3].score + 1) < 1e-6);
+  });
+
+  it("should read vectors, token vectors and staged vectors when attached", () => {
+    const script = `
+      const config = { path: ${JSON.stringify(collPath)}, dimensions: 4, indexType: "hnsw", metric: "cosine", sharedSegments: true };
+      zvec.createCollection(config);
+      const unit = (i) => { const v = new Float32Array(4); v[i] = 1; return v; };
+      zvec.insertVector(config.path, "a", unit(0));
+      zvec.insertVector(config.path, "b", unit(1));
+      zvec.setTokenVectors(config.path, "a", [unit(1)]);
+      zvec.setTokenVectors(config.path, "b", [unit(2)]);
+      zvec.stageVector(config.path, "a", unit(3));
+      zvec.buildIndex(config.path);
+      console.log("ok");`;
+    assert.equal(runInChild(script), "ok");
+    attachReadonly(collPath);
+
+    const sims = pairwiseSimilarities(collPath, ["a", "b"]);
+    assert.deepEqual([...sims], [1, 0, 0, 1]);
+    const results = search(collPath, basisVector(4, 0), 1, { lateInteraction: [basisVector(4, 2)], rerankFactor: 2 });
+    assert.equal(results[0].id, "b");
+    assert.equal(stagingStatus(collPath).staged, 1);
+  });
+
+  it("should reject collections without shared segments", () => {
+    assert.equal(runInChild(childWriter(false, ["a"])), "ok");
+    assert.throws(() => attachReadonly(collPath), /no shared segments/);
   });
 });
 
@@ -291,6 +3472,7 @@ describe("persistence", () => {
   });
 
   afterEach(() => {
+    closeCollection(collPath);
     rmSync(tmpDir, { recursive: true, force: true });
   });
 
@@ -316,4 +3498,234 @@ describe("persistence", () => {
     assert.equal(s.dimensions, DIMS);
     assert.ok(s.fileSizeBytes > 0);
   });
+
+  it("should serve exact searches until a lazy index is built", async () => {
+    const config = { path: collPath, dimensions: 4, indexType: "hnsw", metric: "cosine" };
+    createCollection(config);
+    for (let i = 0; i < 200; i++) {
+      insertVector(collPath, `p${i}`, randomVector(4));
+    }
+    insertVector(collPath, "target", basisVector(4, 2));
+    buildIndex(collPath);
+    closeCollection(collPath);
+
+    createCollection({ ...config, lazyIndex: true });
+    assert.equal(search(collPath, basisVector(4, 2), 1)[0].id, "target");
+    insertVector(collPath, "late", basisVector(4, 3));
+    assert.equal(search(collPath, basisVector(4, 3), 1)[0].id, "late");
+
+    while (!health(collPath).indexReady) {
+      await new Promise((resolve) => setTimeout(resolve, 10));
+    }
+    assert.equal(stats(collPath).count, 202);
+    assert.equal(search(collPath, basisVector(4, 3), 1)[0].id, "late");
+    assert.equal(exportHnswlib(collPath, join(tmpDir, "out.bin")), 202);
+
+    const { id } = listOperations().findLast((o) => o.kind === "build");
+    while (operationStatus(id).phase !== "done") {
+      await new Promise((resolve) => setTimeout(resolve, 10));
+    }
+    assert.equal(operationStatus(id).processed, 201);
+    assert.equal(operationStatus(id).total, 201);
+  });
+
+  it("should preload several collections and report each one", () => {
+    const config = { dimensions: 4, indexType: "hnsw", metric: "cosine" };
+    const paths = [collPath, join(tmpDir, "coll2")];
+    for (const [i, path] of paths.entries()) {
+      createCollection({ ...config, path });
+      insertVector(path, "a", basisVector(4, i));
+      buildIndex(path);
+      closeCollection(path);
+    }
+
+    const progress = [];
+    const results = preloadCollections(
+      [...paths.map((path) => ({ ...config, path })), { ...config, path: collPath, dimensions: 8 }],
+      { concurrency: 2, onProgress: (p) => progress.push(p) }
+    );
+    assert.deepEqual(
+      results.map((r) => r.path),
+      [...paths, collPath]
+    );
+    assert.equal(results[0].error, undefined);
+    assert.equal(results[1].error, undefined);
+    assert.match(results[2].error, /Dimension mismatch/);
+    assert.deepEqual(
+      progress.map((p) => p.done),
+      [1, 2, 3]
+    );
+    assert.ok(progress.every((p) => p.total === 3));
+    assert.equal(search(paths[1], basisVector(4, 1), 1)[0].id, "a");
+    closeCollection(paths[1]);
+  });
+
+  it("should save with the configured durability", () => {
+    const config = { path: collPath, dimensions: 4, indexType: "hnsw", metric: "cosine" };
+    assert.throws(
+      () => createCollection({ ...config, durability: "paranoid" }),
+      /Invalid durability 'paranoid'/
+    );
+
+    createCollection({ ...config, durability: "fsync", sharedSegments: true });
+    assert.equal(getConfig(collPath).durability, "fsync");
+    insertVector(collPath, "a", basisVector(4, 0));
+    buildIndex(collPath);
+    closeCollection(collPath);
+
+    createCollection(config);
+    assert.equal(getConfig(collPath).durability, "fsync");
+    assert.equal(search(collPath, basisVector(4, 0), 1)[0].id, "a");
+    closeCollection(collPath);
+
+    createCollection({ ...config, durability: "flush" });
+    buildIndex(collPath);
+    closeCollection(collPath);
+    createCollection(config);
+    assert.equal(getConfig(collPath).durability, "flush");
+  });
+
+  it("should turn away unknown ids with the id filter", () => {
+    const config = { path: collPath, dimensions: 4, indexType: "hnsw", metric: "cosine" };
+    createCollection({ ...config, idFilter: true });
+    for (let i = 0; i < 50; i++) insertVector(collPath, `id-${i}`, randomVector(4));
+    for (let i = 0; i < 200; i++) deleteVector(collPath, `missing-${i}`);
+    assert.equal(deleteVector(collPath, "id-0"), true);
+
+    const { idHits, idMisses, idFilterRejections } = stats(collPath);
+    assert.ok(idHits >= 1);
+    assert.ok(idMisses >= 200);
+    assert.ok(idFilterRejections > 190, `only ${idFilterRejections} rejected`);
+
+    buildIndex(collPath);
+    closeCollection(collPath);
+    createCollection(config);
+    assert.equal(getConfig(collPath).idFilter, true);
+    deleteVector(collPath, "missing-0");
+    assert.equal(stats(collPath).idFilterRejections, 1);
+    assert.equal(deleteVector(collPath, "id-1"), true);
+  });
+
+  it("should read saved metadata without opening the collection", () => {
+    assert.throws(() => openMetadata(collPath), /No collection saved/);
+    createCollection({
+      path: collPath,
+      dimensions: 4,
+      indexType: "hnsw",
+      metric: "cosine",
+      m: 8,
+      schema: { title: "string" },
+    });
+    insertVector(collPath, "a", basisVector(4, 0), { title: "A" });
+    insertVector(collPath, "b", basisVector(4, 1));
+    insertVector(collPath, "c", basisVector(4, 2));
+    deleteVector(collPath, "c");
+    buildIndex(collPath);
+    insertVector(collPath, "unsaved", basisVector(4, 3));
+
+    const meta = openMetadata(collPath);
+    assert.equal(meta.count, 2);
+    assert.equal(meta.tombstones, 0);
+    assert.ok(meta.lastSavedAt > 0);
+    assert.ok(meta.fileSizeBytes > 0);
+    assert.equal(meta.config.dimensions, 4);
+    assert.equal(meta.config.m, 8);
+    assert.deepEqual(meta.config.schema, { title: "string" });
+    assert.equal(meta.config.readOnly, undefined);
+  });
+
+  it("should repair inconsistent id maps and save the result", () => {
+    const config = { path: collPath, dimensions: 4, indexType: "hnsw", metric: "cosine" };
+    createCollection(config);
+    insertVector(collPath, "a", basisVector(4, 0));
+    insertVector(collPath, "b", basisVector(4, 1));
+    insertVector(collPath, "c", basisVector(4, 2), { title: "C" });
+    buildIndex(collPath);
+    closeCollection(collPath);
+
+    // Simulate a crash that left the maps disagreeing
+    const file = join(collPath, "metadata.json");
+    const meta = JSON.parse(readFileSync(file, "utf8"));
+    delete meta.vectors[meta.id_map.b];
+    meta.vectors["99"] = meta.vectors[meta.id_map.a];
+    meta.deleted_ids.push("ghost");
+    writeFileSync(file, JSON.stringify(meta));
+
+    createCollection(config);
+    const report = repairCollection(collPath);
+    assert.deepEqual(report.missingVectors, ["b"]);
+    assert.deepEqual(report.staleTombstones, ["ghost"]);
+    assert.deepEqual(report.duplicateIds, []);
+    assert.equal(report.orphanedVectors, 1);
+    assert.equal(report.strayEntries, 1);
+    assert.equal(report.repaired, true);
+    assert.equal(search(collPath, basisVector(4, 2), 1)[0].id, "c");
+
+    assert.equal(repairCollection(collPath, { dropOrphans: true }).orphanedVectors, 1);
+    const clean = repairCollection(collPath, { dropOrphans: true });
+    assert.equal(clean.orphanedVectors, 0);
+    assert.equal(clean.repaired, false);
+
+    closeCollection(collPath);
+    createCollection(config);
+    assert.equal(stats(collPath).count, 2);
+    assert.deepEqual(repairCollection(collPath).missingVectors, []);
+  });
+
+  it("should save the reverse map and derive it for saves without one", () => {
+    const config = { path: collPath, dimensions: 4, indexType: "hnsw", metric: "cosine" };
+    createCollection(config);
+    insertVector(collPath, "a", basisVector(4, 0));
+    insertVector(collPath, "b", basisVector(4, 1));
+    buildIndex(collPath);
+    closeCollection(collPath);
+
+    const file = join(collPath, "metadata.json");
+    const meta = JSON.parse(readFileSync(file, "utf8"));
+    assert.equal(meta.reverse_map[meta.id_map.a], "a");
+    assert.equal(meta.reverse_map[meta.id_map.b], "b");
+
+    // A save that named the wrong id for a node is fixed by repair
+    meta.reverse_map[meta.id_map.a] = "b";
+    writeFileSync(file, JSON.stringify(meta));
+    createCollection(config);
+    assert.equal(repairCollection(collPath).reverseMapFixes, 1);
+    assert.equal(search(collPath, basisVector(4, 0), 1)[0].id, "a");
+    closeCollection(collPath);
+
+    // Saves from before the reverse map was kept
+    delete meta.reverse_map;
+    writeFileSync(file, JSON.stringify(meta));
+    createCollection(config);
+    assert.equal(search(collPath, basisVector(4, 1), 1)[0].id, "b");
+    assert.equal(checkIndex(collPath).missingFromGraph.length, 0);
+  });
+
+  it("should persist the config and reject mismatches on reopen", () => {
+    const config = {
+      path: collPath,
+      dimensions: DIMS,
+      indexType: "hnsw",
+      metric: "cosine",
+    };
+    createCollection({ ...config, m: 8, efConstruction: 100 });
+    insertVector(collPath, "a", basisVector(DIMS, 0));
+    buildIndex(collPath);
+    closeCollection(collPath);
+
+    assert.throws(() => createCollection({ ...config, dimensions: 128 }), /Dimension mismatch/);
+    assert.throws(() => createCollection({ ...config, m: 32 }), /m mismatch/);
+
+    createCollection(config);
+    const saved = getConfig(collPath);
+    assert.equal(saved.dimensions, DIMS);
+    assert.equal(saved.metric, "cosine");
+    assert.equal(saved.indexType, "hnsw");
+    assert.equal(saved.m, 8);
+    assert.equal(saved.efConstruction, 100);
+    assert.equal(saved.maxElements, 100000);
+    assert.equal(saved.readOnly, false);
+    assert.equal(stats(collPath).count, 1);
+    closeCollection(collPath);
+  });
 });